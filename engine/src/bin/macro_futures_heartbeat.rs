@@ -103,6 +103,7 @@ fn main() {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
@@ -122,7 +123,16 @@ fn main() {
             max_risk_per_position_eur: 80.0,
             max_contracts: 3,
         },
+        gc: InstrumentRiskBudget::default_gold(),
+        mcl: InstrumentRiskBudget::default_crude(),
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: per_pos_cap_eur,
+            max_contracts: 5,
+        },
+        zn: InstrumentRiskBudget::default_bond(),
+        vx: InstrumentRiskBudget::default_vix(),
         max_total_contracts: 4,
+        max_total_risk_eur: 3.0 * per_pos_cap_eur,
     };
 
 
@@ -132,7 +142,8 @@ fn main() {
         .mes
         .max_risk_per_position_eur
         .max(risk_budget.mnq.max_risk_per_position_eur)
-        .max(risk_budget.sixe.max_risk_per_position_eur);
+        .max(risk_budget.sixe.max_risk_per_position_eur)
+        .max(risk_budget.gc.max_risk_per_position_eur);
 
     // Sta bijv. max 3 posities toe aan full risk → v1 ≈ 270 EUR
     let max_sleeve_risk_eur = 5.0 * per_pos_cap_eur;
@@ -156,11 +167,12 @@ fn main() {
         1.0, // eur_per_usd (demo)
         &risk_budget,
         max_sleeve_risk_eur,
+        None,
         &mut sink,
     );
 
     // ===== 5) Heartbeat + orders als JSON naar stdout =====
-    let hb_json = encode_heartbeat_log_event_json(ts_utc, &result, EngineHealth::Healthy);
+    let hb_json = encode_heartbeat_log_event_json(ts_utc, &result, EngineHealth::Healthy, &margin_state, None);
     println!("{}", hb_json);
 
     for order in &result.engine_orders {
@@ -211,7 +223,14 @@ fn make_history_for_demo(
             highest_close_50d: price * 1.01,
             lowest_close_50d: price * 0.97,
 
+            open_interest: 10_000.0,
+            // Net onder close → licht bullish t.o.v. VWAP
+            vwap: price * 0.998,
+            ma_5d: price * 0.999,
+
             fx_carry,
+            commodity_carry: None,
+            bond_carry: None,
         };
 
         bars.push(bar);
@@ -1,11 +1,13 @@
 use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
 use std::slice;
 use std::path::{Path, PathBuf};
 use std::fs::{OpenOptions, File};
 use chrono::{Datelike};
-use serde::Serialize;
-use chrono::Utc;
-use std::io::{self, Write};
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::io::{self, BufWriter, Write};
 
 use crate::risk::{
     GlobalRiskKernel,
@@ -19,12 +21,15 @@ use crate::risk::{
 
 use crate::strategies::macro_futures_sleeve::{
     EngineOrder,
+    EngineOrderSide,
     MacroFuturesSleeve,
     MacroFuturesHeartbeatOutput,
     FuturesSleeveContext,
     FuturesRiskBudget,
     FutureInstrument,
+    FuturesPlannedRisk,
     InstrumentHistory,
+    InstrumentSignal,
     MacroScalars,
 };
 
@@ -39,17 +44,69 @@ pub struct MacroFuturesEngineHeartbeatResult {
 pub enum EngineHealth {
     Healthy,
     Degraded,     // behind on ticks or repeated failures
+    Critical,     // >= critical_threshold degraded ticks op rij → alles flatten
 }
 
 pub struct HeartbeatSupervisor {
     last_tick_ts: Option<i64>,     // UTC seconds
     max_gap_seconds: i64,          // e.g. 65 for once/minute heartbeats
+    critical_threshold: u32,       // e.g. 5 degraded ticks op rij → Critical (0 = uit)
     health: EngineHealth,
+
+    // Statistieken sinds start (zie `statistics`)
+    total_ticks: u64,
+    sum_of_gaps: i64,
+    max_gap_observed: i64,
+    degraded_periods: u32,         // aantal Healthy → Degraded overgangen
+
+    // Huidige degraded-reeks (reset bij een healthy tick)
+    consecutive_degraded: u32,
+    gap_since_healthy: i64,        // som van de gaps sinds de laatste healthy tick
+}
+
+/// Snapshot van de supervisor-statistieken sinds start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SupervisorStats {
+    pub total_ticks: u64,
+    /// Gemiddelde gap tussen opeenvolgende ticks (0.0 bij < 2 ticks)
+    pub avg_gap_seconds: f64,
+    pub max_gap_seconds: i64,
+    pub degraded_periods: u32,
+    pub current_health: EngineHealth,
+}
+
+
+/// Fout in de engine-heartbeat (i.p.v. een panic in de orchestrator).
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeartbeatError {
+    /// Kernel leverde geen envelope voor de verwachte sleeve.
+    MissingEnvelope { sleeve_id: SleeveId },
+    /// Kernel kon niet evalueren (bijv. geen config voor de aangeleverde sleeve-state).
+    KernelError(String),
+}
+
+impl std::fmt::Display for HeartbeatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeartbeatError::MissingEnvelope { sleeve_id } => {
+                write!(f, "missing SleeveRiskEnvelope for {:?}", sleeve_id)
+            }
+            HeartbeatError::KernelError(msg) => write!(f, "risk kernel error: {}", msg),
+        }
+    }
 }
 
+impl std::error::Error for HeartbeatError {}
 
 /// End-to-end heartbeat voor de Macro Futures sleeve:
 /// GlobalRiskKernel → SleeveRiskEnvelope → MacroFuturesSleeve → EngineOrders → OrderSink.
+///
+/// `prev_signals` zijn de signals van de vorige heartbeat (`result.heartbeat.signals`);
+/// alleen dan vult de sleeve `regime_changes`.
+///
+/// Panict als de kernel geen envelope voor `MicroFuturesMacroTrend` oplevert;
+/// zie `run_macro_futures_engine_heartbeat_checked` voor de `Result`-variant.
+#[allow(clippy::too_many_arguments)]
 pub fn run_macro_futures_engine_heartbeat(
     now_ts: i64,
     kernel: &mut GlobalRiskKernel,
@@ -64,8 +121,65 @@ pub fn run_macro_futures_engine_heartbeat(
     eur_per_usd: f64,
     risk_budget: &FuturesRiskBudget,
     max_sleeve_risk_eur: f64,
+    prev_signals: Option<&[InstrumentSignal]>,
     sink: &mut impl OrderSink,
 ) -> MacroFuturesEngineHeartbeatResult {
+    run_macro_futures_engine_heartbeat_checked(
+        now_ts,
+        kernel,
+        portfolio,
+        sleeve_state,
+        margin,
+        vol,
+        sleeve,
+        histories,
+        macro_scalars,
+        current_positions,
+        eur_per_usd,
+        risk_budget,
+        max_sleeve_risk_eur,
+        prev_signals,
+        sink,
+    )
+    .expect("Missing SleeveRiskEnvelope for MicroFuturesMacroTrend")
+}
+
+/// Zelfde als `run_macro_futures_engine_heartbeat`, maar geeft een `HeartbeatError`
+/// terug i.p.v. te panicken als de kernel-config of envelope ontbreekt.
+#[allow(clippy::too_many_arguments)]
+pub fn run_macro_futures_engine_heartbeat_checked(
+    now_ts: i64,
+    kernel: &mut GlobalRiskKernel,
+    portfolio: &PortfolioState,
+    sleeve_state: &mut SleeveState,
+    margin: &MarginState,
+    vol: &VolatilityRegime,
+    sleeve: &MacroFuturesSleeve,
+    histories: HashMap<FutureInstrument, InstrumentHistory>,
+    macro_scalars: MacroScalars,
+    current_positions: HashMap<FutureInstrument, i32>,
+    eur_per_usd: f64,
+    risk_budget: &FuturesRiskBudget,
+    max_sleeve_risk_eur: f64,
+    prev_signals: Option<&[InstrumentSignal]>,
+    sink: &mut impl OrderSink,
+) -> Result<MacroFuturesEngineHeartbeatResult, HeartbeatError> {
+    let has_config = |id: SleeveId| kernel.config.sleeves.iter().any(|c| c.sleeve_id == id);
+
+    // 0) Zonder config voor deze sleeve komt er nooit een envelope
+    if !has_config(SleeveId::MicroFuturesMacroTrend) {
+        return Err(HeartbeatError::MissingEnvelope {
+            sleeve_id: SleeveId::MicroFuturesMacroTrend,
+        });
+    }
+    // kernel.evaluate panict op sleeve-states zonder config
+    if !has_config(sleeve_state.sleeve_id) {
+        return Err(HeartbeatError::KernelError(format!(
+            "missing sleeve config for {:?}",
+            sleeve_state.sleeve_id
+        )));
+    }
+
     // 1) Risk-kernel → envelope voor deze sleeve
     let sleeves_slice: &mut [SleeveState] = slice::from_mut(sleeve_state);
 
@@ -80,7 +194,9 @@ pub fn run_macro_futures_engine_heartbeat(
     let env = envelopes
         .into_iter()
         .find(|e| e.sleeve_id == SleeveId::MicroFuturesMacroTrend)
-        .expect("Missing SleeveRiskEnvelope for MicroFuturesMacroTrend");
+        .ok_or(HeartbeatError::MissingEnvelope {
+            sleeve_id: SleeveId::MicroFuturesMacroTrend,
+        })?;
 
 
     let ctx = FuturesSleeveContext {
@@ -91,11 +207,15 @@ pub fn run_macro_futures_engine_heartbeat(
         current_positions,
         eur_per_usd,
         engine_health: EngineHealth::Healthy, // default
+        position_entry_dates: HashMap::new(),
+        sleeve_equity_usd: sleeve_state.equity_usd,
+        position_convictions: HashMap::new(),
+        soft_halt_base_positions: HashMap::new(),
     };
 
 
-    // 3) Sleeve-heartbeat (plan + intents)
-    let hb = sleeve.run_heartbeat(&ctx, risk_budget, max_sleeve_risk_eur);
+    // 3) Sleeve-heartbeat (plan + intents), met regime-change detectie t.o.v. prev_signals
+    let hb = sleeve.run_heartbeat_with_prev(&ctx, risk_budget, max_sleeve_risk_eur, prev_signals);
 
     // 4) Map naar EngineOrders en push naar sink
     let engine_orders =
@@ -105,11 +225,11 @@ pub fn run_macro_futures_engine_heartbeat(
         sink.submit(order);
     }
 
-    MacroFuturesEngineHeartbeatResult {
+    Ok(MacroFuturesEngineHeartbeatResult {
         envelope: env,
         heartbeat: hb,
         engine_orders,
-    }
+    })
 }
 
 
@@ -121,9 +241,20 @@ pub trait OrderSink {
     fn flush(&mut self) {}
 }
 
+/// Lifecycle-status van een order in de `InMemoryOrderSink`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    Pending,
+    PartiallyFilled { filled: i32 },
+    Filled,
+    Cancelled,
+}
+
+/// Test/simulatie-sink: bewaart elke order met zijn status (start als `Pending`);
+/// fills en cancels worden handmatig gesimuleerd via `fill` / `cancel`.
 #[derive(Debug, Default)]
 pub struct InMemoryOrderSink {
-    pub orders: Vec<EngineOrder>,
+    pub orders: Vec<(EngineOrder, OrderStatus)>,
 }
 
 impl InMemoryOrderSink {
@@ -132,9 +263,68 @@ impl InMemoryOrderSink {
             orders: Vec::new(),
         }
     }
+
+    /// Netto ingestuurde contracts per instrument (Buy = +, Sell = −).
+    pub fn net_position_by_instrument(&self) -> HashMap<FutureInstrument, i32> {
+        let mut net = HashMap::new();
+        for (order, _) in &self.orders {
+            let signed = match order.side {
+                EngineOrderSide::Buy => order.quantity,
+                EngineOrderSide::Sell => -order.quantity,
+            };
+            *net.entry(order.instrument).or_insert(0) += signed;
+        }
+        net
+    }
+
+    /// Som van alle order-quantities (bruto, ongeacht side).
+    pub fn total_abs_contracts(&self) -> i32 {
+        self.orders.iter().map(|(o, _)| o.quantity).sum()
+    }
+
+    /// Simuleer een fill van `qty` contracts op order `idx`: `PartiallyFilled` tot de
+    /// volledige quantity gevuld is, dan `Filled`. Onbekende index, `qty <= 0` en
+    /// orders die al `Filled`/`Cancelled` zijn: no-op.
+    pub fn fill(&mut self, idx: usize, qty: i32) {
+        let Some((order, status)) = self.orders.get_mut(idx) else {
+            return;
+        };
+        if qty <= 0 {
+            return;
+        }
+
+        let already = match *status {
+            OrderStatus::Pending => 0,
+            OrderStatus::PartiallyFilled { filled } => filled,
+            OrderStatus::Filled | OrderStatus::Cancelled => return,
+        };
+
+        let filled = already.saturating_add(qty);
+        *status = if filled >= order.quantity {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled { filled }
+        };
+    }
+
+    /// Annuleer order `idx` als die nog niet volledig gevuld is.
+    pub fn cancel(&mut self, idx: usize) {
+        if let Some((_, status)) = self.orders.get_mut(idx)
+            && *status != OrderStatus::Filled
+        {
+            *status = OrderStatus::Cancelled;
+        }
+    }
+
+    /// Orders die nog (deels) open staan: `Pending` of `PartiallyFilled`.
+    pub fn pending_orders(&self) -> impl Iterator<Item = &(EngineOrder, OrderStatus)> {
+        self.orders.iter().filter(|(_, status)| {
+            matches!(status, OrderStatus::Pending | OrderStatus::PartiallyFilled { .. })
+        })
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OrderLogEvent {
     /// Unix timestamp in UTC (seconden)
     pub ts_utc: i64,
@@ -146,6 +336,8 @@ pub struct OrderLogEvent {
     pub side: String,
     /// Aantal contracts (> 0)
     pub quantity: i32,
+    /// Vrije order-annotaties als genest JSON-object
+    pub metadata: HashMap<String, String>,
 }
 
 impl OrderLogEvent {
@@ -157,6 +349,7 @@ impl OrderLogEvent {
             venue: order.venue.to_string(),
             side: format!("{:?}", order.side),
             quantity: order.quantity,
+            metadata: order.metadata.clone(),
         }
     }
 }
@@ -167,7 +360,264 @@ pub fn encode_order_log_event_json(order: &EngineOrder, ts_utc: i64) -> String {
     serde_json::to_string(&evt).unwrap_or_else(|_| "{}".to_string())
 }
 
+/// Volledige snapshot van de portfolio-input voor de risk-kernel.
+#[derive(Debug, Clone, Serialize)]
+pub struct PortfolioStateLogEvent {
+    pub ts_utc: i64,
+    pub portfolio: PortfolioState,
+    pub margin: MarginState,
+    pub vol: VolatilityRegime,
+}
+
+/// Snapshot van alle sleeve-states zoals ze de risk-kernel in gingen.
 #[derive(Debug, Clone, Serialize)]
+pub struct SleeveStatesLogEvent {
+    pub ts_utc: i64,
+    pub sleeves: Vec<SleeveState>,
+}
+
+/// Convenience: JSON-string van de ruwe portfolio-, margin- en vol-state.
+pub fn encode_portfolio_state_json(
+    ts_utc: i64,
+    state: &PortfolioState,
+    margin: &MarginState,
+    vol: &VolatilityRegime,
+) -> String {
+    let evt = PortfolioStateLogEvent {
+        ts_utc,
+        portfolio: *state,
+        margin: *margin,
+        vol: *vol,
+    };
+    serde_json::to_string(&evt).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Per-sleeve deel van `GlobalRiskStateLogEvent`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SleeveRiskStateLogEvent {
+    pub sleeve_id: SleeveId,
+    pub equity_usd: f64,
+    pub peak_equity_usd: f64,
+    /// Drawdown t.o.v. de sleeve-HWM in procent (≤ 0)
+    pub drawdown_pct: f64,
+    pub open_positions: u32,
+}
+
+/// Snapshot van de volledige kernel-state (HWM's, config-basis, drawdowns).
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobalRiskStateLogEvent {
+    pub ts_utc: i64,
+    pub internal_portfolio_peak_equity: f64,
+    pub initial_equity_usd: f64,
+    pub current_equity: f64,
+    /// Drawdown t.o.v. de interne portfolio-HWM in procent (≤ 0)
+    pub portfolio_drawdown_pct: f64,
+    pub sleeves: Vec<SleeveRiskStateLogEvent>,
+}
+
+fn drawdown_pct(equity: f64, peak: f64) -> f64 {
+    if peak > 0.0 && equity.is_finite() {
+        ((equity / peak) - 1.0).min(0.0) * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Convenience: JSON-string van de volledige risk-kernel state.
+pub fn encode_global_risk_state_json(
+    ts_utc: i64,
+    kernel: &GlobalRiskKernel,
+    portfolio: &PortfolioState,
+    sleeves: &[SleeveState],
+) -> String {
+    let current_equity = portfolio.net_equity();
+    let peak = kernel.internal_portfolio_peak_equity;
+
+    let evt = GlobalRiskStateLogEvent {
+        ts_utc,
+        internal_portfolio_peak_equity: peak,
+        initial_equity_usd: kernel.config().portfolio.initial_equity_usd,
+        current_equity,
+        portfolio_drawdown_pct: drawdown_pct(current_equity, peak),
+        sleeves: sleeves
+            .iter()
+            .map(|s| SleeveRiskStateLogEvent {
+                sleeve_id: s.sleeve_id,
+                equity_usd: s.equity_usd,
+                peak_equity_usd: s.peak_equity_usd,
+                drawdown_pct: drawdown_pct(s.equity_usd, s.peak_equity_usd),
+                open_positions: s.open_positions,
+            })
+            .collect(),
+    };
+    serde_json::to_string(&evt).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Convenience: JSON-string van alle sleeve-states.
+pub fn encode_sleeve_states_json(ts_utc: i64, states: &[SleeveState]) -> String {
+    let evt = SleeveStatesLogEvent {
+        ts_utc,
+        sleeves: states.to_vec(),
+    };
+    serde_json::to_string(&evt).unwrap_or_else(|_| "{}".to_string())
+}
+
+const RISK_REPORT_CSV_HEADER: &str = "instrument,target_contracts,risk_per_contract_eur,total_risk_eur";
+
+/// Fout bij het inlezen van een risk-report CSV. `line` is 1-based (header = regel 1).
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvParseError {
+    MissingHeader,
+    InvalidHeader(String),
+    WrongColumnCount { line: usize, found: usize },
+    UnknownInstrument { line: usize, value: String },
+    InvalidNumber { line: usize, column: &'static str, value: String },
+}
+
+impl std::fmt::Display for CsvParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CsvParseError::MissingHeader => write!(f, "missing CSV header"),
+            CsvParseError::InvalidHeader(h) => write!(f, "invalid CSV header: {}", h),
+            CsvParseError::WrongColumnCount { line, found } => {
+                write!(f, "line {}: expected 4 columns, found {}", line, found)
+            }
+            CsvParseError::UnknownInstrument { line, value } => {
+                write!(f, "line {}: unknown instrument {:?}", line, value)
+            }
+            CsvParseError::InvalidNumber { line, column, value } => {
+                write!(f, "line {}: invalid number {:?} in column {}", line, value, column)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CsvParseError {}
+
+/// Risk-report (uit `plan_risk_report`) als CSV voor Excel / risk-tooling.
+///
+/// Kolommen: `instrument,target_contracts,risk_per_contract_eur,total_risk_eur`.
+/// Floats worden met `{}` geschreven (shortest round-trip), dus `sleeve_plan_from_csv`
+/// geeft exact hetzelfde report terug.
+pub fn sleeve_plan_to_csv(report: &[FuturesPlannedRisk]) -> String {
+    let mut out = String::with_capacity(64 * (report.len() + 1));
+    out.push_str(RISK_REPORT_CSV_HEADER);
+    out.push('\n');
+
+    for r in report {
+        out.push_str(&format!(
+            "{:?},{},{},{}\n",
+            r.instrument,
+            r.target_contracts,
+            r.risk_per_contract_eur,
+            r.total_risk_eur
+        ));
+    }
+
+    out
+}
+
+/// Inverse van `sleeve_plan_to_csv`. Lege regels worden overgeslagen.
+pub fn sleeve_plan_from_csv(csv: &str) -> Result<Vec<FuturesPlannedRisk>, CsvParseError> {
+    let mut lines = csv.lines().enumerate();
+
+    let header = match lines.next() {
+        Some((_, h)) if !h.trim().is_empty() => h.trim(),
+        _ => return Err(CsvParseError::MissingHeader),
+    };
+    if header != RISK_REPORT_CSV_HEADER {
+        return Err(CsvParseError::InvalidHeader(header.to_string()));
+    }
+
+    let mut report = Vec::new();
+
+    for (idx, raw) in lines {
+        let line = idx + 1;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+
+        let cols: Vec<&str> = raw.split(',').map(str::trim).collect();
+        if cols.len() != 4 {
+            return Err(CsvParseError::WrongColumnCount { line, found: cols.len() });
+        }
+
+        let instrument = match cols[0] {
+            "Mes" => FutureInstrument::Mes,
+            "Mnq" => FutureInstrument::Mnq,
+            "SixE" => FutureInstrument::SixE,
+            "Gc" => FutureInstrument::Gc,
+            "Mcl" => FutureInstrument::Mcl,
+            "M2k" => FutureInstrument::M2k,
+            "Zn" => FutureInstrument::Zn,
+            "Vx" => FutureInstrument::Vx,
+            // Custom instrumenten zoals `{:?}` ze schrijft: "Custom(7)"
+            other => match other
+                .strip_prefix("Custom(")
+                .and_then(|rest| rest.strip_suffix(')'))
+                .and_then(|id| id.parse().ok())
+            {
+                Some(id) => FutureInstrument::Custom(id),
+                None => {
+                    return Err(CsvParseError::UnknownInstrument {
+                        line,
+                        value: other.to_string(),
+                    });
+                }
+            },
+        };
+
+        let invalid = |column: &'static str, value: &str| CsvParseError::InvalidNumber {
+            line,
+            column,
+            value: value.to_string(),
+        };
+
+        let target_contracts: i32 = cols[1]
+            .parse()
+            .map_err(|_| invalid("target_contracts", cols[1]))?;
+        let risk_per_contract_eur: f64 = cols[2]
+            .parse()
+            .map_err(|_| invalid("risk_per_contract_eur", cols[2]))?;
+        let total_risk_eur: f64 = cols[3]
+            .parse()
+            .map_err(|_| invalid("total_risk_eur", cols[3]))?;
+
+        report.push(FuturesPlannedRisk {
+            instrument,
+            target_contracts,
+            risk_per_contract_eur,
+            total_risk_eur,
+        });
+    }
+
+    Ok(report)
+}
+
+/// Per-instrument signal-samenvatting in de heartbeat-log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstrumentSignalSummary {
+    pub instrument: String,
+    pub direction: i8,
+    pub conviction: f64,
+    pub effective_score: f64,
+    pub reason: String,
+}
+
+impl InstrumentSignalSummary {
+    pub fn from_signal(sig: &InstrumentSignal) -> Self {
+        Self {
+            instrument: format!("{:?}", sig.instrument),
+            direction: sig.final_signal.direction,
+            conviction: sig.final_signal.conviction,
+            effective_score: sig.final_signal.effective_score,
+            reason: format!("{:?}", sig.reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct HeartbeatLogEvent {
     pub ts_utc: i64,
     pub sleeve_id: String,
@@ -177,19 +627,32 @@ pub struct HeartbeatLogEvent {
     pub max_position_size_usd: f64,
     pub exposure_remaining_usd: f64,
     pub margin_remaining_usd: f64,
+    pub margin_utilization_pct: f64,
 
     pub total_risk_eur: f64,
+    /// Netto long/short-bias van de sleeve in [-1, 1]
+    pub net_direction: f64,
     pub sanity: String,
 
+    /// Verandering t.o.v. de vorige heartbeat (None bij de eerste)
+    #[serde(default)]
+    pub delta_risk_eur: Option<f64>,
+    #[serde(default)]
+    pub delta_exposure_remaining_usd: Option<f64>,
+
+    pub instrument_signals: Vec<InstrumentSignalSummary>,
     pub orders: Vec<OrderLogEvent>,
 }
 
 
 impl HeartbeatLogEvent {
+    /// `previous` = het event van de vorige heartbeat, voor de delta-velden.
     pub fn from_engine_result(
         ts_utc: i64,
         result: &MacroFuturesEngineHeartbeatResult,
         health: EngineHealth,
+        margin: &MarginState,
+        previous: Option<&HeartbeatLogEvent>,
     ) -> Self {
         let sleeve_id = format!("{:?}", result.envelope.sleeve_id);
         let portfolio_risk_state = format!("{:?}", result.envelope.portfolio_risk_state);
@@ -198,10 +661,25 @@ impl HeartbeatLogEvent {
         let max_position_size_usd = result.envelope.max_position_size_usd;
         let exposure_remaining_usd = result.envelope.exposure_remaining_usd;
         let margin_remaining_usd = result.envelope.margin_remaining_usd;
+        let margin_utilization_pct = margin.margin_utilization_pct();
 
         let total_risk_eur = result.heartbeat.sleeve_plan.aggregate.total_risk_eur;
+        let net_direction = result.heartbeat.sleeve_plan.aggregate.net_direction();
         let sanity = format!("{:?}", result.heartbeat.sleeve_plan.sanity);
 
+        let delta_risk_eur = previous.map(|p| total_risk_eur - p.total_risk_eur);
+        let delta_exposure_remaining_usd =
+            previous.map(|p| exposure_remaining_usd - p.exposure_remaining_usd);
+
+        // Signals uit dezelfde heartbeat; gesorteerd voor stabiele log-regels
+        let mut instrument_signals: Vec<InstrumentSignalSummary> = result
+            .heartbeat
+            .signals
+            .iter()
+            .map(InstrumentSignalSummary::from_signal)
+            .collect();
+        instrument_signals.sort_by(|a, b| a.instrument.cmp(&b.instrument));
+
         let orders: Vec<OrderLogEvent> = result
             .engine_orders
             .iter()
@@ -216,11 +694,26 @@ impl HeartbeatLogEvent {
             max_position_size_usd,
             exposure_remaining_usd,
             margin_remaining_usd,
+            margin_utilization_pct,
             total_risk_eur,
+            net_direction,
             sanity,
+            delta_risk_eur,
+            delta_exposure_remaining_usd,
+            instrument_signals,
             orders,
         }
     }
+
+    /// Lees een heartbeat-log (`.jsonl`, één event per regel); lege regels worden overgeslagen.
+    pub fn from_jsonl_file(path: &Path) -> Result<Vec<HeartbeatLogEvent>, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut events = Vec::new();
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            events.push(decode_heartbeat_log_event_json(line)?);
+        }
+        Ok(events)
+    }
 }
 
 
@@ -229,17 +722,28 @@ pub fn encode_heartbeat_log_event_json(
     ts_utc: i64,
     result: &MacroFuturesEngineHeartbeatResult,
     health: EngineHealth,
+    margin: &MarginState,
+    previous: Option<&HeartbeatLogEvent>,
 ) -> String {
-    let evt = HeartbeatLogEvent::from_engine_result(ts_utc, result, health);
+    let evt = HeartbeatLogEvent::from_engine_result(ts_utc, result, health, margin, previous);
     serde_json::to_string(&evt).unwrap_or_else(|_| "{}".to_string())
 }
 
+/// Inverse van `encode_heartbeat_log_event_json` (playback / analyse van logs).
+pub fn decode_heartbeat_log_event_json(json: &str) -> Result<HeartbeatLogEvent, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
 
 /// Variant van de heartbeat-orchestrator met directe heartbeat-logging.
 ///
 /// - Roept `run_macro_futures_engine_heartbeat` aan met exact dezelfde args.
-/// - Encodeert het resultaat als JSON.
+/// - Encodeert het resultaat als JSON, met deltas t.o.v. `previous_event`.
 /// - Stuurt één regel naar de aangeleverde `HeartbeatLogSink`.
+/// - Zet `previous_event` op het event van deze heartbeat (voor de volgende call).
+/// - Geeft `previous_signals` door voor regime-change detectie en zet ze daarna op de
+///   signals van deze heartbeat.
+#[allow(clippy::too_many_arguments)]
 pub fn run_macro_futures_engine_heartbeat_with_logging(
     now_ts: i64,
     supervisor: &mut HeartbeatSupervisor,
@@ -257,16 +761,19 @@ pub fn run_macro_futures_engine_heartbeat_with_logging(
     max_sleeve_risk_eur: f64,
     sink: &mut impl OrderSink,
     heartbeat_log_sink: &mut impl HeartbeatLogSink,
+    previous_event: &mut Option<HeartbeatLogEvent>,
+    previous_signals: &mut Option<Vec<InstrumentSignal>>,
 ) -> MacroFuturesEngineHeartbeatResult {
     // 0) Supervisor-update op basis van deze tick
     supervisor.register_tick(now_ts);
 
-    if supervisor.health() == EngineHealth::Degraded {
+    if supervisor.health() != EngineHealth::Healthy {
         // Emergency event loggen vóór de normale heartbeat
         let sev = HeartbeatSupervisorEvent {
             ts_utc: now_ts,
             status: supervisor.health(),
             msg: "heartbeat_gap_detected",
+            consecutive_degraded: supervisor.consecutive_degraded_count(),
         };
         let sev_json = encode_supervisor_event_json(&sev);
         heartbeat_log_sink.log(&sev_json);
@@ -288,12 +795,22 @@ pub fn run_macro_futures_engine_heartbeat_with_logging(
         eur_per_usd,
         risk_budget,
         max_sleeve_risk_eur,
+        previous_signals.as_deref(),
         sink,
     );
 
     // 2) Encodeer als JSON en log één regel (normale heartbeat)
-    let json_line = encode_heartbeat_log_event_json(now_ts, &result, supervisor.health());
+    let evt = HeartbeatLogEvent::from_engine_result(
+        now_ts,
+        &result,
+        supervisor.health(),
+        margin,
+        previous_event.as_ref(),
+    );
+    let json_line = serde_json::to_string(&evt).unwrap_or_else(|_| "{}".to_string());
     heartbeat_log_sink.log(&json_line);
+    *previous_event = Some(evt);
+    *previous_signals = Some(result.heartbeat.signals.clone());
 
     result
 }
@@ -327,12 +844,98 @@ impl StdoutHeartbeatLogger {
     }
 }
 
+impl Default for StdoutHeartbeatLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Heartbeat-sink die metrics bijhoudt voor een Prometheus-scrape (`render_text`).
+///
+/// Elke `log()` decodeert de regel als `HeartbeatLogEvent`; regels die dat niet zijn
+/// (ongeldige JSON, supervisor-events) tellen niet mee. Gauges tonen de waarde uit de
+/// laatste heartbeat.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PrometheusMetricsAccumulator {
+    pub heartbeat_total: u64,
+    pub total_orders_submitted: u64,
+    pub total_risk_eur: f64,
+    pub position_size_usd_max: f64,
+    pub sleeve_sanity_ok: bool,
+}
+
+impl PrometheusMetricsAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prometheus text exposition format (v0.0.4).
+    pub fn render_text(&self) -> String {
+        let metrics: [(&str, &str, &str, String); 5] = [
+            (
+                "engine_heartbeat_total",
+                "counter",
+                "Aantal gelogde heartbeats",
+                self.heartbeat_total.to_string(),
+            ),
+            (
+                "engine_total_orders_submitted",
+                "counter",
+                "Aantal orders over alle heartbeats",
+                self.total_orders_submitted.to_string(),
+            ),
+            (
+                "engine_total_risk_eur",
+                "gauge",
+                "Totale sleeve-risk in EUR (laatste heartbeat)",
+                self.total_risk_eur.to_string(),
+            ),
+            (
+                "engine_position_size_usd_max",
+                "gauge",
+                "Max. positie-size in USD uit de risk-envelope (laatste heartbeat)",
+                self.position_size_usd_max.to_string(),
+            ),
+            (
+                "engine_sleeve_sanity_ok",
+                "gauge",
+                "1 als de sleeve-risk sanity Ok is, anders 0",
+                u8::from(self.sleeve_sanity_ok).to_string(),
+            ),
+        ];
+
+        let mut out = String::new();
+        for (name, kind, help, value) in metrics {
+            out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value));
+        }
+        out
+    }
+}
+
+impl HeartbeatLogSink for PrometheusMetricsAccumulator {
+    fn log(&mut self, line: &str) {
+        let Ok(evt) = decode_heartbeat_log_event_json(line) else {
+            return;
+        };
+
+        self.heartbeat_total += 1;
+        self.total_orders_submitted += evt.orders.len() as u64;
+        self.total_risk_eur = evt.total_risk_eur;
+        self.position_size_usd_max = evt.max_position_size_usd;
+        self.sleeve_sanity_ok = evt.sanity == "Ok";
+    }
+}
+
 /// Batching sink: buffert N heartbeat JSON-lines en schrijft
 /// ze pas door naar een onderliggende HeartbeatLogSink bij flush().
 pub struct BatchingHeartbeatLogger {
     inner: Box<dyn HeartbeatLogSink>,
     buffer: Vec<String>,
     capacity: usize,
+    /// Some → ook flushen als het oudste gebufferde item zo oud is (zie `log_with_ts`)
+    max_age_secs: Option<u64>,
+    /// ts van het oudste item in de buffer (alleen gezet via `log_with_ts`)
+    first_item_ts: Option<i64>,
 }
 
 impl BatchingHeartbeatLogger {
@@ -343,6 +946,30 @@ impl BatchingHeartbeatLogger {
             inner,
             buffer: Vec::with_capacity(capacity),
             capacity,
+            max_age_secs: None,
+            first_item_ts: None,
+        }
+    }
+
+    /// Als `new`, maar `log_with_ts` flusht ook zodra het oudste gebufferde item
+    /// `max_age_secs` of ouder is, zodat een trage heartbeat niet minutenlang in memory blijft.
+    pub fn with_time_flush(inner: Box<dyn HeartbeatLogSink>, capacity: usize, max_age_secs: u64) -> Self {
+        Self {
+            max_age_secs: Some(max_age_secs),
+            ..Self::new(inner, capacity)
+        }
+    }
+
+    /// `log` met het huidige tijdstip (Unix ts, UTC seconden) voor de time-based flush.
+    pub fn log_with_ts(&mut self, line: &str, now_ts: i64) {
+        self.first_item_ts.get_or_insert(now_ts);
+        self.log(line);
+
+        if let (Some(max_age), Some(first_ts)) = (self.max_age_secs, self.first_item_ts)
+            && !self.buffer.is_empty()
+            && now_ts.saturating_sub(first_ts) >= max_age as i64
+        {
+            self.flush_inner();
         }
     }
 
@@ -355,6 +982,7 @@ impl BatchingHeartbeatLogger {
         for line in self.buffer.drain(..) {
             self.inner.log(&line);
         }
+        self.first_item_ts = None;
         self.inner.flush();
     }
 
@@ -418,27 +1046,140 @@ impl<W: Write> HeartbeatLogSink for StdoutHeartbeatLogger<W> {
 #[derive(Debug)]
 pub struct FileOrderSink {
     path: PathBuf,
+    /// Some(capaciteit) → gebufferde modus: file blijft open tussen submits.
+    buffer_size: Option<usize>,
+    writer: Option<BufWriter<File>>,
+    /// Some → dagelijkse rotatie: `path` = dir/pattern met `{date}` = YYYYMMDD (UTC)
+    rotation: Option<FileRotation>,
+}
+
+#[derive(Debug)]
+struct FileRotation {
+    dir: PathBuf,
+    filename_pattern: String,
+    current_date: Option<NaiveDate>,
 }
 
 impl FileOrderSink {
     pub fn new<P: Into<PathBuf>>(path: P) -> Self {
         Self {
             path: path.into(),
+            buffer_size: None,
+            writer: None,
+            rotation: None,
         }
     }
-}
 
-impl OrderSink for FileOrderSink {
-    fn submit(&mut self, order: &EngineOrder) {
-        let ts = Utc::now().timestamp();
-        let line = encode_order_log_event_json(order, ts);
+    /// Dagelijks roterende variant: één file per UTC-dag in `dir`, met de naam uit
+    /// `filename_pattern` waarin `{date}` vervangen wordt door YYYYMMDD
+    /// (bijv. `"orders-{date}.jsonl"`). Fout als `{date}` ontbreekt.
+    pub fn with_rotation(dir: &Path, filename_pattern: &str) -> io::Result<Self> {
+        if !filename_pattern.contains("{date}") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("FileOrderSink: filename pattern {:?} must contain {{date}}", filename_pattern),
+            ));
+        }
+
+        Ok(Self {
+            path: dir.to_path_buf(),
+            buffer_size: None,
+            writer: None,
+            rotation: Some(FileRotation {
+                dir: dir.to_path_buf(),
+                filename_pattern: filename_pattern.to_string(),
+                current_date: None,
+            }),
+        })
+    }
+
+    /// Huidig doel-pad (bij rotatie: de file van de laatst gelogde dag).
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Test-helper: submit met een geforceerd tijdstip i.p.v. `Utc::now()`
+    /// (bepaalt `ts_utc` in de log-regel en, bij rotatie, de file).
+    pub fn submit_with_datetime(&mut self, dt: DateTime<Utc>, order: &EngineOrder) {
+        self.rotate_if_needed(dt.date_naive());
+        self.write_line(&encode_order_log_event_json(order, dt.timestamp()));
+    }
+
+    /// Nieuwe dag → open writer flushen/sluiten en naar de file van die dag wisselen.
+    fn rotate_if_needed(&mut self, date: NaiveDate) {
+        let Some(rotation) = &mut self.rotation else {
+            return;
+        };
+        if rotation.current_date == Some(date) {
+            return;
+        }
+
+        rotation.current_date = Some(date);
+        let fname = rotation
+            .filename_pattern
+            .replace("{date}", &date.format("%Y%m%d").to_string());
+        self.path = rotation.dir.join(fname);
 
-        let file_result = OpenOptions::new()
+        OrderSink::flush(self);
+        self.writer = None;
+    }
+
+    /// Gebufferde variant: houdt de file open en schrijft via een `BufWriter`.
+    ///
+    /// Data wordt weggeschreven bij `flush()`, als de buffer vol is, of bij drop.
+    pub fn with_buffer<P: Into<PathBuf>>(path: P, buffer_size_bytes: usize) -> Self {
+        Self {
+            path: path.into(),
+            buffer_size: Some(buffer_size_bytes),
+            writer: None,
+            rotation: None,
+        }
+    }
+
+    fn open_append(&self) -> io::Result<File> {
+        OpenOptions::new()
             .create(true)
             .append(true)
-            .open(&self.path);
+            .open(&self.path)
+    }
 
-        match file_result {
+    /// Lazy open van de gebufferde writer.
+    fn buffered_writer(&mut self, capacity: usize) -> Option<&mut BufWriter<File>> {
+        if self.writer.is_none() {
+            match self.open_append() {
+                Ok(file) => self.writer = Some(BufWriter::with_capacity(capacity, file)),
+                Err(e) => {
+                    debug_assert!(
+                        false,
+                        "FileOrderSink: failed to open log file {:?}: {:?}",
+                        self.path,
+                        e
+                    );
+                    return None;
+                }
+            }
+        }
+
+        self.writer.as_mut()
+    }
+}
+
+impl FileOrderSink {
+    fn write_line(&mut self, line: &str) {
+        if let Some(capacity) = self.buffer_size {
+            if let Some(writer) = self.buffered_writer(capacity)
+                && let Err(e) = writeln!(writer, "{}", line)
+            {
+                debug_assert!(
+                    false,
+                    "FileOrderSink: failed to write to buffered log file: {:?}",
+                    e
+                );
+            }
+            return;
+        }
+
+        match self.open_append() {
             Ok(mut file) => {
                 if let Err(e) = writeln!(file, "{}", line) {
                     debug_assert!(
@@ -460,17 +1201,313 @@ impl OrderSink for FileOrderSink {
     }
 }
 
+impl OrderSink for FileOrderSink {
+    fn submit(&mut self, order: &EngineOrder) {
+        self.submit_with_datetime(Utc::now(), order);
+    }
+
+    fn flush(&mut self) {
+        if let Some(writer) = &mut self.writer
+            && let Err(e) = writer.flush()
+        {
+            debug_assert!(false, "FileOrderSink: failed to flush log file: {:?}", e);
+        }
+    }
+}
+
 
 impl OrderSink for InMemoryOrderSink {
     fn submit(&mut self, order: &EngineOrder) {
-        self.orders.push(order.clone());
+        self.orders.push((order.clone(), OrderStatus::Pending));
+    }
+}
+
+/// Eén TWAP-order in uitvoering: het originele order plus het resterende schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwapSlice {
+    pub order: EngineOrder,
+    /// Nog niet geëmitteerde contracts
+    pub remaining_quantity: i32,
+    /// Child-size: floor(total / slices), min. 1; de laatste slice neemt de rest
+    pub slice_quantity: i32,
+    pub slices_remaining: u32,
+    /// Unix ts (UTC seconden) vanaf wanneer de volgende child-order mag
+    pub next_execution_ts: i64,
+    pub interval_seconds: i64,
+}
+
+/// TWAP-wrapper: `submit` plant een order in als `slices` child-orders met `interval_seconds`
+/// ertussen; `drain_due_slices` stuurt de child-orders die aan de beurt zijn naar de inner sink.
+///
+/// De engine is synchroon, dus de caller roept `drain_due_slices` periodiek aan (bijv. op elke
+/// heartbeat). Een net ingeplande order is bij de eerstvolgende drain direct aan de beurt.
+/// Child-orders krijgen metadata `twap_slice` = "k/N".
+#[derive(Debug)]
+pub struct TwapOrderSink<S: OrderSink> {
+    slices: u32,
+    interval_seconds: i64,
+    pending: Vec<TwapSlice>,
+    /// ts van de laatste drain (i64::MIN vóór de eerste)
+    last_drain_ts: i64,
+    _inner: PhantomData<fn(&mut S)>,
+}
+
+impl<S: OrderSink> TwapOrderSink<S> {
+    pub fn new(slices: u32, interval_seconds: i64) -> Self {
+        assert!(slices > 0, "TwapOrderSink: slices must be > 0");
+        Self {
+            slices,
+            interval_seconds: interval_seconds.max(0),
+            pending: Vec::new(),
+            last_drain_ts: i64::MIN,
+            _inner: PhantomData,
+        }
+    }
+
+    pub fn pending(&self) -> &[TwapSlice] {
+        &self.pending
+    }
+
+    /// Emit per ingeplande order hoogstens één child-order als die aan de beurt is
+    /// (`now_ts >= next_execution_ts`). Afgeronde orders verdwijnen uit `pending`.
+    /// Geeft het aantal geëmitteerde child-orders terug.
+    pub fn drain_due_slices(&mut self, now_ts: i64, inner: &mut S) -> usize {
+        self.last_drain_ts = now_ts;
+        let mut emitted = 0;
+
+        for slice in self.pending.iter_mut() {
+            if now_ts < slice.next_execution_ts || slice.remaining_quantity <= 0 {
+                continue;
+            }
+
+            let quantity = if slice.slices_remaining <= 1 {
+                slice.remaining_quantity
+            } else {
+                slice.slice_quantity.min(slice.remaining_quantity)
+            };
+
+            let mut child = slice.order.clone();
+            child.quantity = quantity;
+            child.metadata.insert(
+                "twap_slice".to_string(),
+                format!("{}/{}", self.slices - slice.slices_remaining + 1, self.slices),
+            );
+            inner.submit(&child);
+            emitted += 1;
+
+            slice.remaining_quantity -= quantity;
+            slice.slices_remaining = slice.slices_remaining.saturating_sub(1);
+            slice.next_execution_ts = now_ts.saturating_add(slice.interval_seconds);
+        }
+
+        self.pending.retain(|s| s.remaining_quantity > 0);
+        emitted
+    }
+}
+
+impl<S: OrderSink> OrderSink for TwapOrderSink<S> {
+    fn submit(&mut self, order: &EngineOrder) {
+        if order.quantity <= 0 {
+            return;
+        }
+
+        self.pending.push(TwapSlice {
+            order: order.clone(),
+            remaining_quantity: order.quantity,
+            slice_quantity: (order.quantity / self.slices as i32).max(1),
+            slices_remaining: self.slices,
+            next_execution_ts: self.last_drain_ts,
+            interval_seconds: self.interval_seconds,
+        });
+    }
+}
+
+/// Submit met een expliciet tijdstip (Unix ts, UTC seconden), voor sinks die
+/// tijd-afhankelijk zijn en in tests/backtests niet op `Utc::now()` mogen leunen.
+pub trait SubmitWithTs {
+    fn submit_with_ts(&mut self, order: &EngineOrder, now_ts: i64);
+}
+
+/// Rate-limiter tegen order-storms: max. `max_per_interval` orders per `interval_secs`;
+/// alles daarboven wordt gedropt (in debug met een melding op stderr).
+/// Het interval reset zodra `now_ts - interval_start_ts >= interval_secs`.
+#[derive(Debug)]
+pub struct RateLimitedOrderSink<S: OrderSink> {
+    pub inner: S,
+    max_per_interval: u32,
+    interval_secs: i64,
+    submitted_in_interval: u32,
+    interval_start_ts: i64,
+    dropped: u64,
+}
+
+impl<S: OrderSink> RateLimitedOrderSink<S> {
+    pub fn new(inner: S, max_per_interval: u32, interval_secs: i64) -> Self {
+        Self {
+            inner,
+            max_per_interval,
+            interval_secs: interval_secs.max(1),
+            submitted_in_interval: 0,
+            interval_start_ts: i64::MIN,
+            dropped: 0,
+        }
+    }
+
+    /// Totaal aantal gedropte orders sinds start.
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: OrderSink> SubmitWithTs for RateLimitedOrderSink<S> {
+    fn submit_with_ts(&mut self, order: &EngineOrder, now_ts: i64) {
+        if now_ts.saturating_sub(self.interval_start_ts) >= self.interval_secs {
+            self.interval_start_ts = now_ts;
+            self.submitted_in_interval = 0;
+        }
+
+        if self.submitted_in_interval >= self.max_per_interval {
+            self.dropped += 1;
+            if cfg!(debug_assertions) {
+                eprintln!(
+                    "RateLimitedOrderSink: dropped {:?} {} x{} ({} per {}s reached)",
+                    order.side, order.symbol, order.quantity, self.max_per_interval, self.interval_secs
+                );
+            }
+            return;
+        }
+
+        self.submitted_in_interval += 1;
+        self.inner.submit(order);
+    }
+}
+
+impl<S: OrderSink> OrderSink for RateLimitedOrderSink<S> {
+    fn submit(&mut self, order: &EngineOrder) {
+        self.submit_with_ts(order, Utc::now().timestamp());
     }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+}
+
+/// Fan-out sink: elke order en flush gaat in volgorde naar alle inner sinks
+/// (bijv. `InMemoryOrderSink` voor debugging + `FileOrderSink` als audit-log).
+///
+/// Panict een inner sink, dan worden de overige sinks nog steeds aangeroepen; daarna
+/// wordt de eerste panic opnieuw opgegooid, zodat een kapotte sink niet stil verdwijnt.
+pub struct CompositeOrderSink {
+    pub sinks: Vec<Box<dyn OrderSink>>,
+}
+
+impl CompositeOrderSink {
+    pub fn new(sinks: Vec<Box<dyn OrderSink>>) -> Self {
+        Self { sinks }
+    }
+
+    fn for_each_sink(&mut self, mut f: impl FnMut(&mut dyn OrderSink)) {
+        let mut first_panic = None;
+
+        for sink in self.sinks.iter_mut() {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(sink.as_mut())));
+            if let Err(payload) = result {
+                first_panic.get_or_insert(payload);
+            }
+        }
+
+        if let Some(payload) = first_panic {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+impl OrderSink for CompositeOrderSink {
+    fn submit(&mut self, order: &EngineOrder) {
+        self.for_each_sink(|sink| sink.submit(order));
+    }
+
+    fn flush(&mut self) {
+        self.for_each_sink(|sink| sink.flush());
+    }
+}
+
+/// Dry-run sink voor backtests/CI: voert niets uit, maar verzamelt per order alle
+/// veld-fouten in `violations` (geen panic, zodat een hele batch gecheckt wordt).
+#[derive(Debug, Default)]
+pub struct ValidatingOrderSink {
+    pub expected_sleeves: Vec<SleeveId>,
+    pub violations: Vec<String>,
+    submitted: usize,
+}
+
+impl ValidatingOrderSink {
+    pub fn new(expected_sleeves: Vec<SleeveId>) -> Self {
+        Self {
+            expected_sleeves,
+            violations: Vec::new(),
+            submitted: 0,
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Panict met een samenvatting van alle violations (no-op als alles klopt).
+    pub fn assert_clean(&self) {
+        if !self.is_clean() {
+            panic!(
+                "ValidatingOrderSink: {} violation(s) in {} order(s):\n  {}",
+                self.violations.len(),
+                self.submitted,
+                self.violations.join("\n  ")
+            );
+        }
+    }
+}
+
+impl OrderSink for ValidatingOrderSink {
+    fn submit(&mut self, order: &EngineOrder) {
+        let idx = self.submitted;
+        self.submitted += 1;
+
+        let label = format!("order #{} ({:?} {})", idx, order.side, order.symbol);
+        if order.quantity <= 0 {
+            self.violations
+                .push(format!("{}: quantity must be > 0, got {}", label, order.quantity));
+        }
+        if order.symbol.is_empty() {
+            self.violations.push(format!("{}: empty symbol", label));
+        }
+        if order.venue.is_empty() {
+            self.violations.push(format!("{}: empty venue", label));
+        }
+        if !self.expected_sleeves.contains(&order.sleeve_id) {
+            self.violations.push(format!(
+                "{}: unexpected sleeve_id {:?} (expected one of {:?})",
+                label, order.sleeve_id, self.expected_sleeves
+            ));
+        }
+    }
+
+    fn flush(&mut self) {}
 }
 
 pub struct FileHeartbeatLogger {
     log_dir: PathBuf,
     current_date: Option<(i32, u32, u32)>,
     file: Option<File>,
+    /// Some → ook roteren op grootte: heartbeat-YYYYMMDD-NNN.jsonl
+    max_bytes: Option<u64>,
+    /// Volgnummer binnen de dag (alleen bij size-rotatie, start op 1)
+    seq: u32,
+    /// Bijgehouden grootte van de huidige file (geen `metadata()` per write)
+    current_size: u64,
 }
 
 impl FileHeartbeatLogger {
@@ -479,21 +1516,42 @@ impl FileHeartbeatLogger {
             log_dir: log_dir.as_ref().to_path_buf(),
             current_date: None,
             file: None,
+            max_bytes: None,
+            seq: 0,
+            current_size: 0,
+        }
+    }
+
+    /// Roteert naast per dag ook zodra de huidige file groter is dan `max_bytes`:
+    /// `heartbeat-20250117-001.jsonl`, `heartbeat-20250117-002.jsonl`, ...
+    pub fn with_size_rotation(dir: &Path, max_bytes: u64) -> Self {
+        Self {
+            max_bytes: Some(max_bytes),
+            ..Self::new(dir)
         }
     }
 
     fn get_file_for_date(&mut self, year: i32, month: u32, day: u32) -> &mut File {
         let date_tuple = (year, month, day);
 
-        let needs_new_file = match self.current_date {
+        let new_day = match self.current_date {
             None => true,
             Some(prev) => prev != date_tuple,
         };
+        let size_exceeded = self
+            .max_bytes
+            .is_some_and(|max| self.file.is_some() && self.current_size >= max);
 
-        if needs_new_file {
+        if new_day || size_exceeded {
             self.current_date = Some(date_tuple);
 
-            let fname = format!("heartbeat-{:04}{:02}{:02}.jsonl", year, month, day);
+            let fname = match self.max_bytes {
+                None => format!("heartbeat-{:04}{:02}{:02}.jsonl", year, month, day),
+                Some(_) => {
+                    self.seq = if new_day { 1 } else { self.seq + 1 };
+                    format!("heartbeat-{:04}{:02}{:02}-{:03}.jsonl", year, month, day, self.seq)
+                }
+            };
             let fpath = self.log_dir.join(fname);
 
             let f = OpenOptions::new()
@@ -502,11 +1560,20 @@ impl FileHeartbeatLogger {
                 .open(&fpath)
                 .expect("FileHeartbeatLogger: cannot open log file");
 
+            // Eenmalig bij openen: bestaande inhoud (bijv. na een restart) telt mee
+            self.current_size = f.metadata().map(|m| m.len()).unwrap_or(0);
             self.file = Some(f);
         }
 
         self.file.as_mut().unwrap()
     }
+
+    fn write_line(&mut self, year: i32, month: u32, day: u32, line: &str) -> io::Result<()> {
+        let file = self.get_file_for_date(year, month, day);
+        writeln!(file, "{}", line)?;
+        self.current_size += line.len() as u64 + 1;
+        Ok(())
+    }
 }
 
 impl HeartbeatLogSink for FileHeartbeatLogger {
@@ -516,9 +1583,7 @@ impl HeartbeatLogSink for FileHeartbeatLogger {
         let m = now.month();
         let d = now.day();
 
-        let file = self.get_file_for_date(y, m, d);
-
-        let _ = writeln!(file, "{}", line);
+        let _ = self.write_line(y, m, d, line);
     }
 
     fn flush(&mut self) {
@@ -535,9 +1600,7 @@ impl FileHeartbeatLogger {
         let m = dt.month();
         let d = dt.day();
 
-        let file = self.get_file_for_date(y, m, d);
-
-        if let Err(e) = writeln!(file, "{}", line) {
+        if let Err(e) = self.write_line(y, m, d, line) {
             debug_assert!(false, "FileHeartbeatLogger: write_with_datetime failed {:?}", e);
         }
     }
@@ -545,6 +1608,8 @@ impl FileHeartbeatLogger {
 
 impl HeartbeatSupervisor {
     pub fn register_tick(&mut self, ts_utc: i64) {
+        self.total_ticks += 1;
+
         match self.last_tick_ts {
             None => {
                 // eerste tick ooit
@@ -553,27 +1618,77 @@ impl HeartbeatSupervisor {
             }
             Some(prev) => {
                 let gap = ts_utc - prev;
+                self.sum_of_gaps += gap;
+                self.max_gap_observed = self.max_gap_observed.max(gap);
+
                 if gap > self.max_gap_seconds {
-                    self.health = EngineHealth::Degraded;
+                    if self.health == EngineHealth::Healthy {
+                        self.degraded_periods += 1;
+                    }
+                    self.consecutive_degraded += 1;
+                    self.gap_since_healthy += gap;
+                    self.health = if self.critical_threshold > 0
+                        && self.consecutive_degraded >= self.critical_threshold
+                    {
+                        EngineHealth::Critical
+                    } else {
+                        EngineHealth::Degraded
+                    };
                 } else {
                     self.health = EngineHealth::Healthy;
+                    self.consecutive_degraded = 0;
+                    self.gap_since_healthy = 0;
                 }
                 self.last_tick_ts = Some(ts_utc);
             }
         }
     }
 
-    pub fn new(max_gap_seconds: i64) -> Self {
+    pub fn new(max_gap_seconds: i64, critical_threshold: u32) -> Self {
         Self {
             last_tick_ts: None,
             max_gap_seconds,
+            critical_threshold,
             health: EngineHealth::Healthy,
+            total_ticks: 0,
+            sum_of_gaps: 0,
+            max_gap_observed: 0,
+            degraded_periods: 0,
+            consecutive_degraded: 0,
+            gap_since_healthy: 0,
         }
     }
 
     pub fn health(&self) -> EngineHealth {
         self.health
     }
+
+    /// Aantal opeenvolgende ticks met gap > max_gap_seconds; 0 na een healthy tick.
+    pub fn consecutive_degraded_count(&self) -> u32 {
+        self.consecutive_degraded
+    }
+
+    /// Cumulatieve gap (seconden) van de ticks sinds de laatste healthy tick.
+    pub fn total_gap_seconds(&self) -> i64 {
+        self.gap_since_healthy
+    }
+
+    pub fn statistics(&self) -> SupervisorStats {
+        let n_gaps = self.total_ticks.saturating_sub(1);
+        let avg_gap_seconds = if n_gaps > 0 {
+            self.sum_of_gaps as f64 / n_gaps as f64
+        } else {
+            0.0
+        };
+
+        SupervisorStats {
+            total_ticks: self.total_ticks,
+            avg_gap_seconds,
+            max_gap_seconds: self.max_gap_observed,
+            degraded_periods: self.degraded_periods,
+            current_health: self.health,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -581,13 +1696,15 @@ pub struct HeartbeatSupervisorEvent {
     pub ts_utc: i64,
     pub status: EngineHealth,
     pub msg: &'static str,
+    pub consecutive_degraded: u32,
 }
 
 pub fn encode_supervisor_event_json(ev: &HeartbeatSupervisorEvent) -> String {
     format!(
-        "{{\"ts_utc\":{},\"status\":\"{:?}\",\"msg\":\"{}\"}}",
+        "{{\"ts_utc\":{},\"status\":\"{:?}\",\"msg\":\"{}\",\"consecutive_degraded\":{}}}",
         ev.ts_utc,
         ev.status,
-        ev.msg
+        ev.msg,
+        ev.consecutive_degraded
     )
 }
@@ -1,6 +1,10 @@
 // risk_kernel.rs
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SleeveId {
     EquityLongShort,
     StatArbResidual,
@@ -14,11 +18,13 @@ pub enum PortfolioRiskState {
     Normal,
     Caution,
     Stress,
+    Recovery, // net uit Halt/Kill: halve size & concurrency tot DD > halt + recovery_buffer
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HaltState {
     None,
+    SoftHalt, // geen nieuwe posities, bestaande terug naar 50%
     Halt, // geen nieuwe trades, bestaande mogen volgens rules uitlopen
     Kill, // alles liquideren, geen nieuwe trades
 }
@@ -31,8 +37,10 @@ pub struct SleeveRiskConfig {
     pub capital_alloc_usd: f64,        // bij start: 2000, 2500, etc.
     pub max_single_pos_risk_frac: f64, // bijv. 0.01 = 1% van sleeve
     pub halt_dd_frac: f64,             // bijv. -0.10
+    pub soft_halt_dd_frac: f64,        // bijv. -0.05, tussen halt_dd_frac en 0.0 (= halt_dd_frac: uit)
     pub kill_dd_frac: f64,             // bijv. -0.15
     pub max_concurrent_positions: u32, // bij options/futures = spreads/contracts
+    pub max_position_size_usd_absolute: Option<f64>, // harde USD-cap per positie, los van de frac
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -43,11 +51,13 @@ pub struct PortfolioRiskConfig {
     pub max_leverage: f64,         // 1.5
     pub rebalance_drift_frac: f64, // 0.15 (±15% threshold)
     pub max_global_positions: u32, // 15
+    pub max_daily_loss_usd: f64,   // 500 → intraday circuit breaker (Kill)
+    pub recovery_buffer_frac: f64, // 0.02 → Recovery tot DD > halt_dd_frac + 2%
 }
 
 // ====== State snapshots ======
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct SleeveState {
     pub sleeve_id: SleeveId,
     pub equity_usd: f64,          // huidige waarde van de sleeve
@@ -57,7 +67,7 @@ pub struct SleeveState {
     pub open_positions: u32,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct PortfolioState {
     pub cash_usd: f64,
     pub open_pnl_usd: f64,
@@ -67,14 +77,37 @@ pub struct PortfolioState {
     pub current_leverage: f64,        // exposure / equity
 }
 
-#[derive(Debug, Clone, Copy)]
+impl PortfolioState {
+    /// Netto equity: cash + open PnL + opgebouwde rente.
+    pub fn net_equity(&self) -> f64 {
+        self.cash_usd + self.open_pnl_usd + self.accrued_interest_usd
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct MarginState {
     pub internal_margin_req_usd: f64, // eigen model
     pub broker_margin_req_usd: f64,   // IBKR real-time (indien beschikbaar)
     pub equity_usd: f64,              // redundante check
 }
 
-#[derive(Debug, Clone, Copy)]
+impl MarginState {
+    /// Broker-margin als percentage van equity, geclampt op 0..100.
+    /// Equity <= 0 (of NaN) telt als volledig benut.
+    pub fn margin_utilization_pct(&self) -> f64 {
+        if self.equity_usd.is_nan() || self.equity_usd <= 0.0 || !self.broker_margin_req_usd.is_finite() {
+            return 100.0;
+        }
+        (self.broker_margin_req_usd / self.equity_usd * 100.0).clamp(0.0, 100.0)
+    }
+
+    /// True zodra de margin-benutting de drempel (in %) bereikt.
+    pub fn is_margin_constrained(&self, threshold_pct: f64) -> bool {
+        self.margin_utilization_pct() >= threshold_pct
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct VolatilityRegime {
     pub rv10_annualized: f64, // realized vol
     pub vix_level: f64,
@@ -84,7 +117,7 @@ pub struct VolatilityRegime {
 
 // ====== Kernel output per sleeve ======
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct SleeveRiskEnvelope {
     pub sleeve_id: SleeveId,
 
@@ -108,8 +141,31 @@ pub struct SleeveRiskEnvelope {
     pub portfolio_risk_state: PortfolioRiskState,
 }
 
+impl SleeveRiskEnvelope {
+    /// Geen Halt/Kill op portfolio- of sleeve-niveau en positieve size & concurrency.
+    /// `SoftHalt` blokkeert hier niet: de envelope laat dan nog (afbouw-)trades toe.
+    pub fn is_tradeable(&self) -> bool {
+        self.reason_not_tradeable().is_none()
+    }
+
+    /// Eerste reden waarom de envelope niet tradeable is: eerst de halts, dan de limieten.
+    pub fn reason_not_tradeable(&self) -> Option<RiskDecisionReason> {
+        if matches!(self.portfolio_halt, HaltState::Halt | HaltState::Kill) {
+            Some(RiskDecisionReason::PortfolioHalt)
+        } else if matches!(self.sleeve_halt, HaltState::Halt | HaltState::Kill) {
+            Some(RiskDecisionReason::SleeveHalt)
+        } else if self.max_position_size_usd <= 0.0 {
+            Some(RiskDecisionReason::PositionSizeZero)
+        } else if self.max_concurrent_positions == 0 {
+            Some(RiskDecisionReason::ConcurrencyLimit)
+        } else {
+            None
+        }
+    }
+}
+
 // risk decision layer
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RiskDecisionReason {
     Ok,
     PortfolioHalt,
@@ -129,11 +185,28 @@ pub struct RiskDecision {
 }
 
 
+/// Geaggregeerde portfolio-risk metrics (naast de per-sleeve envelopes).
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PortfolioRiskSummary {
+    /// Confidence-level van de VaR (bijv. 0.95)
+    pub confidence: f64,
+    pub total_sleeve_equity_usd: f64,
+    /// Geschatte dagelijkse vol als fractie van equity
+    pub daily_vol_frac: f64,
+    /// 1-daagse parametrische VaR in USD (positief getal = verlies)
+    pub portfolio_var_usd: f64,
+}
+
 // ====== Kernel config & struct ======
 
+#[derive(Debug, Clone)]
 pub struct GlobalRiskKernelConfig {
     pub portfolio: PortfolioRiskConfig,
     pub sleeves: Vec<SleeveRiskConfig>,
+    // true → evaluate gebruikt de interne rolling vol (zie push_daily_return) i.p.v.
+    // vol.rv10_annualized, zodra het window vol is
+    pub use_internal_vol_estimate: bool,
+    pub vol_window: usize, // aantal daily returns voor de interne vol, bijv. 20
 }
 
 pub struct GlobalRiskKernel {
@@ -141,7 +214,65 @@ pub struct GlobalRiskKernel {
 
     // interne HWM voor portfolio DD (closed-end, met 20% cashflow-reset-regel)
     pub internal_portfolio_peak_equity: f64,
-    
+
+    // equity bij de eerste evaluate van de huidige UTC-dag (daily loss breaker)
+    pub today_start_equity: f64,
+    current_day: Option<i64>,
+
+    // open posities per sleeve uit de laatste evaluate (voor evaluate_incremental)
+    last_open_positions: Vec<(SleeveId, u32)>,
+
+    // rolling (ts, daily_return) voor de interne vol-schatting, max vol_window entries
+    daily_returns: VecDeque<(i64, f64)>,
+
+    // vol-regime van de laatste evaluate (na een eventuele interne override), voor de VaR
+    last_vol: Option<VolatilityRegime>,
+
+    // waar het portfolio zit in de Halt/Kill → Recovery → Normal overgang
+    recovery_phase: RecoveryPhase,
+
+    // hoogste bekende sleeve-HWM per sleeve; overleeft een restart via `KernelSnapshot`
+    sleeve_peaks: Vec<(SleeveId, f64)>,
+}
+
+/// Persistente kernel-state voor crash recovery (als JSON naar disk).
+/// Alles wat nodig is om DD-tracking en de daily-loss breaker na een restart voort te zetten.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KernelSnapshot {
+    pub internal_portfolio_peak_equity: f64,
+    pub today_start_equity: f64,
+    pub current_day: Option<i64>,
+    pub sleeve_peaks: Vec<(SleeveId, f64)>,
+    pub daily_returns: Vec<(i64, f64)>,
+    /// Ontbreekt in oudere snapshots → `None` (geen lopende recovery)
+    #[serde(default)]
+    pub recovery_phase: RecoveryPhase,
+    /// Vol-regime van de laatste evaluate (voor `estimate_portfolio_var_usd`)
+    #[serde(default)]
+    pub last_vol: Option<VolatilityRegime>,
+}
+
+/// Fase van de re-entry na een portfolio-halt (zie `PortfolioRiskState::Recovery`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RecoveryPhase {
+    /// Geen halt gezien sinds de laatste volledige recovery
+    #[default]
+    None,
+    /// Portfolio staat (of stond bij de laatste evaluate) op Halt/Kill
+    Halted,
+    /// Uit de halt, maar DD nog niet boven halt_dd_frac + recovery_buffer_frac
+    Recovering,
+}
+
+/// Portfolio-brede uitkomst van stap 1–3 van een evaluate-tick, gedeeld door alle sleeves.
+#[derive(Clone, Copy)]
+struct PortfolioLevel {
+    portfolio_halt_state: HaltState,
+    portfolio_risk_state: PortfolioRiskState,
+    exposure_remaining_usd: f64,
+    margin_remaining_usd: f64,
+    volatility_regime_scalar: f64,
+    leverage_scalar: f64,
 }
 
 fn derive_volatility_scalar(vol: &VolatilityRegime) -> f64 {
@@ -151,23 +282,83 @@ fn derive_volatility_scalar(vol: &VolatilityRegime) -> f64 {
 
     // 1) STRESS regime
     if vix >= 35.0 || rv >= 30.0 || slope < 0.0 {
-        return 0.55_f64.max(0.5).min(1.3);
+        return 0.55_f64.clamp(0.5, 1.3);
     }
 
     // 2) ELEVATED regime
     if vix >= 25.0 || rv >= 20.0 {
-        return 0.80_f64.max(0.5).min(1.3);
+        return 0.80_f64.clamp(0.5, 1.3);
     }
 
     // 3) LOW VOL regime
     if vix < 15.0 && rv < 12.0 && slope > 0.5 {
-        return 1.25_f64.max(0.5).min(1.3);
+        return 1.25_f64.clamp(0.5, 1.3);
     }
 
     // 4) NORMAL regime
     1.0
 }
 
+/// Dagelijkse vol-schatting (fractie) uit het vol-regime: max(VIX, RV10) / √252.
+/// VIX en RV10 zijn in procentpunten (18.0 = 18%).
+fn derive_daily_vol_frac(vol: &VolatilityRegime) -> f64 {
+    let annual_pct = vol.vix_level.max(vol.rv10_annualized).max(0.0);
+    if !annual_pct.is_finite() {
+        return 0.0;
+    }
+    annual_pct / 100.0 / 252.0_f64.sqrt()
+}
+
+/// Inverse standaard-normale CDF (Acklam-benadering, rel. fout < 1.2e-9).
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e+01,
+        2.209460984245205e+02,
+        -2.759285104469687e+02,
+        1.38357751867269e+02,
+        -3.066479806614716e+01,
+        2.506628277459239e+00,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e+01,
+        1.615858368580409e+02,
+        -1.556989798598866e+02,
+        6.680131188771972e+01,
+        -1.328068155288572e+01,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-03,
+        -3.223964580411365e-01,
+        -2.400758277161838e+00,
+        -2.549732539343734e+00,
+        4.374664141464968e+00,
+        2.938163982698783e+00,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-03,
+        3.224671290700398e-01,
+        2.445134137142996e+00,
+        3.754408661907416e+00,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    }
+}
+
 fn derive_leverage_scalar(portfolio: &PortfolioState, pcfg: &PortfolioRiskConfig) -> f64 {
     let max_lev = pcfg.max_leverage.max(0.1); // defensief
 
@@ -198,66 +389,72 @@ fn derive_leverage_scalar(portfolio: &PortfolioState, pcfg: &PortfolioRiskConfig
     scalar.clamp(0.0, 1.10)
 }
 
+/// Portfolio halt-state o.b.v. drawdown t.o.v. de HWM (negatief = verlies).
+pub fn derive_portfolio_halt_state(dd_frac: f64, pcfg: &PortfolioRiskConfig) -> HaltState {
+    if dd_frac <= pcfg.kill_dd_frac {
+        HaltState::Kill
+    } else if dd_frac <= pcfg.halt_dd_frac {
+        HaltState::Halt
+    } else {
+        HaltState::None
+    }
+}
+
+/// Portfolio risk-state, zelfde drempels als `derive_portfolio_halt_state`.
+pub fn derive_portfolio_risk_state(dd_frac: f64, pcfg: &PortfolioRiskConfig) -> PortfolioRiskState {
+    if dd_frac <= pcfg.kill_dd_frac {
+        PortfolioRiskState::Stress
+    } else if dd_frac <= pcfg.halt_dd_frac {
+        PortfolioRiskState::Caution
+    } else {
+        PortfolioRiskState::Normal
+    }
+}
+
 pub fn evaluate_new_position_risk(
     sleeve_state: &SleeveState,
     env: &SleeveRiskEnvelope,
 ) -> RiskDecision {
+    let blocked = |reason| RiskDecision {
+        allow_new_position: false,
+        max_new_positions: 0,
+        max_order_notional_usd: 0.0,
+        reason,
+    };
+
     // 1) Hard halts (portfolio of sleeve)
-    if matches!(env.portfolio_halt, HaltState::Halt | HaltState::Kill) {
-        return RiskDecision {
-            allow_new_position: false,
-            max_new_positions: 0,
-            max_order_notional_usd: 0.0,
-            reason: RiskDecisionReason::PortfolioHalt,
-        };
+    if let Some(reason @ (RiskDecisionReason::PortfolioHalt | RiskDecisionReason::SleeveHalt)) =
+        env.reason_not_tradeable()
+    {
+        return blocked(reason);
     }
 
-    if matches!(env.sleeve_halt, HaltState::Halt | HaltState::Kill) {
-        return RiskDecision {
-            allow_new_position: false,
-            max_new_positions: 0,
-            max_order_notional_usd: 0.0,
-            reason: RiskDecisionReason::SleeveHalt,
-        };
+    // 1b) SoftHalt: bestaande posities bouwen af, nieuwe posities zijn geblokkeerd
+    if env.portfolio_halt == HaltState::SoftHalt {
+        return blocked(RiskDecisionReason::PortfolioHalt);
+    }
+    if env.sleeve_halt == HaltState::SoftHalt {
+        return blocked(RiskDecisionReason::SleeveHalt);
     }
 
     // 2) Headroom checks
     if env.margin_remaining_usd <= 0.0 {
-        return RiskDecision {
-            allow_new_position: false,
-            max_new_positions: 0,
-            max_order_notional_usd: 0.0,
-            reason: RiskDecisionReason::NoMarginHeadroom,
-        };
+        return blocked(RiskDecisionReason::NoMarginHeadroom);
     }
 
     if env.exposure_remaining_usd <= 0.0 {
-        return RiskDecision {
-            allow_new_position: false,
-            max_new_positions: 0,
-            max_order_notional_usd: 0.0,
-            reason: RiskDecisionReason::NoExposureHeadroom,
-        };
+        return blocked(RiskDecisionReason::NoExposureHeadroom);
     }
 
-    if env.max_position_size_usd <= 0.0 {
-        return RiskDecision {
-            allow_new_position: false,
-            max_new_positions: 0,
-            max_order_notional_usd: 0.0,
-            reason: RiskDecisionReason::PositionSizeZero,
-        };
+    // 3) Size & concurrency uit de envelope
+    if let Some(reason) = env.reason_not_tradeable() {
+        return blocked(reason);
     }
 
-    // 3) Concurrency limit voor deze sleeve
+    // 4) Concurrency limit voor deze sleeve
     let open = sleeve_state.open_positions;
     if open >= env.max_concurrent_positions {
-        return RiskDecision {
-            allow_new_position: false,
-            max_new_positions: 0,
-            max_order_notional_usd: 0.0,
-            reason: RiskDecisionReason::ConcurrencyLimit,
-        };
+        return blocked(RiskDecisionReason::ConcurrencyLimit);
     }
 
     let max_new_positions = env.max_concurrent_positions - open;
@@ -275,28 +472,227 @@ impl GlobalRiskKernel {
     pub fn new(config: GlobalRiskKernelConfig) -> Self {
         Self {
             internal_portfolio_peak_equity: config.portfolio.initial_equity_usd,
+            today_start_equity: config.portfolio.initial_equity_usd,
+            current_day: None,
+            last_open_positions: Vec::new(),
+            daily_returns: VecDeque::new(),
+            last_vol: None,
+            recovery_phase: RecoveryPhase::None,
+            sleeve_peaks: Vec::new(),
             config,
         }
     }
 
+    /// Kernel herstellen uit een eerder genomen `snapshot()`. Sleeve-HWMs uit de snapshot
+    /// gaan voor als de aangeleverde `SleeveState::peak_equity_usd` lager is (bijv. na een
+    /// restart gereset naar huidige equity).
+    pub fn restore(config: GlobalRiskKernelConfig, snapshot: KernelSnapshot) -> Self {
+        let mut kernel = Self::new(config);
+        kernel.internal_portfolio_peak_equity = snapshot.internal_portfolio_peak_equity;
+        kernel.today_start_equity = snapshot.today_start_equity;
+        kernel.current_day = snapshot.current_day;
+        kernel.sleeve_peaks = snapshot.sleeve_peaks;
+        kernel.recovery_phase = snapshot.recovery_phase;
+        kernel.last_vol = snapshot.last_vol;
+        for (ts, ret) in snapshot.daily_returns {
+            kernel.push_daily_return(ts, ret);
+        }
+        kernel
+    }
+
+    pub fn snapshot(&self) -> KernelSnapshot {
+        KernelSnapshot {
+            internal_portfolio_peak_equity: self.internal_portfolio_peak_equity,
+            today_start_equity: self.today_start_equity,
+            current_day: self.current_day,
+            sleeve_peaks: self.sleeve_peaks.clone(),
+            daily_returns: self.daily_returns.iter().copied().collect(),
+            recovery_phase: self.recovery_phase,
+            last_vol: self.last_vol,
+        }
+    }
+
+    fn record_sleeve_peak(&mut self, sleeve: &SleeveState) {
+        match self.sleeve_peaks.iter_mut().find(|(id, _)| *id == sleeve.sleeve_id) {
+            Some(entry) => entry.1 = sleeve.peak_equity_usd,
+            None => self.sleeve_peaks.push((sleeve.sleeve_id, sleeve.peak_equity_usd)),
+        }
+    }
+
+    /// Voeg een daily portfolio-return toe (fractie, 0.01 = +1%) aan het rolling vol-window.
+    /// Non-finite returns worden genegeerd.
+    pub fn push_daily_return(&mut self, ts: i64, ret: f64) {
+        if !ret.is_finite() {
+            return;
+        }
+        self.daily_returns.push_back((ts, ret));
+        while self.daily_returns.len() > self.config.vol_window.max(2) {
+            self.daily_returns.pop_front();
+        }
+    }
+
+    /// Geannualiseerde realized vol uit het rolling window, in procentpunten (zoals
+    /// `VolatilityRegime::rv10_annualized`): `stdev(returns) * √252 * 100`.
+    /// `None` zolang het window (`vol_window`, min. 2) nog niet vol is.
+    pub fn internal_rv_annualized(&self) -> Option<f64> {
+        let window = self.config.vol_window.max(2);
+        if self.daily_returns.len() < window {
+            return None;
+        }
+
+        let n = self.daily_returns.len() as f64;
+        let mean = self.daily_returns.iter().map(|(_, r)| r).sum::<f64>() / n;
+        let var = self
+            .daily_returns
+            .iter()
+            .map(|(_, r)| (r - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0);
+
+        let rv = var.sqrt() * 252.0_f64.sqrt() * 100.0;
+        rv.is_finite().then_some(rv)
+    }
+
     pub fn config(&self) -> &GlobalRiskKernelConfig {
         &self.config
     }
 
+    /// Variant van `evaluate` voor een portfolio-state die door meerdere sleeve-threads
+    /// gedeeld wordt. Lock wordt alleen vastgehouden om een snapshot te nemen; de
+    /// evaluatie zelf draait lock-vrij. Een poisoned lock levert gewoon de laatste state.
+    pub fn evaluate_shared(
+        &mut self,
+        now_ts: i64,
+        portfolio: Arc<Mutex<PortfolioState>>,
+        sleeves: &mut [SleeveState],
+        margin: &MarginState,
+        vol: &VolatilityRegime,
+    ) -> Vec<SleeveRiskEnvelope> {
+        let snapshot = *portfolio.lock().unwrap_or_else(|e| e.into_inner());
+        self.evaluate(now_ts, &snapshot, sleeves, margin, vol)
+    }
+
     /// Hoofdfunctie: wordt aangeroepen op elke risk-heartbeat.
     pub fn evaluate(
         &mut self,
-        _now_ts: i64,
+        now_ts: i64,
         portfolio: &PortfolioState,
         sleeves: &mut [SleeveState],
         margin: &MarginState,
         vol: &VolatilityRegime,
     ) -> Vec<SleeveRiskEnvelope> {
+        let level = self.evaluate_portfolio_level(now_ts, portfolio, margin, vol);
+
+        // ===== 4) Global concurrency headroom =====
+        let total_open_positions: u32 = sleeves.iter().map(|s| s.open_positions).sum();
+        let (remaining_slots, extra_per_sleeve) =
+            self.concurrency_headroom(total_open_positions, sleeves.len() as u32);
+
+        self.last_open_positions = sleeves
+            .iter()
+            .map(|s| (s.sleeve_id, s.open_positions))
+            .collect();
+
+        // ===== 5) Per-sleeve DD, concurrency & sizing =====
+        let envelopes: Vec<SleeveRiskEnvelope> = sleeves
+            .iter_mut()
+            .map(|sleeve| self.evaluate_sleeve(&level, sleeve, remaining_slots, extra_per_sleeve))
+            .collect();
+
+        for sleeve in sleeves.iter() {
+            self.record_sleeve_peak(sleeve);
+        }
+
+        envelopes
+    }
+
+    /// Zelfde envelope als de bijbehorende entry uit `evaluate`, maar alleen voor één sleeve.
+    /// Portfolio-stappen (DD, daily-loss, headroom, scalars) draaien volledig; de globale
+    /// concurrency gebruikt voor de overige sleeves de open posities uit de laatste
+    /// evaluate (0 als onbekend) en telt alle geconfigureerde sleeves als actief.
+    pub fn evaluate_incremental(
+        &mut self,
+        now_ts: i64,
+        portfolio: &PortfolioState,
+        sleeve: &mut SleeveState,
+        margin: &MarginState,
+        vol: &VolatilityRegime,
+    ) -> SleeveRiskEnvelope {
+        let level = self.evaluate_portfolio_level(now_ts, portfolio, margin, vol);
+
+        match self
+            .last_open_positions
+            .iter_mut()
+            .find(|(id, _)| *id == sleeve.sleeve_id)
+        {
+            Some(entry) => entry.1 = sleeve.open_positions,
+            None => self
+                .last_open_positions
+                .push((sleeve.sleeve_id, sleeve.open_positions)),
+        }
+
+        let total_open_positions: u32 = self.last_open_positions.iter().map(|(_, n)| n).sum();
+        let (remaining_slots, extra_per_sleeve) =
+            self.concurrency_headroom(total_open_positions, self.config.sleeves.len() as u32);
+
+        let envelope = self.evaluate_sleeve(&level, sleeve, remaining_slots, extra_per_sleeve);
+        self.record_sleeve_peak(sleeve);
+        envelope
+    }
+
+    /// Envelope voor één sleeve zonder kennis van de andere sleeves (sleeve-monitor,
+    /// strategy-tests). Zelfde logica als `evaluate`, maar de globale concurrency gaat er
+    /// conservatief van uit dat elke andere geconfigureerde sleeve zijn volle
+    /// `max_concurrent_positions` gebruikt. Raakt `last_open_positions` niet aan.
+    pub fn evaluate_single_sleeve(
+        &mut self,
+        now_ts: i64,
+        portfolio: &PortfolioState,
+        sleeve_state: &mut SleeveState,
+        margin: &MarginState,
+        vol: &VolatilityRegime,
+    ) -> SleeveRiskEnvelope {
+        let level = self.evaluate_portfolio_level(now_ts, portfolio, margin, vol);
+
+        let others_open: u32 = self
+            .config
+            .sleeves
+            .iter()
+            .filter(|c| c.sleeve_id != sleeve_state.sleeve_id)
+            .map(|c| c.max_concurrent_positions)
+            .sum();
+        let total_open_positions = others_open.saturating_add(sleeve_state.open_positions);
+        let (remaining_slots, extra_per_sleeve) =
+            self.concurrency_headroom(total_open_positions, self.config.sleeves.len() as u32);
+
+        let envelope = self.evaluate_sleeve(&level, sleeve_state, remaining_slots, extra_per_sleeve);
+        self.record_sleeve_peak(sleeve_state);
+        envelope
+    }
+
+    /// Stap 1–3: portfolio DD, daily-loss breaker, headroom en scalars.
+    /// Muteert de kernel-state (HWM, daily-loss referentie).
+    fn evaluate_portfolio_level(
+        &mut self,
+        now_ts: i64,
+        portfolio: &PortfolioState,
+        margin: &MarginState,
+        vol: &VolatilityRegime,
+    ) -> PortfolioLevel {
+        let internal_vol;
+        let vol = match self.internal_rv_annualized() {
+            Some(rv) if self.config.use_internal_vol_estimate => {
+                internal_vol = VolatilityRegime { rv10_annualized: rv, ..*vol };
+                &internal_vol
+            }
+            _ => vol,
+        };
+        self.last_vol = Some(*vol);
+
         let pcfg = &self.config.portfolio;
 
         // ===== 1) Portfolio equity & DD =====
-        let equity_now =
-            portfolio.cash_usd + portfolio.open_pnl_usd + portfolio.accrued_interest_usd;
+        let equity_now = portfolio.net_equity();
 
         // interne HWM-update
         if equity_now > self.internal_portfolio_peak_equity {
@@ -309,21 +705,36 @@ impl GlobalRiskKernel {
             0.0
         };
 
-        let portfolio_halt_state = if dd_frac <= pcfg.kill_dd_frac {
-            HaltState::Kill
-        } else if dd_frac <= pcfg.halt_dd_frac {
-            HaltState::Halt
-        } else {
-            HaltState::None
-        };
+        let mut portfolio_halt_state = derive_portfolio_halt_state(dd_frac, pcfg);
+        let mut portfolio_risk_state = derive_portfolio_risk_state(dd_frac, pcfg);
+
+        // ===== 1b) Intraday circuit breaker (reset per UTC-kalenderdag) =====
+        let day = now_ts.div_euclid(86_400);
+        if self.current_day != Some(day) {
+            self.current_day = Some(day);
+            self.today_start_equity = equity_now;
+        }
 
-        let portfolio_risk_state = if dd_frac <= pcfg.kill_dd_frac {
-            PortfolioRiskState::Stress
-        } else if dd_frac <= pcfg.halt_dd_frac {
-            PortfolioRiskState::Caution
+        let daily_pnl = equity_now - self.today_start_equity;
+        if pcfg.max_daily_loss_usd > 0.0 && daily_pnl < -pcfg.max_daily_loss_usd {
+            portfolio_halt_state = HaltState::Kill;
+            portfolio_risk_state = PortfolioRiskState::Stress;
+        }
+
+        // ===== 1c) Recovery: Halt/Kill → Recovery → Normal, nooit direct terug naar Normal =====
+        if matches!(portfolio_halt_state, HaltState::Halt | HaltState::Kill) {
+            self.recovery_phase = RecoveryPhase::Halted;
         } else {
-            PortfolioRiskState::Normal
-        };
+            let recovered = dd_frac > pcfg.halt_dd_frac + pcfg.recovery_buffer_frac.max(0.0);
+            self.recovery_phase = match self.recovery_phase {
+                RecoveryPhase::Halted => RecoveryPhase::Recovering,
+                RecoveryPhase::Recovering if recovered => RecoveryPhase::None,
+                phase => phase,
+            };
+            if self.recovery_phase == RecoveryPhase::Recovering {
+                portfolio_risk_state = PortfolioRiskState::Recovery;
+            }
+        }
 
         // ===== 2) Exposure & margin headroom =====
 
@@ -342,106 +753,276 @@ impl GlobalRiskKernel {
         let volatility_regime_scalar = derive_volatility_scalar(vol);
         let leverage_scalar = derive_leverage_scalar(portfolio, pcfg);
 
-        // ===== 4) Global concurrency headroom =====
-        let total_open_positions: u32 = sleeves.iter().map(|s| s.open_positions).sum();
-        let max_global = pcfg.max_global_positions;
+        PortfolioLevel {
+            portfolio_halt_state,
+            portfolio_risk_state,
+            exposure_remaining_usd,
+            margin_remaining_usd,
+            volatility_regime_scalar,
+            leverage_scalar,
+        }
+    }
 
-        let remaining_slots = if total_open_positions >= max_global {
-            0
-        } else {
-            max_global - total_open_positions
-        };
+    /// Stap 4: (resterende globale slots, extra slots per actieve sleeve).
+    fn concurrency_headroom(&self, total_open_positions: u32, active_sleeves: u32) -> (u32, u32) {
+        let max_global = self.config.portfolio.max_global_positions;
+
+        let remaining_slots = max_global.saturating_sub(total_open_positions);
 
-        let active_sleeves = sleeves.len() as u32;
         let extra_per_sleeve: u32 = if remaining_slots > 0 && active_sleeves > 0 {
             remaining_slots / active_sleeves // floor, conservatief
         } else {
             0
         };
 
-        // ===== 5) Per-sleeve DD, concurrency & sizing =====
-        let mut envelopes = Vec::with_capacity(sleeves.len());
+        (remaining_slots, extra_per_sleeve)
+    }
 
-        for sleeve in sleeves.iter_mut() {
-            let scfg = self
-                .config
-                .sleeves
-                .iter()
-                .find(|c| c.sleeve_id == sleeve.sleeve_id)
-                .expect("missing sleeve config");
+    /// Stap 5: per-sleeve DD, concurrency & sizing. Werkt de sleeve-HWM bij.
+    fn evaluate_sleeve(
+        &self,
+        level: &PortfolioLevel,
+        sleeve: &mut SleeveState,
+        remaining_slots: u32,
+        extra_per_sleeve: u32,
+    ) -> SleeveRiskEnvelope {
+        let PortfolioLevel {
+            portfolio_halt_state,
+            portfolio_risk_state,
+            exposure_remaining_usd,
+            margin_remaining_usd,
+            volatility_regime_scalar,
+            leverage_scalar,
+        } = *level;
+
+        let scfg = self
+            .config
+            .sleeves
+            .iter()
+            .find(|c| c.sleeve_id == sleeve.sleeve_id)
+            .expect("missing sleeve config");
+
+        let equity = sleeve.equity_usd;
+
+        // per-sleeve HWM update (inclusief de HWM die de kernel zelf kent, bijv. na restore)
+        if let Some(&(_, known_peak)) = self.sleeve_peaks.iter().find(|(id, _)| *id == sleeve.sleeve_id)
+            && known_peak > sleeve.peak_equity_usd
+        {
+            sleeve.peak_equity_usd = known_peak;
+        }
+        if equity > sleeve.peak_equity_usd {
+            sleeve.peak_equity_usd = equity;
+        }
 
-            let equity = sleeve.equity_usd;
+        let dd_frac_sleeve = if sleeve.peak_equity_usd > 0.0 {
+            (equity / sleeve.peak_equity_usd) - 1.0
+        } else {
+            0.0
+        };
 
-            // per-sleeve HWM update
-            if equity > sleeve.peak_equity_usd {
-                sleeve.peak_equity_usd = equity;
-            }
+        let sleeve_halt_state = if dd_frac_sleeve <= scfg.kill_dd_frac {
+            HaltState::Kill
+        } else if dd_frac_sleeve <= scfg.halt_dd_frac {
+            HaltState::Halt
+        } else if dd_frac_sleeve <= scfg.soft_halt_dd_frac {
+            HaltState::SoftHalt
+        } else {
+            HaltState::None
+        };
 
-            let dd_frac_sleeve = if sleeve.peak_equity_usd > 0.0 {
-                (equity / sleeve.peak_equity_usd) - 1.0
-            } else {
-                0.0
-            };
+        // ----- Dynamische concurrency cap -----
+        let mut dyn_max_concurrent = scfg.max_concurrent_positions;
 
-            let sleeve_halt_state = if dd_frac_sleeve <= scfg.kill_dd_frac {
-                HaltState::Kill
-            } else if dd_frac_sleeve <= scfg.halt_dd_frac {
-                HaltState::Halt
-            } else {
-                HaltState::None
-            };
+        if remaining_slots == 0 {
+            // geen globale ruimte meer: lock per sleeve op huidige open positions
+            dyn_max_concurrent = sleeve.open_positions;
+        } else {
+            // ieder krijgt een stukje van de resterende slots
+            let target_cap = sleeve.open_positions + extra_per_sleeve;
+            dyn_max_concurrent = dyn_max_concurrent.min(target_cap);
+        }
 
-            // ----- Dynamische concurrency cap -----
-            let mut dyn_max_concurrent = scfg.max_concurrent_positions;
+        // ----- Position size logica (vol/leverage + headroom) -----
+        let base_pos_usd = scfg.capital_alloc_usd * scfg.max_single_pos_risk_frac;
 
-            if remaining_slots == 0 {
-                // geen globale ruimte meer: lock per sleeve op huidige open positions
-                dyn_max_concurrent = sleeve.open_positions;
-            } else {
-                // ieder krijgt een stukje van de resterende slots
-                let target_cap = sleeve.open_positions + extra_per_sleeve;
-                dyn_max_concurrent = dyn_max_concurrent.min(target_cap);
-            }
+        let mut max_position_size_usd =
+            base_pos_usd * volatility_regime_scalar * leverage_scalar;
 
-            // ----- Position size logica (vol/leverage + headroom) -----
-            let base_pos_usd = scfg.capital_alloc_usd * scfg.max_single_pos_risk_frac;
+        // Absolute cap per positie, onafhankelijk van de sleeve-grootte
+        max_position_size_usd =
+            max_position_size_usd.min(scfg.max_position_size_usd_absolute.unwrap_or(f64::MAX));
 
-            let mut max_position_size_usd =
-                base_pos_usd * volatility_regime_scalar * leverage_scalar;
+        // Recovery: halve size en max. de helft van de geconfigureerde concurrency
+        if portfolio_risk_state == PortfolioRiskState::Recovery {
+            max_position_size_usd *= 0.5;
+            dyn_max_concurrent = dyn_max_concurrent.min(scfg.max_concurrent_positions.div_ceil(2));
+        }
 
-            if margin_remaining_usd <= 0.0 || exposure_remaining_usd <= 0.0 {
-                max_position_size_usd = 0.0;
-            } else {
-                max_position_size_usd = max_position_size_usd.min(exposure_remaining_usd);
-            }
+        if margin_remaining_usd <= 0.0 || exposure_remaining_usd <= 0.0 {
+            max_position_size_usd = 0.0;
+        } else {
+            max_position_size_usd = max_position_size_usd.min(exposure_remaining_usd);
+        }
 
-            if matches!(portfolio_halt_state, HaltState::Halt | HaltState::Kill)
-                || matches!(sleeve_halt_state, HaltState::Halt | HaltState::Kill)
-            {
-                max_position_size_usd = 0.0;
-            }
+        if matches!(portfolio_halt_state, HaltState::Halt | HaltState::Kill)
+            || matches!(sleeve_halt_state, HaltState::Halt | HaltState::Kill)
+        {
+            max_position_size_usd = 0.0;
+        }
 
-            let env = SleeveRiskEnvelope {
-                sleeve_id: sleeve.sleeve_id,
-                sleeve_halt: sleeve_halt_state,
-                portfolio_halt: portfolio_halt_state,
+        SleeveRiskEnvelope {
+            sleeve_id: sleeve.sleeve_id,
+            sleeve_halt: sleeve_halt_state,
+            portfolio_halt: portfolio_halt_state,
 
-                max_position_size_usd,
-                max_concurrent_positions: dyn_max_concurrent,
+            max_position_size_usd,
+            max_concurrent_positions: dyn_max_concurrent,
 
-                exposure_remaining_usd,
-                margin_remaining_usd,
+            exposure_remaining_usd,
+            margin_remaining_usd,
 
-                volatility_regime_scalar,
-                leverage_scalar,
+            volatility_regime_scalar,
+            leverage_scalar,
 
-                portfolio_risk_state,
-            };
+            portfolio_risk_state,
+        }
+    }
 
-            envelopes.push(env);
+    /// Stress-scenario: alle sleeves (en dus het portfolio) verliezen tegelijk `loss_pct`
+    /// procent equity (25.0 = -25%). Draait `evaluate` op een kopie van de kernel,
+    /// de kernel-state (HWM, daily-loss referentie) blijft ongewijzigd.
+    ///
+    /// Portfolio-equity vóór de stress = som van de sleeve-equity; margin en exposure neutraal.
+    pub fn stress_test_portfolio(
+        &self,
+        sleeves: &[SleeveState],
+        vol: &VolatilityRegime,
+        loss_pct: f64,
+    ) -> Vec<SleeveRiskEnvelope> {
+        let keep_frac = if loss_pct.is_finite() {
+            1.0 - loss_pct.clamp(0.0, 100.0) / 100.0
+        } else {
+            0.0
+        };
+
+        let mut stressed_sleeves: Vec<SleeveState> = sleeves
+            .iter()
+            .map(|s| {
+                let equity = s.equity_usd * keep_frac;
+                SleeveState {
+                    equity_usd: equity,
+                    unrealized_pnl_usd: s.unrealized_pnl_usd - (s.equity_usd - equity),
+                    ..*s
+                }
+            })
+            .collect();
+
+        let equity_before: f64 = sleeves.iter().map(|s| s.equity_usd).sum();
+        let equity_after = equity_before * keep_frac;
+
+        let portfolio = PortfolioState {
+            cash_usd: equity_after,
+            open_pnl_usd: 0.0,
+            accrued_interest_usd: 0.0,
+            peak_equity_usd: self.internal_portfolio_peak_equity.max(equity_before),
+            total_notional_exposure: 0.0,
+            current_leverage: 0.0,
+        };
+        let margin = MarginState {
+            internal_margin_req_usd: 0.0,
+            broker_margin_req_usd: 0.0,
+            equity_usd: equity_after,
+        };
+
+        let mut scratch = GlobalRiskKernel {
+            config: self.config.clone(),
+            internal_portfolio_peak_equity: self.internal_portfolio_peak_equity.max(equity_before),
+            today_start_equity: self.today_start_equity,
+            current_day: self.current_day,
+            last_open_positions: self.last_open_positions.clone(),
+            daily_returns: self.daily_returns.clone(),
+            last_vol: self.last_vol,
+            recovery_phase: self.recovery_phase,
+            sleeve_peaks: self.sleeve_peaks.clone(),
+        };
+
+        // Zelfde UTC-dag als de laatste evaluate → ook de daily-loss breaker telt mee
+        let now_ts = self.current_day.map(|d| d * 86_400).unwrap_or(0);
+
+        scratch.evaluate(now_ts, &portfolio, &mut stressed_sleeves, &margin, vol)
+    }
+
+    /// Simpele parametrische 1-daagse VaR over alle sleeves:
+    /// `som(sleeve equity) * dagelijkse vol * z(confidence)`.
+    ///
+    /// `confidence` wordt geclampt op [0.5, 0.9999]; 0.95 → z ≈ 1.645, 0.99 → z ≈ 2.326.
+    pub fn evaluate_portfolio_var(
+        &self,
+        sleeves: &[SleeveState],
+        vol: &VolatilityRegime,
+        confidence: f64,
+    ) -> f64 {
+        self.portfolio_risk_summary(sleeves, vol, confidence)
+            .portfolio_var_usd
+    }
+
+    /// Parametrische 1-daagse VaR op de notional exposure van het portfolio:
+    /// `total_notional_exposure * rv10_annualized / √252 * z(confidence)`, met de RV uit de
+    /// laatste `evaluate` (0.0 zolang er nog geen evaluate is geweest).
+    ///
+    /// `confidence` wordt net als bij `evaluate_portfolio_var` geclampt op [0.5, 0.9999].
+    /// `sleeves` telt (nog) niet mee: de exposure komt volledig uit `portfolio`.
+    pub fn estimate_portfolio_var_usd(
+        &self,
+        portfolio: &PortfolioState,
+        _sleeves: &[SleeveState],
+        confidence: f64,
+    ) -> f64 {
+        let exposure = portfolio.total_notional_exposure.abs();
+        let rv_pct = match self.last_vol {
+            Some(vol) => vol.rv10_annualized.max(0.0),
+            None => return 0.0,
+        };
+        if exposure == 0.0 || !exposure.is_finite() || !rv_pct.is_finite() {
+            return 0.0;
         }
 
-        envelopes
+        let confidence = if confidence.is_finite() {
+            confidence.clamp(0.5, 0.9999)
+        } else {
+            0.95
+        };
+
+        exposure * rv_pct / 100.0 / 252.0_f64.sqrt() * normal_quantile(confidence)
+    }
+
+    /// Zelfde VaR als `evaluate_portfolio_var`, inclusief de tussenwaarden.
+    pub fn portfolio_risk_summary(
+        &self,
+        sleeves: &[SleeveState],
+        vol: &VolatilityRegime,
+        confidence: f64,
+    ) -> PortfolioRiskSummary {
+        let confidence = if confidence.is_finite() {
+            confidence.clamp(0.5, 0.9999)
+        } else {
+            0.95
+        };
+
+        let total_sleeve_equity_usd: f64 = sleeves
+            .iter()
+            .map(|s| s.equity_usd.max(0.0))
+            .sum();
+
+        let daily_vol_frac = derive_daily_vol_frac(vol);
+        let z = normal_quantile(confidence);
+
+        PortfolioRiskSummary {
+            confidence,
+            total_sleeve_equity_usd,
+            daily_vol_frac,
+            portfolio_var_usd: total_sleeve_equity_usd * daily_vol_frac * z,
+        }
     }
 
     /// Optioneel: cashflow-reset helper (20% regel)
@@ -22,6 +22,11 @@ pub fn default_portfolio_config_10k() -> PortfolioRiskConfig {
 
         // Max aantal open posities over alle sleeves heen
         max_global_positions: 15,
+
+        // Intraday circuit breaker: -500 USD op één dag → alles flatten
+        max_daily_loss_usd: 500.0,
+        // Na een halt eerst Recovery tot DD > halt_dd_frac + 2%
+        recovery_buffer_frac: 0.02,
     }
 }
 
@@ -36,6 +41,8 @@ pub fn default_global_risk_kernel_config_usd_10k() -> GlobalRiskKernelConfig {
             max_leverage: 1.5,       // max 1.5x notional vs equity
             rebalance_drift_frac: 0.15,
             max_global_positions: 20,
+            max_daily_loss_usd: 500.0, // -5% intraday → Kill
+            recovery_buffer_frac: 0.02,
         },
         sleeves: vec![
             // ==== Equity L/S (core, maar niet ons focuspunt nu) ====
@@ -45,8 +52,10 @@ pub fn default_global_risk_kernel_config_usd_10k() -> GlobalRiskKernelConfig {
                 capital_alloc_usd: 1_500.0,
                 max_single_pos_risk_frac: 0.01,  // 1% van portfolio per positie
                 halt_dd_frac: -0.12,
+                soft_halt_dd_frac: -0.12,
                 kill_dd_frac: -0.18,
                 max_concurrent_positions: 10,
+                max_position_size_usd_absolute: None,
             },
             // ==== Stat-Arb / Residual ====
             // AANGEPAST: capital_alloc_usd 2_500 -> 1_500
@@ -55,8 +64,10 @@ pub fn default_global_risk_kernel_config_usd_10k() -> GlobalRiskKernelConfig {
                 capital_alloc_usd: 1_500.0,
                 max_single_pos_risk_frac: 0.008, // iets lager (meer names, lagere single-name risk)
                 halt_dd_frac: -0.12,
+                soft_halt_dd_frac: -0.12,
                 kill_dd_frac: -0.18,
                 max_concurrent_positions: 20,
+                max_position_size_usd_absolute: None,
             },
             // ==== Microstructure / Intraday ====
             // AANGEPAST: capital_alloc_usd 1_500 -> 1_000
@@ -65,8 +76,10 @@ pub fn default_global_risk_kernel_config_usd_10k() -> GlobalRiskKernelConfig {
                 capital_alloc_usd: 1_000.0,
                 max_single_pos_risk_frac: 0.005, // veel trades, klein per-trade risk
                 halt_dd_frac: -0.08,
+                soft_halt_dd_frac: -0.08,
                 kill_dd_frac: -0.15,
                 max_concurrent_positions: 30,
+                max_position_size_usd_absolute: None,
             },
             // ==== Index Options Vol Premium ====
             // AANGEPAST: capital_alloc_usd 2_000 -> 1_000
@@ -75,8 +88,10 @@ pub fn default_global_risk_kernel_config_usd_10k() -> GlobalRiskKernelConfig {
                 capital_alloc_usd: 1_000.0,
                 max_single_pos_risk_frac: 0.02,  // options: klein aantal posities, hogere per-trade R
                 halt_dd_frac: -0.12,
+                soft_halt_dd_frac: -0.12,
                 kill_dd_frac: -0.20,
                 max_concurrent_positions: 6,
+                max_position_size_usd_absolute: None,
             },
             // ==== Micro Futures Macro Trend (belangrijk voor nu) ====
             // Ongewijzigd op 5_000 → 50% van 10k-profiel
@@ -86,10 +101,14 @@ pub fn default_global_risk_kernel_config_usd_10k() -> GlobalRiskKernelConfig {
                 // WAS: 0.06 → tripte de sanity check (in 10k-profiel stond al 0.05, laten zo)
                 max_single_pos_risk_frac: 0.05, // max 5% per positie (test-range upper bound)
                 halt_dd_frac: -0.15,
+                soft_halt_dd_frac: -0.15,
                 kill_dd_frac: -0.25,
                 max_concurrent_positions: 4,
+                max_position_size_usd_absolute: None,
             },
         ],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
     }
 }
 
@@ -112,8 +131,10 @@ fn mk_sleeve(
         capital_alloc_usd,
         max_single_pos_risk_frac,
         halt_dd_frac,
+        soft_halt_dd_frac: halt_dd_frac, // soft-halt uit
         kill_dd_frac,
         max_concurrent_positions,
+        max_position_size_usd_absolute: None,
     }
 }
 
@@ -197,6 +218,8 @@ pub fn aggressive_25k_global_risk_kernel_config() -> GlobalRiskKernelConfig {
             max_leverage: 2.0,
             rebalance_drift_frac: 0.20,
             max_global_positions: 30,
+            max_daily_loss_usd: 1_250.0, // -5% intraday → Kill
+            recovery_buffer_frac: 0.02,
         },
         sleeves: vec![
             SleeveRiskConfig {
@@ -204,44 +227,56 @@ pub fn aggressive_25k_global_risk_kernel_config() -> GlobalRiskKernelConfig {
                 capital_alloc_usd: 5_000.0,
                 max_single_pos_risk_frac: 0.015,
                 halt_dd_frac: -0.15,
+                soft_halt_dd_frac: -0.15,
                 kill_dd_frac: -0.25,
                 max_concurrent_positions: 15,
+                max_position_size_usd_absolute: None,
             },
             SleeveRiskConfig {
                 sleeve_id: SleeveId::StatArbResidual,
                 capital_alloc_usd: 6_250.0,
                 max_single_pos_risk_frac: 0.01,
                 halt_dd_frac: -0.15,
+                soft_halt_dd_frac: -0.15,
                 kill_dd_frac: -0.25,
                 max_concurrent_positions: 30,
+                max_position_size_usd_absolute: None,
             },
             SleeveRiskConfig {
                 sleeve_id: SleeveId::MicrostructureIntraday,
                 capital_alloc_usd: 3_750.0,
                 max_single_pos_risk_frac: 0.007,
                 halt_dd_frac: -0.10,
+                soft_halt_dd_frac: -0.10,
                 kill_dd_frac: -0.20,
                 max_concurrent_positions: 40,
+                max_position_size_usd_absolute: None,
             },
             SleeveRiskConfig {
                 sleeve_id: SleeveId::OptionsVolPremium,
                 capital_alloc_usd: 5_000.0,
                 max_single_pos_risk_frac: 0.03,
                 halt_dd_frac: -0.15,
+                soft_halt_dd_frac: -0.15,
                 kill_dd_frac: -0.25,
                 max_concurrent_positions: 8,
+                max_position_size_usd_absolute: None,
             },
             // Micro Futures sleeve – nu binnen test-range
             SleeveRiskConfig {
                 sleeve_id: SleeveId::MicroFuturesMacroTrend,
                 capital_alloc_usd: 5_000.0,
                 // afgestemd op sanity-test: <= ~0.03
-                max_single_pos_risk_frac: 0.05,
+                max_single_pos_risk_frac: 0.03,
                 halt_dd_frac: -0.15,
+                soft_halt_dd_frac: -0.15,
                 kill_dd_frac: -0.25,
                 max_concurrent_positions: 4,
+                max_position_size_usd_absolute: None,
             },
         ],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
     }
 }
 
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+
+use crate::execution::EngineHealth;
+use crate::risk::SleeveRiskEnvelope;
+use crate::strategies::macro_futures_sleeve::{
+    FutureInstrument,
+    FuturesOrderIntent,
+    FuturesRiskBudget,
+    FuturesSleeveContext,
+    InstrumentHistory,
+    MacroFuturesSleeve,
+    MacroScalars,
+};
+
+/// Start-equity (USD) voor `BacktestEngine::new`.
+pub const DEFAULT_BACKTEST_EQUITY_USD: f64 = 100_000.0;
+
+/// Historische simulatie van de macro-futures sleeve: één heartbeat per stap.
+///
+/// Orders van stap `i` worden gevuld op de close van stap `i + 1` (laatste bar van
+/// de history op dat moment); orders van de laatste stap blijven ongevuld.
+#[derive(Debug, Clone)]
+pub struct BacktestEngine {
+    pub sleeve: MacroFuturesSleeve,
+    pub risk_budget: FuturesRiskBudget,
+    pub max_sleeve_risk_eur: f64,
+    /// Start-equity in USD; ook `sleeve_equity_usd` in de context (vol-target sizing)
+    pub initial_equity_usd: f64,
+}
+
+/// Samenvatting van een backtest-run (bedragen in USD).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BacktestSummary {
+    pub n_steps: usize,
+    /// Aantal gevulde order-intents
+    pub n_trades: usize,
+    /// Som van |delta_contracts| over alle fills
+    pub contracts_traded: u32,
+    pub realized_pnl_usd: f64,
+    /// Mark-to-market PnL van de open posities op de laatste close
+    pub unrealized_pnl_usd: f64,
+    pub final_equity_usd: f64,
+    /// Grootste daling vanaf een eerdere equity-piek (>= 0)
+    pub max_drawdown_usd: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BacktestResult {
+    /// (as_of, equity in USD) per stap, op de close van die stap
+    pub equity_curve: Vec<(DateTime<Utc>, f64)>,
+    /// Gevulde order-intents in fill-volgorde
+    pub trade_log: Vec<FuturesOrderIntent>,
+    pub summary: BacktestSummary,
+}
+
+/// Handelsdagen per jaar voor annualisatie van de ratio's.
+const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+/// Std-dev / downside-deviation waaronder een ratio als ongedefinieerd (None) telt.
+const MIN_DEVIATION: f64 = 1e-12;
+
+impl BacktestResult {
+    /// Dagelijkse periode-returns: `e_t / e_{t-1} - 1`.
+    ///
+    /// Stappen met een niet-positieve of niet-eindige vorige equity worden overgeslagen.
+    /// Leeg bij < 2 punten.
+    fn daily_returns(&self) -> Vec<f64> {
+        self.equity_curve
+            .windows(2)
+            .filter(|w| w[0].1.is_finite() && w[0].1 > 0.0)
+            .map(|w| w[1].1 / w[0].1 - 1.0)
+            .collect()
+    }
+
+    /// Geannualiseerde Sharpe: `mean(r - rf) / std(r) * sqrt(252)` (sample std).
+    /// None bij < 2 punten of zonder variantie.
+    pub fn sharpe_ratio(&self, risk_free_rate_annual: f64) -> Option<f64> {
+        let returns = self.daily_returns();
+        if returns.is_empty() {
+            return None;
+        }
+        let rf_daily = risk_free_rate_annual / TRADING_DAYS_PER_YEAR;
+        let m = mean(&returns);
+        let sd = sample_std(&returns, m)?;
+        if sd < MIN_DEVIATION {
+            return None;
+        }
+        finite((m - rf_daily) / sd * TRADING_DAYS_PER_YEAR.sqrt())
+    }
+
+    /// Calmar: geannualiseerde return / max drawdown (beide als fractie).
+    /// None bij < 2 punten of zonder drawdown.
+    pub fn calmar_ratio(&self) -> Option<f64> {
+        let returns = self.daily_returns();
+        if returns.is_empty() {
+            return None;
+        }
+        let start = self.equity_curve.first()?.1;
+        let end = self.equity_curve.last()?.1;
+
+        let mut peak = f64::NEG_INFINITY;
+        let mut max_dd: f64 = 0.0;
+        for &(_, e) in &self.equity_curve {
+            peak = peak.max(e);
+            if peak > 0.0 {
+                max_dd = max_dd.max((peak - e) / peak);
+            }
+        }
+        if max_dd < MIN_DEVIATION {
+            return None;
+        }
+
+        let total = end / start;
+        if total <= 0.0 {
+            return None;
+        }
+        let annual = total.powf(TRADING_DAYS_PER_YEAR / returns.len() as f64) - 1.0;
+        finite(annual / max_dd)
+    }
+
+    /// Sortino: `mean(r - rf) / downside_dev * sqrt(252)`; downside-deviation alleen
+    /// over returns onder `target_return` (dagelijks). None bij < 2 punten of zonder downside.
+    pub fn sortino_ratio(&self, risk_free_rate_annual: f64, target_return: f64) -> Option<f64> {
+        let returns = self.daily_returns();
+        if returns.is_empty() {
+            return None;
+        }
+        let rf_daily = risk_free_rate_annual / TRADING_DAYS_PER_YEAR;
+        let downside = (returns
+            .iter()
+            .map(|r| (r - target_return).min(0.0).powi(2))
+            .sum::<f64>()
+            / returns.len() as f64)
+            .sqrt();
+        if downside < MIN_DEVIATION {
+            return None;
+        }
+        finite((mean(&returns) - rf_daily) / downside * TRADING_DAYS_PER_YEAR.sqrt())
+    }
+}
+
+fn mean(xs: &[f64]) -> f64 {
+    xs.iter().sum::<f64>() / xs.len() as f64
+}
+
+/// Sample std-dev (n - 1); None bij < 2 waarden.
+fn sample_std(xs: &[f64], mean: f64) -> Option<f64> {
+    if xs.len() < 2 {
+        return None;
+    }
+    let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (xs.len() - 1) as f64;
+    Some(var.sqrt())
+}
+
+fn finite(x: f64) -> Option<f64> {
+    x.is_finite().then_some(x)
+}
+
+/// Open positie in de simulatie (signed contracts + gemiddelde entry-prijs).
+#[derive(Debug, Clone, Copy, Default)]
+struct SimPosition {
+    contracts: i32,
+    avg_entry: f64,
+}
+
+impl BacktestEngine {
+    pub fn new(
+        sleeve: MacroFuturesSleeve,
+        risk_budget: FuturesRiskBudget,
+        max_sleeve_risk_eur: f64,
+    ) -> Self {
+        Self {
+            sleeve,
+            risk_budget,
+            max_sleeve_risk_eur,
+            initial_equity_usd: DEFAULT_BACKTEST_EQUITY_USD,
+        }
+    }
+
+    /// Draai één heartbeat per stap; het aantal stappen is de kortste van de drie slices.
+    ///
+    /// `histories_over_time[i]` is de history zoals die op stap `i` bekend was
+    /// (laatste bar = "vandaag"); `as_of` komt uit `macro_scalars_series[i]`.
+    pub fn run(
+        &self,
+        histories_over_time: &[HashMap<FutureInstrument, InstrumentHistory>],
+        macro_scalars_series: &[MacroScalars],
+        envelopes: &[SleeveRiskEnvelope],
+    ) -> BacktestResult {
+        let n_steps = histories_over_time
+            .len()
+            .min(macro_scalars_series.len())
+            .min(envelopes.len());
+
+        let mut positions: HashMap<FutureInstrument, SimPosition> = HashMap::new();
+        let mut pending: Vec<FuturesOrderIntent> = Vec::new();
+        let mut trade_log = Vec::new();
+        let mut equity_curve = Vec::with_capacity(n_steps);
+        let mut realized = 0.0;
+        let mut unrealized = 0.0;
+        let mut peak = self.initial_equity_usd;
+        let mut max_drawdown: f64 = 0.0;
+
+        for step in 0..n_steps {
+            let histories = &histories_over_time[step];
+            let closes: HashMap<FutureInstrument, f64> = histories
+                .iter()
+                .filter_map(|(&inst, h)| {
+                    h.bars
+                        .last()
+                        .map(|b| b.close)
+                        .filter(|c| c.is_finite() && *c > 0.0)
+                        .map(|c| (inst, c))
+                })
+                .collect();
+
+            // 1) Orders van de vorige stap vullen op de close van deze stap
+            for intent in pending.drain(..) {
+                let Some(&fill) = closes.get(&intent.instrument) else {
+                    continue;
+                };
+                let pos = positions.entry(intent.instrument).or_default();
+                realized += apply_fill(pos, intent.delta_contracts, fill)
+                    * self.multiplier(intent.instrument);
+                trade_log.push(intent);
+            }
+            positions.retain(|_, p| p.contracts != 0);
+
+            // 2) Mark-to-market op de huidige close
+            let mtm: f64 = positions
+                .iter()
+                .filter_map(|(inst, p)| {
+                    closes.get(inst).map(|&c| {
+                        p.contracts as f64 * (c - p.avg_entry) * self.multiplier(*inst)
+                    })
+                })
+                .sum();
+            if mtm.is_finite() {
+                unrealized = mtm;
+            }
+
+            let equity = self.initial_equity_usd + realized + unrealized;
+            let ms = macro_scalars_series[step];
+            equity_curve.push((ms.as_of, equity));
+            peak = peak.max(equity);
+            max_drawdown = max_drawdown.max(peak - equity);
+
+            // 3) Heartbeat met de posities van dit moment
+            let ctx = FuturesSleeveContext {
+                as_of: ms.as_of,
+                histories: histories.clone(),
+                macro_scalars: ms,
+                risk_envelope: envelopes[step],
+                current_positions: positions.iter().map(|(&i, p)| (i, p.contracts)).collect(),
+                position_entry_dates: HashMap::new(),
+                eur_per_usd: 1.0,
+                engine_health: EngineHealth::Healthy,
+                sleeve_equity_usd: equity.max(0.0),
+                position_convictions: HashMap::new(),
+                soft_halt_base_positions: HashMap::new(),
+            };
+            let hb = self
+                .sleeve
+                .run_heartbeat(&ctx, &self.risk_budget, self.max_sleeve_risk_eur);
+            pending = hb
+                .order_intents
+                .into_iter()
+                .filter(|i| i.delta_contracts != 0)
+                .collect();
+        }
+
+        let summary = BacktestSummary {
+            n_steps,
+            n_trades: trade_log.len(),
+            contracts_traded: trade_log.iter().map(|i| i.delta_contracts.unsigned_abs()).sum(),
+            realized_pnl_usd: realized,
+            unrealized_pnl_usd: unrealized,
+            final_equity_usd: self.initial_equity_usd + realized + unrealized,
+            max_drawdown_usd: max_drawdown,
+        };
+
+        BacktestResult {
+            equity_curve,
+            trade_log,
+            summary,
+        }
+    }
+
+    fn multiplier(&self, inst: FutureInstrument) -> f64 {
+        self.sleeve
+            .spec(inst)
+            .map_or(0.0, |s| s.contract_multiplier as f64)
+    }
+}
+
+/// Verwerk een fill in `pos`; geeft de gerealiseerde PnL in prijspunten
+/// (nog te vermenigvuldigen met de contract-multiplier).
+fn apply_fill(pos: &mut SimPosition, delta: i32, fill: f64) -> f64 {
+    let current = pos.contracts;
+    let target = current + delta;
+
+    // Openen / vergroten: gewogen gemiddelde entry
+    if current == 0 || current.signum() == delta.signum() {
+        let total = current.abs() + delta.abs();
+        pos.avg_entry =
+            (pos.avg_entry * current.abs() as f64 + fill * delta.abs() as f64) / total as f64;
+        pos.contracts = target;
+        return 0.0;
+    }
+
+    // Verkleinen / sluiten / reversal
+    let closed = delta.abs().min(current.abs());
+    let pnl = closed as f64 * current.signum() as f64 * (fill - pos.avg_entry);
+    pos.contracts = target;
+    if target == 0 {
+        pos.avg_entry = 0.0;
+    } else if target.signum() != current.signum() {
+        pos.avg_entry = fill;
+    }
+    pnl
+}
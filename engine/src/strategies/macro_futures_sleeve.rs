@@ -1,16 +1,97 @@
-use std::collections::HashMap;
-use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
 
-use crate::risk::{SleeveRiskEnvelope, HaltState, SleeveId};
+use crate::risk::{SleeveRiskEnvelope, HaltState, RiskDecisionReason, SleeveId};
 use crate::execution::EngineHealth;
 
 // bv: use crate::risk::risk_kernel::SleeveRiskEnvelope;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FutureInstrument {
     Mes,   // Micro E-mini S&P 500
     Mnq,   // Micro E-mini Nasdaq 100
     SixE,  // 6E (Euro FX future)
+    Gc,    // Micro Gold (MGC)
+    Mcl,   // Micro WTI Crude Oil (MCL)
+    M2k,   // Micro E-mini Russell 2000
+    Zn,    // 10-Year T-Note (ZN)
+    Vx,    // VIX futures (VX)
+    Custom(u32), // user-geregistreerd via `InstrumentRegistry`
+}
+
+/// Welke carry-berekening de signal-pipeline voor een instrument gebruikt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CarryType {
+    /// Geen carry, alleen trend (MES/MNQ/M2K/MGC)
+    None,
+    /// Renteverschil uit `fx_carry` (6E)
+    Fx,
+    /// Roll yield uit `commodity_carry` (MCL)
+    Commodity,
+    /// Curve-slope uit `bond_carry` (ZN)
+    Bond,
+    /// VIX term slope uit `MacroScalars` (VX); trend telt niet mee
+    VixTermStructure,
+}
+
+/// Specificatie van een tradebaar instrument.
+#[derive(Debug, Clone)]
+pub struct InstrumentSpec {
+    pub symbol: String,
+    pub venue: String,
+    /// USD per punt
+    pub contract_multiplier: u32,
+    /// ATR-multiple voor de stop; None = config-default van de instrument-klasse
+    pub atr_stop_multiple: Option<f64>,
+    pub carry: CarryType,
+    /// Eigen risk-budget (custom instrumenten); None = veld uit `FuturesRiskBudget`.
+    /// Een custom instrument zonder budget wordt nooit getrade.
+    pub risk_budget: Option<InstrumentRiskBudget>,
+}
+
+/// Specs per instrument. `Default` bevat alle built-in instrumenten; eigen instrumenten
+/// komen erbij via `register` als `FutureInstrument::Custom(id)`.
+#[derive(Debug, Clone)]
+pub struct InstrumentRegistry {
+    specs: HashMap<FutureInstrument, InstrumentSpec>,
+}
+
+impl Default for InstrumentRegistry {
+    fn default() -> Self {
+        let specs = KNOWN_INSTRUMENTS
+            .iter()
+            .filter_map(|&inst| builtin_spec(inst).map(|spec| (inst, spec)))
+            .collect();
+        Self { specs }
+    }
+}
+
+impl InstrumentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registreer (of overschrijf) een custom instrument; geeft de bijbehorende id terug.
+    pub fn register(&mut self, id: u32, spec: InstrumentSpec) -> FutureInstrument {
+        let inst = FutureInstrument::Custom(id);
+        self.specs.insert(inst, spec);
+        inst
+    }
+
+    pub fn spec(&self, inst: FutureInstrument) -> Option<&InstrumentSpec> {
+        self.specs.get(&inst)
+    }
+
+    pub fn contains(&self, inst: FutureInstrument) -> bool {
+        self.specs.contains_key(&inst)
+    }
+
+    /// USD per punt; None voor een niet-geregistreerd instrument.
+    pub fn contract_multiplier(&self, inst: FutureInstrument) -> Option<f64> {
+        self.spec(inst).map(|s| s.contract_multiplier as f64)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -27,6 +108,33 @@ pub struct FxCarryFeatures {
     pub carry_rate_vol_252d: f64,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct CommodityCarryFeatures {
+    /// Geannualiseerde roll yield: (front - second) / spot * 12 (maandcontracten)
+    pub roll_yield_annualized: f64,
+    /// -1 (volle contango) .. +1 (volle backwardation)
+    pub backwardation_score: f64,
+}
+
+impl CommodityCarryFeatures {
+    /// Roll yield uit de front/second-month spread t.o.v. spot, geannualiseerd
+    /// voor maandelijkse rolls. Positief = backwardation (long verdient de roll).
+    pub fn roll_yield_from_spread(front: f64, second: f64, spot: f64) -> f64 {
+        if !front.is_finite() || !second.is_finite() || !spot.is_finite() || spot <= 0.0 {
+            return 0.0;
+        }
+        (front - second) / spot * 12.0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BondCarryFeatures {
+    /// 10y yield (in procentpunten)
+    pub yield_10y: f64,
+    /// 2y yield (in procentpunten)
+    pub yield_2y: f64,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct DailyFeatureBar {
     pub ts: DateTime<Utc>,
@@ -48,8 +156,21 @@ pub struct DailyFeatureBar {
     pub highest_close_50d: f64,
     pub lowest_close_50d: f64,
 
+    /// Open interest in contracts (>= 0)
+    pub open_interest: f64,
+
+    /// Session-VWAP (> 0)
+    pub vwap: f64,
+
+    /// Gemiddelde close over de laatste 5 bars (> 0)
+    pub ma_5d: f64,
+
     /// Alleen Some voor 6E, None voor MES/MNQ
     pub fx_carry: Option<FxCarryFeatures>,
+    /// Alleen Some voor commodity-futures met carry (MCL)
+    pub commodity_carry: Option<CommodityCarryFeatures>,
+    /// Alleen Some voor bond-futures (ZN)
+    pub bond_carry: Option<BondCarryFeatures>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -61,6 +182,25 @@ pub struct FuturesSleeveAggregate {
     pub instrument_count: usize,
 }
 
+impl FuturesSleeveAggregate {
+    /// Netto richting van de sleeve: signed / abs contracts, in [-1, 1].
+    /// 0.0 als er geen contracts zijn (i.p.v. NaN).
+    pub fn net_direction(&self) -> f64 {
+        if self.total_contracts_abs == 0 {
+            return 0.0;
+        }
+        (self.total_contracts_signed as f64 / self.total_contracts_abs as f64).clamp(-1.0, 1.0)
+    }
+
+    pub fn is_net_long(&self) -> bool {
+        self.total_contracts_signed > 0
+    }
+
+    pub fn is_net_short(&self) -> bool {
+        self.total_contracts_signed < 0
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FuturesSleevePlan {
     pub planned_contracts: Vec<FuturesPlannedContracts>,
@@ -69,6 +209,17 @@ pub struct FuturesSleevePlan {
     pub sanity: SleeveRiskSanity,
 }
 
+/// A/B-vergelijking van twee configs op dezelfde context, zie `compare_plans`.
+#[derive(Debug, Clone)]
+pub struct PlanComparison {
+    pub plan_a: FuturesSleevePlan,
+    pub plan_b: FuturesSleevePlan,
+    /// Per instrument: target contracts B - A (instrumenten uit beide plannen)
+    pub diff_contracts: HashMap<FutureInstrument, i32>,
+    /// Aggregate EUR-risk B - A
+    pub diff_risk_eur: f64,
+}
+
 
 #[derive(Debug, Clone)]
 pub struct InstrumentHistory {
@@ -77,25 +228,659 @@ pub struct InstrumentHistory {
     pub bars: Vec<DailyFeatureBar>,
 }
 
+impl InstrumentHistory {
+    /// Vul ontbrekende weekdagen (feestdagen, exchange closures) met forward-filled bars.
+    ///
+    /// Synthetische bar = kopie van de vorige bar met OHLC = vorige close, `volume = 0`
+    /// en `ret_*` = 0. Gaten van meer dan `max_gap_days` weekdagen worden niet gevuld
+    /// (dat is eerder een data-outage dan een holiday).
+    pub fn gap_fill_bars(&mut self, max_gap_days: u32) {
+        if self.bars.len() < 2 || max_gap_days == 0 {
+            return;
+        }
+
+        let mut filled = Vec::with_capacity(self.bars.len());
+
+        for pair in self.bars.windows(2) {
+            let (prev, next) = (pair[0], pair[1]);
+            filled.push(prev);
+
+            let missing: Vec<DateTime<Utc>> = (1..)
+                .map(|d| prev.ts + Duration::days(d))
+                .take_while(|ts| ts.date_naive() < next.ts.date_naive())
+                .filter(|ts| !matches!(ts.weekday(), Weekday::Sat | Weekday::Sun))
+                .collect();
+
+            if missing.is_empty() || missing.len() > max_gap_days as usize {
+                continue;
+            }
+
+            for ts in missing {
+                filled.push(DailyFeatureBar {
+                    ts,
+                    open: prev.close,
+                    high: prev.close,
+                    low: prev.close,
+                    volume: 0.0,
+                    ret_20d: 0.0,
+                    ret_60d: 0.0,
+                    ret_120d: 0.0,
+                    ..prev
+                });
+            }
+        }
+
+        if let Some(last) = self.bars.last() {
+            filled.push(*last);
+        }
+
+        self.bars = filled;
+    }
+
+    /// Drawdown per bar t.o.v. de hoogste close in de laatste `window` bars
+    /// (incl. de bar zelf), als positieve fractie: 0.10 = 10% onder de high.
+    ///
+    /// Lengte == `bars.len()`; ongeldige closes geven 0.0.
+    pub fn compute_drawdown_series(&self, window: usize) -> Vec<f64> {
+        let window = window.max(1);
+
+        (0..self.bars.len())
+            .map(|i| {
+                let close = self.bars[i].close;
+                let start = (i + 1).saturating_sub(window);
+                let high = self.bars[start..=i]
+                    .iter()
+                    .map(|b| b.close)
+                    .filter(|c| c.is_finite())
+                    .fold(f64::NEG_INFINITY, f64::max);
+
+                if !close.is_finite() || !high.is_finite() || high <= 0.0 {
+                    return 0.0;
+                }
+                (1.0 - close / high).max(0.0)
+            })
+            .collect()
+    }
+
+    /// Laad een history uit een CSV-file (eerste regel = header, komma-gescheiden).
+    ///
+    /// Verplicht: date / open / high / low / close / volume. Ontbrekende (of lege)
+    /// feature-kolommen worden uit de OHLCV berekend: ATR als gemiddelde true range,
+    /// `ret_*` als log-close verschil, `vol_*` als std-dev van log-returns.
+    /// Bars blijven in file-volgorde; carry-features zijn altijd None.
+    /// De geladen history moet `validate()` doorstaan.
+    pub fn from_csv(
+        instrument: FutureInstrument,
+        path: &Path,
+        header: CsvHeader,
+    ) -> Result<Self, HistoryLoadError> {
+        let text = std::fs::read_to_string(path).map_err(HistoryLoadError::Io)?;
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+
+        let header_cells: Vec<&str> = match lines.next() {
+            Some((_, l)) => split_csv_line(l),
+            None => return Err(HistoryLoadError::InsufficientData { rows: 0 }),
+        };
+        let find = |name: &str| header_cells.iter().position(|c| *c == name);
+        let required = |name: &str| {
+            find(name).ok_or_else(|| HistoryLoadError::MissingColumn(name.to_string()))
+        };
+        let optional = |name: &Option<String>| name.as_deref().and_then(find);
+
+        let cols_ohlcv = [
+            required(&header.open)?,
+            required(&header.high)?,
+            required(&header.low)?,
+            required(&header.close)?,
+            required(&header.volume)?,
+        ];
+        let date_col = required(&header.date)?;
+        let feature_cols = [
+            optional(&header.atr_14),
+            optional(&header.ret_20d),
+            optional(&header.ret_60d),
+            optional(&header.ret_120d),
+            optional(&header.vol_20d),
+            optional(&header.vol_60d),
+            optional(&header.vol_120d),
+            optional(&header.open_interest),
+            optional(&header.vwap),
+        ];
+
+        let mut rows: Vec<CsvRow> = Vec::new();
+        for (idx, line) in lines {
+            let cells = split_csv_line(line);
+            let cell = |col: usize| cells.get(col).copied().unwrap_or("");
+            let parse_error = |col: usize| HistoryLoadError::Parse {
+                line: idx + 1,
+                column: header_cells.get(col).unwrap_or(&"").to_string(),
+                value: cell(col).to_string(),
+            };
+
+            let ts = parse_csv_date(cell(date_col)).ok_or_else(|| parse_error(date_col))?;
+
+            let mut ohlcv = [0.0; 5];
+            for (v, &col) in ohlcv.iter_mut().zip(&cols_ohlcv) {
+                *v = cell(col).parse::<f64>().map_err(|_| parse_error(col))?;
+            }
+
+            let mut features = [None; 9];
+            for (v, col) in features.iter_mut().zip(feature_cols) {
+                if let Some(col) = col
+                    && !cell(col).is_empty()
+                {
+                    *v = Some(cell(col).parse::<f64>().map_err(|_| parse_error(col))?);
+                }
+            }
+
+            rows.push(CsvRow { ts, ohlcv, features });
+        }
+
+        if rows.is_empty() {
+            return Err(HistoryLoadError::InsufficientData { rows: 0 });
+        }
+
+        let hist = Self {
+            instrument,
+            bars: bars_from_csv_rows(&rows),
+        };
+        hist.validate().map_err(HistoryLoadError::Invalid)?;
+        Ok(hist)
+    }
+
+    /// Controleer tijdsvolgorde en OHLC-consistentie; geeft alle fouten, niet alleen de eerste.
+    ///
+    /// - timestamps strikt oplopend (gelijk → `DuplicateTimestamp`, terug → `MonotoneViolation`)
+    /// - `high >= max(open, close)` en `low <= min(open, close)`
+    /// - `close > 0`
+    pub fn validate(&self) -> Result<(), Vec<HistoryValidationError>> {
+        let errors = validate_bars(None, &self.bars);
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// Voeg nieuwe bars achteraan toe (bv. één bar per dag in productie).
+    ///
+    /// Alleen `new_bars` worden gevalideerd, met de huidige laatste bar als
+    /// voorganger; bij een fout wordt niets toegevoegd en komt de eerste fout terug.
+    pub fn extend(&mut self, new_bars: &[DailyFeatureBar]) -> Result<(), HistoryValidationError> {
+        if let Some(err) = validate_bars(self.bars.last(), new_bars).into_iter().next() {
+            return Err(err);
+        }
+        self.bars.extend_from_slice(new_bars);
+        Ok(())
+    }
+
+    /// Houd alleen de laatste `n` bars (begrenst geheugen bij lange live-runs).
+    pub fn trim_to_last_n(&mut self, n: usize) {
+        let excess = self.bars.len().saturating_sub(n);
+        self.bars.drain(..excess);
+    }
+}
+
+/// Fout in een bar (of bar-paar) van een `InstrumentHistory`, zie `validate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryValidationError {
+    /// Zelfde timestamp als de vorige bar
+    DuplicateTimestamp { ts: DateTime<Utc> },
+    /// high/low sluiten open/close niet in
+    InvalidOHLC { ts: DateTime<Utc>, high: f64, low: f64, open: f64, close: f64 },
+    /// close <= 0
+    NegativePrice { ts: DateTime<Utc>, close: f64 },
+    /// Timestamp vóór die van de vorige bar
+    MonotoneViolation { prev_ts: DateTime<Utc>, ts: DateTime<Utc> },
+}
+
+impl std::fmt::Display for HistoryValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryValidationError::DuplicateTimestamp { ts } => {
+                write!(f, "duplicate bar timestamp {}", ts)
+            }
+            HistoryValidationError::InvalidOHLC { ts, high, low, open, close } => write!(
+                f,
+                "inconsistent OHLC at {}: open {} high {} low {} close {}",
+                ts, open, high, low, close
+            ),
+            HistoryValidationError::NegativePrice { ts, close } => {
+                write!(f, "non-positive close {} at {}", close, ts)
+            }
+            HistoryValidationError::MonotoneViolation { prev_ts, ts } => {
+                write!(f, "bar at {} precedes previous bar at {}", ts, prev_ts)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HistoryValidationError {}
+
+/// Valideer `bars`; `prev` is de bar direct ervoor (voor de tijdsvolgorde van de eerste).
+fn validate_bars(
+    prev: Option<&DailyFeatureBar>,
+    bars: &[DailyFeatureBar],
+) -> Vec<HistoryValidationError> {
+    let mut errors = Vec::new();
+    let mut prev_ts = prev.map(|b| b.ts);
+
+    for bar in bars {
+        if let Some(p) = prev_ts {
+            if bar.ts == p {
+                errors.push(HistoryValidationError::DuplicateTimestamp { ts: bar.ts });
+            } else if bar.ts < p {
+                errors.push(HistoryValidationError::MonotoneViolation { prev_ts: p, ts: bar.ts });
+            }
+        }
+        prev_ts = Some(bar.ts);
+
+        if bar.high < bar.open.max(bar.close) || bar.low > bar.open.min(bar.close) {
+            errors.push(HistoryValidationError::InvalidOHLC {
+                ts: bar.ts,
+                high: bar.high,
+                low: bar.low,
+                open: bar.open,
+                close: bar.close,
+            });
+        }
+        if bar.close <= 0.0 {
+            errors.push(HistoryValidationError::NegativePrice { ts: bar.ts, close: bar.close });
+        }
+    }
+
+    errors
+}
+
+/// Kolomnamen in de CSV per veld van `DailyFeatureBar`.
+///
+/// Optionele kolommen (`None` of niet in de file) worden uit de OHLCV berekend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvHeader {
+    pub date: String,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    pub volume: String,
+    pub atr_14: Option<String>,
+    pub ret_20d: Option<String>,
+    pub ret_60d: Option<String>,
+    pub ret_120d: Option<String>,
+    pub vol_20d: Option<String>,
+    pub vol_60d: Option<String>,
+    pub vol_120d: Option<String>,
+    pub open_interest: Option<String>,
+    pub vwap: Option<String>,
+}
+
+impl Default for CsvHeader {
+    /// Kolomnamen gelijk aan de veldnamen (`date`, `open`, ..., `vwap`).
+    fn default() -> Self {
+        let opt = |name: &str| Some(name.to_string());
+        Self {
+            date: "date".to_string(),
+            open: "open".to_string(),
+            high: "high".to_string(),
+            low: "low".to_string(),
+            close: "close".to_string(),
+            volume: "volume".to_string(),
+            atr_14: opt("atr_14"),
+            ret_20d: opt("ret_20d"),
+            ret_60d: opt("ret_60d"),
+            ret_120d: opt("ret_120d"),
+            vol_20d: opt("vol_20d"),
+            vol_60d: opt("vol_60d"),
+            vol_120d: opt("vol_120d"),
+            open_interest: opt("open_interest"),
+            vwap: opt("vwap"),
+        }
+    }
+}
+
+/// Fout bij het laden van een `InstrumentHistory` uit CSV.
+#[derive(Debug)]
+pub enum HistoryLoadError {
+    /// File niet te lezen
+    Io(std::io::Error),
+    /// Verplichte kolom ontbreekt in de header
+    MissingColumn(String),
+    /// Cel is geen geldige datum / getal (`line` is 1-based)
+    Parse { line: usize, column: String, value: String },
+    /// Geen (of te weinig) data-regels
+    InsufficientData { rows: usize },
+    /// Wel te parsen, maar `InstrumentHistory::validate` faalt
+    Invalid(Vec<HistoryValidationError>),
+}
+
+impl std::fmt::Display for HistoryLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryLoadError::Io(e) => write!(f, "cannot read history file: {}", e),
+            HistoryLoadError::MissingColumn(c) => write!(f, "missing required column: {}", c),
+            HistoryLoadError::Parse { line, column, value } => {
+                write!(f, "line {}: invalid value {:?} in column {}", line, value, column)
+            }
+            HistoryLoadError::InsufficientData { rows } => {
+                write!(f, "insufficient data: {} rows", rows)
+            }
+            HistoryLoadError::Invalid(errors) => {
+                let msgs: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "invalid history: {}", msgs.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for HistoryLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HistoryLoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Eén geparste CSV-regel: OHLCV + optionele features in de volgorde van `CsvHeader`.
+struct CsvRow {
+    ts: DateTime<Utc>,
+    ohlcv: [f64; 5],
+    features: [Option<f64>; 9],
+}
+
+fn split_csv_line(line: &str) -> Vec<&str> {
+    line.split(',').map(|c| c.trim().trim_matches('"')).collect()
+}
+
+/// `YYYY-MM-DD` (00:00 UTC) of RFC 3339.
+fn parse_csv_date(v: &str) -> Option<DateTime<Utc>> {
+    if let Ok(d) = NaiveDate::parse_from_str(v, "%Y-%m-%d") {
+        return d.and_hms_opt(0, 0, 0).map(|dt| dt.and_utc());
+    }
+    DateTime::parse_from_rfc3339(v).ok().map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Bouw bars uit CSV-regels; ontbrekende features uit de OHLCV.
+fn bars_from_csv_rows(rows: &[CsvRow]) -> Vec<DailyFeatureBar> {
+    let closes: Vec<f64> = rows.iter().map(|r| r.ohlcv[3]).collect();
+    // log_rets[i] = ln(close_i / close_{i-1}); index 0 heeft geen return
+    let log_rets: Vec<f64> = (0..closes.len())
+        .map(|i| if i == 0 { 0.0 } else { (closes[i] / closes[i - 1]).ln() })
+        .collect();
+
+    let log_ret_n = |i: usize, n: usize| {
+        if i >= n { (closes[i] / closes[i - n]).ln() } else { 0.0 }
+    };
+    let vol_n = |i: usize, n: usize| {
+        let start = (i + 1).saturating_sub(n).max(1);
+        let window = log_rets.get(start..=i).unwrap_or(&[]);
+        if window.len() < 2 {
+            return 0.0;
+        }
+        let m = window.iter().sum::<f64>() / window.len() as f64;
+        let var = window.iter().map(|r| (r - m).powi(2)).sum::<f64>() / (window.len() - 1) as f64;
+        var.sqrt()
+    };
+    let true_range = |i: usize| {
+        let [_, high, low, _, _] = rows[i].ohlcv;
+        match i.checked_sub(1).map(|p| closes[p]) {
+            Some(prev) => (high - low).max((high - prev).abs()).max((low - prev).abs()),
+            None => high - low,
+        }
+    };
+    let mean_over = |i: usize, n: usize, f: &dyn Fn(usize) -> f64| {
+        let start = (i + 1).saturating_sub(n);
+        (start..=i).map(f).sum::<f64>() / (i + 1 - start) as f64
+    };
+
+    rows.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let [open, high, low, close, volume] = row.ohlcv;
+            let [atr, r20, r60, r120, v20, v60, v120, oi, vwap] = row.features;
+            let start_50 = (i + 1).saturating_sub(50);
+            let closes_50 = &closes[start_50..=i];
+
+            DailyFeatureBar {
+                ts: row.ts,
+                open,
+                high,
+                low,
+                close,
+                volume,
+                atr_14: atr.unwrap_or_else(|| mean_over(i, 14, &true_range)),
+                ret_20d: r20.unwrap_or_else(|| log_ret_n(i, 20)),
+                ret_60d: r60.unwrap_or_else(|| log_ret_n(i, 60)),
+                ret_120d: r120.unwrap_or_else(|| log_ret_n(i, 120)),
+                vol_20d: v20.unwrap_or_else(|| vol_n(i, 20)),
+                vol_60d: v60.unwrap_or_else(|| vol_n(i, 60)),
+                vol_120d: v120.unwrap_or_else(|| vol_n(i, 120)),
+                highest_close_50d: closes_50.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                lowest_close_50d: closes_50.iter().copied().fold(f64::INFINITY, f64::min),
+                open_interest: oi.unwrap_or(0.0),
+                // Zonder VWAP-kolom: typical price als benadering
+                vwap: vwap.unwrap_or((high + low + close) / 3.0),
+                ma_5d: mean_over(i, 5, &|j| closes[j]),
+                fx_carry: None,
+                commodity_carry: None,
+                bond_carry: None,
+            }
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct MacroScalars {
     pub as_of: DateTime<Utc>,
     pub risk_on_scalar: f64, // 0.7 .. 1.3
     pub usd_scalar: f64,     // 0.7 .. 1.3
+    /// VIX term slope (zie `VolatilityRegime::vix_term_slope`); > 0 = contango
+    pub vix_term_slope: f64,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct InstrumentRiskBudget {
     pub max_risk_per_position_eur: f64,
     pub max_contracts: u32,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// In TOML: top-level `max_total_contracts` / `max_total_risk_eur` plus een sectie
+/// per instrument (`[mes]`, `[mnq]`, `[sixe]`, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct FuturesRiskBudget {
     pub mes: InstrumentRiskBudget,   // v1: 90 EUR, 3 contracts
     pub mnq: InstrumentRiskBudget,   // v1: 90 EUR, 3 contracts
     pub sixe: InstrumentRiskBudget,  // v1: 60 EUR, 3 contracts
+    pub gc: InstrumentRiskBudget,    // v1: 80 EUR, 2 contracts
+    pub mcl: InstrumentRiskBudget,   // v1: 80 EUR, 2 contracts
+    pub m2k: InstrumentRiskBudget,   // v1: 90 EUR, 3 contracts
+    pub zn: InstrumentRiskBudget,     // v1: 100 EUR, 1 contract
+    pub vx: InstrumentRiskBudget,     // v1: 100 EUR, 1 contract
     pub max_total_contracts: u32,    // v1: 3 contracts totaal
+    pub max_total_risk_eur: f64,     // sleeve-breed risico-plafond in EUR
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetValidationError {
+    /// max_contracts (of max_total_contracts) is 0 → er kan nooit iets getrade worden
+    MaxContractsZero,
+    /// max_risk_per_position_eur <= 0 of NaN
+    MaxRiskNotPositive,
+    /// max_risk_per_position_eur is oneindig → geen effectieve cap
+    MaxRiskInfinite,
+}
+
+impl std::fmt::Display for BudgetValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetValidationError::MaxContractsZero => write!(f, "max contracts must be > 0"),
+            BudgetValidationError::MaxRiskNotPositive => {
+                write!(f, "max_risk_per_position_eur must be > 0")
+            }
+            BudgetValidationError::MaxRiskInfinite => {
+                write!(f, "max_risk_per_position_eur must be finite")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BudgetValidationError {}
+
+impl InstrumentRiskBudget {
+    /// v1-budget voor micro gold: 80 EUR risico, max 2 contracts.
+    pub fn default_gold() -> Self {
+        Self {
+            max_risk_per_position_eur: 80.0,
+            max_contracts: 2,
+        }
+    }
+
+    /// v1-budget voor micro crude: 80 EUR risico, max 2 contracts.
+    pub fn default_crude() -> Self {
+        Self {
+            max_risk_per_position_eur: 80.0,
+            max_contracts: 2,
+        }
+    }
+
+    /// v1-budget voor ZN: groot contract → max 1 contract, 100 EUR risico.
+    pub fn default_bond() -> Self {
+        Self {
+            max_risk_per_position_eur: 100.0,
+            max_contracts: 1,
+        }
+    }
+
+    /// v1-budget voor VX: conservatief, max 1 contract, 100 EUR risico.
+    pub fn default_vix() -> Self {
+        Self {
+            max_risk_per_position_eur: 100.0,
+            max_contracts: 1,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), BudgetValidationError> {
+        if self.max_contracts == 0 {
+            return Err(BudgetValidationError::MaxContractsZero);
+        }
+        if self.max_risk_per_position_eur.is_infinite() {
+            return Err(BudgetValidationError::MaxRiskInfinite);
+        }
+        if self.max_risk_per_position_eur.is_nan() || self.max_risk_per_position_eur <= 0.0 {
+            return Err(BudgetValidationError::MaxRiskNotPositive);
+        }
+        Ok(())
+    }
+}
+
+impl FuturesRiskBudget {
+    /// Valideert alle instrument-budgets plus de sleeve-brede contract-cap.
+    pub fn validate(&self) -> Result<(), BudgetValidationError> {
+        self.mes.validate()?;
+        self.mnq.validate()?;
+        self.sixe.validate()?;
+        self.gc.validate()?;
+        self.mcl.validate()?;
+        self.m2k.validate()?;
+        self.zn.validate()?;
+        self.vx.validate()?;
+        if self.max_total_contracts == 0 {
+            return Err(BudgetValidationError::MaxContractsZero);
+        }
+        if self.max_total_risk_eur.is_infinite() {
+            return Err(BudgetValidationError::MaxRiskInfinite);
+        }
+        if self.max_total_risk_eur.is_nan() || self.max_total_risk_eur <= 0.0 {
+            return Err(BudgetValidationError::MaxRiskNotPositive);
+        }
+        Ok(())
+    }
+
+    /// Laad en valideer een budget uit een TOML-file.
+    pub fn from_toml_file(path: &Path) -> Result<Self, ConfigError> {
+        let budget: Self = read_toml_file(path)?;
+        budget
+            .validate()
+            .map_err(|e| ConfigError::Invalid(vec![e.to_string()]))?;
+        Ok(budget)
+    }
+}
+
+/// Fout bij het laden van een config- of budget-file.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// File niet te lezen
+    Io(std::io::Error),
+    /// Geen geldige TOML of velden kloppen niet met de struct
+    Parse(toml::de::Error),
+    /// Wel te parsen, maar de validatie faalt (één melding per fout)
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "cannot read config file: {}", e),
+            ConfigError::Parse(e) => write!(f, "invalid config TOML: {}", e),
+            ConfigError::Invalid(errors) => write!(f, "invalid config: {}", errors.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            ConfigError::Parse(e) => Some(e),
+            ConfigError::Invalid(_) => None,
+        }
+    }
+}
+
+fn read_toml_file<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+    toml::from_str(&text).map_err(ConfigError::Parse)
+}
+
+/// Aparte budgets voor nieuwe posities en voor bijschalen in bestaande posities
+/// (typisch lagere caps voor `scale_in` om agressief middelen te voorkomen).
+#[derive(Debug, Clone, Copy)]
+pub struct SplitRiskBudget {
+    pub initial_entry: FuturesRiskBudget,
+    pub scale_in: FuturesRiskBudget,
+}
+
+impl SplitRiskBudget {
+    /// Per instrument het `scale_in`-budget als er al een positie is, anders `initial_entry`.
+    /// `scale_in.max_contracts` begrenst de bijschaling, niet de totale positie:
+    /// cap = `|current| + scale_in.max_contracts`. Sleeve-brede caps komen uit `initial_entry`.
+    pub fn effective_budget(
+        &self,
+        current_positions: &HashMap<FutureInstrument, i32>,
+    ) -> FuturesRiskBudget {
+        let pick = |inst: FutureInstrument, field: fn(&FuturesRiskBudget) -> InstrumentRiskBudget| {
+            let current = current_positions.get(&inst).copied().unwrap_or(0).unsigned_abs();
+            if current == 0 {
+                return field(&self.initial_entry);
+            }
+            let scale_in = field(&self.scale_in);
+            InstrumentRiskBudget {
+                max_contracts: current.saturating_add(scale_in.max_contracts),
+                ..scale_in
+            }
+        };
+
+        FuturesRiskBudget {
+            mes: pick(FutureInstrument::Mes, |b| b.mes),
+            mnq: pick(FutureInstrument::Mnq, |b| b.mnq),
+            sixe: pick(FutureInstrument::SixE, |b| b.sixe),
+            gc: pick(FutureInstrument::Gc, |b| b.gc),
+            mcl: pick(FutureInstrument::Mcl, |b| b.mcl),
+            m2k: pick(FutureInstrument::M2k, |b| b.m2k),
+            zn: pick(FutureInstrument::Zn, |b| b.zn),
+            vx: pick(FutureInstrument::Vx, |b| b.vx),
+            ..self.initial_entry
+        }
+    }
 }
 
 
@@ -105,12 +890,16 @@ pub enum SignalReason {
     InsufficientHistory,
     InvalidData,
     BelowThreshold,
+    /// Laatste bar ouder dan `max_bar_age_hours` t.o.v. `as_of` (bevroren feed)
+    StaleData,
+    /// Volume van de signal-bar onder `min_volume_contracts` (dunne markt)
+    LowVolume,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct RawSignal {
     pub trend_score: f64,  // -3 .. +3
-    pub carry_score: f64,  // -2 .. +2 (6E), 0 anders
+    pub carry_score: f64,  // -2 .. +2 (6E / MCL / ZN / VX), 0 anders
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -153,40 +942,249 @@ pub struct FuturesSleeveContext {
     pub macro_scalars: MacroScalars,
     pub risk_envelope: SleeveRiskEnvelope,
     pub current_positions: HashMap<FutureInstrument, i32>, // signed contracts
+    /// Entry-moment van open posities (voor age-based sizing); ontbreekt = "nieuw"
+    pub position_entry_dates: HashMap<FutureInstrument, DateTime<Utc>>,
 
     /// EUR per 1 USD (account in EUR, futures in USD)
     /// Voor MES/MNQ/6E is contract_notional_usd in USD;
     /// risk in EUR = contract_notional_usd * eur_per_usd.
     pub eur_per_usd: f64,
     pub engine_health: EngineHealth,
+    /// Sleeve-equity in USD (nodig voor vol-target sizing)
+    pub sleeve_equity_usd: f64,
+    /// Conviction bij de laatste order per open positie (voor `entry_filter_threshold`)
+    pub position_convictions: HashMap<FutureInstrument, f64>,
+    /// Posities bij de eerste heartbeat van de lopende SoftHalt; ontbreekt = huidige positie.
+    /// Bijgehouden door `MacroFuturesSleeveState::record_soft_halt`.
+    pub soft_halt_base_positions: HashMap<FutureInstrument, i32>,
 }
 
-#[derive(Debug, Clone)]
-pub struct MacroFuturesHeartbeatOutput {
-    pub sleeve_plan: FuturesSleevePlan,
-    pub order_intents: Vec<FuturesOrderIntent>,
-}
+impl FuturesSleeveContext {
+    /// Start een builder; handig in tests en bij incrementele opbouw in productie.
+    pub fn builder() -> FuturesSleeveContextBuilder {
+        FuturesSleeveContextBuilder::default()
+    }
 
+    /// Consistentie-check vóór het plannen (één melding per fout):
+    /// `eur_per_usd`, de macro-scalars en `max_position_size_usd`.
+    ///
+    /// Een open positie zonder history is géén fout: die wordt bewust geflat
+    /// (zie `plan_order_intents`) en staat in `warnings`.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        let mut positive = |name: &str, v: f64| {
+            if !v.is_finite() || v <= 0.0 {
+                errors.push(format!("{} must be finite and > 0, got {}", name, v));
+            }
+        };
+        positive("eur_per_usd", self.eur_per_usd);
+        positive("macro_scalars.risk_on_scalar", self.macro_scalars.risk_on_scalar);
+        positive("macro_scalars.usd_scalar", self.macro_scalars.usd_scalar);
 
-#[derive(Debug, Clone)]
-pub struct InstrumentRiskIntent {
-    /// Welk instrument
-    pub instrument: FutureInstrument,
-    /// -1.0 .. +1.0: fractie van het instrument-budget die we willen gebruiken.
-    /// direction * conviction
-    pub desired_risk_frac: f64,
-    /// Volledige signal stack voor logging / beslissingen hogerop.
-    pub signal: InstrumentSignal,
-}
+        let max_pos = self.risk_envelope.max_position_size_usd;
+        if max_pos.is_nan() || max_pos < 0.0 {
+            errors.push(format!("risk_envelope.max_position_size_usd must be >= 0, got {}", max_pos));
+        }
 
-#[derive(Debug, Clone, Copy)]
-pub struct FuturesPlannedContracts {
-    pub instrument: FutureInstrument,
-    /// Signed target: +3 = long 3 contracts, -2 = short 2 contracts
-    pub target_contracts: i32,
-}
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
 
-#[derive(Debug, Clone, Copy)]
+    /// Niet-fatale meldingen: open posities zonder history (worden geflat).
+    /// Deterministische volgorde.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut held: Vec<(FutureInstrument, i32)> = self
+            .current_positions
+            .iter()
+            .filter(|&(inst, &qty)| qty != 0 && !self.histories.contains_key(inst))
+            .map(|(&inst, &qty)| (inst, qty))
+            .collect();
+        held.sort_by_key(|(inst, _)| format!("{:?}", inst));
+        held.into_iter()
+            .map(|(inst, qty)| {
+                format!("current_positions[{:?}] = {} has no history; position will be flattened", inst, qty)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    /// Verplicht veld is niet gezet
+    MissingField(&'static str),
+    /// eur_per_usd moet > 0 en finite zijn
+    InvalidEurPerUsd(f64),
+    /// Context is compleet maar faalt `FuturesSleeveContext::validate`
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::MissingField(field) => write!(f, "missing required field: {}", field),
+            BuildError::InvalidEurPerUsd(v) => write!(f, "eur_per_usd must be > 0, got {}", v),
+            BuildError::Invalid(errors) => write!(f, "invalid context: {}", errors.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Builder voor `FuturesSleeveContext`.
+///
+/// Verplicht: `macro_scalars` en `risk_envelope`.
+/// Defaults: `as_of` = `macro_scalars.as_of`, `eur_per_usd` = 1.0, `engine_health` = Healthy,
+/// geen posities. `build` draait ook `FuturesSleeveContext::validate`.
+#[derive(Debug, Clone, Default)]
+pub struct FuturesSleeveContextBuilder {
+    as_of: Option<DateTime<Utc>>,
+    histories: HashMap<FutureInstrument, InstrumentHistory>,
+    macro_scalars: Option<MacroScalars>,
+    risk_envelope: Option<SleeveRiskEnvelope>,
+    current_positions: HashMap<FutureInstrument, i32>,
+    position_entry_dates: HashMap<FutureInstrument, DateTime<Utc>>,
+    eur_per_usd: Option<f64>,
+    sleeve_equity_usd: f64,
+    position_convictions: HashMap<FutureInstrument, f64>,
+    soft_halt_base_positions: HashMap<FutureInstrument, i32>,
+    engine_health: Option<EngineHealth>,
+}
+
+impl FuturesSleeveContextBuilder {
+    pub fn with_as_of(mut self, dt: DateTime<Utc>) -> Self {
+        self.as_of = Some(dt);
+        self
+    }
+
+    pub fn with_history(mut self, inst: FutureInstrument, hist: InstrumentHistory) -> Self {
+        self.histories.insert(inst, hist);
+        self
+    }
+
+    pub fn with_macro_scalars(mut self, ms: MacroScalars) -> Self {
+        self.macro_scalars = Some(ms);
+        self
+    }
+
+    pub fn with_risk_envelope(mut self, env: SleeveRiskEnvelope) -> Self {
+        self.risk_envelope = Some(env);
+        self
+    }
+
+    /// Signed contracts voor een bestaande positie.
+    pub fn with_current_position(mut self, inst: FutureInstrument, contracts: i32) -> Self {
+        self.current_positions.insert(inst, contracts);
+        self
+    }
+
+    pub fn with_eur_per_usd(mut self, v: f64) -> Self {
+        self.eur_per_usd = Some(v);
+        self
+    }
+
+    pub fn with_position_entry_date(mut self, inst: FutureInstrument, entry: DateTime<Utc>) -> Self {
+        self.position_entry_dates.insert(inst, entry);
+        self
+    }
+
+    pub fn with_sleeve_equity_usd(mut self, equity: f64) -> Self {
+        self.sleeve_equity_usd = equity;
+        self
+    }
+
+    pub fn with_position_conviction(mut self, inst: FutureInstrument, conviction: f64) -> Self {
+        self.position_convictions.insert(inst, conviction);
+        self
+    }
+
+    /// Signed contracts bij de eerste heartbeat van de lopende SoftHalt.
+    pub fn with_soft_halt_base_position(mut self, inst: FutureInstrument, contracts: i32) -> Self {
+        self.soft_halt_base_positions.insert(inst, contracts);
+        self
+    }
+
+    pub fn with_engine_health(mut self, h: EngineHealth) -> Self {
+        self.engine_health = Some(h);
+        self
+    }
+
+    pub fn build(self) -> Result<FuturesSleeveContext, BuildError> {
+        let macro_scalars = self
+            .macro_scalars
+            .ok_or(BuildError::MissingField("macro_scalars"))?;
+        let risk_envelope = self
+            .risk_envelope
+            .ok_or(BuildError::MissingField("risk_envelope"))?;
+
+        let eur_per_usd = self.eur_per_usd.unwrap_or(1.0);
+        if !eur_per_usd.is_finite() || eur_per_usd <= 0.0 {
+            return Err(BuildError::InvalidEurPerUsd(eur_per_usd));
+        }
+
+        let ctx = FuturesSleeveContext {
+            as_of: self.as_of.unwrap_or(macro_scalars.as_of),
+            histories: self.histories,
+            macro_scalars,
+            risk_envelope,
+            current_positions: self.current_positions,
+            eur_per_usd,
+            engine_health: self.engine_health.unwrap_or(EngineHealth::Healthy),
+            position_entry_dates: self.position_entry_dates,
+            sleeve_equity_usd: self.sleeve_equity_usd,
+            position_convictions: self.position_convictions,
+            soft_halt_base_positions: self.soft_halt_base_positions,
+        };
+        ctx.validate().map_err(BuildError::Invalid)?;
+        Ok(ctx)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MacroFuturesHeartbeatOutput {
+    /// `ctx.as_of` van de heartbeat (signal-timestamp)
+    pub as_of: DateTime<Utc>,
+    pub sleeve_plan: FuturesSleevePlan,
+    pub order_intents: Vec<FuturesOrderIntent>,
+    /// Signals van deze heartbeat (input voor de volgende `run_heartbeat_with_prev`)
+    pub signals: Vec<InstrumentSignal>,
+    /// Signals van de vorige heartbeat, indien meegegeven
+    pub prev_signals: Option<Vec<InstrumentSignal>>,
+    pub regime_changes: Vec<RegimeChange>,
+}
+
+/// Minimale conviction-sprong die als regime change telt.
+const REGIME_CONVICTION_JUMP: f64 = 0.3;
+
+/// Richting-wissel of conviction-sprong voor één instrument tussen twee heartbeats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegimeChange {
+    pub instrument: FutureInstrument,
+    pub prev_direction: i8,
+    pub curr_direction: i8,
+    pub prev_conviction: f64,
+    pub curr_conviction: f64,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct InstrumentRiskIntent {
+    /// Welk instrument
+    pub instrument: FutureInstrument,
+    /// -1.0 .. +1.0: fractie van het instrument-budget die we willen gebruiken.
+    /// direction * conviction
+    pub desired_risk_frac: f64,
+    /// Volledige signal stack voor logging / beslissingen hogerop.
+    pub signal: InstrumentSignal,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FuturesPlannedContracts {
+    pub instrument: FutureInstrument,
+    /// Signed target: +3 = long 3 contracts, -2 = short 2 contracts
+    pub target_contracts: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct FuturesPlannedRisk {
     pub instrument: FutureInstrument,
     /// Signed target contracts (identiek aan FuturesPlannedContracts)
@@ -197,6 +1195,25 @@ pub struct FuturesPlannedRisk {
     pub total_risk_eur: f64,
 }
 
+/// Geplande positie plus bijbehorende ATR-stop als reduce-only GTC-order.
+#[derive(Debug, Clone)]
+pub struct PlannedContractsWithStop {
+    pub plan: FuturesPlannedContracts,
+    /// None als er geen geldige prijs/ATR is
+    pub stop_order: Option<EngineOrder>,
+}
+
+/// `FuturesPlannedRisk` plus USD-prijscontext (zie `plan_risk_report_enriched`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuturesPlannedRiskEnriched {
+    pub base: FuturesPlannedRisk,
+    /// Laatste close in USD
+    pub last_price_usd: f64,
+    pub contract_multiplier: u32,
+    /// `last_price_usd * contract_multiplier`
+    pub contract_notional_usd: f64,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct FuturesOrderIntent {
     pub instrument: FutureInstrument,
@@ -204,37 +1221,505 @@ pub struct FuturesOrderIntent {
     pub delta_contracts: i32,
 }
 
+/// Order-intent met prijscontext voor pre-trade checks in de order router.
+#[derive(Debug, Clone, Copy)]
+pub struct FuturesOrderIntentPriced {
+    pub intent: FuturesOrderIntent,
+    /// Laatste close uit de history (None als ontbrekend / ongeldig)
+    pub last_price: Option<f64>,
+    /// `last_price * contract_multiplier` in USD
+    pub contract_notional_usd: Option<f64>,
+}
+
+/// Effect van één config-parameter (één richting) op de gross contract-allocatie.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamSensitivity {
+    /// Veldnaam; trend-windows als `trend_windows[<lookback>d]`
+    pub param_name: String,
+    /// Toegepaste relatieve shift (bijv. +0.1 = +10%)
+    pub perturbation: f64,
+    /// Som van |target_contracts| met de huidige config
+    pub base_contracts: i32,
+    /// Som van |target_contracts| met de geperturbeerde config
+    pub perturbed_contracts: i32,
+    pub delta: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensitivityReport {
+    /// Per parameter twee entries: eerst +perturbation, dan -perturbation.
+    pub per_param: Vec<ParamSensitivity>,
+}
+
+/// Pairwise correlaties van daily log-returns, zie `compute_portfolio_correlation`.
+/// Bevat beide ordeningen van elk paar (symmetrisch) plus de diagonaal (1.0).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationMatrix {
+    pub correlations: HashMap<(FutureInstrument, FutureInstrument), f64>,
+}
+
+impl CorrelationMatrix {
+    pub fn get(&self, a: FutureInstrument, b: FutureInstrument) -> Option<f64> {
+        self.correlations.get(&(a, b)).copied()
+    }
+}
+
+/// Directional hit-rate van de signals op één history, zie `backtest_signal_accuracy`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalAccuracyReport {
+    pub instrument: FutureInstrument,
+    /// Aantal niet-flat signals met een bekende forward return
+    pub n_signals: u32,
+    pub correct_direction: u32,
+    /// 0..100 (0.0 als er geen signals zijn)
+    pub accuracy_pct: f64,
+    /// Gem. forward return in signal-richting (direction * ret) bij een juiste richting
+    pub avg_ret_when_correct: f64,
+    /// Idem bij een verkeerde richting (≤ 0)
+    pub avg_ret_when_wrong: f64,
+}
+
+type ConfigParamAccessor = fn(&mut MacroFuturesSleeveConfig) -> &mut f64;
+
+/// Alle f64 config-parameters die `sensitivity_analysis` afloopt (plus `trend_windows`).
+/// Nieuwe f64-velden hier ook toevoegen. Filters met default 0.0 (uit) blijven bij een
+/// relatieve perturbatie 0.0 en geven dan delta 0.
+const SENSITIVITY_PARAMS: [(&str, ConfigParamAccessor); 27] = [
+    ("breakout_weight", |c| &mut c.breakout_weight),
+    ("oi_confirmation_weight", |c| &mut c.oi_confirmation_weight),
+    ("vwap_weight", |c| &mut c.vwap_weight),
+    ("mean_reversion_weight", |c| &mut c.mean_reversion_weight),
+    ("relative_strength_weight", |c| &mut c.relative_strength_weight),
+    ("trend_score_clip", |c| &mut c.trend_score_clip),
+    ("carry_score_clip", |c| &mut c.carry_score_clip),
+    ("carry_vol_floor", |c| &mut c.carry_vol_floor),
+    ("carry_weight_6e", |c| &mut c.carry_weight_6e),
+    ("carry_weight_commodity", |c| &mut c.carry_weight_commodity),
+    ("carry_weight_bond", |c| &mut c.carry_weight_bond),
+    ("vix_contango_threshold", |c| &mut c.vix_contango_threshold),
+    ("effective_score_clip", |c| &mut c.effective_score_clip),
+    ("logistic_k", |c| &mut c.logistic_k),
+    ("logistic_m", |c| &mut c.logistic_m),
+    ("min_effective_score", |c| &mut c.min_effective_score),
+    ("min_conviction", |c| &mut c.min_conviction),
+    ("atr_stop_multiple_index", |c| &mut c.atr_stop_multiple_index),
+    ("atr_stop_multiple_fx", |c| &mut c.atr_stop_multiple_fx),
+    ("atr_stop_multiple_gold", |c| &mut c.atr_stop_multiple_gold),
+    ("atr_stop_multiple_bond", |c| &mut c.atr_stop_multiple_bond),
+    ("min_volume_contracts", |c| &mut c.min_volume_contracts),
+    ("min_age_scalar", |c| &mut c.min_age_scalar),
+    ("entry_filter_threshold", |c| &mut c.entry_filter_threshold),
+    ("min_turnover_frac", |c| &mut c.min_turnover_frac),
+    ("min_carry_sharpe", |c| &mut c.min_carry_sharpe),
+    ("margin_buffer_pct", |c| &mut c.margin_buffer_pct),
+];
+
+/// Versie van `MacroFuturesSleeveConfig::json_schema`; ophogen bij schema-wijzigingen.
+///
+/// v2: `trend_weight_20d/60d/120d` vervangen door `trend_windows`; plus alle velden
+/// die na v1 zijn toegevoegd (o.a. `min_volume_contracts`, `min_delta_contracts`,
+/// `min_carry_sharpe`, `margin_buffer_pct`). Alle velden zijn optioneel (default).
+pub const SLEEVE_CONFIG_JSON_SCHEMA_VERSION: u32 = 2;
+
+/// JSON Schema (draft-7) van `MacroFuturesSleeveConfig`; defaults = `Default`-impl.
+const SLEEVE_CONFIG_JSON_SCHEMA: &str = r#"{
+  "$schema": "http://json-schema.org/draft-07/schema#",
+  "$id": "macro_futures_sleeve_config.schema.json",
+  "title": "MacroFuturesSleeveConfig",
+  "description": "Configuratie van de Macro Futures sleeve (MES / MNQ / M2K / 6E / MGC / MCL / ZN / VX)",
+  "type": "object",
+  "properties": {
+    "trend_windows": {
+      "type": "array",
+      "description": "Momentum-lookbacks als [lookback_days, weight]; 20/60/120 uit de bar, overige uit de closes",
+      "items": {
+        "type": "array",
+        "items": [
+          { "type": "integer", "minimum": 1 },
+          { "type": "number", "minimum": 0 }
+        ],
+        "minItems": 2,
+        "maxItems": 2
+      },
+      "default": [[20, 0.45], [60, 0.3], [120, 0.15]]
+    },
+    "breakout_weight": {
+      "type": "number",
+      "description": "Gewicht van de 50d-breakout component",
+      "default": 0.1,
+      "minimum": 0
+    },
+    "oi_confirmation_weight": {
+      "type": "number",
+      "description": "Gewicht van de open-interest bevestiging (0 = uit)",
+      "default": 0.0,
+      "minimum": 0
+    },
+    "vwap_weight": {
+      "type": "number",
+      "description": "Gewicht van de VWAP-relatieve score (0 = uit)",
+      "default": 0.0,
+      "minimum": 0
+    },
+    "mean_reversion_weight": {
+      "type": "number",
+      "description": "Gewicht van de 5d mean-reversion score (0 = uit)",
+      "default": 0.0,
+      "minimum": 0
+    },
+    "mean_reversion_weight_overrides": {
+      "type": "object",
+      "description": "Per-instrument override van mean_reversion_weight (bijv. {\"SixE\": 0.5})",
+      "additionalProperties": {
+        "type": "number",
+        "minimum": 0
+      },
+      "default": {}
+    },
+    "relative_strength_weight": {
+      "type": "number",
+      "description": "Tilt van desired_risk_frac door de MNQ/MES relative strength (0 = uit)",
+      "default": 0.0,
+      "minimum": 0
+    },
+    "trend_score_clip": {
+      "type": "number",
+      "description": "Clip op de raw trend-score (±)",
+      "default": 3.0,
+      "exclusiveMinimum": 0
+    },
+    "carry_score_clip": {
+      "type": "number",
+      "description": "Clip op de raw carry-score voor 6E (±)",
+      "default": 2.0,
+      "exclusiveMinimum": 0
+    },
+    "carry_vol_floor": {
+      "type": "number",
+      "description": "Ondergrens van de carry-vol in de carry z-score",
+      "default": 0.25,
+      "exclusiveMinimum": 0
+    },
+    "carry_weight_6e": {
+      "type": "number",
+      "description": "Carry-gewicht voor 6E vóór de USD-scalar",
+      "default": 0.5,
+      "minimum": 0
+    },
+    "carry_weight_commodity": {
+      "type": "number",
+      "description": "Carry-gewicht voor de MCL roll yield",
+      "default": 0.5,
+      "minimum": 0
+    },
+    "carry_weight_bond": {
+      "type": "number",
+      "description": "Carry-gewicht voor de ZN curve-carry (10y - 2y)",
+      "default": 0.5,
+      "minimum": 0
+    },
+    "vix_contango_threshold": {
+      "type": "number",
+      "description": "VIX term slope waarboven VX short gaat (contango)",
+      "default": 1.0
+    },
+    "effective_score_clip": {
+      "type": "number",
+      "description": "Clip op de effectieve score (±)",
+      "default": 5.0,
+      "exclusiveMinimum": 0
+    },
+    "logistic_k": {
+      "type": "number",
+      "description": "Steilheid van de logistic conviction-mapping",
+      "default": 1.3,
+      "exclusiveMinimum": 0
+    },
+    "logistic_m": {
+      "type": "number",
+      "description": "Midpoint van de logistic conviction-mapping",
+      "default": 1.3,
+      "minimum": 0
+    },
+    "min_effective_score": {
+      "type": "number",
+      "description": "Min. |effective_score| voor een niet-flat signal",
+      "default": 1.0,
+      "minimum": 0
+    },
+    "min_conviction": {
+      "type": "number",
+      "description": "Min. conviction voor een niet-flat signal",
+      "default": 0.3,
+      "minimum": 0,
+      "maximum": 1
+    },
+    "atr_stop_multiple_index": {
+      "type": "number",
+      "description": "ATR-multiple voor stop-risk per contract (MES/MNQ)",
+      "default": 0.25,
+      "exclusiveMinimum": 0
+    },
+    "atr_stop_multiple_fx": {
+      "type": "number",
+      "description": "ATR-multiple voor stop-risk per contract (6E)",
+      "default": 0.5,
+      "exclusiveMinimum": 0
+    },
+    "atr_stop_multiple_gold": {
+      "type": "number",
+      "description": "ATR-multiple voor stop-risk per contract (MGC)",
+      "default": 0.4,
+      "exclusiveMinimum": 0
+    },
+    "atr_stop_multiple_bond": {
+      "type": "number",
+      "description": "ATR-multiple voor stop-risk per contract (ZN)",
+      "default": 0.5,
+      "exclusiveMinimum": 0
+    },
+    "momentum_lag": {
+      "type": "integer",
+      "description": "Signal-lag in bars (0 = laatste bar)",
+      "default": 0,
+      "minimum": 0
+    },
+    "min_history_bars": {
+      "type": "integer",
+      "description": "Minimaal aantal bars per history",
+      "default": 120,
+      "minimum": 1
+    },
+    "max_bar_age_hours": {
+      "type": "integer",
+      "description": "Max. leeftijd (uren) van de laatste bar t.o.v. as_of",
+      "default": 48,
+      "minimum": 0
+    },
+    "min_volume_contracts": {
+      "type": "number",
+      "description": "Min. volume (contracts) op de signal-bar (0 = uit)",
+      "default": 0.0,
+      "minimum": 0
+    },
+    "min_age_scalar": {
+      "type": "number",
+      "description": "Ondergrens van de age-scalar na max_holding_days",
+      "default": 0.5,
+      "minimum": 0,
+      "maximum": 1
+    },
+    "use_continuous_breakout": {
+      "type": "boolean",
+      "description": "Continue breakout-strength i.p.v. -1/0/+1",
+      "default": false
+    },
+    "vol_target_pct": {
+      "type": [
+        "number",
+        "null"
+      ],
+      "description": "Vol-target in % (null = conviction * max_position_size)",
+      "default": null,
+      "exclusiveMinimum": 0
+    },
+    "vol_target_annual": {
+      "type": [
+        "number",
+        "null"
+      ],
+      "description": "Annual vol-contributie per positie (bijv. 0.10); schaalt max_position_size met target / (vol_20d * sqrt(252)). Niet samen met vol_target_pct",
+      "default": null,
+      "exclusiveMinimum": 0
+    },
+    "entry_filter_threshold": {
+      "type": "number",
+      "description": "Min. conviction-verandering voor bijkopen (0 = altijd)",
+      "default": 0.0,
+      "minimum": 0
+    },
+    "min_delta_contracts": {
+      "type": "integer",
+      "description": "Min. |delta| in contracts voor een order-intent (0 = altijd; reversals en sluiten altijd)",
+      "default": 0,
+      "minimum": 0
+    },
+    "min_turnover_frac": {
+      "type": "number",
+      "description": "Min. bijkoop-notional als fractie van max_position_size_usd (0 = altijd)",
+      "default": 0.0,
+      "minimum": 0,
+      "maximum": 1
+    },
+    "correlation_window": {
+      "type": "integer",
+      "description": "Aantal log-returns voor de pairwise correlatie-matrix",
+      "default": 60,
+      "minimum": 2
+    },
+    "min_carry_sharpe": {
+      "type": "number",
+      "description": "Min. carry-Sharpe (rate / vol) voor een volledige carry-quality",
+      "default": 0.5,
+      "exclusiveMinimum": 0
+    },
+    "margin_buffer_pct": {
+      "type": "number",
+      "description": "Fractie van de resterende margin die als reserve ongebruikt blijft",
+      "default": 0.1,
+      "minimum": 0,
+      "maximum": 1
+    }
+  },
+  "additionalProperties": false
+}"#;
+
+/// Versie van de sleeve-logica, mee gelogd in order-metadata.
+const SLEEVE_VERSION: &str = "1.0";
+
+/// Default minimale history-lengte voor een signal (ret_120d + marge).
+const MIN_BARS: usize = 120;
+
+/// Waarom een instrument (nog) geen positie krijgt, zie `explain_flat_signal`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlatExplanation {
+    /// Te weinig bars (of helemaal geen history voor dit instrument)
+    NoHistory { bars_available: usize, min_required: usize },
+    /// Laatste bar bevat een ongeldig feature-veld
+    InvalidData { field: &'static str },
+    /// Laatste bar is ouder dan `max_bar_age_hours`
+    StaleData { age_hours: i64, max_age_hours: u64 },
+    /// Volume van de signal-bar onder `min_volume_contracts`
+    LowVolume { volume: f64, min_required: f64 },
+    /// |effective_score| (of conviction) onder de drempel
+    BelowThreshold { effective_score: f64, min_required: f64 },
+    /// Portfolio- of sleeve-halt vanuit de risk-kernel
+    Halted { halt_state: HaltState },
+    /// Engine degraded → geen nieuwe posities
+    EngineDegraded,
+    /// Geen exposure/margin-headroom (of max_position_size = 0) over
+    NoHeadroom,
+    /// Alle concurrency-slots zijn bezet
+    ConcurrencyCapReached,
+    /// Geen blokkade gevonden; contracts volgen uit sizing/caps (eventueel 0)
+    NoPlan,
+}
+
+/// Waarom `plan_positions` een instrument overslaat, zie `plan_positions_verbose`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanSkipReason {
+    /// Portfolio- of sleeve-halt (Halt/Kill), of SoftHalt voor een nog niet aangehouden instrument
+    Halted,
+    /// Engine degraded → geen nieuwe posities
+    EngineDegraded,
+    /// Envelope laat geen posities toe (max size of max concurrency = 0)
+    NoRiskCapacity,
+    /// Flat signal of conviction 0
+    NoSignal,
+    /// Exposure- of margin-headroom op
+    NoHeadroom,
+    /// Alle concurrency-slots bezet voor een nieuw instrument
+    ConcurrencyCapReached,
+    /// Target-notional < $1 na headroom-fit (of niet te bepalen)
+    BelowMinNotional,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PositionPlanEntry {
+    pub instrument: FutureInstrument,
+    pub position: Option<FuturesPlannedPosition>,
+    pub skip_reason: Option<PlanSkipReason>,
+}
+
+impl PositionPlanEntry {
+    fn skipped(instrument: FutureInstrument, reason: PlanSkipReason) -> Self {
+        Self {
+            instrument,
+            position: None,
+            skip_reason: Some(reason),
+        }
+    }
+}
+
+/// Mismatch tussen een signal en de geplande positie voor hetzelfde instrument.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InconsistencyReport {
+    pub instrument: FutureInstrument,
+    /// Richting van het signal (-1 / 0 / +1)
+    pub signal_direction: i8,
+    /// Teken van de geplande contracts (-1 / 0 / +1)
+    pub planned_sign: i8,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EngineOrderSide {
     Buy,
     Sell,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Geldigheid van een order bij de broker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OrderExpiry {
+    /// Vervalt aan het einde van de handelsdag
+    #[default]
+    Day,
+    /// Good-till-cancelled (o.a. protective stops)
+    Gtc,
+}
+
+/// `Eq` is handmatig: de prijzen zijn altijd finite (stops worden vooraf gefilterd en
+/// JSON kent geen NaN), dus `PartialEq` is hier een volledige equivalentie.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EngineOrder {
     pub sleeve_id: SleeveId,
     pub instrument: FutureInstrument,
-    pub symbol: &'static str,
-    pub venue: &'static str,
+    pub symbol: String,
+    pub venue: String,
     pub side: EngineOrderSide,
     /// Absolute aantal contracts (altijd > 0)
     pub quantity: i32,
+    /// Vrije annotaties (sleeve_version, signal_ts, parent order id, ...)
+    pub metadata: HashMap<String, String>,
+    /// Limit-prijs; None = market (of stop-market met `stop_price`)
+    #[serde(default)]
+    pub limit_price: Option<f64>,
+    /// Trigger-prijs van een stop-order; None = geen stop
+    #[serde(default)]
+    pub stop_price: Option<f64>,
+    #[serde(default)]
+    pub expiry: OrderExpiry,
+    /// Mag de positie alleen verkleinen (stops / exits)
+    #[serde(default)]
+    pub is_reduce_only: bool,
 }
 
+impl Eq for EngineOrder {}
 
-#[derive(Debug, Clone)]
+
+/// Ontbrekende velden in TOML/JSON vallen terug op `Default`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MacroFuturesSleeveConfig {
     // Trend scoring
-    pub trend_weight_20d: f64,   // 0.45
-    pub trend_weight_60d: f64,   // 0.30
-    pub trend_weight_120d: f64,  // 0.15
+    pub trend_windows: Vec<(u32, f64)>, // (lookback_days, weight), classic: 20/60/120
     pub breakout_weight: f64,    // 0.10
+    pub oi_confirmation_weight: f64, // 0.0 (uit)
+    pub vwap_weight: f64,            // 0.0 (uit)
+    pub mean_reversion_weight: f64,  // 0.0 (uit)
+    // Per-instrument override van mean_reversion_weight (bijv. alleen 6E)
+    pub mean_reversion_weight_overrides: HashMap<FutureInstrument, f64>,
+    // Tilt van desired_risk_frac binnen RELATIVE_STRENGTH_PAIR (0.0 = uit)
+    pub relative_strength_weight: f64,
     pub trend_score_clip: f64,   // 3.0
 
     // Carry
     pub carry_score_clip: f64,   // 2.0
     pub carry_vol_floor: f64,    // 0.25
     pub carry_weight_6e: f64,    // 0.5
+    pub carry_weight_commodity: f64, // 0.5 (MCL roll yield)
+    pub carry_weight_bond: f64,      // 0.5 (ZN curve-carry)
+    pub vix_contango_threshold: f64, // 1.0 (VX short boven deze term slope)
 
     // Effective score
     pub effective_score_clip: f64, // 5.0
@@ -250,20 +1735,174 @@ pub struct MacroFuturesSleeveConfig {
     // ATR-gebaseerde stop-risk per contract
     pub atr_stop_multiple_index: f64, // bijv. 0.25 * ATR voor index futures
     pub atr_stop_multiple_fx: f64,    // bijv. 0.5 * ATR voor 6E
+    pub atr_stop_multiple_gold: f64,  // bijv. 0.4 * ATR voor MGC
+    pub atr_stop_multiple_bond: f64,  // bijv. 0.5 * ATR voor ZN
+
+    // Signal-lag in bars (0 = geen lag) tegen whipsaws
+    pub momentum_lag: u32,
+
+    // Minimaal aantal bars per history (default MIN_BARS; lager voor pure 20d-varianten)
+    pub min_history_bars: usize,
+
+    // Max. leeftijd van de laatste bar t.o.v. as_of voordat het signal als stale geldt
+    pub max_bar_age_hours: u64,
+
+    // Min. volume (contracts) op de signal-bar; daaronder geen signal (0.0 = uit)
+    pub min_volume_contracts: f64,
+
+    // Ondergrens van de age-scalar voor posities op/na max_holding_days
+    pub min_age_scalar: f64,
+
+    // Breakout als positie binnen de 50d-range ([-1, 1]) i.p.v. -1/0/+1
+    pub use_continuous_breakout: bool,
+
+    // Vol-targeting (in %, bijv. 10.0): None = conviction * max_position_size
+    pub vol_target_pct: Option<f64>,
+
+    // Annual vol-contributie per positie (bijv. 0.10): max_position_size * target / instrument-vol.
+    // Niet samen met vol_target_pct (zie `validate`).
+    pub vol_target_annual: Option<f64>,
+
+    // Min. conviction-verandering voor bijkopen in dezelfde richting (0.0 = altijd)
+    pub entry_filter_threshold: f64,
+
+    // Turnover-filter: min. |delta| in contracts (0 = uit) en min. bijkoop-notional
+    // als fractie van max_position_size_usd (0.0 = uit). Reversals en sluiten gaan altijd door.
+    pub min_delta_contracts: u32,
+    pub min_turnover_frac: f64,
+
+    // Aantal log-returns voor `compute_portfolio_correlation`
+    pub correlation_window: usize,
+
+    // Min. carry-Sharpe (rate / vol) voor de volledige Sharpe-component van de carry-quality
+    pub min_carry_sharpe: f64,
+
+    // Fractie van margin_remaining die ongebruikt blijft (0.10 = 10% reserve)
+    pub margin_buffer_pct: f64,
 }
 
 
+impl MacroFuturesSleeveConfig {
+    /// JSON Schema (draft-7) voor config-files, bedoeld voor editor-validatie.
+    pub fn json_schema() -> &'static str {
+        SLEEVE_CONFIG_JSON_SCHEMA
+    }
+
+    /// Laad een config uit een TOML-file en valideer hem (zie `validate`).
+    pub fn from_toml_file(path: &Path) -> Result<Self, ConfigError> {
+        let cfg: Self = read_toml_file(path)?;
+        cfg.validate().map_err(ConfigError::Invalid)?;
+        Ok(cfg)
+    }
+
+    /// De klassieke 20d/60d/120d-setup (= `Default`).
+    pub fn classic() -> Self {
+        Self::default()
+    }
+
+    /// Trend-lookbacks van de klassieke setup: 20d (0.45), 60d (0.30), 120d (0.15).
+    pub fn classic_trend_windows() -> Vec<(u32, f64)> {
+        vec![(20, 0.45), (60, 0.30), (120, 0.15)]
+    }
+
+    /// Controleer de config op waarden die runtime stil fout gaan (deling door 0, negatieve
+    /// gewichten, ...). Geeft alle gevonden fouten terug, elk met de naam van het veld.
+    ///
+    /// De trend-blend (`trend_windows` + `breakout_weight`) plus de overlay-gewichten (OI,
+    /// VWAP, mean-reversion incl. de grootste override, relative strength) moet samen <= 1.0
+    /// zijn; de carry-gewichten tellen daar niet in mee en moeten alleen >= 0 zijn.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        let mut non_negative = |name: &str, v: f64| {
+            if !v.is_finite() || v < 0.0 {
+                errors.push(format!("{} must be finite and >= 0, got {}", name, v));
+            }
+        };
+        for (days, weight) in &self.trend_windows {
+            non_negative(&format!("trend_windows[{}d]", days), *weight);
+        }
+        non_negative("breakout_weight", self.breakout_weight);
+        non_negative("oi_confirmation_weight", self.oi_confirmation_weight);
+        non_negative("vwap_weight", self.vwap_weight);
+        non_negative("mean_reversion_weight", self.mean_reversion_weight);
+        for (inst, weight) in &self.mean_reversion_weight_overrides {
+            non_negative(&format!("mean_reversion_weight_overrides[{:?}]", inst), *weight);
+        }
+        non_negative("relative_strength_weight", self.relative_strength_weight);
+        non_negative("carry_weight_6e", self.carry_weight_6e);
+        non_negative("carry_weight_commodity", self.carry_weight_commodity);
+        non_negative("carry_weight_bond", self.carry_weight_bond);
+        non_negative("logistic_m", self.logistic_m);
+        non_negative("min_effective_score", self.min_effective_score);
+
+        // Mean-reversion per instrument: override of globaal, dus de zwaarste telt
+        let mean_reversion_max = self
+            .mean_reversion_weight_overrides
+            .values()
+            .fold(self.mean_reversion_weight, |acc, &w| acc.max(w));
+        let weight_sum: f64 = self.trend_windows.iter().map(|(_, w)| w).sum::<f64>()
+            + self.breakout_weight
+            + self.oi_confirmation_weight
+            + self.vwap_weight
+            + mean_reversion_max
+            + self.relative_strength_weight;
+        if weight_sum > 1.0 + 1e-9 {
+            errors.push(format!(
+                "trend_windows + breakout_weight + oi_confirmation_weight + vwap_weight \
+                 + mean_reversion_weight + relative_strength_weight must sum to <= 1.0, got {}",
+                weight_sum
+            ));
+        }
+
+        let positive = [
+            ("trend_score_clip", self.trend_score_clip),
+            ("carry_score_clip", self.carry_score_clip),
+            ("effective_score_clip", self.effective_score_clip),
+            ("logistic_k", self.logistic_k),
+            ("atr_stop_multiple_index", self.atr_stop_multiple_index),
+            ("atr_stop_multiple_fx", self.atr_stop_multiple_fx),
+            ("atr_stop_multiple_gold", self.atr_stop_multiple_gold),
+            ("atr_stop_multiple_bond", self.atr_stop_multiple_bond),
+        ];
+        for (name, v) in positive {
+            if !v.is_finite() || v <= 0.0 {
+                errors.push(format!("{} must be finite and > 0, got {}", name, v));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.min_conviction) {
+            errors.push(format!(
+                "min_conviction must be in [0, 1], got {}",
+                self.min_conviction
+            ));
+        }
+
+        if self.vol_target_pct.is_some() && self.vol_target_annual.is_some() {
+            errors.push("vol_target_pct and vol_target_annual are mutually exclusive".to_string());
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
 impl Default for MacroFuturesSleeveConfig {
     fn default() -> Self {
         Self {
-            trend_weight_20d: 0.45,
-            trend_weight_60d: 0.30,
-            trend_weight_120d: 0.15,
+            trend_windows: Self::classic_trend_windows(),
             breakout_weight: 0.10,
+            oi_confirmation_weight: 0.0,
+            vwap_weight: 0.0,
+            mean_reversion_weight: 0.0,
+            mean_reversion_weight_overrides: HashMap::new(),
+            relative_strength_weight: 0.0,
             trend_score_clip: 3.0,
             carry_score_clip: 2.0,
             carry_vol_floor: 0.25,
             carry_weight_6e: 0.5,
+            carry_weight_commodity: 0.5,
+            carry_weight_bond: 0.5,
+            vix_contango_threshold: 1.0,
             effective_score_clip: 5.0,
 
             // 🔧 AANPASSINGEN HIER:
@@ -278,19 +1917,154 @@ impl Default for MacroFuturesSleeveConfig {
             // - FX:   0.5  * ATR * 125k
             atr_stop_multiple_index: 0.25,
             atr_stop_multiple_fx: 0.5,
+            atr_stop_multiple_gold: 0.4,
+            atr_stop_multiple_bond: 0.5,
+
+            momentum_lag: 0,
+
+            min_history_bars: MIN_BARS,
+            max_bar_age_hours: 48,
+            min_volume_contracts: 0.0,
+
+            min_age_scalar: 0.5,
+
+            use_continuous_breakout: false,
+
+            vol_target_pct: None,
+            vol_target_annual: None,
+
+            entry_filter_threshold: 0.0,
+            min_delta_contracts: 0,
+            min_turnover_frac: 0.0,
+
+            correlation_window: 60,
+
+            min_carry_sharpe: 0.5,
+
+            margin_buffer_pct: 0.10,
         }
     }
 }
 
 
+/// Ongewogen trend-componenten (intern, o.a. voor de signal-trace).
+#[derive(Debug, Clone)]
+struct TrendComponents {
+    /// (lookback_days, z-score) in de volgorde van `trend_windows`
+    z: Vec<(u32, f64)>,
+    breakout: f64,
+}
+
 #[derive(Debug, Clone)]
 pub struct MacroFuturesSleeve {
     pub cfg: MacroFuturesSleeveConfig,
+    pub registry: InstrumentRegistry,
+}
+
+/// Equity-index pair voor de relative-strength tilt (positieve score = eerste sterker).
+pub const RELATIVE_STRENGTH_PAIR: (FutureInstrument, FutureInstrument) =
+    (FutureInstrument::Mnq, FutureInstrument::Mes);
+
+/// Lookback (bars) voor de OI-verandering in `compute_oi_confirmation_score`.
+const OI_LOOKBACK_BARS: usize = 20;
+
+/// Relatieve OI-verandering waaronder de OI-bevestiging als ruis (0.0) telt.
+const OI_NOISE_THRESHOLD: f64 = 0.02;
+
+/// Max. aantal effective scores per instrument in `MacroFuturesSleeveState`.
+const SCORE_HISTORY_LEN: usize = 20;
+
+/// Aantal recente scores voor `score_momentum`.
+const SCORE_MOMENTUM_WINDOW: usize = 5;
+
+/// State die tussen heartbeats meegaat (de sleeve zelf blijft stateless).
+#[derive(Debug, Clone, Default)]
+pub struct MacroFuturesSleeveState {
+    /// Effective scores per instrument, oudste eerst (max `SCORE_HISTORY_LEN`)
+    pub signal_score_history: HashMap<FutureInstrument, VecDeque<f64>>,
+    /// Posities bij de eerste heartbeat van de lopende SoftHalt
+    /// (voor `FuturesSleeveContext::soft_halt_base_positions`)
+    pub soft_halt_base_positions: HashMap<FutureInstrument, i32>,
+}
+
+impl MacroFuturesSleeveState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Voeg de effective scores van één heartbeat toe (niet-finite scores worden overgeslagen).
+    pub fn record_signals(&mut self, signals: &[InstrumentSignal]) {
+        for sig in signals {
+            let score = sig.final_signal.effective_score;
+            if !score.is_finite() {
+                continue;
+            }
+
+            let hist = self.signal_score_history.entry(sig.instrument).or_default();
+            hist.push_back(score);
+            while hist.len() > SCORE_HISTORY_LEN {
+                hist.pop_front();
+            }
+        }
+    }
+
+    /// Leg de posities vast bij de eerste SoftHalt-heartbeat; buiten een SoftHalt leeg.
+    pub fn record_soft_halt(&mut self, ctx: &FuturesSleeveContext) {
+        if !MacroFuturesSleeve::soft_halted(ctx) {
+            self.soft_halt_base_positions.clear();
+            return;
+        }
+        for (&inst, &current) in &ctx.current_positions {
+            let base = ctx.soft_halt_base_positions.get(&inst).copied().unwrap_or(current);
+            if base != 0 {
+                self.soft_halt_base_positions.entry(inst).or_insert(base);
+            }
+        }
+    }
 }
 
 impl MacroFuturesSleeve {
+    /// Sleeve met alleen de built-in instrumenten.
+    ///
+    /// Een ongeldige config (zie `MacroFuturesSleeveConfig::validate`) geeft een panic in
+    /// debug-builds; gebruik `try_new` om de fouten zelf af te handelen.
     pub fn new(cfg: MacroFuturesSleeveConfig) -> Self {
-        Self { cfg }
+        Self::with_registry(cfg, InstrumentRegistry::default())
+    }
+
+    /// Als `new`, maar met de validatie-fouten als `Err` i.p.v. een debug-panic.
+    pub fn try_new(cfg: MacroFuturesSleeveConfig) -> Result<Self, Vec<String>> {
+        cfg.validate()?;
+        Ok(Self { cfg, registry: InstrumentRegistry::default() })
+    }
+
+    /// Sleeve met een eigen instrument-registry (bijv. extra custom instrumenten).
+    pub fn with_registry(cfg: MacroFuturesSleeveConfig, registry: InstrumentRegistry) -> Self {
+        if let Err(errors) = cfg.validate() {
+            debug_assert!(false, "invalid MacroFuturesSleeveConfig: {}", errors.join("; "));
+        }
+        Self { cfg, registry }
+    }
+
+    /// Spec uit de registry; None voor niet-geregistreerde custom instrumenten.
+    pub fn spec(&self, inst: FutureInstrument) -> Option<&InstrumentSpec> {
+        self.registry.spec(inst)
+    }
+
+    /// (symbol, venue, contract-multiplier); onbekend instrument → multiplier 0 (niet tradebaar).
+    fn instrument_metadata(&self, inst: FutureInstrument) -> (&str, &str, u32) {
+        self.spec(inst)
+            .map(|s| (s.symbol.as_str(), s.venue.as_str(), s.contract_multiplier))
+            .unwrap_or(("", "", 0))
+    }
+
+    /// Contract-multiplier in USD per punt (MES = 5, MNQ = 2, 6E = 125k EUR).
+    fn contract_multiplier(&self, inst: FutureInstrument) -> f64 {
+        self.instrument_metadata(inst).2 as f64
+    }
+
+    fn carry_type(&self, inst: FutureInstrument) -> CarryType {
+        self.spec(inst).map(|s| s.carry).unwrap_or(CarryType::None)
     }
 
     pub fn evaluate_signals(
@@ -301,10 +2075,12 @@ impl MacroFuturesSleeve {
         let mut out = Vec::new();
 
         for (inst, hist) in &ctx.histories {
-            let signal = self.evaluate_instrument(*inst, hist, &ctx.macro_scalars);
+            let signal = self.evaluate_instrument(*inst, hist, &ctx.macro_scalars, ctx.as_of);
             out.push(signal);
         }
 
+        // Vaste volgorde: de headroom wordt in deze volgorde verdeeld (niet in hash-volgorde)
+        out.sort_by_key(|s| format!("{:?}", s.instrument));
         out
     }
 
@@ -321,61 +2097,443 @@ impl MacroFuturesSleeve {
         // 1) Eerst de ruwe signals berekenen
         let signals = self.evaluate_signals(ctx, risk_budget);
 
-        // 2) Map elke signal naar een risk-intent
+        // 2) Relative-strength tilt binnen het equity-index pair
+        let (strong, weak) = RELATIVE_STRENGTH_PAIR;
+        let rs_tilt = if self.cfg.relative_strength_weight != 0.0 {
+            self.cfg.relative_strength_weight
+                * self.compute_relative_strength_signal(ctx, RELATIVE_STRENGTH_PAIR)
+        } else {
+            0.0
+        };
+
+        // 3) Map elke signal naar een risk-intent
         signals
             .into_iter()
             .map(|signal| {
-                let dir = signal.final_signal.direction as f64;
-                let conv = signal.final_signal.conviction;
-
-                // direction ∈ {-1,0,1}, conviction ∈ [0,1]
-                // → desired_risk_frac ∈ [-1,1]
-                let mut desired_risk_frac = dir * conv;
-
-                // defensief clampen, mocht er ooit iets geks gebeuren
-                if !desired_risk_frac.is_finite() {
-                    desired_risk_frac = 0.0;
+                let tilt = if signal.instrument == strong {
+                    rs_tilt
+                } else if signal.instrument == weak {
+                    -rs_tilt
                 } else {
-                    desired_risk_frac = desired_risk_frac.clamp(-1.0, 1.0);
-                }
-
+                    0.0
+                };
                 InstrumentRiskIntent {
                     instrument: signal.instrument,
-                    desired_risk_frac,
+                    desired_risk_frac: Self::tilted_risk_frac(&signal, tilt),
                     signal,
                 }
             })
             .collect()
     }
 
+    /// Relative strength tussen de twee instrumenten van `pair`: het verschil in
+    /// `ret_20d` (signal-bar), gedeeld door de gecombineerde `vol_20d` en geclipt op [-1, 1].
+    /// Positief = `pair.0` sterker dan `pair.1`; 0.0 als een history ontbreekt of ongeldig is.
+    pub fn compute_relative_strength_signal(
+        &self,
+        ctx: &FuturesSleeveContext,
+        pair: (FutureInstrument, FutureInstrument),
+    ) -> f64 {
+        let bar_a = ctx.histories.get(&pair.0).and_then(|h| self.signal_bar(h));
+        let bar_b = ctx.histories.get(&pair.1).and_then(|h| self.signal_bar(h));
+        let (a, b) = match (bar_a, bar_b) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return 0.0,
+        };
+
+        let vol = a.vol_20d.hypot(b.vol_20d);
+        let score = (a.ret_20d - b.ret_20d) / vol;
+        if !score.is_finite() || vol <= 0.0 {
+            return 0.0;
+        }
+        score.clamp(-1.0, 1.0)
+    }
+
+    /// `desired_risk_frac` plus een tilt; de richting van het signal blijft leidend
+    /// (flat blijft flat, een tilt kan een positie wel tot 0 terugbrengen maar niet omdraaien).
+    fn tilted_risk_frac(signal: &InstrumentSignal, tilt: f64) -> f64 {
+        let frac = Self::desired_risk_frac(signal);
+        if tilt == 0.0 || !tilt.is_finite() {
+            return frac;
+        }
+
+        match signal.final_signal.direction {
+            d if d > 0 => (frac + tilt).clamp(0.0, 1.0),
+            d if d < 0 => (frac + tilt).clamp(-1.0, 0.0),
+            _ => 0.0,
+        }
+    }
+
+    fn desired_risk_frac(signal: &InstrumentSignal) -> f64 {
+        let dir = signal.final_signal.direction as f64;
+        let conv = signal.final_signal.conviction;
+
+        // direction ∈ {-1,0,1}, conviction ∈ [0,1]
+        // → desired_risk_frac ∈ [-1,1]
+        let desired_risk_frac = dir * conv;
+
+        // defensief clampen, mocht er ooit iets geks gebeuren
+        if !desired_risk_frac.is_finite() {
+            0.0
+        } else {
+            desired_risk_frac.clamp(-1.0, 1.0)
+        }
+    }
+
+    /// Halt / lege envelope / degraded engine → geen nieuwe posities.
+    fn new_positions_blocked(ctx: &FuturesSleeveContext) -> bool {
+        !ctx.risk_envelope.is_tradeable() || ctx.engine_health != EngineHealth::Healthy
+    }
+
+    /// Portfolio- of sleeve-envelope staat op `SoftHalt`: geen nieuwe posities, bestaande
+    /// posities naar 50% (zie `soft_halt_target`; in de planning is de max. size gehalveerd).
+    fn soft_halted(ctx: &FuturesSleeveContext) -> bool {
+        let env = &ctx.risk_envelope;
+        env.portfolio_halt == HaltState::SoftHalt || env.sleeve_halt == HaltState::SoftHalt
+    }
+
+    /// SoftHalt-target voor een aangehouden positie: de helft (afgerond naar 0) van de positie
+    /// bij de start van de SoftHalt (`soft_halt_base_positions`, anders `current`).
+    /// Nooit groter dan `current`, dus een volgende SoftHalt-heartbeat halveert niet opnieuw.
+    /// Zonder history → flat (zoals buiten een SoftHalt).
+    fn soft_halt_target(ctx: &FuturesSleeveContext, inst: FutureInstrument, current: i32) -> i32 {
+        if !ctx.histories.contains_key(&inst) {
+            return 0;
+        }
+        let base = ctx
+            .soft_halt_base_positions
+            .get(&inst)
+            .copied()
+            .filter(|b| b.signum() == current.signum())
+            .unwrap_or(current);
+        let half = base / 2;
+        if half.abs() < current.abs() { half } else { current }
+    }
+
+    /// Max. positie-size uit de envelope; gehalveerd tijdens een `SoftHalt`.
+    fn max_position_size_usd(ctx: &FuturesSleeveContext) -> f64 {
+        let max = ctx.risk_envelope.max_position_size_usd;
+        if Self::soft_halted(ctx) { max * 0.5 } else { max }
+    }
+
+    /// Signed USD-notional vóór headroom-caps.
+    ///
+    /// - default: `frac * max_position_size_usd`
+    /// - vol-target (annual): `frac * max_position_size_usd * vol_target_annual / (vol_20d * sqrt(252))`
+    /// - vol-target: `(vol_target_pct / 100 / vol_20d) * sleeve_equity / n_instruments * frac`,
+    ///   begrensd op `max_position_size_usd` van de envelope
+    fn raw_target_notional(
+        &self,
+        ctx: &FuturesSleeveContext,
+        instrument: FutureInstrument,
+        frac: f64,
+    ) -> Option<f64> {
+        let base = Self::max_position_size_usd(ctx);
+
+        if let Some(target) = self.cfg.vol_target_annual {
+            let bar = self.signal_bar(ctx.histories.get(&instrument)?)?;
+            let instrument_vol_annualized = bar.vol_20d * 252f64.sqrt();
+            if !instrument_vol_annualized.is_finite() || instrument_vol_annualized <= 0.0 {
+                return None;
+            }
+
+            let scale = target / instrument_vol_annualized;
+            return Some((frac * base * scale).clamp(-base, base));
+        }
+
+        let vol_target_pct = match self.cfg.vol_target_pct {
+            Some(v) => v,
+            None => return Some(frac * base),
+        };
+
+        let bar = self.signal_bar(ctx.histories.get(&instrument)?)?;
+        if !bar.vol_20d.is_finite() || bar.vol_20d <= 0.0 {
+            return None;
+        }
+
+        let n_instruments = ctx.histories.len().max(1) as f64;
+        let per_instrument =
+            (vol_target_pct / 100.0 / bar.vol_20d) * ctx.sleeve_equity_usd / n_instruments;
+
+        Some((per_instrument * frac).clamp(-base, base))
+    }
+
+    /// `margin_remaining_usd` minus de reserve van `margin_buffer_pct`.
+    fn usable_margin_usd(&self, env: &SleeveRiskEnvelope) -> f64 {
+        let buffer = if self.cfg.margin_buffer_pct.is_finite() {
+            self.cfg.margin_buffer_pct.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        env.margin_remaining_usd.max(0.0) * (1.0 - buffer)
+    }
+
+    /// Schaal een signed target-notional terug tot binnen de headroom.
+    /// `None` als er (na scaling) minder dan $1 overblijft.
+    fn fit_notional_to_headroom(target_notional: f64, allowed_notional: f64) -> Option<f64> {
+        if !target_notional.is_finite() || allowed_notional <= 0.0 {
+            return None;
+        }
+
+        // mini-filter: < $1 exposure = negeren
+        if target_notional.abs() < 1.0 {
+            return None;
+        }
+
+        // Indien nodig terugschalen tot binnen headroom
+        let mut fitted = target_notional;
+        if fitted.abs() > allowed_notional {
+            let scale = allowed_notional / fitted.abs();
+            if !scale.is_finite() || scale <= 0.0 {
+                return None;
+            }
+
+            fitted *= scale;
+
+            // Na scaling kan het < $1 zijn → dan alsnog skippen
+            if fitted.abs() < 1.0 {
+                return None;
+            }
+        }
+
+        Some(fitted)
+    }
+
+    /// Verklaar waarom `plan_contracts` (mogelijk) geen positie geeft voor `instrument`.
+    ///
+    /// Checks in volgorde: history → data → thresholds → halts → engine-health →
+    /// headroom → concurrency. Wordt niets gevonden dan `NoPlan`.
+    pub fn explain_flat_signal(
+        &self,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+        instrument: FutureInstrument,
+    ) -> FlatExplanation {
+        // 1) Signal-niveau
+        let hist = match ctx.histories.get(&instrument) {
+            Some(h) => h,
+            None => {
+                return FlatExplanation::NoHistory {
+                    bars_available: 0,
+                    min_required: self.min_bars_required(),
+                };
+            }
+        };
+
+        let signal = self.evaluate_instrument(instrument, hist, &ctx.macro_scalars, ctx.as_of);
+        match signal.reason {
+            SignalReason::InsufficientHistory => {
+                return FlatExplanation::NoHistory {
+                    bars_available: hist.bars.len(),
+                    min_required: self.min_bars_required(),
+                };
+            }
+            SignalReason::InvalidData => {
+                let field = self
+                    .signal_bar(hist)
+                    .and_then(Self::first_invalid_feature)
+                    .unwrap_or("effective_score");
+                return FlatExplanation::InvalidData { field };
+            }
+            SignalReason::StaleData => {
+                let age_hours = hist
+                    .bars
+                    .last()
+                    .map(|b| (ctx.as_of - b.ts).num_hours())
+                    .unwrap_or(0);
+                return FlatExplanation::StaleData {
+                    age_hours,
+                    max_age_hours: self.cfg.max_bar_age_hours,
+                };
+            }
+            SignalReason::LowVolume => {
+                return FlatExplanation::LowVolume {
+                    volume: self.signal_bar(hist).map(|b| b.volume).unwrap_or(0.0),
+                    min_required: self.cfg.min_volume_contracts,
+                };
+            }
+            SignalReason::BelowThreshold => {
+                return FlatExplanation::BelowThreshold {
+                    effective_score: signal.final_signal.effective_score,
+                    min_required: self.cfg.min_effective_score,
+                };
+            }
+            SignalReason::Normal => {}
+        }
+
+        // 2) Envelope-niveau
+        let env = &ctx.risk_envelope;
+        for halt_state in [env.portfolio_halt, env.sleeve_halt] {
+            if matches!(halt_state, HaltState::Halt | HaltState::Kill) {
+                return FlatExplanation::Halted { halt_state };
+            }
+        }
+
+        let held = ctx.current_positions.get(&instrument).is_some_and(|&c| c != 0);
+        if Self::soft_halted(ctx) && !held {
+            return FlatExplanation::Halted { halt_state: HaltState::SoftHalt };
+        }
+
+        if ctx.engine_health != EngineHealth::Healthy {
+            return FlatExplanation::EngineDegraded;
+        }
+
+        if env.max_position_size_usd <= 0.0
+            || env.exposure_remaining_usd <= 0.0
+            || env.margin_remaining_usd <= 0.0
+        {
+            return FlatExplanation::NoHeadroom;
+        }
+
+        // 3) Sleeve-niveau: valt het instrument af in plan_positions?
+        let planned = self.plan_positions(ctx, risk_budget);
+        if planned.iter().any(|p| p.instrument == instrument) {
+            return FlatExplanation::NoPlan;
+        }
+
+        let is_flat = |inst: &FutureInstrument| {
+            ctx.current_positions.get(inst).copied().unwrap_or(0) == 0
+        };
+
+        if is_flat(&instrument) {
+            let open_now = ctx.current_positions.values().filter(|&&v| v != 0).count();
+            let newly_planned = planned.iter().filter(|p| is_flat(&p.instrument)).count();
+            if (open_now + newly_planned) as u32 >= env.max_concurrent_positions {
+                return FlatExplanation::ConcurrencyCapReached;
+            }
+        }
+
+        // Slots vrij, maar eerdere instrumenten hebben de headroom opgebruikt
+        FlatExplanation::NoHeadroom
+    }
+
+    /// Sizing-scalar per open positie o.b.v. hoe lang die al loopt.
+    ///
+    /// 1.0 bij entry vandaag (of onbekende entry-datum), lineair aflopend naar
+    /// `cfg.min_age_scalar` op `max_holding_days`. Flat instrumenten zitten niet in de map.
+    pub fn compute_position_age_scalars(
+        &self,
+        ctx: &FuturesSleeveContext,
+        max_holding_days: u32,
+    ) -> HashMap<FutureInstrument, f64> {
+        let min_scalar = self.cfg.min_age_scalar.clamp(0.0, 1.0);
+
+        ctx.current_positions
+            .iter()
+            .filter(|&(_, &qty)| qty != 0)
+            .map(|(&inst, _)| {
+                let age_days = ctx
+                    .position_entry_dates
+                    .get(&inst)
+                    .map(|entry| (ctx.as_of - *entry).num_days().max(0))
+                    .unwrap_or(0);
+
+                let progress = if max_holding_days == 0 {
+                    if age_days > 0 { 1.0 } else { 0.0 }
+                } else {
+                    (age_days as f64 / max_holding_days as f64).min(1.0)
+                };
+
+                (inst, 1.0 - (1.0 - min_scalar) * progress)
+            })
+            .collect()
+    }
+
+    /// Positieplan voor één instrument in isolatie.
+    ///
+    /// Geen concurrency-slots (één instrument = één slot); alleen halts, engine-health
+    /// en de volledige exposure/margin-headroom van de envelope worden toegepast.
+    pub fn plan_position_for_instrument(
+        &self,
+        ctx: &FuturesSleeveContext,
+        _risk_budget: &FuturesRiskBudget,
+        instrument: FutureInstrument,
+    ) -> Option<FuturesPlannedPosition> {
+        if Self::new_positions_blocked(ctx) {
+            return None;
+        }
+
+        let hist = ctx.histories.get(&instrument)?;
+        let signal = self.evaluate_instrument(instrument, hist, &ctx.macro_scalars, ctx.as_of);
+
+        let dir = signal.final_signal.direction;
+        let frac = Self::desired_risk_frac(&signal);
+        if dir == 0 || signal.final_signal.conviction <= 0.0 || frac == 0.0 {
+            return None;
+        }
+
+        // SoftHalt: alleen bestaande posities (verkleind) plannen
+        let held = ctx.current_positions.get(&instrument).is_some_and(|&c| c != 0);
+        if Self::soft_halted(ctx) && !held {
+            return None;
+        }
+
+        let env = &ctx.risk_envelope;
+        let allowed_notional = env
+            .exposure_remaining_usd
+            .max(0.0)
+            .min(self.usable_margin_usd(env));
+
+        let target_notional_usd = Self::fit_notional_to_headroom(
+            self.raw_target_notional(ctx, instrument, frac)?,
+            allowed_notional,
+        )?;
+
+        Some(FuturesPlannedPosition {
+            instrument,
+            target_direction: dir,
+            target_notional_usd,
+        })
+    }
+
         pub fn plan_positions(
         &self,
         ctx: &FuturesSleeveContext,
         risk_budget: &FuturesRiskBudget,
     ) -> Vec<FuturesPlannedPosition> {
+        self.plan_positions_verbose(ctx, risk_budget)
+            .into_iter()
+            .filter_map(|entry| entry.position)
+            .collect()
+    }
+
+    /// Zelfde planning als `plan_positions`, maar met één entry per instrument in
+    /// `ctx.histories`: óf een positie, óf de reden waarom het instrument is overgeslagen.
+    pub fn plan_positions_verbose(
+        &self,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+    ) -> Vec<PositionPlanEntry> {
         let env = &ctx.risk_envelope;
 
+        // 0) Eerst de intents ophalen (direction * conviction, één per history)
+        let intents = self.evaluate_risk_intents(ctx, risk_budget);
+
         // 1) Als risk-kernel zegt "stop", max size = 0 ...
-        if matches!(env.portfolio_halt, HaltState::Halt | HaltState::Kill)
-            || matches!(env.sleeve_halt, HaltState::Halt | HaltState::Kill)
-            || env.max_position_size_usd <= 0.0
-            || env.max_concurrent_positions == 0
-        {
-            return Vec::new();
-        }
+        //    en bij EngineHealth degraded geen nieuwe posities (alleen flatten via order_intents)
+        if Self::new_positions_blocked(ctx) {
+            let halted = matches!(
+                env.reason_not_tradeable(),
+                Some(RiskDecisionReason::PortfolioHalt | RiskDecisionReason::SleeveHalt)
+            );
+            let reason = if halted {
+                PlanSkipReason::Halted
+            } else if ctx.engine_health != EngineHealth::Healthy {
+                PlanSkipReason::EngineDegraded
+            } else {
+                PlanSkipReason::NoRiskCapacity
+            };
 
-        // 1b) EngineHealth degraded → geen nieuwe posities (alleen flatten via order_intents)
-        if let EngineHealth::Degraded = ctx.engine_health {
-            return Vec::new();
+            return intents
+                .into_iter()
+                .map(|intent| PositionPlanEntry::skipped(intent.instrument, reason))
+                .collect();
         }
 
 
         // 2) Headroom in USD voor deze sleeve (exposure + margin)
         let mut exposure_remaining = env.exposure_remaining_usd.max(0.0);
-        let mut margin_remaining = env.margin_remaining_usd.max(0.0);
-
-        // 3) Eerst de intents ophalen (direction * conviction per instrument)
-        let intents = self.evaluate_risk_intents(ctx, risk_budget);
+        let mut margin_remaining = self.usable_margin_usd(env);
 
         // 4) Concurrency: hoeveel instrumenten hebben NU een niet-0 positie?
         let current_open = ctx
@@ -387,99 +2545,89 @@ impl MacroFuturesSleeve {
         let max_slots = env.max_concurrent_positions;
         let mut used_slots = current_open.min(max_slots);
 
+        let soft_halt = Self::soft_halted(ctx);
+
         // 5) Map intents → geplande USD-notional per instrument,
         //    met headroom (exposure/margin) + concurrency-limiet
-        intents
-            .into_iter()
-            .filter_map(move |intent| {
-                let dir = intent.signal.final_signal.direction;
-                let conv = intent.signal.final_signal.conviction;
-                let frac = intent.desired_risk_frac; // direction * conviction
+        let mut out = Vec::with_capacity(intents.len());
 
-                // Flat of geen conviction? Dan plannen we niks.
-                if dir == 0 || conv <= 0.0 || frac == 0.0 {
-                    return None;
-                }
-
-                // Geen headroom meer? Dan plannen we niets meer in deze sleeve.
-                if exposure_remaining <= 0.0 || margin_remaining <= 0.0 {
-                    return None;
-                }
-
-                // Bepaal of dit een nieuw instrument is (nu flat, straks non-zero)
-                let current_pos = ctx
-                    .current_positions
-                    .get(&intent.instrument)
-                    .copied()
-                    .unwrap_or(0);
-
-                let is_new_instrument = current_pos == 0;
-
-                // Concurrency-cap: geen nieuwe instrument-slots als we al vol zitten
-                if is_new_instrument && used_slots >= max_slots {
-                    return None;
-                }
+        for intent in intents {
+            let inst = intent.instrument;
+            let dir = intent.signal.final_signal.direction;
+            let conv = intent.signal.final_signal.conviction;
+            let frac = intent.desired_risk_frac; // direction * conviction
 
-                let base = env.max_position_size_usd;
-
-                // desired_risk_frac ∈ [-1,1] → scale van 0 tot base
-                let mut target_notional = frac * base;
-                if !target_notional.is_finite() {
-                    return None;
-                }
-
-                let mut abs_target = target_notional.abs();
+            // Flat of geen conviction? Dan plannen we niks.
+            if dir == 0 || conv <= 0.0 || frac == 0.0 {
+                out.push(PositionPlanEntry::skipped(inst, PlanSkipReason::NoSignal));
+                continue;
+            }
 
-                // mini-filter: < $1 exposure = negeren
-                if abs_target < 1.0 {
-                    return None;
-                }
+            // Geen headroom meer? Dan plannen we niets meer in deze sleeve.
+            if exposure_remaining <= 0.0 || margin_remaining <= 0.0 {
+                out.push(PositionPlanEntry::skipped(inst, PlanSkipReason::NoHeadroom));
+                continue;
+            }
 
-                // Headroom-cap in USD (exposure + margin)
-                let allowed_notional = exposure_remaining.min(margin_remaining);
-                if allowed_notional <= 0.0 {
-                    return None;
-                }
+            // Bepaal of dit een nieuw instrument is (nu flat, straks non-zero)
+            let current_pos = ctx.current_positions.get(&inst).copied().unwrap_or(0);
 
-                // Indien nodig terugschalen tot binnen headroom
-                if abs_target > allowed_notional {
-                    let scale = allowed_notional / abs_target;
-                    if !scale.is_finite() || scale <= 0.0 {
-                        return None;
-                    }
+            let is_new_instrument = current_pos == 0;
 
-                    target_notional *= scale;
-                    abs_target = target_notional.abs();
+            // SoftHalt: geen nieuwe posities, bestaande via de gehalveerde max-size
+            if is_new_instrument && soft_halt {
+                out.push(PositionPlanEntry::skipped(inst, PlanSkipReason::Halted));
+                continue;
+            }
+
+            // Concurrency-cap: geen nieuwe instrument-slots als we al vol zitten
+            if is_new_instrument && used_slots >= max_slots {
+                out.push(PositionPlanEntry::skipped(inst, PlanSkipReason::ConcurrencyCapReached));
+                continue;
+            }
 
-                    // Na scaling kan het < $1 zijn → dan alsnog skippen
-                    if abs_target < 1.0 {
-                        return None;
-                    }
+            // desired_risk_frac ∈ [-1,1] → scale van 0 tot base (of vol-target),
+            // daarna capped op de headroom in USD (exposure + margin)
+            let allowed_notional = exposure_remaining.min(margin_remaining);
+            let target_notional = match self
+                .raw_target_notional(ctx, inst, frac)
+                .and_then(|raw| Self::fit_notional_to_headroom(raw, allowed_notional))
+            {
+                Some(n) => n,
+                None => {
+                    out.push(PositionPlanEntry::skipped(inst, PlanSkipReason::BelowMinNotional));
+                    continue;
                 }
+            };
+            let abs_target = target_notional.abs();
 
-                // Headroom verbruiken (USD-notional ~ exposure & margin)
-                exposure_remaining = (exposure_remaining - abs_target).max(0.0);
-                margin_remaining = (margin_remaining - abs_target).max(0.0);
+            // Headroom verbruiken (USD-notional ~ exposure & margin)
+            exposure_remaining = (exposure_remaining - abs_target).max(0.0);
+            margin_remaining = (margin_remaining - abs_target).max(0.0);
 
-                // Als we effectief een nieuwe positie openen op een instrument
-                // dat eerder flat was, telt dat als extra concurrency-slot
-                if is_new_instrument {
-                    used_slots = used_slots.saturating_add(1);
-                }
+            // Als we effectief een nieuwe positie openen op een instrument
+            // dat eerder flat was, telt dat als extra concurrency-slot
+            if is_new_instrument {
+                used_slots = used_slots.saturating_add(1);
+            }
 
-                Some(FuturesPlannedPosition {
-                    instrument: intent.instrument,
+            out.push(PositionPlanEntry {
+                instrument: inst,
+                position: Some(FuturesPlannedPosition {
+                    instrument: inst,
                     target_direction: dir,
                     target_notional_usd: target_notional,
-                })
-            })
-            .collect()
+                }),
+                skip_reason: None,
+            });
+        }
+
+        out
     }
 
 
 
-        /// Interne helper: berekent zowel target contracts als risk in EUR per instrument.
-        /// Interne helper: berekent zowel target contracts als risk in EUR per instrument.
+    /// Interne helper: berekent zowel target contracts als risk in EUR per instrument.
     ///
     /// Nieuwe V1-logica:
     /// - `plan_positions` gebruikt de risk-envelope voor:
@@ -488,13 +2636,18 @@ impl MacroFuturesSleeve {
     ///   - headroom
     /// - Hier vertalen we:
     ///   - conviction → gewenste risk-fractie per instrument
-    ///   - ATR-stop → per-contract risk in USD/EUR
-    ///   - profielen + envelope → harde caps in contracts
+    ///   - ATR-stop → per-contract risk: `ATR14 * atr_stop_multiple * multiplier` (USD → EUR)
+    ///   - profielen + envelope → harde caps in contracts (incl. `max_risk_per_position_eur`)
     fn plan_contracts_with_risk_internal(
         &self,
         ctx: &FuturesSleeveContext,
         risk_budget: &FuturesRiskBudget,
     ) -> Vec<(FuturesPlannedContracts, FuturesPlannedRisk)> {
+        // 0) Ongeldig budget (0 contracts, oneindig risk, ...) → niets plannen
+        if risk_budget.validate().is_err() {
+            return Vec::new();
+        }
+
         // 1) Eerst de USD-notional plannen (om de relatieve intensiteit te lezen)
         let planned = self.plan_positions(ctx, risk_budget);
 
@@ -519,6 +2672,15 @@ impl MacroFuturesSleeve {
                 FutureInstrument::Mes => risk_budget.mes,
                 FutureInstrument::Mnq => risk_budget.mnq,
                 FutureInstrument::SixE => risk_budget.sixe,
+                FutureInstrument::Gc => risk_budget.gc,
+                FutureInstrument::Mcl => risk_budget.mcl,
+                FutureInstrument::M2k => risk_budget.m2k,
+                FutureInstrument::Zn => risk_budget.zn,
+                FutureInstrument::Vx => risk_budget.vx,
+                FutureInstrument::Custom(_) => match self.spec(p.instrument).and_then(|s| s.risk_budget) {
+                    Some(b) => b,
+                    None => continue,
+                },
             };
 
             let inst_max_contracts: i32 = inst_budget.max_contracts as i32;
@@ -538,6 +2700,22 @@ impl MacroFuturesSleeve {
                 continue;
             }
 
+            // Risk per contract = verlies tot de ATR-stop, in EUR
+            let risk_per_contract_eur = match ctx
+                .histories
+                .get(&p.instrument)
+                .and_then(|h| h.bars.last())
+                .and_then(|bar| self.stop_distance(p.instrument, bar))
+            {
+                Some(distance) => {
+                    distance * self.contract_multiplier(p.instrument) * ctx.eur_per_usd
+                }
+                None => continue,
+            };
+            if !risk_per_contract_eur.is_finite() || risk_per_contract_eur <= 0.0 {
+                continue;
+            }
+
             // Ruwe contracts o.b.v. frac van inst_max_contracts
             let mut abs_contracts: i32 =
                 (inst_max_contracts as f64 * abs_frac).round() as i32;
@@ -547,10 +2725,26 @@ impl MacroFuturesSleeve {
                 abs_contracts = 1;
             }
 
-            // Caps toepassen: per instrument + globale max_total_contracts
-            abs_contracts = abs_contracts
-                .min(inst_max_contracts)
+            // Risk-cap: max_risk_per_position_eur / risk per contract
+            let max_by_risk = if inst_budget.max_risk_per_position_eur.is_finite() {
+                (inst_budget.max_risk_per_position_eur / risk_per_contract_eur).floor() as i32
+            } else {
+                inst_max_contracts
+            };
+
+            // Caps toepassen: per instrument + risk + globale max_total_contracts
+            let cap = inst_max_contracts
+                .min(max_by_risk)
                 .min(remaining_total.max(0));
+            abs_contracts = abs_contracts.min(cap);
+
+            // Laatste stap: vol-regime van de envelope (stress < 1.0 < low-vol).
+            // Opschalen mag nooit door de caps heen.
+            let regime_scalar = ctx.risk_envelope.volatility_regime_scalar;
+            if regime_scalar.is_finite() {
+                abs_contracts = ((abs_contracts as f64 * regime_scalar.max(0.0)).floor() as i32)
+                    .min(cap);
+            }
 
             if abs_contracts <= 0 {
                 continue;
@@ -558,21 +2752,6 @@ impl MacroFuturesSleeve {
 
             let final_target: i32 = sign_i32 * abs_contracts;
 
-            // Risk-per-contract in EUR:
-            // bij inst_max_contracts vol → max_risk_per_position_eur
-            // dus per contract = max_risk / inst_max_contracts
-            let risk_per_contract_eur = if inst_max_contracts > 0
-                && inst_budget.max_risk_per_position_eur.is_finite()
-            {
-                inst_budget.max_risk_per_position_eur / inst_max_contracts as f64
-            } else {
-                0.0
-            };
-
-            if risk_per_contract_eur <= 0.0 {
-                continue;
-            }
-
             let total_risk_eur = risk_per_contract_eur * (abs_contracts as f64);
 
             let planned_contracts = FuturesPlannedContracts {
@@ -592,21 +2771,172 @@ impl MacroFuturesSleeve {
             remaining_total -= abs_contracts;
         }
 
+        // Sleeve-brede EUR-cap: boven max_total_risk_eur steeds de positie met het grootste
+        // risk één contract terugschalen, zodat kleine posities niet wegvallen door afronding
+        loop {
+            let total_risk_eur: f64 = out.iter().map(|(_, r)| r.total_risk_eur).sum();
+            if total_risk_eur <= risk_budget.max_total_risk_eur {
+                break;
+            }
+
+            let Some(idx) = out
+                .iter()
+                .enumerate()
+                .max_by(|(_, (_, a)), (_, (_, b))| a.total_risk_eur.total_cmp(&b.total_risk_eur))
+                .map(|(i, _)| i)
+            else {
+                break;
+            };
+
+            let (c, r) = &mut out[idx];
+            let abs_contracts = r.target_contracts.abs() - 1;
+            if abs_contracts <= 0 {
+                out.remove(idx);
+                continue;
+            }
+
+            let final_target = r.target_contracts.signum() * abs_contracts;
+            c.target_contracts = final_target;
+            r.target_contracts = final_target;
+            r.total_risk_eur = r.risk_per_contract_eur * abs_contracts as f64;
+        }
+
         out
     }
 
+    /// `FuturesSleeveContext::validate` plus de checks die van de config afhangen:
+    /// met `vol_target_pct` moet `sleeve_equity_usd` > 0 zijn (anders sized alles naar 0).
+    pub fn validate_context(&self, ctx: &FuturesSleeveContext) -> Result<(), Vec<String>> {
+        let mut errors = ctx.validate().err().unwrap_or_default();
+
+        if self.cfg.vol_target_pct.is_some()
+            && (!ctx.sleeve_equity_usd.is_finite() || ctx.sleeve_equity_usd <= 0.0)
+        {
+            errors.push(format!(
+                "sleeve_equity_usd must be > 0 when vol_target_pct is set, got {}",
+                ctx.sleeve_equity_usd
+            ));
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
     /// Bestaande API: alleen target contracts per instrument.
     pub fn plan_contracts(
         &self,
         ctx: &FuturesSleeveContext,
         risk_budget: &FuturesRiskBudget,
     ) -> Vec<FuturesPlannedContracts> {
+        #[cfg(debug_assertions)]
+        if let Err(errors) = self.validate_context(ctx) {
+            debug_assert!(false, "invalid FuturesSleeveContext: {}", errors.join("; "));
+        }
+
         self.plan_contracts_with_risk_internal(ctx, risk_budget)
             .into_iter()
             .map(|(contracts, _risk)| contracts)
             .collect()
     }
 
+    /// `plan_contracts` met `initial_entry`-caps voor flat instrumenten en
+    /// `scale_in`-caps voor instrumenten met een bestaande positie.
+    pub fn plan_contracts_with_split_budget(
+        &self,
+        ctx: &FuturesSleeveContext,
+        budget: &SplitRiskBudget,
+    ) -> Vec<FuturesPlannedContracts> {
+        self.plan_contracts(ctx, &budget.effective_budget(&ctx.current_positions))
+    }
+
+    /// `plan_contracts` plus per niet-nul positie een protective stop:
+    /// `last_close ∓ atr_stop_multiple * ATR14`, tegengestelde side, reduce-only, GTC.
+    pub fn plan_contracts_with_stop_orders(
+        &self,
+        sleeve_id: SleeveId,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+    ) -> Vec<PlannedContractsWithStop> {
+        self.plan_contracts(ctx, risk_budget)
+            .into_iter()
+            .map(|plan| {
+                let stop_order = ctx
+                    .histories
+                    .get(&plan.instrument)
+                    .and_then(|h| h.bars.last())
+                    .and_then(|bar| self.stop_price(plan.instrument, bar, plan.target_contracts))
+                    .map(|stop_price| {
+                        let (symbol, venue, _) = self.instrument_metadata(plan.instrument);
+
+                        let mut metadata = HashMap::new();
+                        metadata.insert("sleeve_version".to_string(), SLEEVE_VERSION.to_string());
+                        metadata.insert("signal_ts".to_string(), ctx.as_of.timestamp().to_string());
+                        metadata.insert("order_type".to_string(), "stop".to_string());
+
+                        EngineOrder {
+                            sleeve_id,
+                            instrument: plan.instrument,
+                            symbol: symbol.to_string(),
+                            venue: venue.to_string(),
+                            side: if plan.target_contracts > 0 {
+                                EngineOrderSide::Sell
+                            } else {
+                                EngineOrderSide::Buy
+                            },
+                            quantity: plan.target_contracts.abs(),
+                            metadata,
+                            limit_price: None,
+                            stop_price: Some(stop_price),
+                            expiry: OrderExpiry::Gtc,
+                            is_reduce_only: true,
+                        }
+                    });
+
+                PlannedContractsWithStop { plan, stop_order }
+            })
+            .collect()
+    }
+
+    /// ATR-stop voor een positie van `contracts` (teken = richting); None bij flat of ongeldige data.
+    fn stop_price(
+        &self,
+        inst: FutureInstrument,
+        bar: &DailyFeatureBar,
+        contracts: i32,
+    ) -> Option<f64> {
+        if contracts == 0 {
+            return None;
+        }
+
+        let distance = self.stop_distance(inst, bar)?;
+        let stop = bar.close - contracts.signum() as f64 * distance;
+        (stop > 0.0).then_some(stop)
+    }
+
+    /// Afstand tot de ATR-stop in prijspunten: `atr_stop_multiple * ATR14`
+    /// (spec-override vóór de klasse-multiple). None bij ongeldige data.
+    fn stop_distance(&self, inst: FutureInstrument, bar: &DailyFeatureBar) -> Option<f64> {
+        let class_multiple = match inst {
+            FutureInstrument::SixE => self.cfg.atr_stop_multiple_fx,
+            // Commodities delen de gold-multiple
+            FutureInstrument::Gc | FutureInstrument::Mcl => self.cfg.atr_stop_multiple_gold,
+            FutureInstrument::Zn => self.cfg.atr_stop_multiple_bond,
+            FutureInstrument::Vx => self.cfg.atr_stop_multiple_index,
+            FutureInstrument::Mes | FutureInstrument::Mnq | FutureInstrument::M2k => {
+                self.cfg.atr_stop_multiple_index
+            }
+            FutureInstrument::Custom(_) => self.cfg.atr_stop_multiple_index,
+        };
+        let multiple = self
+            .spec(inst)
+            .and_then(|s| s.atr_stop_multiple)
+            .unwrap_or(class_multiple);
+        let distance = multiple * bar.atr_14;
+        if !distance.is_finite() || distance <= 0.0 || !bar.close.is_finite() || bar.close <= 0.0 {
+            return None;
+        }
+        Some(distance)
+    }
+
     /// Nieuwe API: risk-report per instrument (geschikt voor logging / UI).
     pub fn plan_risk_report(
         &self,
@@ -619,6 +2949,221 @@ impl MacroFuturesSleeve {
             .collect()
     }
 
+    /// `plan_risk_report` verrijkt met laatste prijs en contract-multiplier.
+    pub fn plan_risk_report_enriched(
+        &self,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+    ) -> Vec<FuturesPlannedRiskEnriched> {
+        self.plan_risk_report(ctx, risk_budget)
+            .into_iter()
+            .filter_map(|base| {
+                let last_price_usd = ctx.histories.get(&base.instrument)?.bars.last()?.close;
+                let (_, _, multiplier) = self.instrument_metadata(base.instrument);
+
+                Some(FuturesPlannedRiskEnriched {
+                    base,
+                    last_price_usd,
+                    contract_multiplier: multiplier,
+                    contract_notional_usd: last_price_usd * multiplier as f64,
+                })
+            })
+            .collect()
+    }
+
+    /// Walk-forward check: per bar met genoeg history het signal (neutrale macro)
+    /// vergelijken met de close-to-close return `lookahead_days` bars later.
+    pub fn backtest_signal_accuracy(
+        &self,
+        hist: &InstrumentHistory,
+        lookahead_days: u32,
+    ) -> SignalAccuracyReport {
+        let lookahead = lookahead_days.max(1) as usize;
+        let first = self.min_bars_required().max(1) - 1;
+
+        let mut n_signals = 0u32;
+        let mut correct_direction = 0u32;
+        let mut sum_correct = 0.0f64;
+        let mut sum_wrong = 0.0f64;
+
+        for i in first..hist.bars.len().saturating_sub(lookahead) {
+            let bar = &hist.bars[i];
+            let future = &hist.bars[i + lookahead];
+            if !(bar.close.is_finite() && bar.close > 0.0 && future.close.is_finite()) {
+                continue;
+            }
+
+            let prefix = InstrumentHistory {
+                instrument: hist.instrument,
+                bars: hist.bars[..=i].to_vec(),
+            };
+            let macros = MacroScalars {
+                as_of: bar.ts,
+                risk_on_scalar: 1.0,
+                usd_scalar: 1.0,
+                vix_term_slope: 0.0,
+            };
+
+            let signal = self.evaluate_instrument(hist.instrument, &prefix, &macros, bar.ts);
+            let direction = signal.final_signal.direction;
+            if direction == 0 {
+                continue;
+            }
+
+            let signed_ret = direction as f64 * (future.close / bar.close - 1.0);
+            n_signals += 1;
+            if signed_ret > 0.0 {
+                correct_direction += 1;
+                sum_correct += signed_ret;
+            } else {
+                sum_wrong += signed_ret;
+            }
+        }
+
+        let wrong = n_signals - correct_direction;
+        let avg = |sum: f64, n: u32| if n > 0 { sum / n as f64 } else { 0.0 };
+
+        SignalAccuracyReport {
+            instrument: hist.instrument,
+            n_signals,
+            correct_direction,
+            accuracy_pct: avg(correct_direction as f64 * 100.0, n_signals),
+            avg_ret_when_correct: avg(sum_correct, correct_direction),
+            avg_ret_when_wrong: avg(sum_wrong, wrong),
+        }
+    }
+
+    /// Pearson-correlatie per instrument-paar over de laatste `correlation_window`
+    /// log-returns op gemeenschappelijke datums. Te weinig data of nul-variantie → 0.0.
+    pub fn compute_portfolio_correlation(&self, ctx: &FuturesSleeveContext) -> CorrelationMatrix {
+        let mut instruments: Vec<FutureInstrument> = ctx.histories.keys().copied().collect();
+        instruments.sort_by_key(|inst| format!("{:?}", inst));
+
+        let mut correlations = HashMap::new();
+
+        for (i, &a) in instruments.iter().enumerate() {
+            correlations.insert((a, a), 1.0);
+
+            for &b in &instruments[i + 1..] {
+                let rho = self.pairwise_correlation(&ctx.histories[&a], &ctx.histories[&b]);
+                correlations.insert((a, b), rho);
+                correlations.insert((b, a), rho);
+            }
+        }
+
+        CorrelationMatrix { correlations }
+    }
+
+    fn pairwise_correlation(&self, a: &InstrumentHistory, b: &InstrumentHistory) -> f64 {
+        let closes_b: HashMap<_, f64> = b
+            .bars
+            .iter()
+            .map(|bar| (bar.ts.date_naive(), bar.close))
+            .collect();
+
+        // Closes op datums die in beide histories voorkomen (oplopend)
+        let common: Vec<(f64, f64)> = a
+            .bars
+            .iter()
+            .filter_map(|bar| {
+                let cb = *closes_b.get(&bar.ts.date_naive())?;
+                (bar.close > 0.0 && cb > 0.0).then_some((bar.close, cb))
+            })
+            .collect();
+
+        let rets: Vec<(f64, f64)> = common
+            .windows(2)
+            .map(|w| ((w[1].0 / w[0].0).ln(), (w[1].1 / w[0].1).ln()))
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .collect();
+
+        let window = self.cfg.correlation_window.max(2);
+        let rets = &rets[rets.len().saturating_sub(window)..];
+        if rets.len() < 2 {
+            return 0.0;
+        }
+
+        let n = rets.len() as f64;
+        let mean_x = rets.iter().map(|r| r.0).sum::<f64>() / n;
+        let mean_y = rets.iter().map(|r| r.1).sum::<f64>() / n;
+
+        let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+        for (x, y) in rets {
+            cov += (x - mean_x) * (y - mean_y);
+            var_x += (x - mean_x).powi(2);
+            var_y += (y - mean_y).powi(2);
+        }
+
+        let denom = (var_x * var_y).sqrt();
+        if !denom.is_finite() || denom <= f64::EPSILON {
+            return 0.0;
+        }
+
+        (cov / denom).clamp(-1.0, 1.0)
+    }
+
+    /// Perturbeer elke config-parameter relatief met ±`perturbation` (0.1 = ±10%)
+    /// en meet het effect op de gross contract-allocatie van `plan_contracts`.
+    pub fn sensitivity_analysis(
+        &self,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+        perturbation: f64,
+    ) -> SensitivityReport {
+        let gross = |sleeve: &MacroFuturesSleeve| -> i32 {
+            sleeve
+                .plan_contracts(ctx, risk_budget)
+                .iter()
+                .map(|p| p.target_contracts.abs())
+                .sum()
+        };
+
+        let base_contracts = gross(self);
+        let n_params = self.cfg.trend_windows.len() + SENSITIVITY_PARAMS.len();
+        let mut per_param = Vec::with_capacity(n_params * 2);
+
+        // Trend-gewichten: één parameter per window
+        for i in 0..self.cfg.trend_windows.len() {
+            for shift in [perturbation, -perturbation] {
+                let mut cfg = self.cfg.clone();
+                cfg.trend_windows[i].1 *= 1.0 + shift;
+
+                // Bewust buiten with_registry om: perturbaties mogen buiten validate() vallen
+                let perturbed_contracts =
+                    gross(&MacroFuturesSleeve { cfg, registry: self.registry.clone() });
+
+                per_param.push(ParamSensitivity {
+                    param_name: format!("trend_windows[{}d]", self.cfg.trend_windows[i].0),
+                    perturbation: shift,
+                    base_contracts,
+                    perturbed_contracts,
+                    delta: perturbed_contracts - base_contracts,
+                });
+            }
+        }
+
+        for (param_name, accessor) in SENSITIVITY_PARAMS {
+            for shift in [perturbation, -perturbation] {
+                let mut cfg = self.cfg.clone();
+                *accessor(&mut cfg) *= 1.0 + shift;
+
+                // Bewust buiten with_registry om: perturbaties mogen buiten validate() vallen
+                let perturbed_contracts =
+                    gross(&MacroFuturesSleeve { cfg, registry: self.registry.clone() });
+
+                per_param.push(ParamSensitivity {
+                    param_name: param_name.to_string(),
+                    perturbation: shift,
+                    base_contracts,
+                    perturbed_contracts,
+                    delta: perturbed_contracts - base_contracts,
+                });
+            }
+        }
+
+        SensitivityReport { per_param }
+    }
+
     pub fn aggregate_sleeve_risk(
         &self,
         ctx: &FuturesSleeveContext,
@@ -659,20 +3204,44 @@ impl MacroFuturesSleeve {
     }
 
 
+    /// EUR-risk (notional) van de huidige posities, voor live margin-monitoring.
+    ///
+    /// Som over alle niet-nul posities van `|contracts| * last_close * multiplier * eur_per_usd`.
+    /// Posities zonder geldige laatste prijs tellen niet mee.
+    pub fn total_eur_risk_for_current_positions(&self, ctx: &FuturesSleeveContext) -> f64 {
+        ctx.current_positions
+            .iter()
+            .filter(|(_, contracts)| **contracts != 0)
+            .filter_map(|(inst, contracts)| {
+                let last_price = match ctx.histories.get(inst).and_then(|h| h.bars.last()) {
+                    Some(b) if b.close.is_finite() && b.close > 0.0 => b.close,
+                    _ => return None,
+                };
+                let contract_notional_usd = last_price * self.contract_multiplier(*inst);
+                Some(contracts.unsigned_abs() as f64 * contract_notional_usd * ctx.eur_per_usd)
+            })
+            .sum()
+    }
+
     pub fn check_sleeve_risk_sanity(
         &self,
         ctx: &FuturesSleeveContext,
         risk_budget: &FuturesRiskBudget,
         max_sleeve_risk_eur: f64,
     ) -> SleeveRiskSanity {
-        // Geen zinnige cap → beschouw als "geen limiet"
-        if !max_sleeve_risk_eur.is_finite() || max_sleeve_risk_eur <= 0.0 {
+        // Strengste van de expliciete cap en het budget-plafond;
+        // geen zinnige cap → beschouw als "geen limiet"
+        let cap = [max_sleeve_risk_eur, risk_budget.max_total_risk_eur]
+            .into_iter()
+            .filter(|c| c.is_finite() && *c > 0.0)
+            .fold(f64::INFINITY, f64::min);
+        if !cap.is_finite() {
             return SleeveRiskSanity::Ok;
         }
 
         let agg = self.aggregate_sleeve_risk(ctx, risk_budget);
 
-        if agg.total_risk_eur > max_sleeve_risk_eur {
+        if agg.total_risk_eur > cap {
             SleeveRiskSanity::ExceedsCap
         } else {
             SleeveRiskSanity::Ok
@@ -707,24 +3276,264 @@ impl MacroFuturesSleeve {
         }
     }
 
-        /// Convenience heartbeat voor deze sleeve:
+    /// Convenience heartbeat voor deze sleeve:
     /// - bouwt een volledige sleeve-plan (contracts + risk + aggregate + sanity)
     /// - bouwt de bijbehorende order-intents
     ///
-    /// Gedrag van bestaande API's blijft ongewijzigd; dit is alleen een bundeling.
+    /// Zonder vorige signals, dus `regime_changes` blijft leeg. Een lopende engine
+    /// gebruikt `run_heartbeat_with_prev` (zoals `run_macro_futures_engine_heartbeat`).
     pub fn run_heartbeat(
         &self,
         ctx: &FuturesSleeveContext,
         risk_budget: &FuturesRiskBudget,
         max_sleeve_risk_eur: f64,
+    ) -> MacroFuturesHeartbeatOutput {
+        self.run_heartbeat_with_prev(ctx, risk_budget, max_sleeve_risk_eur, None)
+    }
+
+    /// Entry point voor een lopende engine: zoals `run_heartbeat`, maar met de signals
+    /// van de vorige heartbeat (typisch `prev_output.signals`) voor regime-change detectie.
+    pub fn run_heartbeat_with_prev(
+        &self,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+        max_sleeve_risk_eur: f64,
+        prev_signals: Option<&[InstrumentSignal]>,
     ) -> MacroFuturesHeartbeatOutput {
         let sleeve_plan = self.plan_sleeve(ctx, risk_budget, max_sleeve_risk_eur);
         let order_intents = self.plan_order_intents(ctx, risk_budget);
+        let signals = self.evaluate_signals(ctx, risk_budget);
+
+        #[cfg(debug_assertions)]
+        {
+            let issues =
+                self.signals_consistent_with_plan(&signals, &sleeve_plan.planned_contracts);
+            debug_assert!(
+                issues.is_empty(),
+                "run_heartbeat: signals and plan are inconsistent: {:?}",
+                issues
+            );
+        }
+
+        let regime_changes = match prev_signals {
+            Some(prev) => self.detect_regime_change(prev, &signals),
+            None => Vec::new(),
+        };
 
         MacroFuturesHeartbeatOutput {
+            as_of: ctx.as_of,
             sleeve_plan,
             order_intents,
+            signals,
+            prev_signals: prev_signals.map(|p| p.to_vec()),
+            regime_changes,
+        }
+    }
+
+    /// Zoals `run_heartbeat`, en legt daarna de effective scores (voor `score_momentum`)
+    /// en de SoftHalt-startposities vast in `state`.
+    pub fn run_heartbeat_with_state(
+        &self,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+        max_sleeve_risk_eur: f64,
+        state: &mut MacroFuturesSleeveState,
+    ) -> MacroFuturesHeartbeatOutput {
+        let hb = self.run_heartbeat(ctx, risk_budget, max_sleeve_risk_eur);
+        state.record_signals(&hb.signals);
+        state.record_soft_halt(ctx);
+        hb
+    }
+
+    /// Lineaire trend (OLS-slope per heartbeat) van de laatste 5 effective scores.
+    /// Negatief = verzwakkend signal; < 2 scores → 0.0.
+    pub fn score_momentum(
+        &self,
+        instrument: FutureInstrument,
+        state: &MacroFuturesSleeveState,
+    ) -> f64 {
+        let hist = match state.signal_score_history.get(&instrument) {
+            Some(h) => h,
+            None => return 0.0,
+        };
+
+        let recent: Vec<f64> = hist
+            .iter()
+            .skip(hist.len().saturating_sub(SCORE_MOMENTUM_WINDOW))
+            .copied()
+            .collect();
+        if recent.len() < 2 {
+            return 0.0;
+        }
+
+        let n = recent.len() as f64;
+        let mean_x = (n - 1.0) / 2.0;
+        let mean_y = recent.iter().sum::<f64>() / n;
+
+        let (mut cov, mut var_x) = (0.0, 0.0);
+        for (i, y) in recent.iter().enumerate() {
+            let dx = i as f64 - mean_x;
+            cov += dx * (y - mean_y);
+            var_x += dx * dx;
+        }
+
+        cov / var_x
+    }
+
+    /// A/B-test: `self.cfg` (A) tegen `config_b` (B) op identieke context en budget.
+    ///
+    /// Een ongeldige `config_b` geeft de fouten van `MacroFuturesSleeveConfig::validate`.
+    pub fn compare_plans(
+        &self,
+        config_b: &MacroFuturesSleeveConfig,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+        max_risk_eur: f64,
+    ) -> Result<PlanComparison, Vec<String>> {
+        config_b.validate()?;
+
+        let plan_a = self.plan_sleeve(ctx, risk_budget, max_risk_eur);
+        let plan_b =
+            MacroFuturesSleeve::with_registry(config_b.clone(), self.registry.clone())
+                .plan_sleeve(ctx, risk_budget, max_risk_eur);
+
+        let mut diff_contracts: HashMap<FutureInstrument, i32> = HashMap::new();
+        for p in &plan_a.planned_contracts {
+            *diff_contracts.entry(p.instrument).or_insert(0) -= p.target_contracts;
+        }
+        for p in &plan_b.planned_contracts {
+            *diff_contracts.entry(p.instrument).or_insert(0) += p.target_contracts;
+        }
+
+        let diff_risk_eur = plan_b.aggregate.total_risk_eur - plan_a.aggregate.total_risk_eur;
+
+        Ok(PlanComparison {
+            plan_a,
+            plan_b,
+            diff_contracts,
+            diff_risk_eur,
+        })
+    }
+
+    /// Trend-omslagen / carry-flips tussen twee heartbeats.
+    ///
+    /// Alleen instrumenten die in beide sets zitten; gerapporteerd bij een
+    /// richting-wissel of een conviction-verandering > `REGIME_CONVICTION_JUMP`.
+    pub fn detect_regime_change(
+        &self,
+        prev_signals: &[InstrumentSignal],
+        curr_signals: &[InstrumentSignal],
+    ) -> Vec<RegimeChange> {
+        curr_signals
+            .iter()
+            .filter_map(|curr| {
+                let prev = prev_signals.iter().find(|p| p.instrument == curr.instrument)?;
+
+                let prev_direction = prev.final_signal.direction;
+                let curr_direction = curr.final_signal.direction;
+                let prev_conviction = prev.final_signal.conviction;
+                let curr_conviction = curr.final_signal.conviction;
+
+                let flipped = prev_direction != curr_direction;
+                let jumped = (curr_conviction - prev_conviction).abs() > REGIME_CONVICTION_JUMP;
+
+                (flipped || jumped).then_some(RegimeChange {
+                    instrument: curr.instrument,
+                    prev_direction,
+                    curr_direction,
+                    prev_conviction,
+                    curr_conviction,
+                })
+            })
+            .collect()
+    }
+
+    /// Sanity-check: klopt de richting van elke geplande positie met het signal?
+    ///
+    /// - flat signal met toch een geplande positie is OK (bestaande positie loopt uit)
+    /// - een geplande positie zonder signal, of tegen de signal-richting in, wordt gerapporteerd
+    pub fn signals_consistent_with_plan(
+        &self,
+        signals: &[InstrumentSignal],
+        plan: &[FuturesPlannedContracts],
+    ) -> Vec<InconsistencyReport> {
+        let mut out = Vec::new();
+
+        for p in plan {
+            if p.target_contracts == 0 {
+                continue;
+            }
+
+            let planned_sign = p.target_contracts.signum() as i8;
+
+            let signal = match signals.iter().find(|s| s.instrument == p.instrument) {
+                Some(s) => s,
+                None => {
+                    out.push(InconsistencyReport {
+                        instrument: p.instrument,
+                        signal_direction: 0,
+                        planned_sign,
+                        description: format!(
+                            "planned {} contracts for {:?} without a signal",
+                            p.target_contracts, p.instrument
+                        ),
+                    });
+                    continue;
+                }
+            };
+
+            let signal_direction = signal.final_signal.direction;
+
+            if signal_direction != 0 && signal_direction != planned_sign {
+                out.push(InconsistencyReport {
+                    instrument: p.instrument,
+                    signal_direction,
+                    planned_sign,
+                    description: format!(
+                        "signal direction {} for {:?} but planned {} contracts",
+                        signal_direction, p.instrument, p.target_contracts
+                    ),
+                });
+            }
+        }
+
+        out
+    }
+
+    /// Diversiteit van de signals over instrumenten (0.0 = één factor, 1.0 = ongecorreleerd).
+    ///
+    /// Gemiddelde paarsgewijze correlatie met de mean-centred proxy `(a - m) * (b - m)`,
+    /// genormaliseerd op de gemiddelde kwadratische score `q`:
+    /// `ρ = 1 + 2 * Σ_{i<j} (a_i - m)(a_j - m) / (n * q)` (= `m² / q`, altijd in [0, 1]).
+    /// Score = `1 - ρ`: identieke scores → 0.0, scores met gemiddelde 0 → 1.0.
+    pub fn compute_signal_diversity_score(&self, signals: &[InstrumentSignal]) -> f64 {
+        let scores: Vec<f64> = signals
+            .iter()
+            .map(|s| s.final_signal.effective_score)
+            .filter(|x| x.is_finite())
+            .collect();
+
+        if scores.len() < 2 {
+            return 1.0;
+        }
+
+        let n = scores.len() as f64;
+        let mean = scores.iter().sum::<f64>() / n;
+        let mean_sq = scores.iter().map(|x| x * x).sum::<f64>() / n;
+        if mean_sq <= 0.0 {
+            // Alles flat: geen gedeelde factor
+            return 1.0;
+        }
+
+        let mut sum_cov = 0.0;
+        for i in 0..scores.len() {
+            for j in (i + 1)..scores.len() {
+                sum_cov += (scores[i] - mean) * (scores[j] - mean);
+            }
         }
+
+        let mean_corr = 1.0 + 2.0 * sum_cov / (n * mean_sq);
+        1.0 - mean_corr
     }
 
         /// Map een heartbeat-output naar generieke EngineOrders
@@ -753,15 +3562,24 @@ impl MacroFuturesSleeve {
                     return None;
                 }
 
-                let (symbol, venue) = instrument_metadata(oi.instrument);
+                let (symbol, venue, _) = self.instrument_metadata(oi.instrument);
+
+                let mut metadata = HashMap::new();
+                metadata.insert("sleeve_version".to_string(), SLEEVE_VERSION.to_string());
+                metadata.insert("signal_ts".to_string(), hb.as_of.timestamp().to_string());
 
                 Some(EngineOrder {
                     sleeve_id,
                     instrument: oi.instrument,
-                    symbol,
-                    venue,
+                    symbol: symbol.to_string(),
+                    venue: venue.to_string(),
                     side,
                     quantity,
+                    metadata,
+                    limit_price: None,
+                    stop_price: None,
+                    expiry: OrderExpiry::Day,
+                    is_reduce_only: false,
                 })
             })
             .collect()
@@ -773,9 +3591,50 @@ impl MacroFuturesSleeve {
         ctx: &FuturesSleeveContext,
         risk_budget: &FuturesRiskBudget,
     ) -> Vec<FuturesOrderIntent> {
+        // Critical engine-health: noodstop, alle posities flat (ongeacht signal of halts)
+        if ctx.engine_health == EngineHealth::Critical {
+            return ctx
+                .current_positions
+                .iter()
+                .filter(|&(_, &current)| current != 0)
+                .map(|(&inst, &current)| FuturesOrderIntent {
+                    instrument: inst,
+                    delta_contracts: -current,
+                })
+                .collect();
+        }
+
+        // SoftHalt (zonder Halt/Kill): geen nieuwe posities, aangehouden posities naar
+        // de helft (zie `soft_halt_target`). Halt/Kill → geen targets, dus alles flat via 2b.
+        if Self::soft_halted(ctx) && ctx.risk_envelope.is_tradeable() {
+            return ctx
+                .current_positions
+                .iter()
+                .filter(|&(_, &current)| current != 0)
+                .filter_map(|(&inst, &current)| {
+                    let delta = Self::soft_halt_target(ctx, inst, current) - current;
+                    (delta != 0).then_some(FuturesOrderIntent {
+                        instrument: inst,
+                        delta_contracts: delta,
+                    })
+                })
+                .collect();
+        }
+
         // 1) Bepaal de gewenste target contracts per instrument
         let planned_contracts = self.plan_contracts(ctx, risk_budget);
 
+        // Huidige convictions alleen nodig als het entry-filter aan staat
+        let new_convictions: HashMap<FutureInstrument, f64> =
+            if self.cfg.entry_filter_threshold > 0.0 {
+                self.evaluate_signals(ctx, risk_budget)
+                    .into_iter()
+                    .map(|s| (s.instrument, s.final_signal.conviction))
+                    .collect()
+            } else {
+                HashMap::new()
+            };
+
         // 2) Maak het makkelijk om target per instrument op te zoeken
         //    (max 3 instrumenten, dus gewoon een kleine Vec scan is prima)
         let mut out = Vec::new();
@@ -788,9 +3647,24 @@ impl MacroFuturesSleeve {
                 .copied()
                 .unwrap_or(0);
 
-            let delta = p.target_contracts - current;
+            let target = p.target_contracts;
+            let delta = target - current;
+
+            // Bijkopen in dezelfde richting alleen bij voldoende conviction-verandering
+            let adds_to_position = current != 0
+                && current.signum() == target.signum()
+                && target.abs() > current.abs();
+            if adds_to_position
+                && let (Some(old), Some(new)) = (
+                    ctx.position_convictions.get(&p.instrument),
+                    new_convictions.get(&p.instrument),
+                )
+                && (new - old).abs() <= self.cfg.entry_filter_threshold
+            {
+                continue;
+            }
 
-            if delta != 0 {
+            if delta != 0 && !self.is_churn(ctx, p.instrument, current, target) {
                 out.push(FuturesOrderIntent {
                     instrument: p.instrument,
                     delta_contracts: delta,
@@ -821,15 +3695,204 @@ impl MacroFuturesSleeve {
         out
     }
 
+    /// Turnover-filter voor `plan_order_intents`: `true` als de order te klein is
+    /// (|delta| < `min_delta_contracts`, of een bijkoop onder `min_turnover_frac` van
+    /// `max_position_size_usd`). Reversals en het sluiten van een positie zijn nooit churn.
+    fn is_churn(
+        &self,
+        ctx: &FuturesSleeveContext,
+        instrument: FutureInstrument,
+        current: i32,
+        target: i32,
+    ) -> bool {
+        let reversal = current != 0 && target != 0 && current.signum() != target.signum();
+        if reversal || target == 0 {
+            return false;
+        }
+
+        let delta = (target - current).unsigned_abs();
+        if delta < self.cfg.min_delta_contracts {
+            return true;
+        }
+
+        let increases = current != 0 && target.abs() > current.abs();
+        if increases && self.cfg.min_turnover_frac > 0.0 {
+            let last_price = ctx
+                .histories
+                .get(&instrument)
+                .and_then(|h| h.bars.last())
+                .map(|b| b.close)
+                .filter(|c| c.is_finite() && *c > 0.0);
+
+            if let Some(price) = last_price {
+                let delta_notional =
+                    delta as f64 * price * self.contract_multiplier(instrument);
+                let min_notional =
+                    self.cfg.min_turnover_frac * ctx.risk_envelope.max_position_size_usd;
+                return delta_notional < min_notional;
+            }
+        }
+
+        false
+    }
+
+    /// Alleen de orders die bestaande posities verkleinen of sluiten (incl. halt-flatten).
+    ///
+    /// Bij een reversal (bijv. +3 → -1) is de exit het sluiten van de oude positie (-3);
+    /// het openen van de nieuwe kant hoort bij de entries. Zo kan de caller exits
+    /// vóór entries sturen om margin vrij te maken.
+    pub fn plan_exit_orders(
+        &self,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+    ) -> Vec<FuturesOrderIntent> {
+        self.plan_order_intents(ctx, risk_budget)
+            .into_iter()
+            .filter_map(|oi| {
+                let current = ctx.current_positions.get(&oi.instrument).copied().unwrap_or(0);
+                let (exit_delta, _) = split_exit_entry(current, oi.delta_contracts);
+                (exit_delta != 0).then_some(FuturesOrderIntent {
+                    instrument: oi.instrument,
+                    delta_contracts: exit_delta,
+                })
+            })
+            .collect()
+    }
+
+    /// Tegenhanger van `plan_exit_orders`: alleen orders die een positie openen of vergroten.
+    /// Exits + entries samen per instrument == `plan_order_intents`.
+    pub fn plan_entry_orders(
+        &self,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+    ) -> Vec<FuturesOrderIntent> {
+        self.plan_order_intents(ctx, risk_budget)
+            .into_iter()
+            .filter_map(|oi| {
+                let current = ctx.current_positions.get(&oi.instrument).copied().unwrap_or(0);
+                let (_, entry_delta) = split_exit_entry(current, oi.delta_contracts);
+                (entry_delta != 0).then_some(FuturesOrderIntent {
+                    instrument: oi.instrument,
+                    delta_contracts: entry_delta,
+                })
+            })
+            .collect()
+    }
+
+    /// `plan_order_intents` plus laatste prijs en contract-notional per intent.
+    pub fn plan_order_intents_with_price_context(
+        &self,
+        ctx: &FuturesSleeveContext,
+        risk_budget: &FuturesRiskBudget,
+    ) -> Vec<FuturesOrderIntentPriced> {
+        self.plan_order_intents(ctx, risk_budget)
+            .into_iter()
+            .map(|intent| {
+                let last_price = ctx
+                    .histories
+                    .get(&intent.instrument)
+                    .and_then(|h| h.bars.last())
+                    .map(|b| b.close)
+                    .filter(|c| c.is_finite() && *c > 0.0);
+
+                FuturesOrderIntentPriced {
+                    intent,
+                    last_price,
+                    contract_notional_usd: last_price
+                        .map(|p| p * self.contract_multiplier(intent.instrument)),
+                }
+            })
+            .collect()
+    }
+
+    /// Debug-trace van de volledige signal pipeline, één key per instrument.
+    ///
+    /// Bevat alle tussenwaarden uit `evaluate_instrument`: ruwe z-scores, breakout,
+    /// carry-normalisatie, macro-multipliers, effective score, conviction en thresholds.
+    /// Instrumenten die de validatie niet halen krijgen alleen `reason` + `bars`.
+    pub fn export_signal_trace(&self, ctx: &FuturesSleeveContext) -> serde_json::Value {
+        let mut out = serde_json::Map::new();
+
+        for (inst, hist) in &ctx.histories {
+            let key = format!("{:?}", inst);
+            let signal = self.evaluate_instrument(*inst, hist, &ctx.macro_scalars, ctx.as_of);
+
+            let signal_idx = match self.signal_bar_index(hist) {
+                Some(i) if signal.reason != SignalReason::InsufficientHistory
+                    && signal.reason != SignalReason::InvalidData =>
+                {
+                    i
+                }
+                _ => {
+                    out.insert(
+                        key,
+                        serde_json::json!({
+                            "reason": format!("{:?}", signal.reason),
+                            "bars": hist.bars.len(),
+                        }),
+                    );
+                    continue;
+                }
+            };
+
+            let last_bar = &hist.bars[signal_idx];
+            let c = self.trend_components(&hist.bars[..=signal_idx]);
+            let (trend_scalar, carry_scalar) = self.macro_multipliers(*inst, &ctx.macro_scalars);
+
+            let (carry_rate, carry_vol) = match last_bar.fx_carry {
+                Some(fx) => (fx.carry_rate_annualized, fx.carry_rate_vol_252d),
+                None => (0.0, 0.0),
+            };
+
+            let mut entry = serde_json::json!({
+                "reason": format!("{:?}", signal.reason),
+                "breakout": c.breakout,
+                "trend_raw": signal.raw.trend_score,
+                "carry_rate_annualized": carry_rate,
+                "carry_rate_vol_252d": carry_vol,
+                "carry_raw": signal.raw.carry_score,
+                "trend_scalar": trend_scalar,
+                "carry_scalar": carry_scalar,
+                "trend_macro_adjusted": signal.macro_adj.trend_macro_adjusted,
+                "carry_macro_adjusted": signal.macro_adj.carry_macro_adjusted,
+                "effective_score": signal.final_signal.effective_score,
+                "conviction": signal.final_signal.conviction,
+                "min_effective_score": self.cfg.min_effective_score,
+                "min_conviction": self.cfg.min_conviction,
+                "direction": signal.final_signal.direction,
+            });
+            // z-score per trend-window, bijv. "z20", "z60", "z120"
+            for (days, z) in &c.z {
+                entry[format!("z{}", days)] = serde_json::json!(z);
+            }
+            out.insert(key, entry);
+        }
+
+        serde_json::Value::Object(out)
+    }
+
     fn apply_macro(
         &self,
         inst: FutureInstrument,
         raw: &RawSignal,
         macros: &MacroScalars,
     ) -> MacroAdjustedSignal {
+        let (trend_scalar, carry_scalar) = self.macro_multipliers(inst, macros);
+
+        let trend_macro_adjusted = raw.trend_score * trend_scalar;
+        let carry_macro_adjusted = raw.carry_score * carry_scalar;
+
+        MacroAdjustedSignal {
+            trend_macro_adjusted,
+            carry_macro_adjusted,
+        }
+    }
+
+    /// (trend_scalar, carry_scalar) per instrument o.b.v. de macro-scalars.
+    fn macro_multipliers(&self, inst: FutureInstrument, macros: &MacroScalars) -> (f64, f64) {
         // Trend-scalar per instrument
         let trend_scalar = match inst {
-            FutureInstrument::Mes | FutureInstrument::Mnq => {
+            FutureInstrument::Mes | FutureInstrument::Mnq | FutureInstrument::M2k => {
                 // Equity indices → vooral risk-on sentiment
                 macros.risk_on_scalar
             }
@@ -837,27 +3900,48 @@ impl MacroFuturesSleeve {
                 // FX future → combinatie van risk-on & USD-thema
                 macros.risk_on_scalar * macros.usd_scalar
             }
+            FutureInstrument::Gc => {
+                // Goud is in USD genoteerd → vooral USD-thema
+                macros.usd_scalar
+            }
+            FutureInstrument::Mcl => {
+                // Crude is cyclisch → risk-on sentiment
+                macros.risk_on_scalar
+            }
+            FutureInstrument::Zn => {
+                // Geen macro-tilt: equity/bond-rotatie zit al in de ZN-trend zelf
+                1.0
+            }
+            // VX wordt puur op term-structure getrade, trend telt niet mee
+            FutureInstrument::Vx => 0.0,
+            // Custom: geen macro-tilt (VIX-carry instrumenten volgen VX)
+            FutureInstrument::Custom(_) => match self.carry_type(inst) {
+                CarryType::VixTermStructure => 0.0,
+                _ => 1.0,
+            },
         };
 
-        // Carry-scalar per instrument
-        let carry_scalar = match inst {
-            FutureInstrument::SixE => {
+        // Carry-scalar per carry-type
+        let carry_scalar = match self.carry_type(inst) {
+            CarryType::Fx => {
                 // 6E carry wordt deels gewogen en afhankelijk van USD-thema
                 self.cfg.carry_weight_6e * macros.usd_scalar
             }
-            _ => 0.0, // MES/MNQ hebben geen carry-component
+            // MCL: roll yield, niet macro-gestuurd
+            CarryType::Commodity => self.cfg.carry_weight_commodity,
+            // ZN: curve-carry (10y - 2y)
+            CarryType::Bond => self.cfg.carry_weight_bond,
+            // VX: term-structure carry, ongewogen
+            CarryType::VixTermStructure => 1.0,
+            CarryType::None => 0.0, // MES/MNQ/M2K/MGC hebben geen carry-component
         };
 
-        let trend_macro_adjusted = raw.trend_score * trend_scalar;
-        let carry_macro_adjusted = raw.carry_score * carry_scalar;
-
-        MacroAdjustedSignal {
-            trend_macro_adjusted,
-            carry_macro_adjusted,
-        }
+        (trend_scalar, carry_scalar)
     }
 
-    fn compute_effective_score(
+    /// Combineer macro-adjusted trend (+ carry voor 6E) tot één score,
+    /// geclipt op ±`effective_score_clip`.
+    pub fn compute_effective_score(
         &self,
         inst: FutureInstrument,
         macro_adj: &MacroAdjustedSignal,
@@ -865,8 +3949,8 @@ impl MacroFuturesSleeve {
         // Basis: macro-adjusted trend
         let mut eff = macro_adj.trend_macro_adjusted;
 
-        // 6E krijgt bovenop trend ook carry mee
-        if let FutureInstrument::SixE = inst {
+        // Instrumenten met een carry-type (6E, MCL, ZN, VX) krijgen bovenop trend ook carry mee
+        if self.carry_type(inst) != CarryType::None {
             eff += macro_adj.carry_macro_adjusted;
         }
 
@@ -880,33 +3964,37 @@ impl MacroFuturesSleeve {
         inst: FutureInstrument,
         hist: &InstrumentHistory,
         macros: &MacroScalars,
+        as_of: DateTime<Utc>,
     ) -> InstrumentSignal {
 
+        // 0) Niet-geregistreerd instrument → geen signal
+        if !self.registry.contains(inst) {
+            return self.flat_signal(inst, SignalReason::InvalidData);
+        }
+
         // 1) History length check
-        if let Err(reason) = self.validate_history(hist) {
+        if let Err(reason) = self.validate_history(hist, as_of) {
             return self.flat_signal(inst, reason);
         }
 
-        // 2) Pak laatste bar en valideer features
-        let last_bar = match hist.bars.last() {
-            Some(b) => b,
+        // 2) Pak de signal-bar (laatste bar, of `momentum_lag` bars terug) en valideer features
+        let signal_idx = match self.signal_bar_index(hist) {
+            Some(i) => i,
             None => return self.flat_signal(inst, SignalReason::InsufficientHistory),
         };
+        let last_bar = &hist.bars[signal_idx];
 
         if let Err(reason) = self.validate_features(last_bar) {
             return self.flat_signal(inst, reason);
         }
 
-        // 3) Compute raw trend score
-        let trend_score = self.compute_trend_raw(&hist.bars, inst);
+        // 3+4) Raw trend- en carry-score (carry alleen 6E, anders 0.0)
+        let mut raw = self.compute_raw_signal(inst, &hist.bars[..=signal_idx]);
 
-        // 4) Compute raw carry score (alleen 6E, anders 0.0)
-        let carry_score = self.compute_carry_raw(inst, last_bar);
-
-        let raw = RawSignal {
-            trend_score,
-            carry_score,
-        };
+        // VX: carry komt uit de VIX term slope i.p.v. de bars
+        if self.carry_type(inst) == CarryType::VixTermStructure {
+            raw.carry_score = self.compute_vix_carry_raw(macros);
+        }
 
         // 5) Macro-adjust (risk-on + USD, instrument-specifiek)
         let macro_adj = self.apply_macro(inst, &raw, macros);
@@ -973,18 +4061,56 @@ impl MacroFuturesSleeve {
     }
 
 
-    fn validate_history(&self, hist: &InstrumentHistory) -> Result<(), SignalReason> {
-        const MIN_BARS: usize = 120;
+    /// `min_history_bars` plus de extra bars die de momentum-lag overslaat.
+    fn min_bars_required(&self) -> usize {
+        self.cfg.min_history_bars + self.cfg.momentum_lag as usize
+    }
+
+    /// Index van de bar waarop het signal gebaseerd wordt (`len - 1 - momentum_lag`).
+    fn signal_bar_index(&self, hist: &InstrumentHistory) -> Option<usize> {
+        hist.bars
+            .len()
+            .checked_sub(1 + self.cfg.momentum_lag as usize)
+    }
+
+    fn signal_bar<'a>(&self, hist: &'a InstrumentHistory) -> Option<&'a DailyFeatureBar> {
+        self.signal_bar_index(hist).map(|i| &hist.bars[i])
+    }
 
-        if hist.bars.len() < MIN_BARS {
+    fn validate_history(
+        &self,
+        hist: &InstrumentHistory,
+        as_of: DateTime<Utc>,
+    ) -> Result<(), SignalReason> {
+        if hist.bars.len() < self.min_bars_required() {
             return Err(SignalReason::InsufficientHistory);
         }
 
+        // Bevroren feed: laatste bar te oud t.o.v. as_of (bars in de toekomst tellen niet als stale)
+        if let Some(last) = hist.bars.last()
+            && (as_of - last.ts).num_hours() > self.cfg.max_bar_age_hours as i64
+        {
+            return Err(SignalReason::StaleData);
+        }
+
         Ok(())
     }
 
 
     fn validate_features(&self, bar: &DailyFeatureBar) -> Result<(), SignalReason> {
+        if Self::first_invalid_feature(bar).is_some() {
+            return Err(SignalReason::InvalidData);
+        }
+
+        if bar.volume < self.cfg.min_volume_contracts {
+            return Err(SignalReason::LowVolume);
+        }
+
+        Ok(())
+    }
+
+    /// Naam van het eerste ongeldige feature-veld in de bar (of `None` als alles ok is).
+    fn first_invalid_feature(bar: &DailyFeatureBar) -> Option<&'static str> {
         fn pos(x: f64) -> bool {
             x.is_finite() && x > 0.0
         }
@@ -993,56 +4119,108 @@ impl MacroFuturesSleeve {
             x.is_finite()
         }
 
-        // Prijzen
-        if !pos(bar.open) || !pos(bar.high) || !pos(bar.low) || !pos(bar.close) {
-            return Err(SignalReason::InvalidData);
+        // Prijzen, ATR & volatilities moeten > 0 en finite zijn
+        let positive = [
+            ("open", bar.open),
+            ("high", bar.high),
+            ("low", bar.low),
+            ("close", bar.close),
+            ("vwap", bar.vwap),
+            ("ma_5d", bar.ma_5d),
+            ("atr_14", bar.atr_14),
+            ("vol_20d", bar.vol_20d),
+            ("vol_60d", bar.vol_60d),
+            ("vol_120d", bar.vol_120d),
+            // Breakout-basis moet zinvol zijn
+            ("highest_close_50d", bar.highest_close_50d),
+            ("lowest_close_50d", bar.lowest_close_50d),
+        ];
+        if let Some((field, _)) = positive.iter().find(|(_, x)| !pos(*x)) {
+            return Some(field);
         }
 
         // Volume mag 0 zijn, maar niet negatief of NaN/inf
         if !finite(bar.volume) || bar.volume < 0.0 {
-            return Err(SignalReason::InvalidData);
+            return Some("volume");
         }
 
-        // ATR & volatilities moeten > 0 en finite zijn
-        if !pos(bar.atr_14)
-            || !pos(bar.vol_20d)
-            || !pos(bar.vol_60d)
-            || !pos(bar.vol_120d)
-        {
-            return Err(SignalReason::InvalidData);
+        // Idem voor open interest
+        if !finite(bar.open_interest) || bar.open_interest < 0.0 {
+            return Some("open_interest");
         }
 
         // Returns mogen negatief zijn, maar niet NaN/inf
-        if !finite(bar.ret_20d) || !finite(bar.ret_60d) || !finite(bar.ret_120d) {
-            return Err(SignalReason::InvalidData);
-        }
-
-        // Breakout-basis moet zinvol zijn
-        if !pos(bar.highest_close_50d) || !pos(bar.lowest_close_50d) {
-            return Err(SignalReason::InvalidData);
+        let returns = [
+            ("ret_20d", bar.ret_20d),
+            ("ret_60d", bar.ret_60d),
+            ("ret_120d", bar.ret_120d),
+        ];
+        if let Some((field, _)) = returns.iter().find(|(_, x)| !finite(*x)) {
+            return Some(field);
         }
 
         // FX carry (alleen als aanwezig)
         if let Some(fx) = bar.fx_carry {
-            if !finite(fx.carry_rate_annualized)
-                || !finite(fx.carry_rate_vol_252d)
-                || fx.carry_rate_vol_252d <= 0.0
-            {
-                return Err(SignalReason::InvalidData);
+            if !finite(fx.carry_rate_annualized) {
+                return Some("fx_carry.carry_rate_annualized");
+            }
+            if !finite(fx.carry_rate_vol_252d) || fx.carry_rate_vol_252d <= 0.0 {
+                return Some("fx_carry.carry_rate_vol_252d");
             }
         }
 
-        Ok(())
+        None
+    }
+
+
+    /// Raw trend- en carry-score op basis van de laatste bar van `bars`
+    /// (geen history- of feature-validatie; lege slice → 0.0 / 0.0).
+    pub fn compute_raw_signal(
+        &self,
+        inst: FutureInstrument,
+        bars: &[DailyFeatureBar],
+    ) -> RawSignal {
+        let trend_score = self.compute_trend_raw(bars, inst);
+        let carry_score = match bars.last() {
+            Some(last) => self.compute_carry_raw(inst, last),
+            None => 0.0,
+        };
+
+        RawSignal {
+            trend_score,
+            carry_score,
+        }
     }
 
+    /// Kwaliteit van het carry-signal in [0, 1], gewogen uit:
+    /// - stabiliteit (40%): `|rate| / (|rate| + vol)`, lage carry-vol is beter
+    /// - richting (20%): positieve carry (long 6E) = 1.0, negatief = 0.5
+    /// - Sharpe (40%): `|rate| / vol` t.o.v. `min_carry_sharpe` (1.0 zodra die gehaald wordt)
+    pub fn compute_carry_signal_quality(&self, carry_features: &FxCarryFeatures) -> f64 {
+        let rate = carry_features.carry_rate_annualized;
+        let vol = carry_features.carry_rate_vol_252d;
+
+        if !rate.is_finite() || !vol.is_finite() || vol <= 0.0 {
+            return 0.0;
+        }
+
+        let stability = rate.abs() / (rate.abs() + vol);
+        let direction = if rate > 0.0 { 1.0 } else { 0.5 };
+
+        let sharpe = rate.abs() / vol;
+        let min_sharpe = self.cfg.min_carry_sharpe.max(f64::EPSILON);
+        let sharpe_score = (sharpe / min_sharpe).min(1.0);
+
+        (0.4 * stability + 0.2 * direction + 0.4 * sharpe_score).clamp(0.0, 1.0)
+    }
 
     fn compute_carry_raw(
         &self,
         inst: FutureInstrument,
         last: &DailyFeatureBar,
     ) -> f64 {
-        match inst {
-            FutureInstrument::SixE => {
+        match self.carry_type(inst) {
+            CarryType::Fx => {
                 let fx = match last.fx_carry {
                     Some(fx) => fx,
                     // Geen carry-features beschikbaar → conservatief 0.0
@@ -1058,32 +4236,192 @@ impl MacroFuturesSleeve {
                 let vol_floor = self.cfg.carry_vol_floor.max(f64::EPSILON);
                 let denom = carry_vol.max(vol_floor);
 
-                let z = carry_rate / denom;
+                // Gate met de carry-quality: instabiele / zwakke carry telt minder mee
+                let z = carry_rate / denom * self.compute_carry_signal_quality(&fx);
 
                 let clip = self.cfg.carry_score_clip.abs(); // defensief
                 z.clamp(-clip, clip)
             }
-            // MES / MNQ (en evt. andere) → geen carry-premie in deze sleeve
-            _ => 0.0,
+            CarryType::Commodity => self.compute_commodity_carry_raw(last),
+            CarryType::Bond => {
+                let clip = self.cfg.carry_score_clip.abs();
+                self.compute_bond_carry_raw(last).clamp(-clip, clip)
+            }
+            // VX-carry komt uit MacroScalars (zie evaluate_instrument)
+            CarryType::VixTermStructure => 0.0,
+            // MES / MNQ / M2K / MGC → geen carry-premie in deze sleeve
+            CarryType::None => 0.0,
+        }
+    }
+
+    /// VX-carry: bij sterke contango (`vix_term_slope > vix_contango_threshold`) volledig
+    /// short (`-carry_score_clip`), anders neutraal. Backwardation levert géén long op.
+    pub fn compute_vix_carry_raw(&self, macros: &MacroScalars) -> f64 {
+        let slope = macros.vix_term_slope;
+        if slope.is_finite() && slope > self.cfg.vix_contango_threshold {
+            -self.cfg.carry_score_clip.abs()
+        } else {
+            0.0
+        }
+    }
+
+    /// Bond-carry: curve-slope `(yield_10y - yield_2y) / carry_vol_floor`.
+    /// Inverse curve → negatief. Zonder `bond_carry`-features of bij NaN → 0.0.
+    pub fn compute_bond_carry_raw(&self, last: &DailyFeatureBar) -> f64 {
+        let bc = match last.bond_carry {
+            Some(bc) => bc,
+            None => return 0.0,
+        };
+
+        let slope = bc.yield_10y - bc.yield_2y;
+        if !slope.is_finite() {
+            return 0.0;
+        }
+
+        slope / self.cfg.carry_vol_floor.max(f64::EPSILON)
+    }
+
+    /// Commodity-carry z-score: roll yield / geannualiseerde prijs-vol (met `carry_vol_floor`),
+    /// geschaald met de backwardation-score en geclipt op ±`carry_score_clip`.
+    /// Zonder `commodity_carry`-features → 0.0.
+    pub fn compute_commodity_carry_raw(&self, last: &DailyFeatureBar) -> f64 {
+        let cc = match last.commodity_carry {
+            Some(cc) => cc,
+            None => return 0.0,
+        };
+
+        let roll_yield = cc.roll_yield_annualized;
+        if !roll_yield.is_finite() || !last.vol_20d.is_finite() {
+            return 0.0;
+        }
+
+        let ann_vol = last.vol_20d.abs() * 252f64.sqrt();
+        let denom = ann_vol.max(self.cfg.carry_vol_floor.max(f64::EPSILON));
+
+        // Structurele backwardation telt volledig, contango dempt tot 0
+        let term_weight = if cc.backwardation_score.is_finite() {
+            0.5 + 0.5 * cc.backwardation_score.clamp(-1.0, 1.0)
+        } else {
+            0.5
+        };
+
+        let clip = self.cfg.carry_score_clip.abs();
+        (roll_yield / denom * term_weight).clamp(-clip, clip)
+    }
+
+
+    /// Open-interest bevestiging van de prijstrend (richting van `ret_20d` op de laatste bar):
+    /// +1.0 als OI over `OI_LOOKBACK_BARS` in dezelfde richting beweegt (nieuw geld bevestigt),
+    /// -1.0 bij divergentie, 0.0 als de relatieve OI-verandering binnen `OI_NOISE_THRESHOLD`
+    /// valt of er te weinig / geen bruikbare OI-data is.
+    pub fn compute_oi_confirmation_score(&self, bars: &[DailyFeatureBar]) -> f64 {
+        if bars.len() <= OI_LOOKBACK_BARS {
+            return 0.0;
+        }
+
+        let last = &bars[bars.len() - 1];
+        let prev = &bars[bars.len() - 1 - OI_LOOKBACK_BARS];
+        if !last.ret_20d.is_finite() || last.ret_20d == 0.0 || prev.open_interest <= 0.0 {
+            return 0.0;
         }
+
+        let oi_change = last.open_interest / prev.open_interest - 1.0;
+        if !oi_change.is_finite() || oi_change.abs() < OI_NOISE_THRESHOLD {
+            return 0.0;
+        }
+
+        // Stijgende OI bevestigt de trend (long én short), dalende OI = divergentie
+        if oi_change > 0.0 { 1.0 } else { -1.0 }
+    }
+
+    /// Positie van de close t.o.v. de session-VWAP in ATR's: `(close - vwap) / atr_14`,
+    /// geclipt op [-1, 1]. Ongeldige input → 0.0.
+    pub fn compute_vwap_relative_score(&self, bar: &DailyFeatureBar) -> f64 {
+        let score = (bar.close - bar.vwap) / bar.atr_14;
+        if !score.is_finite() || bar.atr_14 <= 0.0 || bar.vwap <= 0.0 {
+            return 0.0;
+        }
+        score.clamp(-1.0, 1.0)
     }
 
+    /// Korte-termijn mean-reversion: `-(close - ma_5d) / atr_14`, geclipt op [-2, 2].
+    /// Positief als de close onder het 5d-gemiddelde ligt; 0.0 bij ongeldige input.
+    pub fn compute_mean_reversion_raw(&self, bar: &DailyFeatureBar) -> f64 {
+        let score = -(bar.close - bar.ma_5d) / bar.atr_14;
+        if !score.is_finite() || bar.atr_14 <= 0.0 || bar.ma_5d <= 0.0 {
+            return 0.0;
+        }
+        score.clamp(-2.0, 2.0)
+    }
+
+    /// Mean-reversion gewicht voor `inst`: override indien aanwezig, anders de globale waarde.
+    fn mean_reversion_weight_for(&self, inst: FutureInstrument) -> f64 {
+        self.cfg
+            .mean_reversion_weight_overrides
+            .get(&inst)
+            .copied()
+            .unwrap_or(self.cfg.mean_reversion_weight)
+    }
 
     fn compute_trend_raw(
         &self,
         bars: &[DailyFeatureBar],
-        _inst: FutureInstrument,
+        inst: FutureInstrument,
     ) -> f64 {
         let last = match bars.last() {
-            Some(b) => b,
+            Some(last) => last,
             None => return 0.0, // zou niet mogen gebeuren door validate_history, maar fail-safe
         };
+        let c = self.trend_components(bars);
+
+        let momentum: f64 = self
+            .cfg
+            .trend_windows
+            .iter()
+            .zip(&c.z)
+            .map(|((_, weight), (_, z))| weight * z)
+            .sum();
 
-        let z20 = last.ret_20d / last.vol_20d;
-        let z60 = last.ret_60d / last.vol_60d;
-        let z120 = last.ret_120d / last.vol_120d;
+        // Bevestiging versterkt de trend in zijn eigen richting (ook short), divergentie verzwakt
+        let oi_term = self.compute_oi_confirmation_score(bars) * last.ret_20d.signum();
 
-        let brk = if last.close > last.highest_close_50d {
+        let raw =
+            momentum +
+            self.cfg.breakout_weight * c.breakout +
+            self.cfg.oi_confirmation_weight * oi_term +
+            self.cfg.vwap_weight * self.compute_vwap_relative_score(last) +
+            self.mean_reversion_weight_for(inst) * self.compute_mean_reversion_raw(last);
+
+        raw.clamp(-self.cfg.trend_score_clip, self.cfg.trend_score_clip)
+    }
+
+    /// Positie van `close` binnen de 50d-range, geschaald naar [-1, 1]
+    /// (-1 = op de low, 0 = midden, +1 = op de high). Buiten de range wordt geclampt;
+    /// een lege/ongeldige range geeft 0.0.
+    pub fn compute_breakout_strength(close: f64, highest_50d: f64, lowest_50d: f64) -> f64 {
+        let range = highest_50d - lowest_50d;
+        if !range.is_finite() || range <= 0.0 || !close.is_finite() {
+            return 0.0;
+        }
+
+        let pct = (close - lowest_50d) / range;
+        ((pct - 0.5) * 2.0).clamp(-1.0, 1.0)
+    }
+
+    /// Losse (ongewogen) trend-componenten op de laatste bar van `bars`.
+    fn trend_components(&self, bars: &[DailyFeatureBar]) -> TrendComponents {
+        let last = match bars.last() {
+            Some(last) => last,
+            None => return TrendComponents { z: Vec::new(), breakout: 0.0 },
+        };
+
+        let brk = if self.cfg.use_continuous_breakout {
+            Self::compute_breakout_strength(
+                last.close,
+                last.highest_close_50d,
+                last.lowest_close_50d,
+            )
+        } else if last.close > last.highest_close_50d {
             1.0
         } else if last.close < last.lowest_close_50d {
             -1.0
@@ -1091,13 +4429,56 @@ impl MacroFuturesSleeve {
             0.0
         };
 
-        let raw =
-            self.cfg.trend_weight_20d * z20 +
-            self.cfg.trend_weight_60d * z60 +
-            self.cfg.trend_weight_120d * z120 +
-            self.cfg.breakout_weight * brk;
+        let z = self
+            .cfg
+            .trend_windows
+            .iter()
+            .map(|&(days, _)| (days, Self::momentum_z(bars, days)))
+            .collect();
 
-        raw.clamp(-self.cfg.trend_score_clip, self.cfg.trend_score_clip)
+        TrendComponents { z, breakout: brk }
+    }
+
+    /// Momentum z-score `ret / vol` over `days` op de laatste bar van `bars`.
+    ///
+    /// 20/60/120 komen uit de features van de bar; andere lookbacks worden uit de
+    /// closes berekend (log-return over `days` bars, gedeeld door de stdev van de
+    /// daily log-returns in dat window). Te weinig bars of ongeldige data → 0.0.
+    fn momentum_z(bars: &[DailyFeatureBar], days: u32) -> f64 {
+        let last = match bars.last() {
+            Some(last) => last,
+            None => return 0.0,
+        };
+
+        let fixed = match days {
+            20 => Some(last.ret_20d / last.vol_20d),
+            60 => Some(last.ret_60d / last.vol_60d),
+            120 => Some(last.ret_120d / last.vol_120d),
+            _ => None,
+        };
+        if let Some(z) = fixed {
+            return if z.is_finite() { z } else { 0.0 };
+        }
+
+        let n = days as usize;
+        if n < 2 || bars.len() <= n {
+            return 0.0;
+        }
+
+        let window = &bars[bars.len() - 1 - n..];
+        let log_returns: Vec<f64> = window
+            .windows(2)
+            .map(|w| (w[1].close / w[0].close).ln())
+            .collect();
+
+        let mean = log_returns.iter().sum::<f64>() / n as f64;
+        let var = log_returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        let vol = var.sqrt();
+        // Log-return over het window, net als de vol hierboven
+        let ret = (last.close / window[0].close).ln();
+
+        let z = ret / vol;
+        if z.is_finite() { z } else { 0.0 }
     }
 
 
@@ -1154,14 +4535,153 @@ impl MacroFuturesSleeve {
 
 }
 
-fn instrument_metadata(inst: FutureInstrument) -> (&'static str, &'static str) {
-    match inst {
-        FutureInstrument::Mes => ("MES", "CME"),
-        FutureInstrument::Mnq => ("MNQ", "CME"),
-        FutureInstrument::SixE => ("6E", "CME"),
+/// Splits een order-delta in (exit, entry) t.o.v. de huidige positie.
+///
+/// - verkleinen / sluiten → volledig exit
+/// - openen / vergroten → volledig entry
+/// - reversal → exit sluit de oude kant, entry opent de nieuwe
+fn split_exit_entry(current: i32, delta: i32) -> (i32, i32) {
+    let target = current + delta;
+
+    if current == 0 {
+        (0, delta)
+    } else if target == 0 || target.signum() == current.signum() {
+        if target.abs() < current.abs() {
+            (delta, 0)
+        } else {
+            (0, delta)
+        }
+    } else {
+        (-current, target)
+    }
+}
+
+impl EngineOrder {
+    /// Geschatte USD-notional: `quantity * contract_multiplier * last_price_usd`
+    /// (altijd ≥ 0 omdat `quantity` absoluut is). None als `registry` het instrument niet kent.
+    pub fn estimated_notional_usd(
+        &self,
+        registry: &InstrumentRegistry,
+        last_price_usd: f64,
+    ) -> Option<f64> {
+        registry
+            .contract_multiplier(self.instrument)
+            .map(|m| self.quantity as f64 * m * last_price_usd)
+    }
+
+    pub fn to_json_value(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or(serde_json::Value::Null)
+    }
+
+    /// Inverse van de JSON-serialisatie (audit / replay), ook voor custom instrumenten.
+    pub fn from_json(s: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
+    /// Minimale FIX 4.2 `NewOrderSingle` (35=D), SOH-gescheiden, met TransactTime = nu.
+    pub fn to_fix42_new_order_single(&self, cl_ord_id: &str, account: &str) -> String {
+        self.to_fix42_new_order_single_with(cl_ord_id, account, None, Utc::now())
+    }
+
+    /// Als `to_fix42_new_order_single`, met optionele SenderCompID (49) en vaste TransactTime.
+    ///
+    /// Velden: 8, 9 (BodyLength), 35=D, [49], 11, 1, 21=1, 55, 54 (1=Buy, 2=Sell), 38,
+    /// 40 (1=Market, 2=Limit met 44, 3=Stop met 99, 4=Stop-limit met 44 + 99), 60,
+    /// 10 (CheckSum).
+    pub fn to_fix42_new_order_single_with(
+        &self,
+        cl_ord_id: &str,
+        account: &str,
+        sender_comp_id: Option<&str>,
+        transact_time: DateTime<Utc>,
+    ) -> String {
+        const SOH: char = '\x01';
+
+        let side = match self.side {
+            EngineOrderSide::Buy => "1",
+            EngineOrderSide::Sell => "2",
+        };
+
+        let mut fields: Vec<(u32, String)> = vec![(35, "D".to_string())];
+        if let Some(sender) = sender_comp_id {
+            fields.push((49, sender.to_string()));
+        }
+        fields.extend([
+            (11, cl_ord_id.to_string()),
+            (1, account.to_string()),
+            (21, "1".to_string()),
+            (55, self.symbol.to_string()),
+            (54, side.to_string()),
+            (38, self.quantity.to_string()),
+        ]);
+        let ord_type = match (self.limit_price, self.stop_price) {
+            (None, None) => "1",
+            (Some(_), None) => "2",
+            (None, Some(_)) => "3",
+            (Some(_), Some(_)) => "4",
+        };
+        fields.push((40, ord_type.to_string()));
+        if let Some(px) = self.limit_price {
+            fields.push((44, px.to_string()));
+        }
+        if let Some(px) = self.stop_price {
+            fields.push((99, px.to_string()));
+        }
+        fields.push((60, transact_time.format("%Y%m%d-%H:%M:%S").to_string()));
+
+        let body: String = fields
+            .iter()
+            .map(|(tag, value)| format!("{}={}{}", tag, value, SOH))
+            .collect();
+
+        let mut msg = format!("8=FIX.4.2{}9={}{}{}", SOH, body.len(), SOH, body);
+        let checksum = msg.bytes().map(u32::from).sum::<u32>() % 256;
+        msg.push_str(&format!("10={:03}{}", checksum, SOH));
+        msg
     }
 }
 
+const KNOWN_INSTRUMENTS: [FutureInstrument; 8] = [
+    FutureInstrument::Mes,
+    FutureInstrument::Mnq,
+    FutureInstrument::SixE,
+    FutureInstrument::Gc,
+    FutureInstrument::Mcl,
+    FutureInstrument::M2k,
+    FutureInstrument::Zn,
+    FutureInstrument::Vx,
+];
+
+/// Contract-multiplier van de built-in instrumenten (MES = 5, MNQ = 2, 6E = 125k);
+/// None voor `Custom` (gebruik `InstrumentRegistry::contract_multiplier`).
+pub fn contract_multiplier(inst: FutureInstrument) -> Option<f64> {
+    builtin_spec(inst).map(|s| s.contract_multiplier as f64)
+}
+
+/// Spec van de built-in instrumenten; None voor `Custom`.
+fn builtin_spec(inst: FutureInstrument) -> Option<InstrumentSpec> {
+    let (symbol, venue, contract_multiplier, carry) = match inst {
+        FutureInstrument::Mes => ("MES", "CME", 5, CarryType::None),
+        FutureInstrument::Mnq => ("MNQ", "CME", 2, CarryType::None),
+        FutureInstrument::SixE => ("6E", "CME", 125_000, CarryType::Fx),
+        FutureInstrument::Gc => ("MGC", "CME", 10, CarryType::None),
+        FutureInstrument::Mcl => ("MCL", "CME", 100, CarryType::Commodity),
+        FutureInstrument::M2k => ("M2K", "CME", 5, CarryType::None),
+        FutureInstrument::Zn => ("ZN", "CBOT", 1_000, CarryType::Bond),
+        FutureInstrument::Vx => ("VX", "CBOE", 1_000, CarryType::VixTermStructure),
+        FutureInstrument::Custom(_) => return None,
+    };
+
+    Some(InstrumentSpec {
+        symbol: symbol.to_string(),
+        venue: venue.to_string(),
+        contract_multiplier,
+        atr_stop_multiple: None,
+        carry,
+        risk_budget: None,
+    })
+}
+
 
 pub fn demo_macro_futures_sleeve() {
     use chrono::Duration;
@@ -1213,7 +4733,13 @@ pub fn demo_macro_futures_sleeve() {
                 highest_close_50d: price * 1.01,
                 lowest_close_50d: price * 0.97,
 
+                open_interest: 10_000.0,
+                vwap: price * 0.998,
+                ma_5d: price * 0.999,
+
                 fx_carry,
+                commodity_carry: None,
+                bond_carry: None,
             };
 
             bars.push(bar);
@@ -1240,6 +4766,7 @@ pub fn demo_macro_futures_sleeve() {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     // 5) Dummy risk-envelope alsof de risk-kernel dit heeft berekend
@@ -1271,6 +4798,10 @@ pub fn demo_macro_futures_sleeve() {
         current_positions,
         eur_per_usd: 0.92,
         engine_health: EngineHealth::Healthy, // default
+        position_entry_dates: HashMap::new(),
+        sleeve_equity_usd: 0.0,
+        position_convictions: HashMap::new(),
+        soft_halt_base_positions: HashMap::new(),
     };
 
 
@@ -1291,8 +4822,18 @@ pub fn demo_macro_futures_sleeve() {
             max_risk_per_position_eur: 80.0,  // conservatiever vanwege grote contract-size
             max_contracts: 3,
         },
+        gc: InstrumentRiskBudget::default_gold(),
+        mcl: InstrumentRiskBudget::default_crude(),
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 120.0, // zelfde cap als MES
+            max_contracts: 5,
+        },
+        zn: InstrumentRiskBudget::default_bond(),
+        vx: InstrumentRiskBudget::default_vix(),
         // Sleeve-breed: max aantal contracts
         max_total_contracts: 4, // bijv. max 4 contracts totaal
+        // Sleeve-breed: max totaal risico in EUR
+        max_total_risk_eur: 320.0,
     };
 
 
@@ -1 +1,2 @@
-pub mod macro_futures_sleeve;
\ No newline at end of file
+pub mod macro_futures_sleeve;
+pub mod backtest;
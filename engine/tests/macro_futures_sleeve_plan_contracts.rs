@@ -6,22 +6,49 @@ use std::rc::Rc;
 use std::cell::RefCell;
 
 
-use chrono::{Utc, Duration, DateTime, TimeZone};
+use chrono::{Utc, Duration, DateTime, TimeZone, Datelike, Weekday};
 
 use engine::strategies::macro_futures_sleeve::{
     MacroFuturesSleeve,
+    FuturesSleeveAggregate,
     MacroFuturesSleeveConfig,
     FuturesSleeveContext,
     FuturesRiskBudget,
     InstrumentRiskBudget,
     FutureInstrument,
     FxCarryFeatures,
+    CommodityCarryFeatures,
+    BondCarryFeatures,
+    CarryType,
+    InstrumentRegistry,
+    InstrumentSpec,
     DailyFeatureBar,
     demo_macro_futures_sleeve,
     MacroScalars,
     SleeveRiskSanity,
     EngineOrderSide,
     EngineOrder,
+    InstrumentSignal,
+    FinalTradeSignal,
+    RawSignal,
+    MacroAdjustedSignal,
+    SignalReason,
+    FuturesPlannedContracts,
+    FuturesPlannedRisk,
+    BuildError,
+    BudgetValidationError,
+    FlatExplanation,
+    FuturesOrderIntent,
+    FuturesOrderIntentPriced,
+    FuturesPlannedRiskEnriched,
+    SignalAccuracyReport,
+    PlanSkipReason,
+    SLEEVE_CONFIG_JSON_SCHEMA_VERSION,
+    MacroFuturesSleeveState,
+    SplitRiskBudget,
+    OrderExpiry,
+    ConfigError,
+    contract_multiplier,
 };
 
 use engine::execution::{
@@ -29,6 +56,8 @@ use engine::execution::{
     InMemoryOrderSink,
     FileOrderSink,
     run_macro_futures_engine_heartbeat,
+    run_macro_futures_engine_heartbeat_checked,
+    HeartbeatError,
     encode_order_log_event_json,
     encode_heartbeat_log_event_json,
     HeartbeatLogSink,
@@ -40,6 +69,22 @@ use engine::execution::{
     EngineHealth,
     HeartbeatSupervisorEvent,
     encode_supervisor_event_json,
+    encode_portfolio_state_json,
+    encode_sleeve_states_json,
+    encode_global_risk_state_json,
+    sleeve_plan_to_csv,
+    sleeve_plan_from_csv,
+    CsvParseError,
+    TwapOrderSink,
+    CompositeOrderSink,
+    ValidatingOrderSink,
+    OrderStatus,
+    RateLimitedOrderSink,
+    SubmitWithTs,
+    PrometheusMetricsAccumulator,
+    decode_heartbeat_log_event_json,
+    HeartbeatLogEvent,
+    MacroFuturesEngineHeartbeatResult,
 };
 
 use engine::risk::{
@@ -92,19 +137,17 @@ fn make_minimal_ctx() -> FuturesSleeveContext {
         as_of,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
-    let risk_envelope = base_risk_envelope();
-
-    FuturesSleeveContext {
-        as_of,
-        histories: HashMap::new(),
-        macro_scalars,
-        risk_envelope,
-        current_positions: HashMap::new(),
-        eur_per_usd: 1.0,
-        engine_health: EngineHealth::Healthy,
-    }
+    FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(base_risk_envelope())
+        .with_eur_per_usd(1.0)
+        .with_engine_health(EngineHealth::Healthy)
+        .build()
+        .expect("valid minimal test context")
 }
 
 fn minimal_risk_budget() -> FuturesRiskBudget {
@@ -121,7 +164,28 @@ fn minimal_risk_budget() -> FuturesRiskBudget {
             max_risk_per_position_eur: 1_000_000.0,
             max_contracts: 100,
         },
+        gc: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        mcl: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        zn: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        vx: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
         max_total_contracts: 300,
+        max_total_risk_eur: 3_000_000.0,
     }
 }
 
@@ -169,7 +233,14 @@ fn make_history_for_test(
             highest_close_50d: price * 1.01,
             lowest_close_50d: price * 0.97,
 
+            open_interest: 10_000.0,
+            // Net onder close → licht bullish t.o.v. VWAP
+            vwap: price * 0.998,
+            ma_5d: price * 0.999,
+
             fx_carry,
+            commodity_carry: None,
+            bond_carry: None,
         };
 
         bars.push(bar);
@@ -193,16 +264,14 @@ fn risk_budget_blocks_position_when_one_contract_exceeds_risk() {
     let sleeve = MacroFuturesSleeve::new(cfg);
     let now = Utc::now();
 
-    // MES met prijs ~100 → contract_notional ≈ 100 * 5 = 500 USD/EUR
+    // MES met prijs ~106 → ATR-stop ≈ 0.25 * 0.53 = 0.133 punt → ~0.61 EUR per contract
     let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-
     let macro_scalars = engine::strategies::macro_futures_sleeve::MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let risk_envelope = SleeveRiskEnvelope {
@@ -222,24 +291,21 @@ fn risk_budget_blocks_position_when_one_contract_exceeds_risk() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
-    let ctx = FuturesSleeveContext {
-        as_of: now,
-        histories,
-        macro_scalars,
-        risk_envelope,
-        current_positions,
-        eur_per_usd: 0.92,
-        engine_health: EngineHealth::Healthy, // default
-    };
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(now)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
 
-    // Eén contract ≈ 500 EUR risk; budget = 50 → max_by_risk = floor(50/500) = 0 → geen positie
+    // Eén contract ≈ 0.61 EUR risk; budget = 0.5 → max_by_risk = floor(0.5/0.61) = 0 → geen positie
     let risk_budget = FuturesRiskBudget {
         mes: InstrumentRiskBudget {
-            max_risk_per_position_eur: 50.0, // lager dan risk van 1 contract
+            max_risk_per_position_eur: 0.5, // lager dan risk van 1 contract
             max_contracts: 10,
         },
         mnq: InstrumentRiskBudget {
@@ -250,7 +316,28 @@ fn risk_budget_blocks_position_when_one_contract_exceeds_risk() {
             max_risk_per_position_eur: 1_000_000.0,
             max_contracts: 10,
         },
+        gc: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10,
+        },
+        mcl: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10,
+        },
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10,
+        },
+        zn: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10,
+        },
+        vx: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10,
+        },
         max_total_contracts: 10,
+        max_total_risk_eur: 2_000_050.0,
     };
 
     let planned = sleeve.plan_contracts(&ctx, &risk_budget);
@@ -270,13 +357,11 @@ fn risk_budget_trims_contracts_to_risk_cap() {
 
     let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-
     let macro_scalars = MacroScalars {
         as_of,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let risk_envelope = SleeveRiskEnvelope {
@@ -296,31 +381,28 @@ fn risk_budget_trims_contracts_to_risk_cap() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
-    let ctx = FuturesSleeveContext {
-        as_of,
-        histories,
-        macro_scalars,
-        risk_envelope,
-        current_positions,
-        eur_per_usd: 0.92,
-        engine_health: EngineHealth::Healthy,
-    };
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
     // MES:
-    // last_price = 100
-    // contract_notional_usd = 100 * 5 = 500
-    // risk_per_contract_eur = 500 * 0.92 = 460
+    // last_price = 100 * (1 + 0.0005 * 129) = 106.45, ATR14 = 0.5% = 0.532
+    // stop-afstand = 0.25 * 0.532 = 0.133 punt
+    // risk_per_contract_eur = 0.133 * 5 * 0.92 ≈ 0.612
     //
-    // max_risk_per_position_eur = 5_000
-    // → max_by_risk = floor(5_000 / 460) = 10
+    // max_risk_per_position_eur = 6.5
+    // → max_by_risk = floor(6.5 / 0.612) = 10
     //
     // max_contracts = 100, max_total_contracts = 100
     // → finale cap = 10 contracts.
     let risk_budget = FuturesRiskBudget {
         mes: InstrumentRiskBudget {
-            max_risk_per_position_eur: 5_000.0,
+            max_risk_per_position_eur: 6.5,
             max_contracts: 100,
         },
         mnq: InstrumentRiskBudget {
@@ -331,7 +413,28 @@ fn risk_budget_trims_contracts_to_risk_cap() {
             max_risk_per_position_eur: 1_000_000.0,
             max_contracts: 100,
         },
+        gc: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        mcl: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        zn: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        vx: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
         max_total_contracts: 100,
+        max_total_risk_eur: 2_005_000.0,
     };
 
     let planned = sleeve.plan_contracts(&ctx, &risk_budget);
@@ -361,13 +464,11 @@ fn fx_factor_changes_allowed_contracts_in_eur_terms() {
     // MES historie zoals in de andere tests
     let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-
     let macro_scalars = MacroScalars {
         as_of,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     // Groot genoeg base-position zodat risk-cap (niet max_contracts) bindt
@@ -388,18 +489,15 @@ fn fx_factor_changes_allowed_contracts_in_eur_terms() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
     // Basiscontext met MES history + envelope
-    let ctx_base = FuturesSleeveContext {
-        as_of,
-        histories,
-        macro_scalars,
-        risk_envelope,
-        current_positions,
-        eur_per_usd: 1.0, // default, we variëren dit zo
-        engine_health: EngineHealth::Healthy,
-    };
+    let ctx_base = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(1.0) // default, we variëren dit zo
+        .build()
+        .expect("valid test context");
 
     // Case A: eur_per_usd = 1.0  → hogere EUR-risk per contract
     let mut ctx_eur_1 = ctx_base.clone();
@@ -410,9 +508,10 @@ fn fx_factor_changes_allowed_contracts_in_eur_terms() {
     ctx_eur_0_5.eur_per_usd = 0.5;
 
     // Risk-budget: zo gekozen dat risk-cap de beperkende factor is
+    // (~0.67 EUR per contract bij 1.0, ~0.33 EUR bij 0.5)
     let risk_budget = FuturesRiskBudget {
         mes: InstrumentRiskBudget {
-            max_risk_per_position_eur: 5_000.0,
+            max_risk_per_position_eur: 20.0,
             max_contracts: 100,
         },
         mnq: InstrumentRiskBudget {
@@ -423,7 +522,28 @@ fn fx_factor_changes_allowed_contracts_in_eur_terms() {
             max_risk_per_position_eur: 1_000_000.0,
             max_contracts: 100,
         },
+        gc: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        mcl: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        zn: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        vx: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
         max_total_contracts: 100,
+        max_total_risk_eur: 2_005_000.0,
     };
 
     let planned_eur_1 = sleeve.plan_contracts(&ctx_eur_1, &risk_budget);
@@ -460,13 +580,11 @@ fn risk_report_matches_contracts_and_notional() {
     // MES met base_price ~100 → laatste prijs iets >100
     let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let risk_envelope = SleeveRiskEnvelope {
@@ -487,19 +605,16 @@ fn risk_report_matches_contracts_and_notional() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
     let as_of = fixed_as_of();
 
-    let ctx = FuturesSleeveContext {
-        as_of,
-        histories,
-        macro_scalars,
-        risk_envelope,
-        current_positions,
-        eur_per_usd: 0.92,
-        engine_health: EngineHealth::Healthy, // default
-    };
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     // Risk-budget: zorg dat er meerdere contracts kunnen komen,
@@ -517,7 +632,28 @@ fn risk_report_matches_contracts_and_notional() {
             max_risk_per_position_eur: 1_000_000.0,
             max_contracts: 100,
         },
+        gc: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        mcl: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        zn: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
+        vx: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 100,
+        },
         max_total_contracts: 100,
+        max_total_risk_eur: 2_002_000.0,
     };
 
     // Haal contracts én risk-report op
@@ -563,13 +699,11 @@ fn sleeve_exposure_and_margin_headroom_cap_notional_in_usd() {
     // MES historie zoals in de andere tests
     let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     // Hier forceren we dat env-headroom (exposure/margin) de beperkende factor is.
@@ -595,19 +729,16 @@ fn sleeve_exposure_and_margin_headroom_cap_notional_in_usd() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
     let as_of = fixed_as_of();
 
-    let ctx = FuturesSleeveContext {
-        as_of,
-        histories,
-        macro_scalars,
-        risk_envelope,
-        current_positions,
-        eur_per_usd: 0.92,
-        engine_health: EngineHealth::Healthy, // default
-    };
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     // Risk-budget zo zetten dat risico NIET de beperkende factor is (alleen env-headroom).
@@ -624,20 +755,45 @@ fn sleeve_exposure_and_margin_headroom_cap_notional_in_usd() {
             max_risk_per_position_eur: 1_000_000.0,
             max_contracts: 10_000,
         },
+        gc: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        mcl: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        zn: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        vx: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
         max_total_contracts: 10_000,
+        max_total_risk_eur: 3_000_000.0,
     };
 
-    // Gebruik de risk-report om de feitelijke USD-notional te reconstrueren:
-    // total_risk_eur = |contracts| * contract_notional_usd * eur_per_usd
-    // → |contracts| * contract_notional_usd = total_risk_eur / eur_per_usd = absolute USD-exposure
-    let risk_report = sleeve.plan_risk_report(&ctx, &risk_budget);
-
-    let mes_risk = risk_report
-        .iter()
-        .find(|r| r.instrument == FutureInstrument::Mes)
-        .expect("Expected MES in risk_report");
+    // De USD-notional komt uit plan_positions; de contracts daarna uit de risk-report
+    let mes_position = sleeve
+        .plan_positions(&ctx, &risk_budget)
+        .into_iter()
+        .find(|p| p.instrument == FutureInstrument::Mes)
+        .expect("Expected MES in plan_positions");
+    assert!(
+        sleeve
+            .plan_risk_report(&ctx, &risk_budget)
+            .iter()
+            .any(|r| r.instrument == FutureInstrument::Mes),
+        "Expected MES in risk_report"
+    );
 
-    let abs_notional_usd = mes_risk.total_risk_eur / ctx.eur_per_usd;
+    let abs_notional_usd = mes_position.target_notional_usd.abs();
 
     // We verwachten:
     // 1) Dat er überhaupt exposure is (dus > 1 USD, door onze filter in plan_positions)
@@ -666,14 +822,11 @@ fn concurrency_limit_blocks_opening_new_instrument() {
     let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, now);
     let mnq_hist = make_history_for_test(FutureInstrument::Mnq, 16000.0, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-    histories.insert(FutureInstrument::Mnq, mnq_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     // Concurrency-cap = 1 slot.
@@ -695,21 +848,19 @@ fn concurrency_limit_blocks_opening_new_instrument() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let mut current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-    current_positions.insert(FutureInstrument::Mes, 1); // MES al open
-
     let as_of = fixed_as_of();
     // MNQ blijft impliciet flat (0)
 
-    let ctx = FuturesSleeveContext {
-        as_of,
-        histories,
-        macro_scalars,
-        risk_envelope,
-        current_positions,
-        eur_per_usd: 0.92,
-        engine_health: EngineHealth::Healthy, // default
-    };
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_history(FutureInstrument::Mnq, mnq_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_current_position(FutureInstrument::Mes, 1) // MES al open
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     // Risk-budget ruim zetten zodat alleen concurrency/headroom bindt
@@ -726,7 +877,28 @@ fn concurrency_limit_blocks_opening_new_instrument() {
             max_risk_per_position_eur: 1_000_000.0,
             max_contracts: 10_000,
         },
+        gc: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        mcl: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        zn: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        vx: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
         max_total_contracts: 10_000,
+        max_total_risk_eur: 3_000_000.0,
     };
 
     let planned = sleeve.plan_contracts(&ctx, &risk_budget);
@@ -759,14 +931,11 @@ fn halt_or_kill_flattens_existing_positions_and_opens_nothing_new() {
     let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, now);
     let mnq_hist = make_history_for_test(FutureInstrument::Mnq, 16_000.0, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-    histories.insert(FutureInstrument::Mnq, mnq_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     // Sleeve staat in HALT/KILL → we verwachten:
@@ -790,21 +959,20 @@ fn halt_or_kill_flattens_existing_positions_and_opens_nothing_new() {
     };
 
     // MES heeft al een positie, MNQ niet.
-    let mut current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-    current_positions.insert(FutureInstrument::Mes, 3);
-    current_positions.insert(FutureInstrument::Mnq, 0);
 
     let as_of = fixed_as_of();
 
-    let ctx = FuturesSleeveContext {
-        as_of,
-        histories,
-        macro_scalars,
-        risk_envelope,
-        current_positions,
-        eur_per_usd: 0.92,
-        engine_health: EngineHealth::Healthy, // default
-    };
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_history(FutureInstrument::Mnq, mnq_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_current_position(FutureInstrument::Mes, 3)
+        .with_current_position(FutureInstrument::Mnq, 0)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     // Risk-budget ruim → halt moet bepalend zijn, niet risk-budget.
@@ -821,7 +989,28 @@ fn halt_or_kill_flattens_existing_positions_and_opens_nothing_new() {
             max_risk_per_position_eur: 1_000_000.0,
             max_contracts: 10_000,
         },
+        gc: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        mcl: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        zn: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
+        vx: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000_000.0,
+            max_contracts: 10_000,
+        },
         max_total_contracts: 10_000,
+        max_total_risk_eur: 3_000_000.0,
     };
 
     let order_intents = sleeve.plan_order_intents(&ctx, &risk_budget);
@@ -864,14 +1053,11 @@ fn aggregate_sleeve_risk_computes_correct_totals() {
     let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, now);
     let mnq_hist = make_history_for_test(FutureInstrument::Mnq, 16000.0, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-    histories.insert(FutureInstrument::Mnq, mnq_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let risk_envelope = SleeveRiskEnvelope {
@@ -891,19 +1077,17 @@ fn aggregate_sleeve_risk_computes_correct_totals() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
     let as_of = fixed_as_of();
 
-    let ctx = FuturesSleeveContext {
-        as_of,
-        histories,
-        macro_scalars,
-        risk_envelope,
-        current_positions,
-        eur_per_usd: 0.92,
-        engine_health: EngineHealth::Healthy, // default
-    };
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_history(FutureInstrument::Mnq, mnq_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     // Risk-budget ruim (risk-cap mag niet binden)
@@ -911,7 +1095,13 @@ fn aggregate_sleeve_risk_computes_correct_totals() {
         mes: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         mnq: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         sixe: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        gc: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        mcl: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        m2k: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        zn: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        vx: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         max_total_contracts: 100,
+        max_total_risk_eur: 3_000_000.0,
     };
 
     let agg = sleeve.aggregate_sleeve_risk(&ctx, &risk_budget);
@@ -949,14 +1139,11 @@ fn check_sleeve_risk_sanity_flags_when_above_cap() {
     let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, now);
     let mnq_hist = make_history_for_test(FutureInstrument::Mnq, 16_000.0, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-    histories.insert(FutureInstrument::Mnq, mnq_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let risk_envelope = SleeveRiskEnvelope {
@@ -976,19 +1163,17 @@ fn check_sleeve_risk_sanity_flags_when_above_cap() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
     let as_of = fixed_as_of();
 
-    let ctx = FuturesSleeveContext {
-        as_of,
-        histories,
-        macro_scalars,
-        risk_envelope,
-        current_positions,
-        eur_per_usd: 0.92,
-        engine_health: EngineHealth::Healthy, // default
-    };
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_history(FutureInstrument::Mnq, mnq_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     // Ruime risk-budget → aggregate risk > 0
@@ -996,7 +1181,13 @@ fn check_sleeve_risk_sanity_flags_when_above_cap() {
         mes: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         mnq: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         sixe: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        gc: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        mcl: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        m2k: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        zn: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        vx: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         max_total_contracts: 100,
+        max_total_risk_eur: 3_000_000.0,
     };
 
     let agg = sleeve.aggregate_sleeve_risk(&ctx, &risk_budget);
@@ -1040,14 +1231,11 @@ fn plan_sleeve_consistent_with_existing_apis_and_flags_sanity() {
     let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, now);
     let mnq_hist = make_history_for_test(FutureInstrument::Mnq, 16_000.0, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-    histories.insert(FutureInstrument::Mnq, mnq_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let risk_envelope = SleeveRiskEnvelope {
@@ -1067,19 +1255,17 @@ fn plan_sleeve_consistent_with_existing_apis_and_flags_sanity() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
     let as_of = fixed_as_of();
 
-    let ctx = FuturesSleeveContext {
-    as_of,
-    histories,
-    macro_scalars,
-    risk_envelope,
-    current_positions,
-    eur_per_usd: 0.92,
-    engine_health: EngineHealth::Healthy, // default
-};
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_history(FutureInstrument::Mnq, mnq_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     // Ruim risk-budget zodat er posities worden gepland
@@ -1087,7 +1273,13 @@ fn plan_sleeve_consistent_with_existing_apis_and_flags_sanity() {
         mes: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         mnq: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         sixe: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        gc: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        mcl: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        m2k: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        zn: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        vx: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         max_total_contracts: 100,
+        max_total_risk_eur: 3_000_000.0,
     };
 
     // Referentie: losse API-calls
@@ -1147,6 +1339,8 @@ fn global_risk_kernel_and_macro_futures_sleeve_integrate_consistently() {
         max_leverage: 1.5,
         rebalance_drift_frac: 0.15,
         max_global_positions: 10,
+        max_daily_loss_usd: 500.0,
+        recovery_buffer_frac: 0.02,
     };
 
     // Eén sleeve-config voor MicroFuturesMacroTrend
@@ -1155,13 +1349,17 @@ fn global_risk_kernel_and_macro_futures_sleeve_integrate_consistently() {
         capital_alloc_usd: 2_000.0,
         max_single_pos_risk_frac: 0.01, // 1% van 2k = 20 USD base size
         halt_dd_frac: -0.10,
+        soft_halt_dd_frac: -0.10,
         kill_dd_frac: -0.15,
         max_concurrent_positions: 3,
+        max_position_size_usd_absolute: None,
     };
 
     let gcfg = GlobalRiskKernelConfig {
         portfolio: portfolio_cfg,
         sleeves: vec![sleeve_cfg],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
     };
 
     let mut kernel = GlobalRiskKernel::new(gcfg);
@@ -1234,33 +1432,29 @@ fn global_risk_kernel_and_macro_futures_sleeve_integrate_consistently() {
     let mnq_hist = make_history_for_test(FutureInstrument::Mnq, 16_000.0, now);
     let sixe_hist = make_history_for_test(FutureInstrument::SixE, 1.10, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-    histories.insert(FutureInstrument::Mnq, mnq_hist);
-    histories.insert(FutureInstrument::SixE, sixe_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     // Geen open posities in deze integratietest
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
 
     let as_of = fixed_as_of();
     let risk_envelope = base_risk_envelope();
 
 
-    let ctx = FuturesSleeveContext {
-        as_of,
-        histories,
-        macro_scalars,
-        risk_envelope,
-        current_positions,
-        eur_per_usd: 0.92,
-        engine_health: EngineHealth::Healthy, // default
-    };
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_history(FutureInstrument::Mnq, mnq_hist)
+        .with_history(FutureInstrument::SixE, sixe_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     // Risk-budget redelijk ruim; globale risk-kernel headroom moet bindend zijn.
@@ -1277,17 +1471,38 @@ fn global_risk_kernel_and_macro_futures_sleeve_integrate_consistently() {
             max_risk_per_position_eur: 1_000.0,
             max_contracts: 10,
         },
-        max_total_contracts: 10,
-    };
-
-    // Sleeve-plan met een ruime EUR-risk-cap (2x capital alloc)
-    let max_sleeve_risk_eur = sleeve_cfg.capital_alloc_usd * 2.0;
-    let plan = sleeve.plan_sleeve(&ctx, &risk_budget, max_sleeve_risk_eur);
-
-    // Sanity: cap mag niet getriggerd worden in dit scenario.
-    assert_eq!(
-        plan.sanity,
-        SleeveRiskSanity::Ok,
+        gc: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000.0,
+            max_contracts: 10,
+        },
+        mcl: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000.0,
+            max_contracts: 10,
+        },
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000.0,
+            max_contracts: 10,
+        },
+        zn: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000.0,
+            max_contracts: 10,
+        },
+        vx: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000.0,
+            max_contracts: 10,
+        },
+        max_total_contracts: 10,
+        max_total_risk_eur: 3_000.0,
+    };
+
+    // Sleeve-plan met een ruime EUR-risk-cap (2x capital alloc)
+    let max_sleeve_risk_eur = sleeve_cfg.capital_alloc_usd * 2.0;
+    let plan = sleeve.plan_sleeve(&ctx, &risk_budget, max_sleeve_risk_eur);
+
+    // Sanity: cap mag niet getriggerd worden in dit scenario.
+    assert_eq!(
+        plan.sanity,
+        SleeveRiskSanity::Ok,
         "Expected sanity Ok when max_sleeve_risk_eur >> actual total risk"
     );
 
@@ -1319,15 +1534,11 @@ fn run_heartbeat_matches_plan_and_order_intents() {
     let mnq_hist = make_history_for_test(FutureInstrument::Mnq, 16_000.0, now);
     let sixe_hist = make_history_for_test(FutureInstrument::SixE, 1.10, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-    histories.insert(FutureInstrument::Mnq, mnq_hist);
-    histories.insert(FutureInstrument::SixE, sixe_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let risk_envelope = SleeveRiskEnvelope {
@@ -1347,26 +1558,31 @@ fn run_heartbeat_matches_plan_and_order_intents() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
     let as_of = fixed_as_of();
 
-    let ctx = FuturesSleeveContext {
-    as_of,
-    histories,
-    macro_scalars,
-    risk_envelope,
-    current_positions,
-    eur_per_usd: 0.92,
-    engine_health: EngineHealth::Healthy, // default
-};
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_history(FutureInstrument::Mnq, mnq_hist)
+        .with_history(FutureInstrument::SixE, sixe_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     let risk_budget = FuturesRiskBudget {
         mes: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         mnq: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         sixe: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        gc: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        mcl: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        m2k: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        zn: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        vx: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         max_total_contracts: 100,
+        max_total_risk_eur: 3_000_000.0,
     };
 
     // Referentie: losse API’s
@@ -1434,15 +1650,11 @@ fn map_heartbeat_to_engine_orders_respects_side_quantity_and_metadata() {
     let mnq_hist = make_history_for_test(FutureInstrument::Mnq, 16_000.0, now);
     let sixe_hist = make_history_for_test(FutureInstrument::SixE, 1.10, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-    histories.insert(FutureInstrument::Mnq, mnq_hist);
-    histories.insert(FutureInstrument::SixE, sixe_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let risk_envelope = SleeveRiskEnvelope {
@@ -1462,26 +1674,31 @@ fn map_heartbeat_to_engine_orders_respects_side_quantity_and_metadata() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
     let as_of = fixed_as_of();
 
-    let ctx = FuturesSleeveContext {
-    as_of,
-    histories,
-    macro_scalars,
-    risk_envelope,
-    current_positions,
-    eur_per_usd: 0.92,
-    engine_health: EngineHealth::Healthy, // default
-};
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_history(FutureInstrument::Mnq, mnq_hist)
+        .with_history(FutureInstrument::SixE, sixe_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     let risk_budget = FuturesRiskBudget {
         mes: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         mnq: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         sixe: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        gc: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        mcl: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        m2k: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        zn: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        vx: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         max_total_contracts: 100,
+        max_total_risk_eur: 3_000_000.0,
     };
 
     // Heartbeat draaien als referentie
@@ -1546,6 +1763,27 @@ fn map_heartbeat_to_engine_orders_respects_side_quantity_and_metadata() {
                 assert_eq!(eo.symbol, "6E");
                 assert_eq!(eo.venue, "CME");
             }
+            FutureInstrument::Gc => {
+                assert_eq!(eo.symbol, "MGC");
+                assert_eq!(eo.venue, "CME");
+            }
+            FutureInstrument::Mcl => {
+                assert_eq!(eo.symbol, "MCL");
+                assert_eq!(eo.venue, "CME");
+            }
+            FutureInstrument::M2k => {
+                assert_eq!(eo.symbol, "M2K");
+                assert_eq!(eo.venue, "CME");
+            }
+            FutureInstrument::Zn => {
+                assert_eq!(eo.symbol, "ZN");
+                assert_eq!(eo.venue, "CBOT");
+            }
+            FutureInstrument::Vx => {
+                assert_eq!(eo.symbol, "VX");
+                assert_eq!(eo.venue, "CBOE");
+            }
+            FutureInstrument::Custom(_) => panic!("no custom instruments in this test"),
         }
 
         // Sleeve-id moet door-gemapped zijn
@@ -1568,15 +1806,11 @@ fn in_memory_order_sink_collects_engine_orders() {
     let mnq_hist = make_history_for_test(FutureInstrument::Mnq, 16_000.0, now);
     let sixe_hist = make_history_for_test(FutureInstrument::SixE, 1.10, now);
 
-    let mut histories = HashMap::new();
-    histories.insert(FutureInstrument::Mes, mes_hist);
-    histories.insert(FutureInstrument::Mnq, mnq_hist);
-    histories.insert(FutureInstrument::SixE, sixe_hist);
-
     let macro_scalars = MacroScalars {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let risk_envelope = SleeveRiskEnvelope {
@@ -1596,26 +1830,31 @@ fn in_memory_order_sink_collects_engine_orders() {
         portfolio_risk_state: PortfolioRiskState::Normal,
     };
 
-    let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
-
     let as_of = fixed_as_of();
 
-    let ctx = FuturesSleeveContext {
-    as_of,
-    histories,
-    macro_scalars,
-    risk_envelope,
-    current_positions,
-    eur_per_usd: 0.92,
-    engine_health: EngineHealth::Healthy, // default
-};
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_history(FutureInstrument::Mnq, mnq_hist)
+        .with_history(FutureInstrument::SixE, sixe_hist)
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(risk_envelope)
+        .with_eur_per_usd(0.92)
+        .build()
+        .expect("valid test context");
 
 
     let risk_budget = FuturesRiskBudget {
         mes: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         mnq: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         sixe: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        gc: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        mcl: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        m2k: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        zn: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
+        vx: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 100 },
         max_total_contracts: 100,
+        max_total_risk_eur: 3_000_000.0,
     };
 
     // Heartbeat + mapping naar EngineOrders
@@ -1641,7 +1880,7 @@ fn in_memory_order_sink_collects_engine_orders() {
     );
 
     // 2) Inhoud moet één-op-één overeenkomen (dankzij PartialEq/Eq)
-    for (expected, stored) in engine_orders.iter().zip(sink.orders.iter()) {
+    for (expected, (stored, _)) in engine_orders.iter().zip(sink.orders.iter()) {
         assert_eq!(
             stored, expected,
             "Stored EngineOrder in sink must equal submitted EngineOrder"
@@ -1661,6 +1900,8 @@ fn run_macro_futures_engine_heartbeat_end_to_end() {
         max_leverage: 1.5,
         rebalance_drift_frac: 0.15,
         max_global_positions: 10,
+        max_daily_loss_usd: 500.0,
+        recovery_buffer_frac: 0.02,
     };
 
     let sleeve_cfg = SleeveRiskConfig {
@@ -1668,13 +1909,17 @@ fn run_macro_futures_engine_heartbeat_end_to_end() {
         capital_alloc_usd: 2_000.0,
         max_single_pos_risk_frac: 0.01,
         halt_dd_frac: -0.10,
+        soft_halt_dd_frac: -0.10,
         kill_dd_frac: -0.15,
         max_concurrent_positions: 3,
+        max_position_size_usd_absolute: None,
     };
 
     let gcfg = GlobalRiskKernelConfig {
         portfolio: portfolio_cfg,
         sleeves: vec![sleeve_cfg],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
     };
 
     let mut kernel = GlobalRiskKernel::new(gcfg);
@@ -1727,6 +1972,7 @@ fn run_macro_futures_engine_heartbeat_end_to_end() {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
@@ -1735,7 +1981,13 @@ fn run_macro_futures_engine_heartbeat_end_to_end() {
         mes: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
         mnq: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
         sixe: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        gc: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        mcl: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        m2k: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        zn: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        vx: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
         max_total_contracts: 10,
+        max_total_risk_eur: 3_000.0,
     };
 
     // Sleeve-risk cap ruim boven allocatie
@@ -1758,6 +2010,7 @@ fn run_macro_futures_engine_heartbeat_end_to_end() {
         1.0,            // eur_per_usd
         &risk_budget,
         max_sleeve_risk_eur,
+        None,
         &mut sink,
     );
 
@@ -1803,6 +2056,8 @@ fn encode_order_log_event_json_contains_core_fields() {
         max_leverage: 1.5,
         rebalance_drift_frac: 0.15,
         max_global_positions: 10,
+        max_daily_loss_usd: 500.0,
+        recovery_buffer_frac: 0.02,
     };
 
     let sleeve_cfg = SleeveRiskConfig {
@@ -1810,13 +2065,17 @@ fn encode_order_log_event_json_contains_core_fields() {
         capital_alloc_usd: 2_000.0,
         max_single_pos_risk_frac: 0.01,
         halt_dd_frac: -0.10,
+        soft_halt_dd_frac: -0.10,
         kill_dd_frac: -0.15,
         max_concurrent_positions: 3,
+        max_position_size_usd_absolute: None,
     };
 
     let gcfg = GlobalRiskKernelConfig {
         portfolio: portfolio_cfg,
         sleeves: vec![sleeve_cfg],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
     };
 
     let mut kernel = GlobalRiskKernel::new(gcfg);
@@ -1868,6 +2127,7 @@ fn encode_order_log_event_json_contains_core_fields() {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
@@ -1876,7 +2136,13 @@ fn encode_order_log_event_json_contains_core_fields() {
         mes: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
         mnq: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
         sixe: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        gc: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        mcl: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        m2k: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        zn: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        vx: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
         max_total_contracts: 10,
+        max_total_risk_eur: 3_000.0,
     };
 
     let max_sleeve_risk_eur = 4_000.0;
@@ -1896,6 +2162,7 @@ fn encode_order_log_event_json_contains_core_fields() {
         1.0,
         &risk_budget,
         max_sleeve_risk_eur,
+        None,
         &mut sink,
     );
 
@@ -1941,10 +2208,15 @@ fn file_order_sink_writes_json_lines() {
     let order = EngineOrder {
         sleeve_id: SleeveId::MicroFuturesMacroTrend,
         instrument: FutureInstrument::Mes,
-        symbol: "MES",
-        venue: "CME",
+        symbol: "MES".to_string(),
+        venue: "CME".to_string(),
         side: EngineOrderSide::Buy,
         quantity: 3,
+        metadata: HashMap::new(),
+        limit_price: None,
+        stop_price: None,
+        expiry: OrderExpiry::Day,
+        is_reduce_only: false,
     };
 
     sink.submit(&order);
@@ -1987,6 +2259,8 @@ fn encode_heartbeat_log_event_json_contains_risk_and_orders() {
         max_leverage: 1.5,
         rebalance_drift_frac: 0.15,
         max_global_positions: 10,
+        max_daily_loss_usd: 500.0,
+        recovery_buffer_frac: 0.02,
     };
 
     let sleeve_cfg = SleeveRiskConfig {
@@ -1994,13 +2268,17 @@ fn encode_heartbeat_log_event_json_contains_risk_and_orders() {
         capital_alloc_usd: 2_000.0,
         max_single_pos_risk_frac: 0.01,
         halt_dd_frac: -0.10,
+        soft_halt_dd_frac: -0.10,
         kill_dd_frac: -0.15,
         max_concurrent_positions: 3,
+        max_position_size_usd_absolute: None,
     };
 
     let gcfg = GlobalRiskKernelConfig {
         portfolio: portfolio_cfg,
         sleeves: vec![sleeve_cfg],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
     };
 
     let mut kernel = GlobalRiskKernel::new(gcfg);
@@ -2053,6 +2331,7 @@ fn encode_heartbeat_log_event_json_contains_risk_and_orders() {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
@@ -2061,7 +2340,13 @@ fn encode_heartbeat_log_event_json_contains_risk_and_orders() {
         mes: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
         mnq: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
         sixe: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        gc: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        mcl: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        m2k: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        zn: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
+        vx: InstrumentRiskBudget { max_risk_per_position_eur: 1_000.0, max_contracts: 10 },
         max_total_contracts: 10,
+        max_total_risk_eur: 3_000.0,
     };
 
     let max_sleeve_risk_eur = 4_000.0;
@@ -2082,11 +2367,12 @@ fn encode_heartbeat_log_event_json_contains_risk_and_orders() {
         1.0,
         &risk_budget,
         max_sleeve_risk_eur,
+        None,
         &mut sink,
     );
 
     let now_ts: i64 = 1_700_000_000;
-    let json = encode_heartbeat_log_event_json(now_ts, &result, EngineHealth::Healthy);
+    let json = encode_heartbeat_log_event_json(now_ts, &result, EngineHealth::Healthy, &margin_state, None);
     // 1) JSON moet baseline velden bevatten
     assert!(
         json.contains("\"sleeve_id\""),
@@ -2113,6 +2399,16 @@ fn encode_heartbeat_log_event_json_contains_risk_and_orders() {
         "expected engine_health field in heartbeat json, got: {}",
         json
     );
+    assert!(
+        json.contains("\"net_direction\""),
+        "expected net_direction field in heartbeat json, got: {}",
+        json
+    );
+    assert!(
+        json.contains("\"margin_utilization_pct\":0.0"),
+        "expected margin_utilization_pct field in heartbeat json, got: {}",
+        json
+    );
     assert!(
         json.contains("\"Healthy\""),
         "expected engine_health=Healthy in heartbeat json, got: {}",
@@ -2131,6 +2427,27 @@ fn encode_heartbeat_log_event_json_contains_risk_and_orders() {
     }
 }
 
+#[test]
+fn encode_heartbeat_log_event_json_contains_instrument_signals() {
+    let now = fixed_as_of();
+    let mut kernel = heartbeat_kernel_for_test();
+    let (result, margin) = run_heartbeat_for_test(&mut kernel, now, 100.0, HashMap::new());
+    let json = encode_heartbeat_log_event_json(now.timestamp(), &result, EngineHealth::Healthy, &margin, None);
+
+    // Per-instrument signals: één entry per history (MES/MNQ/6E), gesorteerd op instrument
+    let parsed: serde_json::Value = serde_json::from_str(&json).expect("valid json");
+    let signals = parsed["instrument_signals"]
+        .as_array()
+        .expect("instrument_signals array in heartbeat json");
+    assert_eq!(signals.len(), 3);
+    for sig in signals {
+        for key in ["instrument", "direction", "conviction", "effective_score", "reason"] {
+            assert!(sig.get(key).is_some(), "missing {} in {}", key, sig);
+        }
+    }
+    assert_eq!(signals[0]["instrument"], "Mes");
+}
+
 #[test]
 fn stdout_heartbeat_logger_writes_exact_line_plus_newline() {
     // Arrange: in-memory buffer als fake-stdout
@@ -2176,6 +2493,8 @@ fn run_macro_futures_engine_heartbeat_with_logging_emits_single_json_line() {
         max_leverage: 1.5,
         rebalance_drift_frac: 0.15,
         max_global_positions: 10,
+        max_daily_loss_usd: 500.0,
+        recovery_buffer_frac: 0.02,
     };
 
     let sleeve_cfg = SleeveRiskConfig {
@@ -2183,13 +2502,17 @@ fn run_macro_futures_engine_heartbeat_with_logging_emits_single_json_line() {
         capital_alloc_usd: 2_000.0,
         max_single_pos_risk_frac: 0.01, // 1% van 2k = 20 USD base size
         halt_dd_frac: -0.10,
+        soft_halt_dd_frac: -0.10,
         kill_dd_frac: -0.15,
         max_concurrent_positions: 3,
+        max_position_size_usd_absolute: None,
     };
 
     let gcfg = GlobalRiskKernelConfig {
         portfolio: portfolio_cfg,
         sleeves: vec![sleeve_cfg],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
     };
 
     let mut kernel = GlobalRiskKernel::new(gcfg);
@@ -2242,6 +2565,7 @@ fn run_macro_futures_engine_heartbeat_with_logging_emits_single_json_line() {
         as_of: now,
         risk_on_scalar: 1.0,
         usd_scalar: 1.0,
+        vix_term_slope: 0.0,
     };
 
     let current_positions: HashMap<FutureInstrument, i32> = HashMap::new();
@@ -2259,7 +2583,28 @@ fn run_macro_futures_engine_heartbeat_with_logging_emits_single_json_line() {
             max_risk_per_position_eur: 1_000.0,
             max_contracts: 10,
         },
+        gc: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000.0,
+            max_contracts: 10,
+        },
+        mcl: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000.0,
+            max_contracts: 10,
+        },
+        m2k: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000.0,
+            max_contracts: 10,
+        },
+        zn: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000.0,
+            max_contracts: 10,
+        },
+        vx: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1_000.0,
+            max_contracts: 10,
+        },
         max_total_contracts: 10,
+        max_total_risk_eur: 3_000.0,
     };
 
     // Sleeve-risk cap ruim boven allocatie
@@ -2272,9 +2617,10 @@ fn run_macro_futures_engine_heartbeat_with_logging_emits_single_json_line() {
     let mut logger = StdoutHeartbeatLogger::with_writer(cursor);
 
     // ✅ Supervisor toevoegen
-    let mut supervisor = HeartbeatSupervisor::new(65);
+    let mut supervisor = HeartbeatSupervisor::new(65, 5);
 
     // === 3) Heartbeat + logging wrapper ===
+    let mut previous_signals = None;
     let result = run_macro_futures_engine_heartbeat_with_logging(
         now.timestamp(),
         &mut supervisor,          // <--- nieuw
@@ -2292,8 +2638,13 @@ fn run_macro_futures_engine_heartbeat_with_logging_emits_single_json_line() {
         max_sleeve_risk_eur,
         &mut sink,
         &mut logger,
+        &mut None,
+        &mut previous_signals,
     );
 
+    // Signals van deze heartbeat staan klaar voor regime-detectie in de volgende
+    assert_eq!(previous_signals.as_ref().map(Vec::len), Some(result.heartbeat.signals.len()));
+
 
     // === 4) Inhoudelijke sanity, zelfde lijn als end-to-end test ===
 
@@ -2432,14 +2783,14 @@ fn file_heartbeat_logger_rotates_and_writes_jsonl() {
 
     // 1) Temp dir
     let mut dir = std::env::temp_dir();
-    let unique = format!("engine_test_{}", chrono::Utc::now().timestamp_nanos());
+    let unique = format!("engine_test_{}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default());
     dir.push(unique);
     fs::create_dir_all(&dir).expect("cannot create test dir");
 
     let mut logger = FileHeartbeatLogger::new(&dir);
 
     // 2) Log op dag 1
-    let d1 = chrono::Utc.ymd(2025, 11, 17).and_hms(10, 0, 0);
+    let d1 = chrono::Utc.with_ymd_and_hms(2025, 11, 17, 10, 0, 0).unwrap();
     logger.log_with_datetime(d1, "{\"ts\":1,\"msg\":\"A\"}");
     logger.log_with_datetime(d1, "{\"ts\":2,\"msg\":\"B\"}");
     logger.flush();
@@ -2457,7 +2808,7 @@ fn file_heartbeat_logger_rotates_and_writes_jsonl() {
     assert_eq!(lines1[1], "{\"ts\":2,\"msg\":\"B\"}");
 
     // 3) Log op dag 2 → moet nieuwe file aanmaken
-    let d2 = chrono::Utc.ymd(2025, 11, 18).and_hms(10, 0, 0);
+    let d2 = chrono::Utc.with_ymd_and_hms(2025, 11, 18, 10, 0, 0).unwrap();
     logger.log_with_datetime(d2, "{\"ts\":3,\"msg\":\"C\"}");
     logger.flush();
 
@@ -2476,7 +2827,7 @@ fn file_heartbeat_logger_rotates_and_writes_jsonl() {
 
 #[test]
 fn supervisor_stays_healthy_when_no_gap() {
-    let mut sup = HeartbeatSupervisor::new(60);
+    let mut sup = HeartbeatSupervisor::new(60, 5);
     sup.register_tick(1000);
     sup.register_tick(1050);
     assert_eq!(sup.health(), EngineHealth::Healthy);
@@ -2484,7 +2835,7 @@ fn supervisor_stays_healthy_when_no_gap() {
 
 #[test]
 fn supervisor_flags_degraded_on_large_gap() {
-    let mut sup = HeartbeatSupervisor::new(60);
+    let mut sup = HeartbeatSupervisor::new(60, 5);
     sup.register_tick(1000);
     sup.register_tick(2000); // 1000 sec gap
     assert_eq!(sup.health(), EngineHealth::Degraded);
@@ -2492,7 +2843,7 @@ fn supervisor_flags_degraded_on_large_gap() {
 
 #[test]
 fn supervisor_recovers_to_healthy_when_gap_normalizes() {
-    let mut sup = HeartbeatSupervisor::new(60);
+    let mut sup = HeartbeatSupervisor::new(60, 5);
     sup.register_tick(1000);
     sup.register_tick(2000); // degraded
     assert_eq!(sup.health(), EngineHealth::Degraded);
@@ -2507,6 +2858,7 @@ fn encode_supervisor_event_json_basic() {
         ts_utc: 1234,
         status: EngineHealth::Degraded,
         msg: "heartbeat_gap_detected",
+        consecutive_degraded: 1,
     };
 
     let s = encode_supervisor_event_json(&ev);
@@ -2514,11 +2866,12 @@ fn encode_supervisor_event_json_basic() {
     assert!(s.contains("\"ts_utc\":1234"));
     assert!(s.contains("\"status\":\"Degraded\""));
     assert!(s.contains("\"heartbeat_gap_detected\""));
+    assert!(s.contains("\"consecutive_degraded\":1"));
 }
 
 #[test]
 fn heartbeat_supervisor_stays_healthy_on_small_gaps() {
-    let mut sup = HeartbeatSupervisor::new(60); // max 60s gap
+    let mut sup = HeartbeatSupervisor::new(60, 5); // max 60s gap
 
     sup.register_tick(1_000);
     assert_eq!(sup.health(), EngineHealth::Healthy);
@@ -2534,7 +2887,7 @@ fn heartbeat_supervisor_stays_healthy_on_small_gaps() {
 
 #[test]
 fn heartbeat_supervisor_flags_degraded_on_large_gap() {
-    let mut sup = HeartbeatSupervisor::new(60);
+    let mut sup = HeartbeatSupervisor::new(60, 5);
 
     sup.register_tick(1_000);
     assert_eq!(sup.health(), EngineHealth::Healthy);
@@ -2550,6 +2903,7 @@ fn encode_supervisor_event_json_contains_core_fields() {
         ts_utc: 1_234_567,
         status: EngineHealth::Degraded,
         msg: "heartbeat_gap_detected",
+        consecutive_degraded: 1,
     };
 
     let s = encode_supervisor_event_json(&ev);
@@ -2607,3 +2961,3808 @@ fn test_degraded_allows_flatten() {
     assert_eq!(oi.instrument, FutureInstrument::Mes);
     assert_eq!(oi.delta_contracts, -2); // full flatten
 }
+
+#[test]
+fn encode_portfolio_state_json_contains_raw_state() {
+    let portfolio = PortfolioState {
+        cash_usd: 9_500.0,
+        open_pnl_usd: -120.0,
+        accrued_interest_usd: 3.5,
+        peak_equity_usd: 10_000.0,
+        total_notional_exposure: 4_000.0,
+        current_leverage: 0.42,
+    };
+    let margin = MarginState {
+        internal_margin_req_usd: 800.0,
+        broker_margin_req_usd: 900.0,
+        equity_usd: 9_383.5,
+    };
+    let vol = VolatilityRegime {
+        rv10_annualized: 12.0,
+        vix_level: 18.0,
+        vix_term_slope: 0.3,
+        regime_scalar: 1.0,
+    };
+
+    let s = encode_portfolio_state_json(1_700_000_000, &portfolio, &margin, &vol);
+    let v: serde_json::Value = serde_json::from_str(&s).expect("valid json");
+
+    assert_eq!(v["ts_utc"].as_i64(), Some(1_700_000_000));
+    assert_eq!(v["portfolio"]["cash_usd"].as_f64(), Some(9_500.0));
+    assert_eq!(v["margin"]["equity_usd"].as_f64(), Some(9_383.5));
+    assert_eq!(v["vol"]["vix_level"].as_f64(), Some(18.0));
+}
+
+#[test]
+fn encode_sleeve_states_json_contains_every_sleeve() {
+    let states = vec![
+        SleeveState {
+            sleeve_id: SleeveId::MicroFuturesMacroTrend,
+            equity_usd: 2_000.0,
+            realized_pnl_usd: 0.0,
+            unrealized_pnl_usd: 0.0,
+            peak_equity_usd: 2_100.0,
+            open_positions: 2,
+        },
+        SleeveState {
+            sleeve_id: SleeveId::EquityLongShort,
+            equity_usd: 1_500.0,
+            realized_pnl_usd: 10.0,
+            unrealized_pnl_usd: -5.0,
+            peak_equity_usd: 1_500.0,
+            open_positions: 0,
+        },
+    ];
+
+    let s = encode_sleeve_states_json(42, &states);
+    let v: serde_json::Value = serde_json::from_str(&s).expect("valid json");
+
+    let sleeves = v["sleeves"].as_array().expect("sleeves array");
+    assert_eq!(sleeves.len(), 2);
+    assert_eq!(sleeves[0]["sleeve_id"], "MicroFuturesMacroTrend");
+    assert_eq!(sleeves[0]["equity_usd"].as_f64(), Some(2_000.0));
+    assert_eq!(sleeves[1]["equity_usd"].as_f64(), Some(1_500.0));
+}
+
+#[test]
+fn encode_global_risk_state_json_has_finite_numbers() {
+    let mut kernel = GlobalRiskKernel::new(GlobalRiskKernelConfig {
+        portfolio: PortfolioRiskConfig {
+            initial_equity_usd: 10_000.0,
+            halt_dd_frac: -0.08,
+            kill_dd_frac: -0.12,
+            max_leverage: 1.5,
+            rebalance_drift_frac: 0.15,
+            max_global_positions: 10,
+            max_daily_loss_usd: 500.0,
+            recovery_buffer_frac: 0.02,
+        },
+        sleeves: vec![],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
+    });
+    kernel.internal_portfolio_peak_equity = 10_500.0;
+
+    let portfolio = PortfolioState {
+        cash_usd: 9_800.0,
+        open_pnl_usd: 150.0,
+        accrued_interest_usd: 50.0,
+        peak_equity_usd: 10_500.0,
+        total_notional_exposure: 0.0,
+        current_leverage: 0.0,
+    };
+
+    let sleeves = vec![SleeveState {
+        sleeve_id: SleeveId::MicroFuturesMacroTrend,
+        equity_usd: 1_800.0,
+        realized_pnl_usd: 0.0,
+        unrealized_pnl_usd: 0.0,
+        peak_equity_usd: 2_000.0,
+        open_positions: 2,
+    }];
+
+    let s = encode_global_risk_state_json(7, &kernel, &portfolio, &sleeves);
+    let v: serde_json::Value = serde_json::from_str(&s).expect("valid json");
+
+    for key in [
+        "internal_portfolio_peak_equity",
+        "initial_equity_usd",
+        "current_equity",
+        "portfolio_drawdown_pct",
+    ] {
+        let x = v[key].as_f64().unwrap_or_else(|| panic!("missing {}", key));
+        assert!(x.is_finite(), "{} not finite", key);
+    }
+    assert_eq!(v["current_equity"].as_f64(), Some(10_000.0));
+    assert!((v["portfolio_drawdown_pct"].as_f64().unwrap() + 100.0 / 21.0).abs() < 1e-9);
+
+    let sleeve = &v["sleeves"][0];
+    assert_eq!(sleeve["sleeve_id"], "MicroFuturesMacroTrend");
+    for key in ["equity_usd", "peak_equity_usd", "drawdown_pct", "open_positions"] {
+        let x = sleeve[key].as_f64().unwrap_or_else(|| panic!("missing sleeve {}", key));
+        assert!(x.is_finite(), "sleeve {} not finite", key);
+    }
+    assert!((sleeve["drawdown_pct"].as_f64().unwrap() + 10.0).abs() < 1e-9);
+}
+
+fn make_signal(inst: FutureInstrument, direction: i8, effective_score: f64) -> InstrumentSignal {
+    InstrumentSignal {
+        instrument: inst,
+        final_signal: FinalTradeSignal {
+            direction,
+            conviction: if direction == 0 { 0.0 } else { 0.8 },
+            effective_score,
+        },
+        raw: RawSignal {
+            trend_score: effective_score,
+            carry_score: 0.0,
+        },
+        macro_adj: MacroAdjustedSignal {
+            trend_macro_adjusted: effective_score,
+            carry_macro_adjusted: 0.0,
+        },
+        reason: if direction == 0 {
+            SignalReason::BelowThreshold
+        } else {
+            SignalReason::Normal
+        },
+    }
+}
+
+#[test]
+fn signals_consistent_with_plan_flags_opposite_direction() {
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let signals = vec![make_signal(FutureInstrument::Mes, 1, 2.5)];
+    let plan = vec![FuturesPlannedContracts {
+        instrument: FutureInstrument::Mes,
+        target_contracts: -2,
+    }];
+
+    let reports = sleeve.signals_consistent_with_plan(&signals, &plan);
+
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].instrument, FutureInstrument::Mes);
+    assert_eq!(reports[0].signal_direction, 1);
+    assert_eq!(reports[0].planned_sign, -1);
+}
+
+#[test]
+fn signals_consistent_with_plan_accepts_flat_signal_with_existing_position() {
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    // MES-signal is flat, maar er staat nog een bestaande long → geen inconsistentie
+    let signals = vec![
+        make_signal(FutureInstrument::Mes, 0, 0.4),
+        make_signal(FutureInstrument::Mnq, 1, 2.0),
+    ];
+    let plan = vec![
+        FuturesPlannedContracts {
+            instrument: FutureInstrument::Mes,
+            target_contracts: 2,
+        },
+        FuturesPlannedContracts {
+            instrument: FutureInstrument::Mnq,
+            target_contracts: 1,
+        },
+    ];
+
+    let reports = sleeve.signals_consistent_with_plan(&signals, &plan);
+    assert!(reports.is_empty(), "unexpected reports: {:?}", reports);
+}
+
+#[test]
+fn signal_diversity_score_is_low_for_identical_signals() {
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let signals = vec![
+        make_signal(FutureInstrument::Mes, 1, 3.0),
+        make_signal(FutureInstrument::Mnq, 1, 3.0),
+        make_signal(FutureInstrument::SixE, 1, 3.0),
+    ];
+
+    let score = sleeve.compute_signal_diversity_score(&signals);
+    assert!(score.abs() < 1e-9, "expected ~0.0 for identical signals, got {}", score);
+}
+
+#[test]
+fn signal_diversity_score_is_high_for_opposite_signals() {
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let signals = vec![
+        make_signal(FutureInstrument::Mes, 1, 2.0),
+        make_signal(FutureInstrument::Mnq, -1, -2.0),
+        make_signal(FutureInstrument::SixE, 0, 0.0),
+    ];
+
+    let score = sleeve.compute_signal_diversity_score(&signals);
+    assert!((score - 1.0).abs() < 1e-9, "expected ~1.0 for opposite signals, got {}", score);
+
+    // Twee long, één short: deels gedeelde factor
+    let mixed = vec![
+        make_signal(FutureInstrument::Mes, 1, 2.0),
+        make_signal(FutureInstrument::Mnq, -1, -2.0),
+        make_signal(FutureInstrument::SixE, 1, 2.0),
+    ];
+    let score = sleeve.compute_signal_diversity_score(&mixed);
+    assert!((score - 8.0 / 9.0).abs() < 1e-9, "got {}", score);
+}
+
+#[test]
+fn signal_diversity_score_for_uncorrelated_and_partially_correlated_signals() {
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    // Gemiddelde score 0, geen spiegelbeeld: ongecorreleerd
+    let uncorrelated = vec![
+        make_signal(FutureInstrument::Mes, 1, 3.0),
+        make_signal(FutureInstrument::Mnq, -1, -1.0),
+        make_signal(FutureInstrument::SixE, -1, -2.0),
+    ];
+    let score = sleeve.compute_signal_diversity_score(&uncorrelated);
+    assert!((score - 1.0).abs() < 1e-9, "got {}", score);
+
+    // Eén actief signal, de rest flat: m² / q = 1/3 → diversiteit 2/3
+    let single = vec![
+        make_signal(FutureInstrument::Mes, 1, 3.0),
+        make_signal(FutureInstrument::Mnq, 0, 0.0),
+        make_signal(FutureInstrument::SixE, 0, 0.0),
+    ];
+    let score = sleeve.compute_signal_diversity_score(&single);
+    assert!((score - 2.0 / 3.0).abs() < 1e-9, "got {}", score);
+    assert!(score > 0.0 && score < 1.0);
+}
+
+#[test]
+fn buffered_file_order_sink_flushes_on_drop() {
+    let mut path = env::temp_dir();
+    path.push(format!(
+        "macro_futures_buffered_order_sink_{}.jsonl",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    let _ = fs::remove_file(&path);
+
+    let order = EngineOrder {
+        sleeve_id: SleeveId::MicroFuturesMacroTrend,
+        instrument: FutureInstrument::Mes,
+        symbol: "MES".to_string(),
+        venue: "CME".to_string(),
+        side: EngineOrderSide::Buy,
+        quantity: 1,
+        metadata: HashMap::new(),
+        limit_price: None,
+        stop_price: None,
+        expiry: OrderExpiry::Day,
+        is_reduce_only: false,
+    };
+
+    {
+        let mut sink = FileOrderSink::with_buffer(&path, 64 * 1024);
+        for _ in 0..10 {
+            sink.submit(&order);
+        }
+        // geen flush(): drop van de BufWriter moet alles wegschrijven
+    }
+
+    let contents = fs::read_to_string(&path).expect("buffered sink must create the file");
+    assert_eq!(contents.lines().count(), 10);
+    assert!(contents.lines().all(|l| l.contains("\"symbol\":\"MES\"")));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn futures_sleeve_context_builder_fills_defaults() {
+    let as_of = fixed_as_of();
+    let hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars {
+            as_of,
+            risk_on_scalar: 1.0,
+            usd_scalar: 1.0,
+            vix_term_slope: 0.0,
+        })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, hist)
+        .with_current_position(FutureInstrument::Mes, 2)
+        .build()
+        .expect("builder with required fields must succeed");
+
+    assert_eq!(ctx.as_of, as_of);
+    assert_eq!(ctx.eur_per_usd, 1.0);
+    assert_eq!(ctx.engine_health, EngineHealth::Healthy);
+    assert!(ctx.histories.contains_key(&FutureInstrument::Mes));
+    assert_eq!(ctx.current_positions.get(&FutureInstrument::Mes), Some(&2));
+}
+
+#[test]
+fn futures_sleeve_context_builder_rejects_non_positive_eur_per_usd() {
+    let as_of = fixed_as_of();
+
+    let err = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_macro_scalars(MacroScalars {
+            as_of,
+            risk_on_scalar: 1.0,
+            usd_scalar: 1.0,
+            vix_term_slope: 0.0,
+        })
+        .with_risk_envelope(base_risk_envelope())
+        .with_eur_per_usd(0.0)
+        .build()
+        .expect_err("eur_per_usd = 0 must be rejected");
+
+    assert_eq!(err, BuildError::InvalidEurPerUsd(0.0));
+}
+
+#[test]
+fn export_signal_trace_contains_all_steps_per_instrument() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_macro_scalars(MacroScalars {
+            as_of,
+            risk_on_scalar: 1.0,
+            usd_scalar: 1.0,
+            vix_term_slope: 0.0,
+        })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .with_history(FutureInstrument::SixE, make_history_for_test(FutureInstrument::SixE, 1.10, as_of))
+        .build()
+        .expect("valid ctx");
+
+    let trace = sleeve.export_signal_trace(&ctx);
+    let obj = trace.as_object().expect("trace must be a JSON object");
+    assert_eq!(obj.len(), 2);
+
+    let numeric_keys = [
+        "z20",
+        "z60",
+        "z120",
+        "breakout",
+        "trend_raw",
+        "carry_raw",
+        "trend_scalar",
+        "carry_scalar",
+        "trend_macro_adjusted",
+        "carry_macro_adjusted",
+        "effective_score",
+        "conviction",
+        "min_effective_score",
+        "min_conviction",
+        "direction",
+    ];
+
+    for inst in ["Mes", "SixE"] {
+        let entry = &obj[inst];
+        assert!(entry["reason"].is_string(), "{}: missing reason", inst);
+        for key in numeric_keys {
+            let v = entry[key]
+                .as_f64()
+                .unwrap_or_else(|| panic!("{}: missing numeric key {}", inst, key));
+            assert!(v.is_finite(), "{}: {} is not finite", inst, key);
+        }
+    }
+
+    // 6E heeft carry → carry_raw moet niet-nul zijn
+    assert!(obj["SixE"]["carry_raw"].as_f64().unwrap() != 0.0);
+}
+
+#[test]
+fn sleeve_plan_csv_round_trip() {
+    let report = vec![
+        FuturesPlannedRisk {
+            instrument: FutureInstrument::Mes,
+            target_contracts: 2,
+            risk_per_contract_eur: 23_412.5,
+            total_risk_eur: 46_825.0,
+        },
+        FuturesPlannedRisk {
+            instrument: FutureInstrument::Mnq,
+            target_contracts: 0,
+            risk_per_contract_eur: 0.0,
+            total_risk_eur: 0.0,
+        },
+        FuturesPlannedRisk {
+            instrument: FutureInstrument::SixE,
+            target_contracts: -1,
+            risk_per_contract_eur: 137_500.1 / 3.0,
+            total_risk_eur: 137_500.1 / 3.0,
+        },
+    ];
+
+    let csv = sleeve_plan_to_csv(&report);
+    assert!(csv.starts_with("instrument,target_contracts,risk_per_contract_eur,total_risk_eur\n"));
+    assert_eq!(csv.lines().count(), 4);
+
+    let parsed = sleeve_plan_from_csv(&csv).expect("round-trip must parse");
+    assert_eq!(parsed, report);
+
+    // Onbekend instrument → nette fout i.p.v. panic
+    let bad = "instrument,target_contracts,risk_per_contract_eur,total_risk_eur\nMgc,1,1.0,1.0\n";
+    assert_eq!(
+        sleeve_plan_from_csv(bad),
+        Err(CsvParseError::UnknownInstrument { line: 2, value: "Mgc".to_string() })
+    );
+}
+
+#[test]
+fn sensitivity_analysis_finds_parameter_that_moves_allocation() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_macro_scalars(MacroScalars {
+            as_of,
+            risk_on_scalar: 1.0,
+            usd_scalar: 1.0,
+            vix_term_slope: 0.0,
+        })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .with_history(FutureInstrument::Mnq, make_history_for_test(FutureInstrument::Mnq, 200.0, as_of))
+        .with_history(FutureInstrument::SixE, make_history_for_test(FutureInstrument::SixE, 1.10, as_of))
+        .build()
+        .expect("valid ctx");
+
+    let report = sleeve.sensitivity_analysis(&ctx, &minimal_risk_budget(), 0.5);
+
+    // 3 trend-windows + 27 config-parameters, elk in beide richtingen
+    assert_eq!(report.per_param.len(), 60);
+    for p in &report.per_param {
+        assert_eq!(p.delta, p.perturbed_contracts - p.base_contracts);
+    }
+
+    assert!(
+        report.per_param.iter().any(|p| p.delta != 0),
+        "expected at least one parameter to change the allocation, got: {:?}",
+        report.per_param
+    );
+
+    // Trend-windows per lookback benoemd
+    for name in ["trend_windows[20d]", "trend_windows[60d]", "trend_windows[120d]"] {
+        assert_eq!(report.per_param.iter().filter(|p| p.param_name == name).count(), 2);
+    }
+
+    // Elk f64-veld van de config zit in het rapport
+    let cfg_json = serde_json::to_value(MacroFuturesSleeveConfig::default()).unwrap();
+    for (name, value) in cfg_json.as_object().unwrap() {
+        if value.is_f64() {
+            assert!(
+                report.per_param.iter().any(|p| p.param_name == *name),
+                "{} missing from sensitivity_analysis",
+                name
+            );
+        }
+    }
+}
+
+#[test]
+fn plan_position_for_instrument_respects_halt() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let macro_scalars = MacroScalars {
+        as_of,
+        risk_on_scalar: 1.0,
+        usd_scalar: 1.0,
+        vix_term_slope: 0.0,
+    };
+
+    let healthy = FuturesSleeveContext::builder()
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .build()
+        .expect("valid ctx");
+
+    let planned = sleeve
+        .plan_position_for_instrument(&healthy, &minimal_risk_budget(), FutureInstrument::Mes)
+        .expect("healthy sleeve with full history must plan a position");
+    assert_eq!(planned.instrument, FutureInstrument::Mes);
+    assert_eq!(planned.target_direction, 1);
+    assert!(planned.target_notional_usd > 0.0);
+    assert!(planned.target_notional_usd <= base_risk_envelope().max_position_size_usd);
+
+    // Instrument zonder history → None
+    assert!(sleeve
+        .plan_position_for_instrument(&healthy, &minimal_risk_budget(), FutureInstrument::Mnq)
+        .is_none());
+
+    let halted = FuturesSleeveContext::builder()
+        .with_macro_scalars(macro_scalars)
+        .with_risk_envelope(SleeveRiskEnvelope {
+            sleeve_halt: HaltState::Halt,
+            ..base_risk_envelope()
+        })
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .build()
+        .expect("valid ctx");
+
+    assert!(sleeve
+        .plan_position_for_instrument(&halted, &minimal_risk_budget(), FutureInstrument::Mes)
+        .is_none());
+}
+
+#[test]
+fn risk_budget_validate_rejects_nonsense_parameters() {
+    assert_eq!(minimal_risk_budget().validate(), Ok(()));
+
+    let zero_contracts = InstrumentRiskBudget {
+        max_risk_per_position_eur: 90.0,
+        max_contracts: 0,
+    };
+    assert_eq!(zero_contracts.validate(), Err(BudgetValidationError::MaxContractsZero));
+
+    let infinite_risk = InstrumentRiskBudget {
+        max_risk_per_position_eur: f64::INFINITY,
+        max_contracts: 3,
+    };
+    assert_eq!(infinite_risk.validate(), Err(BudgetValidationError::MaxRiskInfinite));
+
+    let negative_risk = InstrumentRiskBudget {
+        max_risk_per_position_eur: -1.0,
+        max_contracts: 3,
+    };
+    assert_eq!(negative_risk.validate(), Err(BudgetValidationError::MaxRiskNotPositive));
+
+    let mut budget = minimal_risk_budget();
+    budget.sixe = infinite_risk;
+    assert_eq!(budget.validate(), Err(BudgetValidationError::MaxRiskInfinite));
+
+    let mut budget = minimal_risk_budget();
+    budget.max_total_contracts = 0;
+    assert_eq!(budget.validate(), Err(BudgetValidationError::MaxContractsZero));
+
+    // Ongeldig budget → plan_contracts plant niets
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .build()
+        .expect("valid ctx");
+
+    assert!(!sleeve.plan_contracts(&ctx, &minimal_risk_budget()).is_empty());
+    assert!(sleeve.plan_contracts(&ctx, &budget).is_empty());
+}
+
+#[test]
+fn explain_flat_signal_covers_every_reason() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let budget = minimal_risk_budget();
+
+    let ctx_with = |hist: engine::strategies::macro_futures_sleeve::InstrumentHistory,
+                    env: SleeveRiskEnvelope,
+                    health: EngineHealth,
+                    positions: &[(FutureInstrument, i32)]| {
+        let mut b = FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(env)
+            .with_engine_health(health)
+            .with_history(hist.instrument, hist);
+        for &(inst, qty) in positions {
+            b = b.with_current_position(inst, qty);
+        }
+        b.build().expect("valid ctx")
+    };
+    let mes = || make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    let explain = |ctx: &FuturesSleeveContext| sleeve.explain_flat_signal(ctx, &budget, FutureInstrument::Mes);
+
+    // Healthy baseline: niets blokkeert
+    let ctx = ctx_with(mes(), base_risk_envelope(), EngineHealth::Healthy, &[]);
+    assert_eq!(explain(&ctx), FlatExplanation::NoPlan);
+
+    // Geen history voor het instrument
+    assert_eq!(
+        sleeve.explain_flat_signal(&ctx, &budget, FutureInstrument::Mnq),
+        FlatExplanation::NoHistory { bars_available: 0, min_required: 120 }
+    );
+
+    // Te korte history
+    let mut short = mes();
+    short.bars.truncate(50);
+    let ctx = ctx_with(short, base_risk_envelope(), EngineHealth::Healthy, &[]);
+    assert_eq!(
+        explain(&ctx),
+        FlatExplanation::NoHistory { bars_available: 50, min_required: 120 }
+    );
+
+    // Ongeldige ATR in de laatste bar
+    let mut bad = mes();
+    bad.bars.last_mut().unwrap().atr_14 = f64::NAN;
+    let ctx = ctx_with(bad, base_risk_envelope(), EngineHealth::Healthy, &[]);
+    assert_eq!(explain(&ctx), FlatExplanation::InvalidData { field: "atr_14" });
+
+    // Geen trend → onder de drempel
+    let mut flat = mes();
+    {
+        let last = flat.bars.last_mut().unwrap();
+        last.ret_20d = 0.0;
+        last.ret_60d = 0.0;
+        last.ret_120d = 0.0;
+    }
+    let ctx = ctx_with(flat, base_risk_envelope(), EngineHealth::Healthy, &[]);
+    match explain(&ctx) {
+        FlatExplanation::BelowThreshold { effective_score, min_required } => {
+            assert!(effective_score.abs() < min_required);
+        }
+        other => panic!("expected BelowThreshold, got {:?}", other),
+    }
+
+    // Sleeve-halt vanuit de kernel
+    let halted = SleeveRiskEnvelope { sleeve_halt: HaltState::Kill, ..base_risk_envelope() };
+    let ctx = ctx_with(mes(), halted, EngineHealth::Healthy, &[]);
+    assert_eq!(explain(&ctx), FlatExplanation::Halted { halt_state: HaltState::Kill });
+
+    // Degraded engine
+    let ctx = ctx_with(mes(), base_risk_envelope(), EngineHealth::Degraded, &[]);
+    assert_eq!(explain(&ctx), FlatExplanation::EngineDegraded);
+
+    // Geen exposure-headroom
+    let no_room = SleeveRiskEnvelope { exposure_remaining_usd: 0.0, ..base_risk_envelope() };
+    let ctx = ctx_with(mes(), no_room, EngineHealth::Healthy, &[]);
+    assert_eq!(explain(&ctx), FlatExplanation::NoHeadroom);
+
+    // Eén slot, al bezet door een open 6E-positie
+    let one_slot = SleeveRiskEnvelope { max_concurrent_positions: 1, ..base_risk_envelope() };
+    let ctx = ctx_with(mes(), one_slot, EngineHealth::Healthy, &[(FutureInstrument::SixE, 1)]);
+    assert_eq!(explain(&ctx), FlatExplanation::ConcurrencyCapReached);
+}
+
+#[test]
+fn momentum_lag_uses_earlier_bar_for_signal() {
+    let as_of = fixed_as_of();
+    let no_lag = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let lagged = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        momentum_lag: 1,
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    // Laatste bar draait de trend om; de bar ervoor is nog de normale uptrend
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    {
+        let last = hist.bars.last_mut().unwrap();
+        last.ret_20d = -0.05;
+        last.ret_60d = -0.10;
+        last.ret_120d = -0.20;
+    }
+    let mut without_last = hist.clone();
+    without_last.bars.pop();
+
+    let ctx_for = |h: engine::strategies::macro_futures_sleeve::InstrumentHistory| {
+        FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(base_risk_envelope())
+            .with_history(FutureInstrument::Mes, h)
+            .build()
+            .expect("valid ctx")
+    };
+    let budget = minimal_risk_budget();
+
+    let sig_no_lag = no_lag.evaluate_signals(&ctx_for(hist.clone()), &budget).remove(0);
+    let sig_lagged = lagged.evaluate_signals(&ctx_for(hist), &budget).remove(0);
+    let sig_reference = no_lag.evaluate_signals(&ctx_for(without_last), &budget).remove(0);
+
+    assert_eq!(sig_no_lag.final_signal.direction, -1);
+    assert_eq!(sig_lagged.final_signal.direction, 1);
+    assert_eq!(sig_lagged.final_signal.effective_score, sig_reference.final_signal.effective_score);
+    assert_eq!(sig_lagged.final_signal.conviction, sig_reference.final_signal.conviction);
+
+    // Lag vraagt één extra bar history
+    let mut exact = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    exact.bars.truncate(120);
+    let sig = lagged.evaluate_signals(&ctx_for(exact), &budget).remove(0);
+    assert_eq!(sig.reason, SignalReason::InsufficientHistory);
+}
+
+#[test]
+fn aggregate_net_direction_reflects_long_short_bias() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    // Alle test-histories zijn uptrends → long-only plan
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .with_history(FutureInstrument::SixE, make_history_for_test(FutureInstrument::SixE, 1.10, as_of))
+        .build()
+        .expect("valid ctx");
+
+    let agg = sleeve.aggregate_sleeve_risk(&ctx, &minimal_risk_budget());
+    assert!(agg.total_contracts_abs > 0);
+    assert!(agg.net_direction() > 0.0);
+    assert!(agg.is_net_long());
+    assert!(!agg.is_net_short());
+
+    // Gemengd: 2 long, 3 short
+    let mixed = FuturesSleeveAggregate {
+        total_contracts_signed: -1,
+        total_contracts_abs: 5,
+        total_risk_eur: 0.0,
+        total_notional_usd: 0.0,
+        instrument_count: 2,
+    };
+    let nd = mixed.net_direction();
+    assert!(nd > -1.0 && nd < 1.0);
+    assert!((nd + 0.2).abs() < 1e-12);
+    assert!(mixed.is_net_short());
+
+    // Geen contracts → 0.0, geen NaN
+    let empty = FuturesSleeveAggregate { total_contracts_signed: 0, total_contracts_abs: 0, ..mixed };
+    assert_eq!(empty.net_direction(), 0.0);
+}
+
+#[test]
+fn engine_orders_carry_metadata_into_order_log_json() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .build()
+        .expect("valid ctx");
+
+    let hb = sleeve.run_heartbeat(&ctx, &minimal_risk_budget(), 1_000_000.0);
+    let orders = sleeve.map_heartbeat_to_engine_orders(SleeveId::MicroFuturesMacroTrend, &hb);
+    assert!(!orders.is_empty(), "expected at least one order for a fresh uptrend");
+
+    let signal_ts = as_of.timestamp().to_string();
+    for o in &orders {
+        assert_eq!(o.metadata.get("sleeve_version").map(String::as_str), Some("1.0"));
+        assert_eq!(o.metadata.get("signal_ts"), Some(&signal_ts));
+    }
+
+    let json = encode_order_log_event_json(&orders[0], 1_700_000_000);
+    let v: serde_json::Value = serde_json::from_str(&json).expect("valid order json");
+    assert_eq!(v["metadata"]["sleeve_version"], "1.0");
+    assert_eq!(v["metadata"]["signal_ts"], signal_ts.as_str());
+}
+
+#[test]
+fn position_age_scalars_decay_linearly_to_min() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_current_position(FutureInstrument::Mes, 2)
+        .with_current_position(FutureInstrument::Mnq, -1)
+        .with_current_position(FutureInstrument::SixE, 0)
+        .with_position_entry_date(FutureInstrument::Mes, as_of - Duration::days(10))
+        .with_position_entry_date(FutureInstrument::Mnq, as_of - Duration::days(45))
+        .build()
+        .expect("valid ctx");
+
+    let scalars = sleeve.compute_position_age_scalars(&ctx, 20);
+
+    // 10 van 20 dagen → halverwege 1.0 en 0.5
+    assert!((scalars[&FutureInstrument::Mes] - 0.75).abs() < 1e-12);
+    // Ouder dan max_holding_days → min_age_scalar
+    assert_eq!(scalars[&FutureInstrument::Mnq], 0.5);
+    // Flat → niet in de map
+    assert!(!scalars.contains_key(&FutureInstrument::SixE));
+
+    // Open positie zonder entry-datum telt als nieuw
+    let fresh = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_current_position(FutureInstrument::Mes, 1)
+        .build()
+        .expect("valid ctx");
+    assert_eq!(sleeve.compute_position_age_scalars(&fresh, 20)[&FutureInstrument::Mes], 1.0);
+}
+
+#[test]
+fn volatility_regime_scalar_scales_final_contracts() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let contracts_with = |regime_scalar: f64| {
+        let env = SleeveRiskEnvelope {
+            max_position_size_usd: 20_000.0,
+            volatility_regime_scalar: regime_scalar,
+            ..base_risk_envelope()
+        };
+        let ctx = FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(env)
+            .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+            .build()
+            .expect("valid ctx");
+        sleeve
+            .plan_contracts(&ctx, &minimal_risk_budget())
+            .iter()
+            .map(|p| p.target_contracts)
+            .sum::<i32>()
+    };
+
+    let normal = contracts_with(1.0);
+    let stressed = contracts_with(0.55);
+
+    assert!(normal >= 10, "need a meaningful base size, got {}", normal);
+    assert_eq!(stressed, (normal as f64 * 0.55).floor() as i32);
+    let ratio = stressed as f64 / normal as f64;
+    assert!(ratio > 0.45 && ratio <= 0.55, "ratio = {}", ratio);
+
+    // Opschalen blijft binnen de notional-cap
+    assert!(contracts_with(1.25) >= normal);
+    assert_eq!(contracts_with(0.0), 0);
+}
+
+#[test]
+fn detect_regime_change_reports_direction_flip() {
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let prev = vec![
+        make_signal(FutureInstrument::Mes, 1, 2.0),
+        make_signal(FutureInstrument::SixE, 1, 1.5),
+    ];
+    let curr = vec![
+        make_signal(FutureInstrument::Mes, -1, -2.0),
+        make_signal(FutureInstrument::SixE, 1, 1.6),
+    ];
+
+    let changes = sleeve.detect_regime_change(&prev, &curr);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].instrument, FutureInstrument::Mes);
+    assert_eq!(changes[0].prev_direction, 1);
+    assert_eq!(changes[0].curr_direction, -1);
+
+    // Via de heartbeat: vorige signals long, nu short (omgedraaide trend) → gedetecteerd
+    let as_of = fixed_as_of();
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    {
+        let last = hist.bars.last_mut().unwrap();
+        last.ret_20d = -0.05;
+        last.ret_60d = -0.10;
+        last.ret_120d = -0.20;
+    }
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, hist)
+        .build()
+        .expect("valid ctx");
+
+    let hb = sleeve.run_heartbeat_with_prev(&ctx, &minimal_risk_budget(), 1_000_000.0, Some(&prev));
+    assert!(hb.prev_signals.is_some());
+    assert_eq!(hb.regime_changes.len(), 1);
+    assert_eq!(hb.regime_changes[0].curr_direction, -1);
+
+    // Zonder vorige signals geen changes
+    let hb = sleeve.run_heartbeat(&ctx, &minimal_risk_budget(), 1_000_000.0);
+    assert!(hb.prev_signals.is_none());
+    assert!(hb.regime_changes.is_empty());
+    assert_eq!(hb.signals.len(), 1);
+}
+
+#[test]
+fn gap_fill_bars_inserts_forward_filled_weekdays() {
+    // Ma 2024-01-08 t/m vr 2024-01-19, met di/wo/do 9-11 jan weggehaald
+    let monday = Utc.with_ymd_and_hms(2024, 1, 8, 21, 0, 0).unwrap();
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, monday);
+    hist.bars = (0..12)
+        .map(|d| monday + Duration::days(d))
+        .filter(|ts| !matches!(ts.weekday(), Weekday::Sat | Weekday::Sun))
+        .filter(|ts| !(9..=11).contains(&ts.day()))
+        .zip(hist.bars.iter().copied())
+        .map(|(ts, bar)| DailyFeatureBar { ts, ..bar })
+        .collect();
+    assert_eq!(hist.bars.len(), 7);
+
+    let before_gap = hist.bars[0];
+    hist.gap_fill_bars(5);
+
+    // 3 weekdagen bijgevuld; weekend blijft leeg
+    assert_eq!(hist.bars.len(), 10);
+    for (i, day) in [9u32, 10, 11].into_iter().enumerate() {
+        let bar = hist.bars[1 + i];
+        assert_eq!(bar.ts.day(), day);
+        assert_eq!(bar.close, before_gap.close);
+        assert_eq!(bar.volume, 0.0);
+        assert_eq!((bar.ret_20d, bar.ret_60d, bar.ret_120d), (0.0, 0.0, 0.0));
+    }
+    assert!(hist.bars.windows(2).all(|w| w[0].ts < w[1].ts));
+
+    // Gap groter dan max_gap_days → niets bijvullen
+    let mut hist2 = hist.clone();
+    hist2.bars.drain(1..4);
+    hist2.gap_fill_bars(2);
+    assert_eq!(hist2.bars.len(), 7);
+}
+
+#[test]
+fn max_total_risk_eur_scales_all_allocations_back() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    // ~1 EUR risk per contract: ATR-stop = 0.25 * 0.5% * prijs, MES ~160 * 5, MNQ ~400 * 2
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 150.0, as_of))
+        .with_history(FutureInstrument::Mnq, make_history_for_test(FutureInstrument::Mnq, 375.0, as_of))
+        .build()
+        .expect("valid ctx");
+
+    let per_inst = InstrumentRiskBudget {
+        max_risk_per_position_eur: 80.0,
+        max_contracts: 100,
+    };
+    let mut budget = FuturesRiskBudget {
+        mes: per_inst,
+        mnq: per_inst,
+        sixe: per_inst,
+        gc: per_inst,
+        mcl: per_inst,
+        m2k: per_inst,
+        zn: per_inst,
+        vx: per_inst,
+        max_total_contracts: 300,
+        max_total_risk_eur: 1_000.0,
+    };
+
+    // Zonder bindend plafond: elk instrument ≈ 80 EUR
+    let uncapped = sleeve.plan_risk_report(&ctx, &budget);
+    assert_eq!(uncapped.len(), 2);
+    for r in &uncapped {
+        assert!(r.total_risk_eur > 75.0 && r.total_risk_eur <= 80.0, "got {:?}", r);
+    }
+
+    budget.max_total_risk_eur = 100.0;
+    let capped = sleeve.plan_risk_report(&ctx, &budget);
+    assert_eq!(capped.len(), 2);
+
+    let total: f64 = capped.iter().map(|r| r.total_risk_eur).sum();
+    assert!(total <= 100.0, "total risk {} above cap", total);
+    for r in &capped {
+        assert!((r.total_risk_eur - 50.0).abs() < 3.0, "expected ~50 EUR, got {:?}", r);
+    }
+
+    // Sanity gebruikt ook het budget-plafond
+    let agg = sleeve.aggregate_sleeve_risk(&ctx, &budget);
+    assert!(agg.total_risk_eur <= 100.0);
+}
+
+#[test]
+fn max_total_risk_eur_trims_largest_position_first_with_single_contracts() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    // 1 contract per instrument: MES ~2 EUR, M2k ~1.3 EUR, MNQ ~1 EUR risk
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 300.0, as_of))
+        .with_history(FutureInstrument::Mnq, make_history_for_test(FutureInstrument::Mnq, 375.0, as_of))
+        .with_history(FutureInstrument::M2k, make_history_for_test(FutureInstrument::M2k, 200.0, as_of))
+        .build()
+        .expect("valid ctx");
+
+    let per_inst = InstrumentRiskBudget {
+        max_risk_per_position_eur: 80.0,
+        max_contracts: 1,
+    };
+    let mut budget = FuturesRiskBudget {
+        mes: per_inst,
+        mnq: per_inst,
+        sixe: per_inst,
+        gc: per_inst,
+        mcl: per_inst,
+        m2k: per_inst,
+        zn: per_inst,
+        vx: per_inst,
+        max_total_contracts: 10,
+        max_total_risk_eur: 1_000.0,
+    };
+
+    let uncapped = sleeve.plan_risk_report(&ctx, &budget);
+    assert_eq!(uncapped.len(), 3, "got {:?}", uncapped);
+    assert!(uncapped.iter().all(|r| r.target_contracts.abs() == 1));
+    let mes_risk = uncapped.iter().find(|r| r.instrument == FutureInstrument::Mes).unwrap().total_risk_eur;
+    let total: f64 = uncapped.iter().map(|r| r.total_risk_eur).sum();
+
+    // Plafond net onder het totaal: alleen de grootste positie (MES) valt af,
+    // de rest houdt zijn ene contract (proportioneel floor-en zou alles op 0 zetten)
+    budget.max_total_risk_eur = total - mes_risk / 2.0;
+    let capped = sleeve.plan_risk_report(&ctx, &budget);
+    assert_eq!(capped.len(), 2, "got {:?}", capped);
+    assert!(capped.iter().all(|r| r.instrument != FutureInstrument::Mes));
+    assert!(capped.iter().all(|r| r.target_contracts.abs() == 1));
+    let capped_total: f64 = capped.iter().map(|r| r.total_risk_eur).sum();
+    assert!(capped_total <= budget.max_total_risk_eur);
+}
+
+#[test]
+fn engine_order_json_round_trip() {
+    let mut metadata = HashMap::new();
+    metadata.insert("parent_order_id".to_string(), "abc-123".to_string());
+
+    let order = EngineOrder {
+        sleeve_id: SleeveId::MicroFuturesMacroTrend,
+        instrument: FutureInstrument::SixE,
+        symbol: "6E".to_string(),
+        venue: "CME".to_string(),
+        side: EngineOrderSide::Sell,
+        quantity: 2,
+        metadata,
+        limit_price: None,
+        stop_price: None,
+        expiry: OrderExpiry::Day,
+        is_reduce_only: false,
+    };
+
+    let value = order.to_json_value();
+    assert_eq!(value["symbol"], "6E");
+    assert_eq!(value["side"], "Sell");
+
+    let json = serde_json::to_string(&order).expect("serializable");
+    let back = EngineOrder::from_json(&json).expect("round-trip");
+
+    assert_eq!(back, order);
+    assert_eq!(back.symbol, "6E");
+    assert_eq!(back.venue, "CME");
+    assert_eq!(back.side, EngineOrderSide::Sell);
+    assert_eq!(back.quantity, 2);
+
+    // Custom instrument (symbol buiten de built-ins) round-tript ook
+    let custom = EngineOrder {
+        instrument: FutureInstrument::Custom(1),
+        symbol: "MYM".to_string(),
+        venue: "CBOT".to_string(),
+        ..order
+    };
+    let back = EngineOrder::from_json(&serde_json::to_string(&custom).unwrap()).expect("custom round-trip");
+    assert_eq!(back, custom);
+
+    // Ongeldige JSON → nette fout
+    assert!(EngineOrder::from_json(&json.replace("\"quantity\":2", "\"quantity\":\"two\"")).is_err());
+}
+
+#[test]
+fn continuous_breakout_strength_maps_range_to_unit_interval() {
+    assert_eq!(MacroFuturesSleeve::compute_breakout_strength(105.0, 110.0, 100.0), 0.0);
+    assert_eq!(MacroFuturesSleeve::compute_breakout_strength(110.0, 110.0, 100.0), 1.0);
+    assert_eq!(MacroFuturesSleeve::compute_breakout_strength(100.0, 110.0, 100.0), -1.0);
+    assert_eq!(MacroFuturesSleeve::compute_breakout_strength(107.5, 110.0, 100.0), 0.5);
+    // Buiten de range geclampt, lege range neutraal
+    assert_eq!(MacroFuturesSleeve::compute_breakout_strength(120.0, 110.0, 100.0), 1.0);
+    assert_eq!(MacroFuturesSleeve::compute_breakout_strength(100.0, 100.0, 100.0), 0.0);
+
+    // Flag schakelt de trend-score om: test-history zit in de range (ternary = 0)
+    let as_of = fixed_as_of();
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .build()
+        .expect("valid ctx");
+
+    let ternary = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let continuous = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        use_continuous_breakout: true,
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    let trace_t = ternary.export_signal_trace(&ctx);
+    let trace_c = continuous.export_signal_trace(&ctx);
+    assert_eq!(trace_t["Mes"]["breakout"].as_f64(), Some(0.0));
+    // close = 1.0, high = 1.01, low = 0.97 → (0.03/0.04 - 0.5) * 2 = 0.5
+    let brk = trace_c["Mes"]["breakout"].as_f64().unwrap();
+    assert!((brk - 0.5).abs() < 1e-9, "got {}", brk);
+}
+
+#[test]
+fn vol_target_sizing_scales_inversely_with_realized_vol() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        vol_target_pct: Some(10.0),
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    let wide_env = SleeveRiskEnvelope {
+        max_position_size_usd: 1.0e9,
+        exposure_remaining_usd: 1.0e9,
+        margin_remaining_usd: 1.0e9,
+        ..base_risk_envelope()
+    };
+
+    let notional_for = |vol_mult: f64| {
+        // ret én vol samen schalen → zelfde z-scores, zelfde conviction
+        let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+        for bar in &mut hist.bars {
+            bar.vol_20d *= vol_mult;
+            bar.ret_20d *= vol_mult;
+        }
+        let ctx = FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(wide_env)
+            .with_sleeve_equity_usd(10_000.0)
+            .with_history(FutureInstrument::Mes, hist)
+            .build()
+            .expect("valid ctx");
+        let planned = sleeve.plan_positions(&ctx, &minimal_risk_budget());
+        assert_eq!(planned.len(), 1);
+        planned[0].target_notional_usd
+    };
+
+    let low_vol = notional_for(1.0);
+    let high_vol = notional_for(2.0);
+
+    assert!(low_vol > 0.0);
+    assert!((high_vol / low_vol - 0.5).abs() < 1e-9, "low {} high {}", low_vol, high_vol);
+    // 10% / 1% * 10k / 1 instrument = 100k * conviction
+    assert!(low_vol <= 100_000.0 && low_vol > 30_000.0, "got {}", low_vol);
+}
+
+#[test]
+fn validate_context_requires_sleeve_equity_for_vol_target() {
+    let vol_target = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        vol_target_pct: Some(10.0),
+        ..MacroFuturesSleeveConfig::default()
+    });
+    let plain = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    // Builder-default: sleeve_equity_usd = 0.0
+    let ctx = make_minimal_ctx();
+    assert!(plain.validate_context(&ctx).is_ok());
+    let errors = vol_target.validate_context(&ctx).expect_err("zero equity must fail");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("sleeve_equity_usd"), "errors: {:?}", errors);
+
+    let funded = FuturesSleeveContext { sleeve_equity_usd: 10_000.0, ..ctx };
+    assert!(vol_target.validate_context(&funded).is_ok());
+}
+
+#[test]
+fn checked_heartbeat_returns_missing_envelope_without_sleeve_config() {
+    let now = fixed_as_of();
+
+    // Kernel zonder enkele sleeve-config
+    let mut kernel = GlobalRiskKernel::new(GlobalRiskKernelConfig {
+        portfolio: PortfolioRiskConfig {
+            initial_equity_usd: 10_000.0,
+            halt_dd_frac: -0.08,
+            kill_dd_frac: -0.12,
+            max_leverage: 1.5,
+            rebalance_drift_frac: 0.15,
+            max_global_positions: 10,
+            max_daily_loss_usd: 500.0,
+            recovery_buffer_frac: 0.02,
+        },
+        sleeves: vec![],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
+    });
+
+    let portfolio_state = PortfolioState {
+        cash_usd: 10_000.0,
+        open_pnl_usd: 0.0,
+        accrued_interest_usd: 0.0,
+        peak_equity_usd: 10_000.0,
+        total_notional_exposure: 0.0,
+        current_leverage: 0.0,
+    };
+    let mut sleeve_state = SleeveState {
+        sleeve_id: SleeveId::MicroFuturesMacroTrend,
+        equity_usd: 2_000.0,
+        realized_pnl_usd: 0.0,
+        unrealized_pnl_usd: 0.0,
+        peak_equity_usd: 2_000.0,
+        open_positions: 0,
+    };
+    let margin_state = MarginState {
+        internal_margin_req_usd: 0.0,
+        broker_margin_req_usd: 0.0,
+        equity_usd: 10_000.0,
+    };
+    let vol_regime = VolatilityRegime {
+        rv10_annualized: 12.0,
+        vix_level: 18.0,
+        vix_term_slope: 0.3,
+        regime_scalar: 1.0,
+    };
+
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let mut histories = HashMap::new();
+    histories.insert(
+        FutureInstrument::Mes,
+        make_history_for_test(FutureInstrument::Mes, 100.0, now),
+    );
+
+    let mut sink = InMemoryOrderSink::new();
+
+    let result = run_macro_futures_engine_heartbeat_checked(
+        now.timestamp(),
+        &mut kernel,
+        &portfolio_state,
+        &mut sleeve_state,
+        &margin_state,
+        &vol_regime,
+        &sleeve,
+        histories,
+        MacroScalars { as_of: now, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 },
+        HashMap::new(),
+        1.0,
+        &minimal_risk_budget(),
+        4_000.0,
+        None,
+        &mut sink,
+    );
+
+    assert!(matches!(
+        result,
+        Err(HeartbeatError::MissingEnvelope { sleeve_id: SleeveId::MicroFuturesMacroTrend })
+    ));
+    assert!(sink.orders.is_empty());
+}
+
+#[test]
+fn total_eur_risk_for_current_positions_sums_abs_contract_notional() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let mes_hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    let mnq_hist = make_history_for_test(FutureInstrument::Mnq, 16_000.0, as_of);
+    let mes_close = mes_hist.bars.last().unwrap().close;
+    let mnq_close = mnq_hist.bars.last().unwrap().close;
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, mes_hist)
+        .with_history(FutureInstrument::Mnq, mnq_hist)
+        // SixE zonder positie → telt niet mee
+        .with_history(
+            FutureInstrument::SixE,
+            make_history_for_test(FutureInstrument::SixE, 1.10, as_of),
+        )
+        .with_current_position(FutureInstrument::Mes, 2)
+        .with_current_position(FutureInstrument::Mnq, -1)
+        .with_eur_per_usd(0.9)
+        .build()
+        .expect("valid ctx");
+
+    // MES multiplier 5, MNQ multiplier 2
+    let expected = (2.0 * mes_close * 5.0 + 1.0 * mnq_close * 2.0) * 0.9;
+    let risk = sleeve.total_eur_risk_for_current_positions(&ctx);
+
+    assert!((risk - expected).abs() < 1e-9, "got {} expected {}", risk, expected);
+}
+
+#[test]
+fn min_history_bars_is_configurable() {
+    let as_of = fixed_as_of();
+
+    // Alleen de laatste 35 bars bewaren
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    let drop = hist.bars.len() - 35;
+    hist.bars.drain(..drop);
+    assert_eq!(hist.bars.len(), 35);
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, hist)
+        .build()
+        .expect("valid ctx");
+
+    let default_sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let short_sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        min_history_bars: 30,
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    let default_sig = default_sleeve.evaluate_signals(&ctx, &minimal_risk_budget()).remove(0);
+    assert_eq!(default_sig.reason, SignalReason::InsufficientHistory);
+
+    let short_sig = short_sleeve.evaluate_signals(&ctx, &minimal_risk_budget()).remove(0);
+    assert_ne!(short_sig.reason, SignalReason::InsufficientHistory);
+    assert_ne!(short_sig.final_signal.direction, 0);
+}
+
+#[test]
+fn priced_order_intents_carry_last_price_and_contract_notional() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    hist.bars.last_mut().unwrap().close = 100.0;
+
+    // Grote bestaande positie → er komt altijd een (afbouw-)intent
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, hist)
+        .with_current_position(FutureInstrument::Mes, 50)
+        .build()
+        .expect("valid ctx");
+
+    let plain = sleeve.plan_order_intents(&ctx, &minimal_risk_budget());
+    let priced: Vec<FuturesOrderIntentPriced> =
+        sleeve.plan_order_intents_with_price_context(&ctx, &minimal_risk_budget());
+
+    assert_eq!(priced.len(), plain.len());
+    let mes = priced
+        .iter()
+        .find(|p| p.intent.instrument == FutureInstrument::Mes)
+        .expect("MES intent");
+
+    assert_eq!(mes.last_price, Some(100.0));
+    assert_eq!(mes.contract_notional_usd, Some(500.0));
+}
+
+#[test]
+fn compute_effective_score_clips_at_configured_bound() {
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        effective_score_clip: 2.0,
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    let adj = |trend: f64, carry: f64| MacroAdjustedSignal {
+        trend_macro_adjusted: trend,
+        carry_macro_adjusted: carry,
+    };
+
+    // Binnen de band → ongewijzigd; carry telt alleen voor 6E
+    assert_eq!(sleeve.compute_effective_score(FutureInstrument::Mes, &adj(1.5, 3.0)), 1.5);
+    assert_eq!(sleeve.compute_effective_score(FutureInstrument::SixE, &adj(0.5, 1.0)), 1.5);
+
+    // Buiten de band → geclipt op ±clip
+    assert_eq!(sleeve.compute_effective_score(FutureInstrument::Mnq, &adj(4.0, 0.0)), 2.0);
+    assert_eq!(sleeve.compute_effective_score(FutureInstrument::Mes, &adj(-7.0, 0.0)), -2.0);
+    assert_eq!(sleeve.compute_effective_score(FutureInstrument::SixE, &adj(-1.5, -1.5)), -2.0);
+
+    // compute_raw_signal: MES heeft nooit carry, lege slice is neutraal
+    let hist = make_history_for_test(FutureInstrument::Mes, 100.0, fixed_as_of());
+    let raw = sleeve.compute_raw_signal(FutureInstrument::Mes, &hist.bars);
+    assert!(raw.trend_score > 0.0);
+    assert_eq!(raw.carry_score, 0.0);
+
+    let empty = sleeve.compute_raw_signal(FutureInstrument::SixE, &[]);
+    assert_eq!((empty.trend_score, empty.carry_score), (0.0, 0.0));
+}
+
+#[test]
+fn enriched_risk_report_includes_price_and_multiplier() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    hist.bars.last_mut().unwrap().close = 100.0;
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, hist)
+        .build()
+        .expect("valid ctx");
+
+    let budget = minimal_risk_budget();
+    let report = sleeve.plan_risk_report(&ctx, &budget);
+    let enriched: Vec<FuturesPlannedRiskEnriched> = sleeve.plan_risk_report_enriched(&ctx, &budget);
+
+    assert_eq!(enriched.len(), report.len());
+    let mes = enriched
+        .iter()
+        .find(|e| e.base.instrument == FutureInstrument::Mes)
+        .expect("MES in risk report");
+
+    assert_eq!(mes.base, report[0]);
+    assert_eq!(mes.last_price_usd, 100.0);
+    assert_eq!(mes.contract_multiplier, 5);
+    assert_eq!(mes.contract_notional_usd, 500.0);
+}
+
+#[test]
+fn entry_filter_suppresses_adds_on_small_conviction_change() {
+    let as_of = fixed_as_of();
+    let budget = minimal_risk_budget();
+
+    let base_sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let filtered_sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        entry_filter_threshold: 0.1,
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    let ctx_for = |current: i32, old_conviction: f64| {
+        FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(base_risk_envelope())
+            .with_history(
+                FutureInstrument::Mes,
+                make_history_for_test(FutureInstrument::Mes, 100.0, as_of),
+            )
+            .with_current_position(FutureInstrument::Mes, current)
+            .with_position_conviction(FutureInstrument::Mes, old_conviction)
+            .build()
+            .expect("valid ctx")
+    };
+
+    // Target en huidige conviction bepalen op een flat context
+    let probe = ctx_for(0, 0.0);
+    let target = base_sleeve.plan_contracts(&probe, &budget)[0].target_contracts;
+    let conviction = base_sleeve.evaluate_signals(&probe, &budget)[0].final_signal.conviction;
+    assert!(target >= 2, "need room to add, target = {}", target);
+
+    // Long 1 onder target, conviction nauwelijks veranderd
+    let small_change = ctx_for(target - 1, conviction - 0.05);
+    assert_eq!(base_sleeve.plan_order_intents(&small_change, &budget).len(), 1);
+    assert!(filtered_sleeve.plan_order_intents(&small_change, &budget).is_empty());
+
+    // Grote conviction-verandering → bijkopen gaat wel door
+    let big_change = ctx_for(target - 1, conviction - 0.2);
+    let intents = filtered_sleeve.plan_order_intents(&big_change, &budget);
+    assert_eq!(intents.len(), 1);
+    assert_eq!(intents[0].delta_contracts, 1);
+}
+
+#[test]
+fn plan_exit_orders_returns_only_position_reductions() {
+    let as_of = fixed_as_of();
+    // Budget voor exact 1 MES long (~0.67 EUR ATR-risk per contract)
+    let budget = FuturesRiskBudget {
+        mes: InstrumentRiskBudget {
+            max_risk_per_position_eur: 1.0,
+            max_contracts: 100,
+        },
+        ..minimal_risk_budget()
+    };
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let ctx_for = |mes_current: i32| {
+        FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(base_risk_envelope())
+            .with_history(
+                FutureInstrument::Mes,
+                make_history_for_test(FutureInstrument::Mes, 100.0, as_of),
+            )
+            .with_current_position(FutureInstrument::Mes, mes_current)
+            .build()
+            .expect("valid ctx")
+    };
+
+    let flat = ctx_for(0);
+    assert_eq!(sleeve.plan_contracts(&flat, &budget)[0].target_contracts, 1);
+    // Vanuit flat is er niets te sluiten
+    assert!(sleeve.plan_exit_orders(&flat, &budget).is_empty());
+
+    // +3 MES, nieuw target +1 → exit van -2
+    let exits = sleeve.plan_exit_orders(&ctx_for(3), &budget);
+    assert_eq!(exits.len(), 1);
+    assert_eq!(exits[0].instrument, FutureInstrument::Mes);
+    assert_eq!(exits[0].delta_contracts, -2);
+
+    // -2 MES (reversal naar +1) → exit sluit de short volledig
+    let exits = sleeve.plan_exit_orders(&ctx_for(-2), &budget);
+    assert_eq!(exits[0].delta_contracts, 2);
+}
+
+#[test]
+fn exit_plus_entry_orders_net_to_plan_order_intents() {
+    let as_of = fixed_as_of();
+    let budget = minimal_risk_budget();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    // Mix: MES reductie, MNQ reversal (short → long), 6E nieuw
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .with_history(FutureInstrument::Mnq, make_history_for_test(FutureInstrument::Mnq, 100.0, as_of))
+        .with_history(FutureInstrument::SixE, make_history_for_test(FutureInstrument::SixE, 1.10, as_of))
+        .with_current_position(FutureInstrument::Mes, 20)
+        .with_current_position(FutureInstrument::Mnq, -2)
+        .build()
+        .expect("valid ctx");
+
+    let exits = sleeve.plan_exit_orders(&ctx, &budget);
+    let entries = sleeve.plan_entry_orders(&ctx, &budget);
+
+    assert!(!exits.is_empty() && !entries.is_empty());
+    // Exits verkleinen altijd, entries vergroten altijd
+    for e in &exits {
+        let current = ctx.current_positions.get(&e.instrument).copied().unwrap_or(0);
+        assert!(current != 0 && e.delta_contracts.signum() == -current.signum());
+    }
+
+    let net_by_instrument = |intents: &[FuturesOrderIntent]| {
+        let mut m: HashMap<FutureInstrument, i32> = HashMap::new();
+        for oi in intents {
+            *m.entry(oi.instrument).or_insert(0) += oi.delta_contracts;
+        }
+        m
+    };
+
+    let mut split = exits.clone();
+    split.extend(entries.iter().copied());
+
+    assert_eq!(
+        net_by_instrument(&split),
+        net_by_instrument(&sleeve.plan_order_intents(&ctx, &budget))
+    );
+}
+
+#[test]
+fn backtest_signal_accuracy_on_trending_history_beats_coin_flip() {
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let hist = make_history_for_test(FutureInstrument::Mes, 100.0, fixed_as_of());
+
+    let report: SignalAccuracyReport = sleeve.backtest_signal_accuracy(&hist, 5);
+
+    // 130 bars: eerste signal op index 119 (120 bars), laatste met 5d lookahead op index 124
+    assert_eq!(report.instrument, FutureInstrument::Mes);
+    assert_eq!(report.n_signals, 6);
+    assert!(report.accuracy_pct > 50.0, "got {}", report.accuracy_pct);
+    assert!(report.avg_ret_when_correct > 0.0);
+
+    // Lookahead langer dan de history → geen signals
+    let empty = sleeve.backtest_signal_accuracy(&hist, 50);
+    assert_eq!(empty.n_signals, 0);
+    assert_eq!(empty.accuracy_pct, 0.0);
+}
+
+#[test]
+fn plan_positions_verbose_reports_halt_for_every_instrument() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let budget = minimal_risk_budget();
+
+    let ctx_with = |env: SleeveRiskEnvelope| {
+        FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(env)
+            .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+            .with_history(FutureInstrument::Mnq, make_history_for_test(FutureInstrument::Mnq, 100.0, as_of))
+            .with_history(FutureInstrument::SixE, make_history_for_test(FutureInstrument::SixE, 1.10, as_of))
+            .build()
+            .expect("valid ctx")
+    };
+
+    let halted = ctx_with(SleeveRiskEnvelope {
+        sleeve_halt: HaltState::Halt,
+        ..base_risk_envelope()
+    });
+
+    let entries = sleeve.plan_positions_verbose(&halted, &budget);
+    assert_eq!(entries.len(), 3);
+    for e in &entries {
+        assert!(e.position.is_none());
+        assert_eq!(e.skip_reason, Some(PlanSkipReason::Halted));
+    }
+    assert!(sleeve.plan_positions(&halted, &budget).is_empty());
+
+    // Normaal: verbose-posities == plan_positions, elke entry óf positie óf reden
+    let normal = ctx_with(base_risk_envelope());
+    let entries = sleeve.plan_positions_verbose(&normal, &budget);
+    assert_eq!(entries.len(), 3);
+    assert!(entries.iter().all(|e| e.position.is_some() != e.skip_reason.is_some()));
+    assert_eq!(
+        entries.iter().filter(|e| e.position.is_some()).count(),
+        sleeve.plan_positions(&normal, &budget).len()
+    );
+}
+
+#[test]
+fn config_json_schema_describes_all_fields() {
+    let schema: serde_json::Value =
+        serde_json::from_str(MacroFuturesSleeveConfig::json_schema()).expect("valid json schema");
+
+    assert_eq!(SLEEVE_CONFIG_JSON_SCHEMA_VERSION, 2);
+    assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+
+    let props = schema["properties"].as_object().expect("properties object");
+    assert!(props.contains_key("logistic_k"));
+    assert_eq!(props["logistic_k"]["type"], "number");
+
+    // Schema en struct lopen niet uit elkaar: elk veld uit de Debug-output staat erin
+    let dbg = format!("{:?}", MacroFuturesSleeveConfig::default());
+    let body = dbg
+        .trim_start_matches("MacroFuturesSleeveConfig {")
+        .trim_end_matches('}');
+    let field_names: Vec<&str> = body
+        .split(", ")
+        .filter_map(|kv| kv.split_once(": ").map(|(k, _)| k))
+        .map(str::trim)
+        .collect();
+
+    assert_eq!(field_names.len(), props.len());
+    for name in &field_names {
+        assert!(props.contains_key(*name), "missing {} in schema", name);
+    }
+
+    // Veldenset van schema v2: verandert deze, dan ook de versie ophogen
+    let v2_fields = [
+        "trend_windows", "breakout_weight", "oi_confirmation_weight", "vwap_weight",
+        "mean_reversion_weight", "mean_reversion_weight_overrides", "relative_strength_weight",
+        "trend_score_clip", "carry_score_clip", "carry_vol_floor", "carry_weight_6e",
+        "carry_weight_commodity", "carry_weight_bond", "vix_contango_threshold",
+        "effective_score_clip", "logistic_k", "logistic_m", "min_effective_score",
+        "min_conviction", "atr_stop_multiple_index", "atr_stop_multiple_fx",
+        "atr_stop_multiple_gold", "atr_stop_multiple_bond", "momentum_lag",
+        "min_history_bars", "max_bar_age_hours", "min_volume_contracts", "min_age_scalar",
+        "use_continuous_breakout", "vol_target_pct", "vol_target_annual",
+        "entry_filter_threshold", "min_delta_contracts", "min_turnover_frac",
+        "correlation_window", "min_carry_sharpe", "margin_buffer_pct",
+    ];
+    assert_eq!(
+        field_names,
+        v2_fields,
+        "config fields changed: bump SLEEVE_CONFIG_JSON_SCHEMA_VERSION and update this list"
+    );
+}
+
+#[test]
+fn portfolio_correlation_matrix_is_symmetric_with_unit_diagonal() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    // Ruis op de closes: MES/MNQ bewegen samen, 6E volgt een ander patroon
+    let noisy = |inst: FutureInstrument, base: f64, f: fn(f64) -> f64| {
+        let mut h = make_history_for_test(inst, base, as_of);
+        for (i, bar) in h.bars.iter_mut().enumerate() {
+            bar.close *= 1.0 + 0.01 * f(i as f64);
+        }
+        h
+    };
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, noisy(FutureInstrument::Mes, 100.0, f64::sin))
+        .with_history(FutureInstrument::Mnq, noisy(FutureInstrument::Mnq, 16_000.0, f64::sin))
+        .with_history(FutureInstrument::SixE, noisy(FutureInstrument::SixE, 1.10, |x| (0.37 * x).cos()))
+        .build()
+        .expect("valid ctx");
+
+    let m = sleeve.compute_portfolio_correlation(&ctx);
+    let all = [FutureInstrument::Mes, FutureInstrument::Mnq, FutureInstrument::SixE];
+
+    assert_eq!(m.correlations.len(), 9);
+    for a in all {
+        assert_eq!(m.get(a, a), Some(1.0));
+        for b in all {
+            let rho = m.get(a, b).expect("pair present");
+            assert!((-1.0..=1.0).contains(&rho), "{:?}/{:?} = {}", a, b, rho);
+            assert_eq!(Some(rho), m.get(b, a));
+        }
+    }
+
+    // Zelfde ruispatroon → vrijwel perfect gecorreleerd
+    assert!(m.get(FutureInstrument::Mes, FutureInstrument::Mnq).unwrap() > 0.99);
+}
+
+#[test]
+fn score_momentum_is_negative_for_declining_scores() {
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let mut state = MacroFuturesSleeveState::new();
+
+    // Alleen de laatste 5 tellen: eerst een stijging, daarna monotoon dalend
+    state.signal_score_history.insert(
+        FutureInstrument::Mes,
+        [0.5, 1.0, 3.0, 2.6, 2.1, 1.5, 1.2].into_iter().collect(),
+    );
+    assert!(sleeve.score_momentum(FutureInstrument::Mes, &state) < 0.0);
+
+    state
+        .signal_score_history
+        .insert(FutureInstrument::Mnq, [1.0, 1.5, 2.0].into_iter().collect());
+    assert!((sleeve.score_momentum(FutureInstrument::Mnq, &state) - 0.5).abs() < 1e-12);
+
+    // Onbekend instrument → neutraal
+    assert_eq!(sleeve.score_momentum(FutureInstrument::SixE, &state), 0.0);
+
+    // run_heartbeat_with_state legt per heartbeat één score per instrument vast
+    let ctx = make_minimal_ctx();
+    let mut live = MacroFuturesSleeveState::new();
+    for _ in 0..3 {
+        sleeve.run_heartbeat_with_state(&ctx, &minimal_risk_budget(), 1_000_000.0, &mut live);
+    }
+    for inst in ctx.histories.keys() {
+        assert_eq!(live.signal_score_history[inst].len(), 3);
+        assert_eq!(sleeve.score_momentum(*inst, &live), 0.0);
+    }
+}
+
+#[test]
+fn split_budget_uses_lower_scale_in_caps_for_existing_positions() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let ctx_with_mes = |current: i32| {
+        FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(base_risk_envelope())
+            .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+            .with_current_position(FutureInstrument::Mes, current)
+            .build()
+            .expect("valid ctx")
+    };
+
+    let initial_entry = minimal_risk_budget();
+    let scale_in = FuturesRiskBudget {
+        mes: InstrumentRiskBudget { max_risk_per_position_eur: 1_000_000.0, max_contracts: 1 },
+        ..initial_entry
+    };
+    let split = SplitRiskBudget { initial_entry, scale_in };
+    let uniform = SplitRiskBudget { initial_entry, scale_in: initial_entry };
+
+    let target = |ctx: &FuturesSleeveContext, b: &SplitRiskBudget| {
+        sleeve.plan_contracts_with_split_budget(ctx, b)[0].target_contracts
+    };
+
+    // Flat → initial_entry, split maakt niets uit
+    let flat = ctx_with_mes(0);
+    assert_eq!(target(&flat, &split), target(&flat, &uniform));
+
+    // Bestaande long 1 → scale_in-caps: minder extra contracts
+    let existing = ctx_with_mes(1);
+    let full_add = target(&existing, &uniform) - 1;
+    let capped_add = target(&existing, &split) - 1;
+    assert!(full_add > 1, "need room to scale in, got {}", full_add);
+    assert!(capped_add < full_add);
+    // scale_in.max_contracts (1) begrenst de bijschaling, niet de totale positie
+    assert_eq!(capped_add, 1);
+
+    // Bestaande long 3 boven de scale_in-cap → niet afbouwen, hooguit 3 + 1
+    let large = ctx_with_mes(3);
+    let large_target = target(&large, &split);
+    assert!(large_target >= 3, "scale_in cap must not force a reduction, got {}", large_target);
+    assert!(large_target <= 4);
+    assert_eq!(split.effective_budget(&large.current_positions).mes.max_contracts, 4);
+}
+
+#[test]
+fn drawdown_series_tracks_rolling_high() {
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, fixed_as_of());
+
+    // make_history_for_test is monotoon stijgend → geen drawdown
+    let dd = hist.compute_drawdown_series(20);
+    assert_eq!(dd.len(), hist.bars.len());
+    assert!(dd.iter().all(|&d| d == 0.0));
+
+    // Laatste bar 12% onder de vorige close
+    let n = hist.bars.len();
+    let prev_close = hist.bars[n - 2].close;
+    hist.bars[n - 1].close = prev_close * 0.88;
+
+    let dd = hist.compute_drawdown_series(20);
+    assert!(dd[n - 1] >= 0.10, "got {}", dd[n - 1]);
+    assert_eq!(dd[n - 2], 0.0);
+}
+
+#[test]
+fn planned_long_gets_reduce_only_sell_stop_below_last_price() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .build()
+        .expect("valid ctx");
+    let last_price = ctx.histories[&FutureInstrument::Mes].bars.last().unwrap().close;
+
+    let planned = sleeve.plan_contracts_with_stop_orders(
+        SleeveId::MicroFuturesMacroTrend,
+        &ctx,
+        &minimal_risk_budget(),
+    );
+
+    assert_eq!(planned.len(), 1);
+    let entry = &planned[0];
+    assert!(entry.plan.target_contracts > 0);
+
+    let stop = entry.stop_order.as_ref().expect("stop for long MES");
+    assert_eq!(stop.side, EngineOrderSide::Sell);
+    assert_eq!(stop.quantity, entry.plan.target_contracts);
+    assert_eq!(stop.expiry, OrderExpiry::Gtc);
+    assert!(stop.is_reduce_only);
+
+    // Stop-market: trigger in stop_price, geen (marketable) limit
+    assert_eq!(stop.limit_price, None);
+    let trigger = stop.stop_price.expect("stop price");
+    assert!(trigger < last_price, "stop {} vs last {}", trigger, last_price);
+    // 0.25 * ATR(0.5% van de prijs)
+    assert!((last_price - trigger - 0.25 * last_price * 0.005).abs() < 1e-9);
+}
+
+#[test]
+fn carry_signal_quality_rewards_stable_positive_high_sharpe_carry() {
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let fx = |rate: f64, vol: f64| FxCarryFeatures {
+        carry_rate_annualized: rate,
+        carry_rate_vol_252d: vol,
+    };
+
+    // Stabiel, positief, Sharpe 50 → bijna 1.0
+    let high = sleeve.compute_carry_signal_quality(&fx(0.05, 0.001));
+    assert!(high > 0.95 && high <= 1.0, "got {}", high);
+
+    // Zelfde carry negatief → lager door de richting-component
+    let negative = sleeve.compute_carry_signal_quality(&fx(-0.05, 0.001));
+    assert!(negative < high);
+
+    // Instabiel en lage Sharpe → laag
+    let noisy = sleeve.compute_carry_signal_quality(&fx(0.002, 0.02));
+    assert!(noisy < 0.5, "got {}", noisy);
+
+    // Ongeldige vol → geen quality
+    assert_eq!(sleeve.compute_carry_signal_quality(&fx(0.05, 0.0)), 0.0);
+}
+
+#[test]
+fn supervisor_statistics_track_gaps_and_degraded_periods() {
+    let mut sup = HeartbeatSupervisor::new(60, 5);
+    assert_eq!(sup.statistics().total_ticks, 0);
+    assert_eq!(sup.statistics().avg_gap_seconds, 0.0);
+
+    // Gaps: 60, 120 (degraded), 60, 90 (degraded) → 2 degraded-periodes
+    for ts in [1_000, 1_060, 1_180, 1_240, 1_330] {
+        sup.register_tick(ts);
+    }
+
+    let stats = sup.statistics();
+    assert_eq!(stats.total_ticks, 5);
+    assert_eq!(stats.avg_gap_seconds, 82.5);
+    assert_eq!(stats.max_gap_seconds, 120);
+    assert_eq!(stats.degraded_periods, 2);
+    assert_eq!(stats.current_health, EngineHealth::Degraded);
+}
+
+#[test]
+fn compare_plans_reports_contract_and_risk_differences() {
+    let as_of = fixed_as_of();
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .with_history(FutureInstrument::Mnq, make_history_for_test(FutureInstrument::Mnq, 100.0, as_of))
+        .build()
+        .expect("valid ctx");
+    let budget = minimal_risk_budget();
+
+    let sleeve_a = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    // B: nooit een trade (threshold onhaalbaar hoog)
+    let config_b = MacroFuturesSleeveConfig {
+        min_effective_score: 100.0,
+        ..MacroFuturesSleeveConfig::default()
+    };
+
+    let cmp = sleeve_a
+        .compare_plans(&config_b, &ctx, &budget, 1_000_000.0)
+        .expect("valid config_b");
+
+    assert!(!cmp.plan_a.planned_contracts.is_empty());
+    assert!(cmp.plan_b.planned_contracts.is_empty());
+
+    for p in &cmp.plan_a.planned_contracts {
+        assert_eq!(cmp.diff_contracts[&p.instrument], -p.target_contracts);
+    }
+    assert_eq!(cmp.diff_contracts.len(), cmp.plan_a.planned_contracts.len());
+
+    let expected =
+        cmp.plan_b.aggregate.total_risk_eur - cmp.plan_a.aggregate.total_risk_eur;
+    assert_eq!(cmp.diff_risk_eur, expected);
+    assert!(cmp.diff_risk_eur < 0.0);
+
+    // Zelfde config → geen verschil
+    let same = sleeve_a
+        .compare_plans(&sleeve_a.cfg, &ctx, &budget, 1_000_000.0)
+        .expect("valid config");
+    assert!(same.diff_contracts.values().all(|&d| d == 0));
+    assert_eq!(same.diff_risk_eur, 0.0);
+
+    // Ongeldige B → validatiefouten i.p.v. een debug_assert-panic
+    let invalid_b = MacroFuturesSleeveConfig {
+        logistic_k: 0.0,
+        ..MacroFuturesSleeveConfig::default()
+    };
+    let errors = sleeve_a
+        .compare_plans(&invalid_b, &ctx, &budget, 1_000_000.0)
+        .expect_err("invalid config_b");
+    assert!(errors.iter().any(|e| e.contains("logistic_k")), "{errors:?}");
+}
+
+#[test]
+fn margin_buffer_keeps_reserve_unused() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        margin_buffer_pct: 0.20,
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(SleeveRiskEnvelope {
+            max_position_size_usd: 100_000.0,
+            exposure_remaining_usd: 100_000.0,
+            margin_remaining_usd: 1_000.0,
+            ..base_risk_envelope()
+        })
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .with_history(FutureInstrument::Mnq, make_history_for_test(FutureInstrument::Mnq, 100.0, as_of))
+        .build()
+        .expect("valid ctx");
+
+    let planned = sleeve.plan_positions(&ctx, &minimal_risk_budget());
+    assert!(!planned.is_empty());
+
+    let used: f64 = planned.iter().map(|p| p.target_notional_usd.abs()).sum();
+    assert!(used <= 800.0 + 1e-9, "used {}", used);
+    assert!(used > 700.0, "buffer should be the binding cap, used {}", used);
+}
+
+#[test]
+fn gold_history_is_routed_through_plan_contracts_with_gold_budget() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(SleeveRiskEnvelope {
+            max_position_size_usd: 100_000.0,
+            exposure_remaining_usd: 100_000.0,
+            margin_remaining_usd: 100_000.0,
+            ..base_risk_envelope()
+        })
+        .with_history(FutureInstrument::Gc, make_history_for_test(FutureInstrument::Gc, 200.0, as_of))
+        .build()
+        .expect("valid ctx");
+
+    // Gold-cap van 2 contracts bindt, ondanks ruim risk-budget
+    let budget = FuturesRiskBudget {
+        gc: InstrumentRiskBudget {
+            max_contracts: InstrumentRiskBudget::default_gold().max_contracts,
+            ..minimal_risk_budget().gc
+        },
+        ..minimal_risk_budget()
+    };
+
+    let planned = sleeve.plan_contracts(&ctx, &budget);
+    assert_eq!(planned.len(), 1);
+    assert_eq!(planned[0].instrument, FutureInstrument::Gc);
+    assert_eq!(planned[0].target_contracts, 2);
+
+    let gold = InstrumentRiskBudget::default_gold();
+    assert_eq!(gold.max_risk_per_position_eur, 80.0);
+    assert!(gold.validate().is_ok());
+}
+
+#[test]
+fn positive_roll_yield_gives_positive_commodity_carry_and_conviction() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    // Backwardation: front 80.0, second 79.2 op spot 80.0 → 12% per jaar
+    let roll_yield = CommodityCarryFeatures::roll_yield_from_spread(80.0, 79.2, 80.0);
+    assert!((roll_yield - 0.12).abs() < 1e-9);
+
+    let mut hist = make_history_for_test(FutureInstrument::Mcl, 80.0, as_of);
+    for bar in &mut hist.bars {
+        bar.vol_20d = 0.005; // lage vol → carry_vol_floor bindt
+        bar.commodity_carry = Some(CommodityCarryFeatures {
+            roll_yield_annualized: roll_yield,
+            backwardation_score: 0.8,
+        });
+    }
+
+    let carry = sleeve.compute_commodity_carry_raw(hist.bars.last().unwrap());
+    assert!(carry > 0.0, "carry = {}", carry);
+
+    let raw = sleeve.compute_raw_signal(FutureInstrument::Mcl, &hist.bars);
+    assert_eq!(raw.carry_score, carry);
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mcl, hist)
+        .build()
+        .expect("valid ctx");
+
+    let signals = sleeve.evaluate_signals(&ctx, &minimal_risk_budget());
+    assert_eq!(signals.len(), 1);
+    let sig = &signals[0];
+    assert_eq!(sig.instrument, FutureInstrument::Mcl);
+    assert!(sig.macro_adj.carry_macro_adjusted > 0.0);
+    assert!(sig.final_signal.conviction > 0.0);
+
+    // Zonder carry-features → carry 0
+    let mut flat_bar = *ctx.histories[&FutureInstrument::Mcl].bars.last().unwrap();
+    flat_bar.commodity_carry = None;
+    assert_eq!(sleeve.compute_commodity_carry_raw(&flat_bar), 0.0);
+}
+
+#[test]
+fn m2k_uptrend_is_not_opened_when_mes_and_mnq_fill_concurrency_cap() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let ctx_with_cap = |max_concurrent_positions: u32| {
+        FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(SleeveRiskEnvelope {
+                max_concurrent_positions,
+                ..base_risk_envelope()
+            })
+            .with_history(FutureInstrument::M2k, make_history_for_test(FutureInstrument::M2k, 100.0, as_of))
+            .with_current_position(FutureInstrument::Mes, 1)
+            .with_current_position(FutureInstrument::Mnq, 1)
+            .build()
+            .expect("valid ctx")
+    };
+
+    // MES + MNQ bezetten beide slots → geen M2K
+    let planned = sleeve.plan_contracts(&ctx_with_cap(2), &minimal_risk_budget());
+    assert!(
+        planned.iter().all(|p| p.instrument != FutureInstrument::M2k),
+        "unexpected M2K position: {:?}",
+        planned
+    );
+
+    // Eén slot extra → M2K-uptrend wordt long geopend
+    let planned = sleeve.plan_contracts(&ctx_with_cap(3), &minimal_risk_budget());
+    let m2k = planned
+        .iter()
+        .find(|p| p.instrument == FutureInstrument::M2k)
+        .expect("M2K planned with free slot");
+    assert!(m2k.target_contracts > 0);
+}
+
+#[test]
+fn inverted_yield_curve_gives_negative_bond_carry() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let mut hist = make_history_for_test(FutureInstrument::Zn, 110.0, as_of);
+    let with_curve = |bars: &mut [DailyFeatureBar], y10: f64, y2: f64| {
+        for bar in bars {
+            bar.bond_carry = Some(BondCarryFeatures { yield_10y: y10, yield_2y: y2 });
+        }
+    };
+
+    // Inverse curve: 10y 4.0 < 2y 4.5 → (4.0 - 4.5) / 0.25 = -2.0
+    with_curve(&mut hist.bars, 4.0, 4.5);
+    let last = *hist.bars.last().unwrap();
+    let carry = sleeve.compute_bond_carry_raw(&last);
+    assert!((carry - -2.0).abs() < 1e-9, "carry = {}", carry);
+
+    let raw = sleeve.compute_raw_signal(FutureInstrument::Zn, &hist.bars);
+    assert!(raw.carry_score < 0.0);
+
+    // Normale curve → positieve carry
+    with_curve(&mut hist.bars, 4.5, 4.0);
+    assert!(sleeve.compute_bond_carry_raw(hist.bars.last().unwrap()) > 0.0);
+
+    let zn = InstrumentRiskBudget::default_bond();
+    assert_eq!(zn.max_contracts, 1);
+}
+
+#[test]
+fn steep_vix_contango_gives_short_vx_signal() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let signal_for_slope = |vix_term_slope: f64| {
+        let ctx = FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope })
+            .with_risk_envelope(base_risk_envelope())
+            // Uptrend in de bars: mag VX niet long maken
+            .with_history(FutureInstrument::Vx, make_history_for_test(FutureInstrument::Vx, 18.0, as_of))
+            .build()
+            .expect("valid ctx");
+        sleeve.evaluate_signals(&ctx, &minimal_risk_budget()).remove(0)
+    };
+
+    let contango = signal_for_slope(1.5);
+    assert_eq!(contango.instrument, FutureInstrument::Vx);
+    assert!(contango.raw.carry_score < 0.0);
+    assert_eq!(contango.final_signal.direction, -1);
+    assert!(contango.final_signal.conviction > 0.0);
+
+    // Vlakke curve → neutraal, ondanks de uptrend
+    let flat = signal_for_slope(0.3);
+    assert_eq!(flat.final_signal.direction, 0);
+}
+
+#[test]
+fn registered_custom_instrument_appears_in_plan_contracts() {
+    let as_of = fixed_as_of();
+
+    let mut registry = InstrumentRegistry::default();
+    let mym = registry.register(
+        1,
+        InstrumentSpec {
+            symbol: "MYM".to_string(),
+            venue: "CBOT".to_string(),
+            contract_multiplier: 1,
+            atr_stop_multiple: Some(0.3),
+            carry: CarryType::None,
+            risk_budget: Some(InstrumentRiskBudget {
+                max_risk_per_position_eur: 1_000_000.0,
+                max_contracts: 4,
+            }),
+        },
+    );
+    assert_eq!(mym, FutureInstrument::Custom(1));
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(mym, make_history_for_test(mym, 400.0, as_of))
+        .build()
+        .expect("valid ctx");
+
+    let sleeve = MacroFuturesSleeve::with_registry(MacroFuturesSleeveConfig::default(), registry);
+    let planned = sleeve.plan_contracts(&ctx, &minimal_risk_budget());
+    assert_eq!(planned.len(), 1);
+    assert_eq!(planned[0].instrument, mym);
+    assert_eq!(planned[0].target_contracts, 4);
+
+    // Zonder registratie → geen plan voor het custom instrument
+    let plain = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    assert!(plain.plan_contracts(&ctx, &minimal_risk_budget()).is_empty());
+}
+
+/// Default-config met de trend-blend (trend_windows + breakout) gehalveerd naar 0.5,
+/// zodat overlay-gewichten tot samen 0.5 nog valideren.
+fn overlay_room_config() -> MacroFuturesSleeveConfig {
+    let default = MacroFuturesSleeveConfig::default();
+    MacroFuturesSleeveConfig {
+        trend_windows: default.trend_windows.iter().map(|&(d, w)| (d, w / 2.0)).collect(),
+        breakout_weight: default.breakout_weight / 2.0,
+        ..default
+    }
+}
+
+#[test]
+fn oi_confirmation_score_confirms_or_diverges_with_trend() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        oi_confirmation_weight: 0.5,
+        ..overlay_room_config()
+    });
+    let baseline = MacroFuturesSleeve::new(overlay_room_config());
+
+    // Milde uptrend (ret_20d > 0, trend-score ruim onder de clip) met OI die per bar
+    // met `oi_step` verandert
+    let history_with_oi = |oi_step: f64| {
+        let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+        for (i, bar) in hist.bars.iter_mut().enumerate() {
+            bar.ret_20d = 0.01;
+            bar.ret_60d = 0.0;
+            bar.ret_120d = 0.0;
+            bar.open_interest = 10_000.0 + oi_step * i as f64;
+        }
+        hist
+    };
+
+    // Stijgende OI → bevestiging, trend-score hoger dan zonder OI-gewicht
+    let rising = history_with_oi(50.0);
+    assert_eq!(sleeve.compute_oi_confirmation_score(&rising.bars), 1.0);
+    let with_oi = sleeve.compute_raw_signal(FutureInstrument::Mes, &rising.bars).trend_score;
+    let without = baseline.compute_raw_signal(FutureInstrument::Mes, &rising.bars).trend_score;
+    assert!((with_oi - without - 0.5).abs() < 1e-9, "{} vs {}", with_oi, without);
+
+    // Dalende OI bij stijgende prijs → divergentie
+    let falling = history_with_oi(-50.0);
+    assert_eq!(sleeve.compute_oi_confirmation_score(&falling.bars), -1.0);
+    let diverging = sleeve.compute_raw_signal(FutureInstrument::Mes, &falling.bars).trend_score;
+    assert!(diverging < without);
+
+    // Downtrend met stijgende OI → bevestiging maakt de short sterker (score lager)
+    let mut down = history_with_oi(50.0);
+    for bar in &mut down.bars {
+        bar.ret_20d = -0.01;
+    }
+    assert_eq!(sleeve.compute_oi_confirmation_score(&down.bars), 1.0);
+    let down_with_oi = sleeve.compute_raw_signal(FutureInstrument::Mes, &down.bars).trend_score;
+    let down_without = baseline.compute_raw_signal(FutureInstrument::Mes, &down.bars).trend_score;
+    assert!(down_without < 0.0, "expected downtrend, got {}", down_without);
+    assert!((down_with_oi - down_without + 0.5).abs() < 1e-9, "{} vs {}", down_with_oi, down_without);
+
+    // Vlakke OI → ruis
+    assert_eq!(sleeve.compute_oi_confirmation_score(&history_with_oi(0.0).bars), 0.0);
+
+    // Negatieve / NaN OI → ongeldige features → flat signal
+    let mut bad = history_with_oi(0.0);
+    bad.bars.last_mut().unwrap().open_interest = f64::NAN;
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, bad)
+        .build()
+        .expect("valid ctx");
+    let sig = sleeve.evaluate_signals(&ctx, &minimal_risk_budget()).remove(0);
+    assert_eq!(sig.reason, SignalReason::InvalidData);
+}
+
+#[test]
+fn vwap_below_close_adds_positive_trend_component() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        vwap_weight: 0.5,
+        ..overlay_room_config()
+    });
+    let baseline = MacroFuturesSleeve::new(overlay_room_config());
+
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    // Trend-score onder de clip houden zodat de VWAP-component zichtbaar is
+    for bar in &mut hist.bars {
+        bar.ret_20d = 0.01;
+        bar.ret_60d = 0.0;
+        bar.ret_120d = 0.0;
+    }
+
+    // vwap = 0.998 * close, atr = 0.005 * close → (0.002 / 0.005) = 0.4
+    let last = hist.bars.last().unwrap();
+    let vwap_score = sleeve.compute_vwap_relative_score(last);
+    assert!((vwap_score - 0.4).abs() < 1e-9, "vwap score = {}", vwap_score);
+
+    let with_vwap = sleeve.compute_raw_signal(FutureInstrument::Mes, &hist.bars).trend_score;
+    let without = baseline.compute_raw_signal(FutureInstrument::Mes, &hist.bars).trend_score;
+    assert!((with_vwap - without - 0.5 * 0.4).abs() < 1e-9);
+
+    // Ver boven VWAP → geclipt op 1.0
+    let far = DailyFeatureBar { vwap: last.close * 0.9, ..*last };
+    assert_eq!(sleeve.compute_vwap_relative_score(&far), 1.0);
+}
+
+#[test]
+fn stale_last_bar_gives_flat_stale_data_signal() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        max_bar_age_hours: 48,
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    let signal_with_last_bar_at = |last_bar_ts: DateTime<Utc>| {
+        let ctx = FuturesSleeveContext::builder()
+            .with_as_of(as_of)
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(base_risk_envelope())
+            .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, last_bar_ts))
+            .build()
+            .expect("valid ctx");
+        sleeve.evaluate_signals(&ctx, &minimal_risk_budget()).remove(0)
+    };
+
+    // Feed 72 uur bevroren → StaleData, flat
+    let stale = signal_with_last_bar_at(as_of - Duration::hours(72));
+    assert_eq!(stale.reason, SignalReason::StaleData);
+    assert_eq!(stale.final_signal.direction, 0);
+
+    // Verse data → normaal signal
+    let fresh = signal_with_last_bar_at(as_of - Duration::hours(12));
+    assert_eq!(fresh.reason, SignalReason::Normal);
+    assert_ne!(fresh.final_signal.direction, 0);
+}
+
+#[test]
+fn low_volume_signal_bar_blocks_new_position() {
+    let as_of = fixed_as_of();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        min_volume_contracts: 2_000.0,
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    let ctx_with_volume = |volume: f64| {
+        let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+        hist.bars.last_mut().unwrap().volume = volume;
+        FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(base_risk_envelope())
+            .with_history(FutureInstrument::Mes, hist)
+            .build()
+            .expect("valid ctx")
+    };
+
+    // Dunne markt → LowVolume, geen positie
+    let thin = ctx_with_volume(100.0);
+    let sig = sleeve.evaluate_signals(&thin, &minimal_risk_budget()).remove(0);
+    assert_eq!(sig.reason, SignalReason::LowVolume);
+    assert_eq!(sig.final_signal.direction, 0);
+    assert!(sleeve.plan_contracts(&thin, &minimal_risk_budget()).is_empty());
+
+    // Zelfde setup met voldoende volume → wel een positie
+    let liquid = ctx_with_volume(5_000.0);
+    let planned = sleeve.plan_contracts(&liquid, &minimal_risk_budget());
+    assert_eq!(planned.len(), 1);
+    assert!(planned[0].target_contracts > 0);
+}
+
+#[test]
+fn price_far_above_ma_5d_reduces_long_conviction_via_mean_reversion() {
+    let as_of = fixed_as_of();
+    let budget = minimal_risk_budget();
+
+    let baseline = MacroFuturesSleeve::new(overlay_room_config());
+    let mut overrides = HashMap::new();
+    overrides.insert(FutureInstrument::Mes, 0.25);
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        mean_reversion_weight_overrides: overrides,
+        ..overlay_room_config()
+    });
+
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    // Trend-score onder de clip houden: z20 = 6, z60 = 4, z120 = 0 (met de halve blend)
+    for bar in &mut hist.bars {
+        bar.ret_20d = 0.06;
+        bar.ret_60d = 0.048;
+        bar.ret_120d = 0.0;
+    }
+    // Close 3 ATR boven het 5d-gemiddelde → mean-reversion geclipt op -2
+    let last = hist.bars.last_mut().unwrap();
+    last.ma_5d = last.close - 3.0 * last.atr_14;
+    assert_eq!(sleeve.compute_mean_reversion_raw(last), -2.0);
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, hist)
+        .build()
+        .expect("valid ctx");
+
+    let base_sig = &baseline.evaluate_signals(&ctx, &budget)[0];
+    let mr_sig = &sleeve.evaluate_signals(&ctx, &budget)[0];
+
+    assert!((base_sig.raw.trend_score - mr_sig.raw.trend_score - 0.25 * 2.0).abs() < 1e-9);
+    // Nog steeds long, maar met lagere conviction
+    assert_eq!(base_sig.final_signal.direction, 1);
+    assert_eq!(mr_sig.final_signal.direction, 1);
+    assert!(
+        mr_sig.final_signal.conviction < base_sig.final_signal.conviction,
+        "mr {} vs base {}",
+        mr_sig.final_signal.conviction,
+        base_sig.final_signal.conviction
+    );
+}
+
+#[test]
+fn relative_strength_favors_outperforming_mnq_over_mes() {
+    let as_of = fixed_as_of();
+    let budget = minimal_risk_budget();
+
+    let mut mes = make_history_for_test(FutureInstrument::Mes, 5000.0, as_of);
+    let mut mnq = make_history_for_test(FutureInstrument::Mnq, 18000.0, as_of);
+    mes.bars.last_mut().unwrap().ret_20d = 0.03;
+    mnq.bars.last_mut().unwrap().ret_20d = 0.15;
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, mes)
+        .with_history(FutureInstrument::Mnq, mnq)
+        .build()
+        .expect("valid ctx");
+
+    let baseline = MacroFuturesSleeve::new(overlay_room_config());
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        relative_strength_weight: 0.2,
+        ..overlay_room_config()
+    });
+
+    let score = sleeve.compute_relative_strength_signal(
+        &ctx,
+        (FutureInstrument::Mnq, FutureInstrument::Mes),
+    );
+    assert!(score > 0.0, "score = {}", score);
+    let reversed = sleeve.compute_relative_strength_signal(
+        &ctx,
+        (FutureInstrument::Mes, FutureInstrument::Mnq),
+    );
+    assert_eq!(reversed, -score);
+
+    let frac = |s: &MacroFuturesSleeve, inst: FutureInstrument| {
+        s.evaluate_risk_intents(&ctx, &budget)
+            .into_iter()
+            .find(|i| i.instrument == inst)
+            .expect("intent")
+            .desired_risk_frac
+    };
+
+    let base_mes = frac(&baseline, FutureInstrument::Mes);
+    let base_mnq = frac(&baseline, FutureInstrument::Mnq);
+    assert!(base_mes > 0.0 && base_mnq > 0.0);
+
+    // Tilt richting MNQ: MES-fractie omlaag, MNQ omhoog (tot max. 1.0)
+    assert!(frac(&sleeve, FutureInstrument::Mes) < base_mes);
+    assert!(frac(&sleeve, FutureInstrument::Mnq) >= base_mnq);
+    assert!(
+        (frac(&sleeve, FutureInstrument::Mnq) - (base_mnq + 0.2 * score).min(1.0)).abs() < 1e-9
+    );
+}
+
+#[test]
+fn vol_target_annual_scales_position_by_target_over_instrument_vol() {
+    let as_of = fixed_as_of();
+    let budget = minimal_risk_budget();
+
+    // Instrument-vol 15% annual → vol_20d = 0.15 / sqrt(252)
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    for bar in &mut hist.bars {
+        bar.vol_20d = 0.15 / 252f64.sqrt();
+    }
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, hist)
+        .build()
+        .expect("valid ctx");
+
+    let unconstrained = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let vol_targeted = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        vol_target_annual: Some(0.10),
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    let base = unconstrained.plan_positions(&ctx, &budget);
+    let targeted = vol_targeted.plan_positions(&ctx, &budget);
+    assert_eq!(base.len(), 1);
+    assert_eq!(targeted.len(), 1);
+
+    // 0.10 / 0.15 = 2/3 van de conviction-geschaalde size
+    let ratio = targeted[0].target_notional_usd / base[0].target_notional_usd;
+    assert!((ratio - 2.0 / 3.0).abs() < 1e-9, "ratio = {}", ratio);
+
+    // Niet te combineren met vol_target_pct
+    let both = MacroFuturesSleeveConfig {
+        vol_target_pct: Some(10.0),
+        ..vol_targeted.cfg.clone()
+    };
+    let errors = both.validate().expect_err("both vol targets must fail");
+    assert!(errors.iter().any(|e| e.contains("mutually exclusive")), "errors: {:?}", errors);
+}
+
+#[test]
+fn min_delta_contracts_blocks_small_adds_but_not_reversals() {
+    let as_of = fixed_as_of();
+    let budget = minimal_risk_budget();
+
+    let base_sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let filtered_sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        min_delta_contracts: 2,
+        ..MacroFuturesSleeveConfig::default()
+    });
+
+    let ctx_for = |current: i32| {
+        FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(base_risk_envelope())
+            .with_history(
+                FutureInstrument::Mes,
+                make_history_for_test(FutureInstrument::Mes, 100.0, as_of),
+            )
+            .with_current_position(FutureInstrument::Mes, current)
+            .build()
+            .expect("valid ctx")
+    };
+
+    let target = base_sleeve.plan_contracts(&ctx_for(0), &budget)[0].target_contracts;
+    assert!(target >= 2, "need room to add, target = {}", target);
+
+    // 1 contract bijkopen → onder min_delta_contracts
+    let one_short = ctx_for(target - 1);
+    assert_eq!(base_sleeve.plan_order_intents(&one_short, &budget).len(), 1);
+    assert!(filtered_sleeve.plan_order_intents(&one_short, &budget).is_empty());
+
+    // Reversal (short → long) gaat altijd door, ook met een absurd hoge drempel
+    let strict_sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+        min_delta_contracts: 1_000,
+        ..MacroFuturesSleeveConfig::default()
+    });
+    let intents = strict_sleeve.plan_order_intents(&ctx_for(-1), &budget);
+    assert_eq!(intents.len(), 1);
+    assert_eq!(intents[0].delta_contracts, target + 1);
+}
+
+#[test]
+fn custom_trend_windows_compute_z_scores_from_closes() {
+    let as_of = fixed_as_of();
+
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, as_of);
+    // Uptrend met ruis zodat de realized vol > 0 is
+    for (i, bar) in hist.bars.iter_mut().enumerate() {
+        let noise = if i % 2 == 0 { 1.002 } else { 0.998 };
+        bar.close = 100.0 * (1.0 + 0.001 * i as f64) * noise;
+    }
+
+    // Klassieke setup is de default
+    let classic = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::classic());
+    assert_eq!(
+        classic.cfg.trend_windows,
+        MacroFuturesSleeveConfig::default().trend_windows
+    );
+    assert_eq!(classic.cfg.trend_windows, vec![(20, 0.45), (60, 0.30), (120, 0.15)]);
+
+    let with_window = |windows: Vec<(u32, f64)>| {
+        MacroFuturesSleeve::new(MacroFuturesSleeveConfig {
+            trend_windows: windows,
+            ..MacroFuturesSleeveConfig::default()
+        })
+    };
+
+    // Verwachte 10d z-score: log-return / stdev(daily log-returns) over 10 bars
+    let closes: Vec<f64> = hist.bars.iter().map(|b| b.close).collect();
+    let window = &closes[closes.len() - 11..];
+    let log_rets: Vec<f64> = window.windows(2).map(|w| (w[1] / w[0]).ln()).collect();
+    let mean = log_rets.iter().sum::<f64>() / 10.0;
+    let vol = (log_rets.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 9.0).sqrt();
+    let expected_z10 = (window[10] / window[0]).ln() / vol;
+
+    let only_10d = with_window(vec![(10, 0.5)]).compute_raw_signal(FutureInstrument::Mes, &hist.bars);
+    let none = with_window(Vec::new()).compute_raw_signal(FutureInstrument::Mes, &hist.bars);
+    assert!(
+        (only_10d.trend_score - none.trend_score - 0.5 * expected_z10).abs() < 1e-9,
+        "10d {} vs none {} (z10 = {})",
+        only_10d.trend_score,
+        none.trend_score,
+        expected_z10
+    );
+
+    // Lookback langer dan de history → bijdrage 0.0
+    let too_long = with_window(vec![(500, 0.5)]).compute_raw_signal(FutureInstrument::Mes, &hist.bars);
+    assert_eq!(too_long.trend_score, none.trend_score);
+
+    // Vaste window met vol 0 → z niet-finite → bijdrage 0.0 (niet geclipt naar de max)
+    let mut zero_vol = hist.clone();
+    zero_vol.bars.last_mut().unwrap().vol_60d = 0.0;
+    let z60 = with_window(vec![(60, 0.5)]).compute_raw_signal(FutureInstrument::Mes, &zero_vol.bars);
+    let none_zero_vol = with_window(Vec::new()).compute_raw_signal(FutureInstrument::Mes, &zero_vol.bars);
+    assert_eq!(z60.trend_score, none_zero_vol.trend_score);
+
+    // Signal-trace toont een z-key per window
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, hist)
+        .build()
+        .expect("valid ctx");
+    let trace = with_window(vec![(10, 0.5), (40, 0.3)]).export_signal_trace(&ctx);
+    assert!(trace["Mes"]["z10"].is_number());
+    assert!(trace["Mes"]["z40"].is_number());
+    assert!(trace["Mes"].get("z20").is_none());
+}
+
+#[test]
+fn config_validate_rejects_zero_logistic_k_and_accepts_default() {
+    assert_eq!(MacroFuturesSleeveConfig::default().validate(), Ok(()));
+    assert!(MacroFuturesSleeve::try_new(MacroFuturesSleeveConfig::default()).is_ok());
+
+    let bad = MacroFuturesSleeveConfig {
+        logistic_k: 0.0,
+        min_conviction: 1.5,
+        ..MacroFuturesSleeveConfig::default()
+    };
+    let errors = bad.validate().expect_err("logistic_k = 0 must fail");
+    assert_eq!(errors.len(), 2, "errors: {:?}", errors);
+    assert!(errors.iter().any(|e| e.contains("logistic_k")));
+    assert!(errors.iter().any(|e| e.contains("min_conviction")));
+
+    assert_eq!(
+        MacroFuturesSleeve::try_new(bad).err().map(|e| e.len()),
+        Some(2)
+    );
+
+    // Momentum-blend boven 1.0
+    let overweight = MacroFuturesSleeveConfig {
+        breakout_weight: 0.5,
+        ..MacroFuturesSleeveConfig::default()
+    };
+    let errors = overweight.validate().expect_err("sum > 1.0 must fail");
+    assert!(errors[0].contains("breakout_weight"), "errors: {:?}", errors);
+
+    // Overlay-gewichten tellen mee in de som, ook een mean-reversion override
+    for overlay in [
+        MacroFuturesSleeveConfig { oi_confirmation_weight: 0.1, ..MacroFuturesSleeveConfig::default() },
+        MacroFuturesSleeveConfig { vwap_weight: 0.1, ..MacroFuturesSleeveConfig::default() },
+        MacroFuturesSleeveConfig { mean_reversion_weight: 0.1, ..MacroFuturesSleeveConfig::default() },
+        MacroFuturesSleeveConfig {
+            mean_reversion_weight_overrides: HashMap::from([(FutureInstrument::Mes, 0.1)]),
+            ..MacroFuturesSleeveConfig::default()
+        },
+        MacroFuturesSleeveConfig { relative_strength_weight: 0.1, ..MacroFuturesSleeveConfig::default() },
+    ] {
+        let errors = overlay.validate().expect_err("overlay on a full blend must fail");
+        assert!(errors[0].contains("must sum to <= 1.0"), "errors: {:?}", errors);
+    }
+    let with_room = MacroFuturesSleeveConfig {
+        oi_confirmation_weight: 0.2,
+        vwap_weight: 0.1,
+        mean_reversion_weight_overrides: HashMap::from([(FutureInstrument::Mes, 0.1)]),
+        relative_strength_weight: 0.1,
+        ..overlay_room_config()
+    };
+    assert_eq!(with_room.validate(), Ok(()));
+}
+
+#[test]
+fn config_and_budget_round_trip_through_toml() {
+    let cfg = MacroFuturesSleeveConfig {
+        vol_target_pct: Some(10.0),
+        mean_reversion_weight_overrides: HashMap::from([(FutureInstrument::SixE, 0.3)]),
+        ..overlay_room_config()
+    };
+
+    let text = toml::to_string(&cfg).expect("serialize config");
+    let back: MacroFuturesSleeveConfig = toml::from_str(&text).expect("deserialize config");
+    assert_eq!(back, cfg);
+    assert_eq!(back.trend_windows, cfg.trend_windows);
+    assert_eq!(back.vol_target_annual, None);
+
+    // Via een file, inclusief validatie
+    let mut path = env::temp_dir();
+    path.push(format!(
+        "macro_futures_sleeve_config_{}.toml",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    fs::write(&path, &text).expect("write config");
+    let loaded = MacroFuturesSleeveConfig::from_toml_file(&path).expect("load config");
+    assert_eq!(loaded, cfg);
+
+    let invalid = text.replace("logistic_k = 1.3", "logistic_k = 0.0");
+    fs::write(&path, invalid).expect("write config");
+    match MacroFuturesSleeveConfig::from_toml_file(&path) {
+        Err(ConfigError::Invalid(errors)) => assert!(errors[0].contains("logistic_k")),
+        other => panic!("expected Invalid, got {:?}", other),
+    }
+    let _ = fs::remove_file(&path);
+    assert!(matches!(
+        MacroFuturesSleeveConfig::from_toml_file(&path),
+        Err(ConfigError::Io(_))
+    ));
+
+    // Budget: per-instrument secties + top-level caps
+    let budget = minimal_risk_budget();
+    let budget_text = toml::to_string(&budget).expect("serialize budget");
+    assert!(budget_text.contains("[mes]"));
+    assert!(budget_text.contains("[sixe]"));
+    assert!(budget_text.contains("max_total_contracts"));
+    let budget_back: FuturesRiskBudget = toml::from_str(&budget_text).expect("deserialize budget");
+    assert_eq!(budget_back, budget);
+}
+
+#[test]
+fn partial_config_toml_falls_back_to_defaults() {
+    let mut path = env::temp_dir();
+    path.push(format!(
+        "macro_futures_sleeve_partial_config_{}.toml",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    fs::write(&path, "logistic_k = 2.0\nmin_delta_contracts = 2\n").expect("write config");
+    let loaded = MacroFuturesSleeveConfig::from_toml_file(&path);
+    let _ = fs::remove_file(&path);
+
+    let expected = MacroFuturesSleeveConfig {
+        logistic_k: 2.0,
+        min_delta_contracts: 2,
+        ..MacroFuturesSleeveConfig::default()
+    };
+    assert_eq!(loaded.expect("partial config loads"), expected);
+
+    // Leeg bestand = volledige default
+    let empty: MacroFuturesSleeveConfig = toml::from_str("").expect("empty config");
+    assert_eq!(empty, MacroFuturesSleeveConfig::default());
+}
+
+#[test]
+fn soft_halt_halves_existing_positions_and_blocks_new_ones() {
+    let as_of = fixed_as_of();
+    let budget = minimal_risk_budget();
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+
+    let ctx_for = |halt: HaltState| {
+        FuturesSleeveContext::builder()
+            .with_macro_scalars(MacroScalars { as_of, risk_on_scalar: 1.0, usd_scalar: 1.0, vix_term_slope: 0.0 })
+            .with_risk_envelope(SleeveRiskEnvelope { sleeve_halt: halt, ..base_risk_envelope() })
+            .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+            .with_history(FutureInstrument::Mnq, make_history_for_test(FutureInstrument::Mnq, 200.0, as_of))
+            .with_current_position(FutureInstrument::Mes, 5)
+            .build()
+            .expect("valid ctx")
+    };
+
+    let normal = ctx_for(HaltState::None);
+    let soft = ctx_for(HaltState::SoftHalt);
+
+    // plan_positions: MES (aangehouden) op halve size, MNQ (nieuw) overgeslagen
+    let normal_mes = sleeve
+        .plan_positions(&normal, &budget)
+        .into_iter()
+        .find(|p| p.instrument == FutureInstrument::Mes)
+        .expect("mes planned");
+    let soft_plan = sleeve.plan_positions(&soft, &budget);
+    assert_eq!(soft_plan.len(), 1);
+    assert_eq!(soft_plan[0].instrument, FutureInstrument::Mes);
+    assert!((soft_plan[0].target_notional_usd - 0.5 * normal_mes.target_notional_usd).abs() < 1e-9);
+
+    assert_eq!(
+        sleeve.explain_flat_signal(&soft, &budget, FutureInstrument::Mnq),
+        FlatExplanation::Halted { halt_state: HaltState::SoftHalt }
+    );
+
+    // Order-intents: 5 → 2 (helft, afgerond naar 0), niets voor MNQ
+    let intents = sleeve.plan_order_intents(&soft, &budget);
+    assert_eq!(intents.len(), 1);
+    assert_eq!(intents[0].instrument, FutureInstrument::Mes);
+    assert_eq!(intents[0].delta_contracts, -3);
+
+    // Short: -5 → -2
+    let short = FuturesSleeveContext {
+        current_positions: HashMap::from([(FutureInstrument::Mes, -5)]),
+        ..soft.clone()
+    };
+    let intents = sleeve.plan_order_intents(&short, &budget);
+    assert_eq!(intents.len(), 1);
+    assert_eq!(intents[0].delta_contracts, 3);
+
+    // Tweede SoftHalt-heartbeat op 2, met de startpositie uit de state: niet opnieuw halveren
+    let mut state = MacroFuturesSleeveState::new();
+    state.record_soft_halt(&soft);
+    assert_eq!(state.soft_halt_base_positions, HashMap::from([(FutureInstrument::Mes, 5)]));
+    let next = FuturesSleeveContext {
+        current_positions: HashMap::from([(FutureInstrument::Mes, 2)]),
+        soft_halt_base_positions: state.soft_halt_base_positions.clone(),
+        ..soft.clone()
+    };
+    assert!(sleeve.plan_order_intents(&next, &budget).is_empty());
+    state.record_soft_halt(&next);
+    assert_eq!(state.soft_halt_base_positions[&FutureInstrument::Mes], 5);
+
+    // Einde SoftHalt → state leeg
+    state.record_soft_halt(&normal);
+    assert!(state.soft_halt_base_positions.is_empty());
+
+    // Kill (portfolio) wint van een SoftHalt (sleeve): alles flat
+    let kill = FuturesSleeveContext {
+        risk_envelope: SleeveRiskEnvelope {
+            portfolio_halt: HaltState::Kill,
+            sleeve_halt: HaltState::SoftHalt,
+            ..base_risk_envelope()
+        },
+        ..soft
+    };
+    let intents = sleeve.plan_order_intents(&kill, &budget);
+    assert_eq!(intents.len(), 1);
+    assert_eq!(intents[0].delta_contracts, -5);
+}
+
+#[test]
+fn twap_sink_splits_order_into_equal_slices() {
+    let order = EngineOrder {
+        sleeve_id: SleeveId::MicroFuturesMacroTrend,
+        instrument: FutureInstrument::Mes,
+        symbol: "MES".to_string(),
+        venue: "CME".to_string(),
+        side: EngineOrderSide::Buy,
+        quantity: 10,
+        metadata: HashMap::new(),
+        limit_price: None,
+        stop_price: None,
+        expiry: OrderExpiry::Day,
+        is_reduce_only: false,
+    };
+
+    let mut twap: TwapOrderSink<InMemoryOrderSink> = TwapOrderSink::new(5, 60);
+    let mut inner = InMemoryOrderSink::new();
+    twap.submit(&order);
+
+    let t0 = fixed_as_of().timestamp();
+    for i in 0..5 {
+        // Vóór het interval gebeurt er niets
+        if i > 0 {
+            assert_eq!(twap.drain_due_slices(t0 + i * 60 - 1, &mut inner), 0);
+        }
+        assert_eq!(twap.drain_due_slices(t0 + i * 60, &mut inner), 1);
+        assert_eq!(inner.orders.len(), i as usize + 1);
+        assert_eq!(inner.orders[i as usize].0.quantity, 2);
+    }
+
+    assert!(twap.pending().is_empty());
+    assert_eq!(inner.total_abs_contracts(), 10);
+    assert!(inner.orders.iter().all(|(o, _)| o.side == EngineOrderSide::Buy && o.symbol == "MES"));
+    assert_eq!(inner.orders[4].0.metadata["twap_slice"], "5/5");
+}
+
+/// Deelbare `InMemoryOrderSink` die ook telt hoe vaak `flush` is aangeroepen.
+struct SharedOrderSink(Rc<RefCell<(InMemoryOrderSink, u32)>>);
+
+impl OrderSink for SharedOrderSink {
+    fn submit(&mut self, order: &EngineOrder) {
+        self.0.borrow_mut().0.submit(order);
+    }
+
+    fn flush(&mut self) {
+        self.0.borrow_mut().1 += 1;
+    }
+}
+
+fn composite_test_order() -> EngineOrder {
+    EngineOrder {
+        sleeve_id: SleeveId::MicroFuturesMacroTrend,
+        instrument: FutureInstrument::Mnq,
+        symbol: "MNQ".to_string(),
+        venue: "CME".to_string(),
+        side: EngineOrderSide::Sell,
+        quantity: 1,
+        metadata: HashMap::new(),
+        limit_price: None,
+        stop_price: None,
+        expiry: OrderExpiry::Day,
+        is_reduce_only: false,
+    }
+}
+
+#[test]
+fn composite_order_sink_fans_out_submit_and_flush() {
+    let a = Rc::new(RefCell::new((InMemoryOrderSink::new(), 0)));
+    let b = Rc::new(RefCell::new((InMemoryOrderSink::new(), 0)));
+    let mut composite = CompositeOrderSink::new(vec![
+        Box::new(SharedOrderSink(a.clone())),
+        Box::new(SharedOrderSink(b.clone())),
+    ]);
+
+    let order = composite_test_order();
+    composite.submit(&order);
+    composite.flush();
+
+    for shared in [&a, &b] {
+        let (sink, flushes) = &*shared.borrow();
+        assert_eq!(sink.orders, vec![(order.clone(), OrderStatus::Pending)]);
+        assert_eq!(*flushes, 1);
+    }
+}
+
+#[test]
+fn composite_order_sink_still_calls_remaining_sinks_after_panic() {
+    struct PanickingSink;
+    impl OrderSink for PanickingSink {
+        fn submit(&mut self, _order: &EngineOrder) {
+            panic!("broken sink");
+        }
+    }
+
+    let after = Rc::new(RefCell::new((InMemoryOrderSink::new(), 0)));
+    let mut composite = CompositeOrderSink::new(vec![
+        Box::new(PanickingSink),
+        Box::new(SharedOrderSink(after.clone())),
+    ]);
+
+    let order = composite_test_order();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| composite.submit(&order)));
+
+    // Panic wordt doorgegeven, maar pas nadat de volgende sink de order heeft gekregen
+    assert!(result.is_err());
+    assert_eq!(after.borrow().0.orders.len(), 1);
+}
+
+#[test]
+fn validating_sink_collects_violations_and_assert_clean_panics() {
+    let mut sink = ValidatingOrderSink::new(vec![SleeveId::MicroFuturesMacroTrend]);
+
+    let good = composite_test_order();
+    sink.submit(&good);
+    sink.assert_clean();
+
+    let zero = EngineOrder { quantity: 0, ..composite_test_order() };
+    let foreign = EngineOrder {
+        sleeve_id: SleeveId::EquityLongShort,
+        ..composite_test_order()
+    };
+    sink.submit(&zero);
+    sink.submit(&foreign);
+    sink.flush();
+    assert_eq!(sink.violations.len(), 2);
+
+    let err = std::panic::catch_unwind(|| sink.assert_clean()).unwrap_err();
+    let msg = err.downcast_ref::<String>().expect("formatted panic message");
+    assert!(msg.contains("quantity"), "{msg}");
+    assert!(msg.contains("EquityLongShort"), "{msg}");
+}
+
+#[test]
+fn in_memory_sink_nets_contracts_per_instrument() {
+    let mes = |side, quantity| EngineOrder {
+        instrument: FutureInstrument::Mes,
+        symbol: "MES".to_string(),
+        side,
+        quantity,
+        ..composite_test_order()
+    };
+
+    let mut sink = InMemoryOrderSink::new();
+    sink.submit(&mes(EngineOrderSide::Buy, 3));
+    sink.submit(&mes(EngineOrderSide::Sell, 1));
+    sink.submit(&composite_test_order()); // MNQ Sell 1
+
+    let net = sink.net_position_by_instrument();
+    assert_eq!(net[&FutureInstrument::Mes], 2);
+    assert_eq!(net[&FutureInstrument::Mnq], -1);
+    assert_eq!(net.len(), 2);
+    assert_eq!(sink.total_abs_contracts(), 5);
+}
+
+#[test]
+fn in_memory_sink_tracks_partial_and_full_fills() {
+    let mut sink = InMemoryOrderSink::new();
+    sink.submit(&EngineOrder { quantity: 5, ..composite_test_order() });
+    sink.submit(&composite_test_order());
+    assert_eq!(sink.pending_orders().count(), 2);
+
+    sink.fill(0, 2);
+    assert_eq!(sink.orders[0].1, OrderStatus::PartiallyFilled { filled: 2 });
+    assert_eq!(sink.pending_orders().count(), 2);
+
+    sink.fill(0, 3);
+    assert_eq!(sink.orders[0].1, OrderStatus::Filled);
+
+    sink.cancel(1);
+    assert_eq!(sink.orders[1].1, OrderStatus::Cancelled);
+    assert_eq!(sink.pending_orders().count(), 0);
+
+    // Buiten bereik of op een gevulde order: no-op
+    sink.fill(7, 1);
+    sink.fill(0, 1);
+    assert_eq!(sink.orders[0].1, OrderStatus::Filled);
+}
+
+#[test]
+fn file_order_sink_rotates_daily_by_filename_pattern() {
+    let mut dir = env::temp_dir();
+    dir.push(format!(
+        "engine_order_rotation_{}",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    fs::create_dir_all(&dir).expect("cannot create test dir");
+
+    // Pattern zonder {date} → fout bij constructie
+    assert!(FileOrderSink::with_rotation(&dir, "orders.jsonl").is_err());
+
+    let mut sink = FileOrderSink::with_rotation(&dir, "orders-{date}.jsonl").expect("valid pattern");
+
+    let d1 = Utc.with_ymd_and_hms(2025, 11, 17, 10, 0, 0).unwrap();
+    let d2 = Utc.with_ymd_and_hms(2025, 11, 18, 9, 30, 0).unwrap();
+    let buy = EngineOrder {
+        instrument: FutureInstrument::Mes,
+        symbol: "MES".to_string(),
+        side: EngineOrderSide::Buy,
+        quantity: 3,
+        ..composite_test_order()
+    };
+    let sell = composite_test_order();
+
+    sink.submit_with_datetime(d1, &buy);
+    sink.submit_with_datetime(d1, &sell);
+    sink.submit_with_datetime(d2, &sell);
+    sink.flush();
+
+    let c1 = fs::read_to_string(dir.join("orders-20251117.jsonl")).expect("day-1 file");
+    let c2 = fs::read_to_string(dir.join("orders-20251118.jsonl")).expect("day-2 file");
+    assert_eq!(
+        c1,
+        format!(
+            "{}\n{}\n",
+            encode_order_log_event_json(&buy, d1.timestamp()),
+            encode_order_log_event_json(&sell, d1.timestamp())
+        )
+    );
+    assert_eq!(c2, format!("{}\n", encode_order_log_event_json(&sell, d2.timestamp())));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn rate_limited_sink_drops_orders_above_interval_limit() {
+    let mut sink = RateLimitedOrderSink::new(InMemoryOrderSink::new(), 4, 60);
+    let order = composite_test_order();
+    let t0 = fixed_as_of().timestamp();
+
+    for i in 0..6 {
+        sink.submit_with_ts(&order, t0 + i);
+    }
+    assert_eq!(sink.inner.orders.len(), 4);
+    assert_eq!(sink.dropped_count(), 2);
+
+    // Nieuw interval → weer ruimte
+    sink.submit_with_ts(&order, t0 + 60);
+    assert_eq!(sink.into_inner().orders.len(), 5);
+}
+
+#[test]
+fn engine_order_estimated_notional_uses_contract_multiplier() {
+    let mes = EngineOrder {
+        instrument: FutureInstrument::Mes,
+        symbol: "MES".to_string(),
+        side: EngineOrderSide::Buy,
+        quantity: 3,
+        ..composite_test_order()
+    };
+    let registry = InstrumentRegistry::default();
+    assert_eq!(mes.estimated_notional_usd(&registry, 5_000.0), Some(75_000.0));
+
+    let six_e = EngineOrder {
+        instrument: FutureInstrument::SixE,
+        symbol: "6E".to_string(),
+        side: EngineOrderSide::Buy,
+        quantity: 1,
+        ..composite_test_order()
+    };
+    assert!((six_e.estimated_notional_usd(&registry, 1.10).unwrap() - 137_500.0).abs() < 1e-6);
+
+    assert_eq!(contract_multiplier(FutureInstrument::Mnq), Some(2.0));
+
+    // Custom: alleen via de registry waarin het instrument staat
+    let mut custom_registry = InstrumentRegistry::default();
+    let mym = custom_registry.register(
+        1,
+        InstrumentSpec {
+            symbol: "MYM".to_string(),
+            venue: "CBOT".to_string(),
+            contract_multiplier: 1,
+            atr_stop_multiple: None,
+            carry: CarryType::None,
+            risk_budget: None,
+        },
+    );
+    let custom = EngineOrder { instrument: mym, symbol: "MYM".to_string(), ..mes };
+    assert_eq!(contract_multiplier(mym), None);
+    assert_eq!(custom.estimated_notional_usd(&registry, 40_000.0), None);
+    assert_eq!(custom.estimated_notional_usd(&custom_registry, 40_000.0), Some(120_000.0));
+}
+
+#[test]
+fn engine_order_encodes_fix42_new_order_single() {
+    let buy = EngineOrder {
+        instrument: FutureInstrument::Mes,
+        symbol: "MES".to_string(),
+        side: EngineOrderSide::Buy,
+        quantity: 3,
+        ..composite_test_order()
+    };
+    let ts = Utc.with_ymd_and_hms(2025, 1, 17, 14, 30, 0).unwrap();
+
+    let msg = buy.to_fix42_new_order_single_with("ORD-1", "U123", Some("SENDER"), ts);
+    let fields: Vec<&str> = msg.trim_end_matches('\x01').split('\x01').collect();
+
+    assert_eq!(fields[0], "8=FIX.4.2");
+    assert!(fields[1].starts_with("9="));
+    assert_eq!(fields[2], "35=D");
+    for expected in ["49=SENDER", "11=ORD-1", "1=U123", "55=MES", "54=1", "38=3", "40=1", "60=20250117-14:30:00"] {
+        assert!(fields.contains(&expected), "missing {expected} in {fields:?}");
+    }
+    assert!(fields.last().unwrap().starts_with("10="));
+
+    // Zonder sender geen 49; Sell → 54=2
+    let sell = EngineOrder { side: EngineOrderSide::Sell, ..buy };
+    let msg = sell.to_fix42_new_order_single("ORD-2", "U123");
+    assert!(msg.contains("\x0154=2\x01"));
+    assert!(!msg.contains("\x0149="));
+
+    // Stop-market: 40=3 met StopPx (99), geen Price (44)
+    let stop = EngineOrder { stop_price: Some(4950.25), is_reduce_only: true, ..sell.clone() };
+    let msg = stop.to_fix42_new_order_single_with("ORD-3", "U123", None, ts);
+    assert!(msg.contains("\x0140=3\x01"), "got {msg:?}");
+    assert!(msg.contains("\x0199=4950.25\x01"));
+    assert!(!msg.contains("\x0144="));
+
+    // Limit: 40=2 met 44
+    let limit = EngineOrder { limit_price: Some(5000.5), ..sell };
+    let msg = limit.to_fix42_new_order_single_with("ORD-4", "U123", None, ts);
+    assert!(msg.contains("\x0140=2\x0144=5000.5\x01"), "got {msg:?}");
+    assert!(!msg.contains("\x0199="));
+}
+
+#[test]
+fn prometheus_accumulator_renders_heartbeat_metrics() {
+    let now = fixed_as_of();
+    let mut kernel = heartbeat_kernel_for_test();
+    let (result, margin) = run_heartbeat_for_test(&mut kernel, now, 100.0, HashMap::new());
+    let line = encode_heartbeat_log_event_json(now.timestamp(), &result, EngineHealth::Healthy, &margin, None);
+    let evt = decode_heartbeat_log_event_json(&line).expect("decodable heartbeat json");
+    assert!(!evt.orders.is_empty(), "test-heartbeat moet orders bevatten");
+
+    let supervisor_line = encode_supervisor_event_json(&HeartbeatSupervisorEvent {
+        ts_utc: now.timestamp(),
+        status: EngineHealth::Degraded,
+        msg: "heartbeat_gap_detected",
+        consecutive_degraded: 1,
+    });
+
+    let mut metrics = PrometheusMetricsAccumulator::new();
+    metrics.log(&line);
+    metrics.log(&supervisor_line); // geen HeartbeatLogEvent → telt niet mee
+    metrics.log("not json"); // telt niet mee
+
+    assert_eq!(metrics.heartbeat_total, 1);
+    assert_eq!(metrics.total_orders_submitted, evt.orders.len() as u64);
+    assert_eq!(metrics.total_risk_eur, evt.total_risk_eur);
+    assert_eq!(metrics.position_size_usd_max, evt.max_position_size_usd);
+    assert_eq!(metrics.sleeve_sanity_ok, evt.sanity == "Ok");
+
+    let text = metrics.render_text();
+    assert!(text.contains("engine_heartbeat_total 1\n"), "{text}");
+    assert!(text.contains(&format!("engine_total_orders_submitted {}\n", evt.orders.len())), "{text}");
+    assert!(text.contains(&format!("engine_total_risk_eur {}\n", evt.total_risk_eur)), "{text}");
+    assert!(text.contains(&format!("engine_position_size_usd_max {}\n", evt.max_position_size_usd)), "{text}");
+    assert!(text.contains(&format!("engine_sleeve_sanity_ok {}\n", u8::from(evt.sanity == "Ok"))), "{text}");
+    assert!(text.contains("# TYPE engine_heartbeat_total counter"), "{text}");
+}
+
+#[test]
+fn file_heartbeat_logger_rotates_by_size_with_sequence_numbers() {
+    let mut dir = env::temp_dir();
+    dir.push(format!(
+        "engine_heartbeat_size_rotation_{}",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    fs::create_dir_all(&dir).expect("cannot create test dir");
+
+    let mut logger = FileHeartbeatLogger::with_size_rotation(&dir, 100);
+    let dt = Utc.with_ymd_and_hms(2025, 1, 17, 10, 0, 0).unwrap();
+
+    // 3 regels van 50 bytes (+ newline): na 2 regels zit file 1 boven 100 bytes
+    let line = format!("{{\"msg\":\"{}\"}}", "x".repeat(40));
+    assert_eq!(line.len(), 50);
+    for _ in 0..3 {
+        logger.log_with_datetime(dt, &line);
+    }
+    logger.flush();
+
+    let f1 = fs::read_to_string(dir.join("heartbeat-20250117-001.jsonl")).expect("first file");
+    let f2 = fs::read_to_string(dir.join("heartbeat-20250117-002.jsonl")).expect("second file");
+    assert_eq!(f1.lines().count(), 2);
+    assert_eq!(f2.lines().count(), 1);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// Eén volledige engine-heartbeat (kernel → sleeve → orders) op MES/MNQ/6E test-history.
+fn run_heartbeat_for_test(
+    kernel: &mut GlobalRiskKernel,
+    now: DateTime<Utc>,
+    mes_price: f64,
+    current_positions: HashMap<FutureInstrument, i32>,
+) -> (MacroFuturesEngineHeartbeatResult, MarginState) {
+    run_heartbeat_for_test_with_prev(kernel, now, mes_price, current_positions, None)
+}
+
+/// Zoals `run_heartbeat_for_test`, met de signals van een vorige heartbeat.
+fn run_heartbeat_for_test_with_prev(
+    kernel: &mut GlobalRiskKernel,
+    now: DateTime<Utc>,
+    mes_price: f64,
+    current_positions: HashMap<FutureInstrument, i32>,
+    prev_signals: Option<&[InstrumentSignal]>,
+) -> (MacroFuturesEngineHeartbeatResult, MarginState) {
+    let portfolio_state = PortfolioState {
+        cash_usd: 10_000.0,
+        open_pnl_usd: 0.0,
+        accrued_interest_usd: 0.0,
+        peak_equity_usd: 10_000.0,
+        total_notional_exposure: 0.0,
+        current_leverage: 0.0,
+    };
+    let mut sleeve_state = SleeveState {
+        sleeve_id: SleeveId::MicroFuturesMacroTrend,
+        equity_usd: 2_000.0,
+        realized_pnl_usd: 0.0,
+        unrealized_pnl_usd: 0.0,
+        peak_equity_usd: 2_000.0,
+        open_positions: current_positions.values().filter(|&&c| c != 0).count() as u32,
+    };
+    let margin_state = MarginState {
+        internal_margin_req_usd: 0.0,
+        broker_margin_req_usd: 500.0,
+        equity_usd: 10_000.0,
+    };
+    let vol_regime = VolatilityRegime {
+        rv10_annualized: 12.0,
+        vix_level: 18.0,
+        vix_term_slope: 0.3,
+        regime_scalar: 1.0,
+    };
+
+    let mut histories = HashMap::new();
+    histories.insert(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, mes_price, now));
+    histories.insert(FutureInstrument::Mnq, make_history_for_test(FutureInstrument::Mnq, 16_000.0, now));
+    histories.insert(FutureInstrument::SixE, make_history_for_test(FutureInstrument::SixE, 1.10, now));
+
+    let macro_scalars = MacroScalars {
+        as_of: now,
+        risk_on_scalar: 1.0,
+        usd_scalar: 1.0,
+        vix_term_slope: 0.0,
+    };
+
+    let result = run_macro_futures_engine_heartbeat(
+        now.timestamp(),
+        kernel,
+        &portfolio_state,
+        &mut sleeve_state,
+        &margin_state,
+        &vol_regime,
+        &MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default()),
+        histories,
+        macro_scalars,
+        current_positions,
+        1.0,
+        &minimal_risk_budget(),
+        4_000.0,
+        prev_signals,
+        &mut InMemoryOrderSink::new(),
+    );
+    (result, margin_state)
+}
+
+#[test]
+fn engine_heartbeat_detects_regime_change_against_previous_signals() {
+    let now = fixed_as_of();
+
+    // Eerste heartbeat zonder vorige signals: niets te vergelijken
+    let mut kernel = heartbeat_kernel_for_test();
+    let (first, _) = run_heartbeat_for_test(&mut kernel, now, 100.0, HashMap::new());
+    assert!(first.heartbeat.prev_signals.is_none());
+    assert!(first.heartbeat.regime_changes.is_empty());
+    let mes = first
+        .heartbeat
+        .signals
+        .iter()
+        .find(|s| s.instrument == FutureInstrument::Mes)
+        .expect("MES signal");
+    assert_eq!(mes.final_signal.direction, 1);
+
+    // Zelfde signals als vorige heartbeat → geen change
+    let mut kernel = heartbeat_kernel_for_test();
+    let (same, _) = run_heartbeat_for_test_with_prev(
+        &mut kernel,
+        now,
+        100.0,
+        HashMap::new(),
+        Some(&first.heartbeat.signals),
+    );
+    assert!(same.heartbeat.prev_signals.is_some());
+    assert!(same.heartbeat.regime_changes.is_empty(), "{:?}", same.heartbeat.regime_changes);
+
+    // Vorige heartbeat was MES short → richting-wissel gerapporteerd
+    let prev = vec![make_signal(FutureInstrument::Mes, -1, -2.0)];
+    let mut kernel = heartbeat_kernel_for_test();
+    let (flipped, _) =
+        run_heartbeat_for_test_with_prev(&mut kernel, now, 100.0, HashMap::new(), Some(&prev));
+    assert_eq!(flipped.heartbeat.regime_changes.len(), 1);
+    assert_eq!(flipped.heartbeat.regime_changes[0].instrument, FutureInstrument::Mes);
+    assert_eq!(flipped.heartbeat.regime_changes[0].prev_direction, -1);
+    assert_eq!(flipped.heartbeat.regime_changes[0].curr_direction, 1);
+}
+
+fn heartbeat_kernel_for_test() -> GlobalRiskKernel {
+    GlobalRiskKernel::new(GlobalRiskKernelConfig {
+        portfolio: PortfolioRiskConfig {
+            initial_equity_usd: 10_000.0,
+            halt_dd_frac: -0.08,
+            kill_dd_frac: -0.12,
+            max_leverage: 1.5,
+            rebalance_drift_frac: 0.15,
+            max_global_positions: 10,
+            max_daily_loss_usd: 500.0,
+            recovery_buffer_frac: 0.02,
+        },
+        sleeves: vec![SleeveRiskConfig {
+            sleeve_id: SleeveId::MicroFuturesMacroTrend,
+            capital_alloc_usd: 2_000.0,
+            max_single_pos_risk_frac: 0.5, // 1k USD max size → een paar MES-contracts
+            halt_dd_frac: -0.10,
+            soft_halt_dd_frac: -0.10,
+            kill_dd_frac: -0.15,
+            max_concurrent_positions: 3,
+            max_position_size_usd_absolute: None,
+        }],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
+    })
+}
+
+#[test]
+fn heartbeat_log_event_json_round_trip() {
+    let now = fixed_as_of();
+    let mut kernel = heartbeat_kernel_for_test();
+    let (result, margin) = run_heartbeat_for_test(&mut kernel, now, 100.0, HashMap::new());
+
+    let ts = now.timestamp();
+    let json = encode_heartbeat_log_event_json(ts, &result, EngineHealth::Healthy, &margin, None);
+    let decoded = decode_heartbeat_log_event_json(&json).expect("decodable heartbeat json");
+    let original = HeartbeatLogEvent::from_engine_result(ts, &result, EngineHealth::Healthy, &margin, None);
+
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+    assert_eq!(decoded.ts_utc, original.ts_utc);
+    assert!(close(decoded.max_position_size_usd, original.max_position_size_usd));
+    assert!(close(decoded.exposure_remaining_usd, original.exposure_remaining_usd));
+    assert!(close(decoded.margin_remaining_usd, original.margin_remaining_usd));
+    assert!(close(decoded.margin_utilization_pct, original.margin_utilization_pct));
+    assert!(close(decoded.total_risk_eur, original.total_risk_eur));
+    assert!(close(decoded.net_direction, original.net_direction));
+    assert_eq!(decoded.instrument_signals.len(), original.instrument_signals.len());
+    for (d, o) in decoded.instrument_signals.iter().zip(&original.instrument_signals) {
+        assert_eq!(d.direction, o.direction);
+        assert!(close(d.conviction, o.conviction));
+        assert!(close(d.effective_score, o.effective_score));
+    }
+    assert_eq!(decoded.orders, original.orders);
+
+    // .jsonl-file met twee events (plus lege regel) terug inlezen
+    let mut path = env::temp_dir();
+    path.push(format!("engine_heartbeat_roundtrip_{}.jsonl", Utc::now().timestamp_nanos_opt().unwrap_or_default()));
+    fs::write(&path, format!("{}\n\n{}\n", json, json)).unwrap();
+    let events = HeartbeatLogEvent::from_jsonl_file(&path).expect("readable jsonl");
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].sleeve_id, original.sleeve_id);
+    let _ = fs::remove_file(&path);
+
+    assert!(decode_heartbeat_log_event_json("{}").is_err());
+}
+
+#[test]
+fn heartbeat_log_event_deltas_track_change_from_previous_heartbeat() {
+    let now = fixed_as_of();
+    let mut kernel = heartbeat_kernel_for_test();
+    let margin = MarginState {
+        internal_margin_req_usd: 0.0,
+        broker_margin_req_usd: 500.0,
+        equity_usd: 10_000.0,
+    };
+
+    // 1) Eerste heartbeat: geen vorige → geen deltas
+    let (first_result, _) = run_heartbeat_for_test(&mut kernel, now, 100.0, HashMap::new());
+    let first = HeartbeatLogEvent::from_engine_result(
+        now.timestamp(),
+        &first_result,
+        EngineHealth::Healthy,
+        &margin,
+        None,
+    );
+    assert_eq!(first.delta_risk_eur, None);
+    assert_eq!(first.delta_exposure_remaining_usd, None);
+
+    // 2) Een uur later, MES 50% hoger → andere contract-risk
+    let later = now + Duration::hours(1);
+    let (second_result, _) = run_heartbeat_for_test(&mut kernel, later, 150.0, HashMap::new());
+    let json = encode_heartbeat_log_event_json(
+        later.timestamp(),
+        &second_result,
+        EngineHealth::Healthy,
+        &margin,
+        Some(&first),
+    );
+    let second = decode_heartbeat_log_event_json(&json).unwrap();
+
+    assert!(first.total_risk_eur > 0.0);
+    let expected = second.total_risk_eur - first.total_risk_eur;
+    assert!(expected.abs() > 1.0);
+    assert!((second.delta_risk_eur.unwrap() - expected).abs() < 1e-9);
+    let expected = second.exposure_remaining_usd - first.exposure_remaining_usd;
+    assert!((second.delta_exposure_remaining_usd.unwrap() - expected).abs() < 1e-9);
+}
+
+#[test]
+fn batching_heartbeat_logger_flushes_on_max_age() {
+    let spy = Rc::new(RefCell::new(SpySink::new()));
+    struct SpyWrapper(Rc<RefCell<SpySink>>);
+    impl HeartbeatLogSink for SpyWrapper {
+        fn log(&mut self, line: &str) {
+            self.0.borrow_mut().log(line);
+        }
+    }
+
+    let mut logger = BatchingHeartbeatLogger::with_time_flush(Box::new(SpyWrapper(spy.clone())), 10, 60);
+    let t0 = fixed_as_of().timestamp();
+
+    logger.log_with_ts("{\"a\":1}", t0);
+    assert_eq!(logger.buffered_len(), 1);
+    assert!(spy.borrow().lines.borrow().is_empty());
+
+    // 70s later: oudste item ouder dan 60s → flush, ook al is de capaciteit (10) niet bereikt
+    logger.log_with_ts("{\"b\":2}", t0 + 70);
+    assert_eq!(logger.buffered_len(), 0);
+    assert_eq!(*spy.borrow().lines.borrow(), vec!["{\"a\":1}", "{\"b\":2}"]);
+
+    // Timer start opnieuw bij het volgende item
+    logger.log_with_ts("{\"c\":3}", t0 + 100);
+    logger.log_with_ts("{\"d\":4}", t0 + 130);
+    assert_eq!(logger.buffered_len(), 2);
+}
+
+#[test]
+fn supervisor_counts_consecutive_degraded_ticks_and_resets_when_healthy() {
+    let mut sup = HeartbeatSupervisor::new(60, 5);
+    sup.register_tick(1_000);
+
+    for (i, ts) in [1_200, 1_400, 1_700].into_iter().enumerate() {
+        sup.register_tick(ts);
+        assert_eq!(sup.health(), EngineHealth::Degraded);
+        assert_eq!(sup.consecutive_degraded_count(), i as u32 + 1);
+    }
+    assert_eq!(sup.consecutive_degraded_count(), 3);
+    assert_eq!(sup.total_gap_seconds(), 700);
+
+    sup.register_tick(1_730);
+    assert_eq!(sup.health(), EngineHealth::Healthy);
+    assert_eq!(sup.consecutive_degraded_count(), 0);
+    assert_eq!(sup.total_gap_seconds(), 0);
+}
+
+#[test]
+fn critical_engine_health_flattens_all_positions() {
+    let mut sup = HeartbeatSupervisor::new(60, 5);
+    sup.register_tick(1_000);
+    for i in 1..=4 {
+        sup.register_tick(1_000 + i * 120);
+        assert_eq!(sup.health(), EngineHealth::Degraded);
+    }
+    sup.register_tick(1_000 + 5 * 120);
+    assert_eq!(sup.consecutive_degraded_count(), 5);
+    assert_eq!(sup.health(), EngineHealth::Critical);
+
+    // Sterke trend op MES/MNQ: bij Healthy zouden de posities blijven staan
+    let as_of = fixed_as_of();
+    let ctx = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_macro_scalars(MacroScalars {
+            as_of,
+            risk_on_scalar: 1.0,
+            usd_scalar: 1.0,
+            vix_term_slope: 0.0,
+        })
+        .with_risk_envelope(base_risk_envelope())
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .with_history(FutureInstrument::Mnq, make_history_for_test(FutureInstrument::Mnq, 16_000.0, as_of))
+        .with_current_position(FutureInstrument::Mes, 2)
+        .with_current_position(FutureInstrument::Mnq, -1)
+        .with_engine_health(sup.health())
+        .build()
+        .unwrap();
+
+    let sleeve = MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default());
+    let budget = minimal_risk_budget();
+    assert!(sleeve.plan_positions(&ctx, &budget).is_empty());
+
+    let mut intents = sleeve.plan_order_intents(&ctx, &budget);
+    intents.sort_by_key(|i| i.delta_contracts);
+    assert_eq!(intents.len(), 2);
+    assert_eq!((intents[0].instrument, intents[0].delta_contracts), (FutureInstrument::Mes, -2));
+    assert_eq!((intents[1].instrument, intents[1].delta_contracts), (FutureInstrument::Mnq, 1));
+
+    // Ook met een sleeve-halt (die normaal alleen blokkeert) gaat alles flat
+    let halted = FuturesSleeveContext {
+        risk_envelope: SleeveRiskEnvelope { sleeve_halt: HaltState::SoftHalt, ..base_risk_envelope() },
+        ..ctx
+    };
+    assert_eq!(sleeve.plan_order_intents(&halted, &budget).len(), 2);
+}
+
+#[test]
+fn backtest_engine_smoke_runs_over_mes_history() {
+    use engine::strategies::backtest::BacktestEngine;
+
+    let full = make_history_for_test(FutureInstrument::Mes, 100.0, fixed_as_of());
+    let min_bars = 120;
+
+    // Eén stap per bar vanaf MIN_BARS: history zoals die op dat moment bekend was
+    let mut histories_over_time = Vec::new();
+    let mut scalars = Vec::new();
+    let mut envelopes = Vec::new();
+    for end in min_bars..=full.bars.len() {
+        let bars = full.bars[..end].to_vec();
+        let as_of = bars.last().unwrap().ts;
+        let mut histories = HashMap::new();
+        histories.insert(
+            FutureInstrument::Mes,
+            engine::strategies::macro_futures_sleeve::InstrumentHistory {
+                instrument: FutureInstrument::Mes,
+                bars,
+            },
+        );
+        histories_over_time.push(histories);
+        scalars.push(MacroScalars {
+            as_of,
+            risk_on_scalar: 1.0,
+            usd_scalar: 1.0,
+            vix_term_slope: 0.0,
+        });
+        envelopes.push(base_risk_envelope());
+    }
+
+    let engine = BacktestEngine::new(
+        MacroFuturesSleeve::new(MacroFuturesSleeveConfig::default()),
+        minimal_risk_budget(),
+        1_000_000.0,
+    );
+    let result = engine.run(&histories_over_time, &scalars, &envelopes);
+
+    assert!(!result.equity_curve.is_empty());
+    assert_eq!(result.equity_curve.len(), histories_over_time.len());
+    assert_eq!(result.summary.n_steps, histories_over_time.len());
+    // Eerste stap: nog niets gevuld → equity = start-equity
+    assert_eq!(result.equity_curve[0].1, engine.initial_equity_usd);
+    assert_eq!(result.summary.n_trades, result.trade_log.len());
+    assert!(result.summary.final_equity_usd.is_finite());
+    assert!(result.summary.max_drawdown_usd >= 0.0);
+
+    // Lichte uptrend: long entry, gevuld op de volgende close, daarna in de plus
+    assert!(!result.trade_log.is_empty());
+    assert!(result.trade_log[0].delta_contracts > 0);
+    assert!(result.summary.final_equity_usd > engine.initial_equity_usd);
+}
+
+#[test]
+fn backtest_ratios_are_none_for_constant_growth_equity_curve() {
+    use engine::strategies::backtest::{BacktestResult, BacktestSummary};
+
+    let start = fixed_as_of();
+    let result_for = |equity: &[f64]| BacktestResult {
+        equity_curve: equity
+            .iter()
+            .enumerate()
+            .map(|(i, &e)| (start + Duration::days(i as i64), e))
+            .collect(),
+        trade_log: Vec::new(),
+        summary: BacktestSummary {
+            n_steps: equity.len(),
+            n_trades: 0,
+            contracts_traded: 0,
+            realized_pnl_usd: 0.0,
+            unrealized_pnl_usd: 0.0,
+            final_equity_usd: *equity.last().unwrap_or(&0.0),
+            max_drawdown_usd: 0.0,
+        },
+    };
+
+    // Constante procentuele groei: geen variantie, geen drawdown, geen downside
+    let growth: Vec<f64> = (0..30).map(|i| 100_000.0 * 1.001_f64.powi(i)).collect();
+    let r = result_for(&growth);
+    assert_eq!(r.sharpe_ratio(0.0), None);
+    assert_eq!(r.calmar_ratio(), None);
+    assert_eq!(r.sortino_ratio(0.0, 0.0), None);
+
+    // Eén punt: te weinig data
+    let single = result_for(&[100_000.0]);
+    assert_eq!(single.sharpe_ratio(0.0), None);
+    assert_eq!(single.calmar_ratio(), None);
+
+    // Met ruis en een dip zijn alle drie gedefinieerd
+    let noisy = result_for(&[100_000.0, 101_000.0, 100_500.0, 102_000.0, 101_200.0, 103_000.0]);
+    assert!(noisy.sharpe_ratio(0.0).unwrap() > 0.0);
+    assert!(noisy.calmar_ratio().unwrap() > 0.0);
+    assert!(noisy.sortino_ratio(0.0, 0.0).unwrap() > 0.0);
+}
+
+#[test]
+fn instrument_history_from_csv_computes_missing_features() {
+    use chrono::NaiveDate;
+    use engine::strategies::macro_futures_sleeve::{CsvHeader, HistoryLoadError, InstrumentHistory};
+
+    let mut dir = env::temp_dir();
+    dir.push(format!(
+        "macro_futures_history_from_csv_{}",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    fs::create_dir_all(&dir).expect("cannot create test dir");
+
+    // 30 dagen OHLCV, alleen ATR als feature-kolom meegegeven
+    let mut csv = String::from("date,open,high,low,close,volume,atr_14\n");
+    for i in 0..30 {
+        let close = 100.0 + i as f64;
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + Duration::days(i);
+        csv.push_str(&format!(
+            "{},{},{},{},{},1000,2.5\n",
+            date.format("%Y-%m-%d"),
+            close - 0.5,
+            close + 1.0,
+            close - 1.0,
+            close
+        ));
+    }
+    let path = dir.join("mes.csv");
+    fs::write(&path, &csv).unwrap();
+
+    let hist = InstrumentHistory::from_csv(FutureInstrument::Mes, &path, CsvHeader::default())
+        .expect("valid csv");
+    assert_eq!(hist.bars.len(), 30);
+
+    let bar = hist.bars[25];
+    assert_eq!(bar.ts.date_naive(), NaiveDate::from_ymd_opt(2024, 1, 26).unwrap());
+    assert_eq!(bar.close, 125.0);
+    // Aanwezige kolom wordt overgenomen, de rest berekend
+    assert_eq!(bar.atr_14, 2.5);
+    assert!((bar.ret_20d - (125.0f64 / 105.0).ln()).abs() < 1e-12);
+    assert_eq!(bar.ret_60d, 0.0);
+    assert!(bar.vol_20d > 0.0);
+    assert_eq!(bar.highest_close_50d, 125.0);
+    assert_eq!(bar.lowest_close_50d, 100.0);
+    assert!((bar.ma_5d - 123.0).abs() < 1e-12);
+
+    // Zonder ATR-kolom: ATR uit true range (hier steeds 2.0)
+    let no_atr = CsvHeader { atr_14: None, ..CsvHeader::default() };
+    let hist = InstrumentHistory::from_csv(FutureInstrument::Mes, &path, no_atr).unwrap();
+    assert!((hist.bars[25].atr_14 - 2.0).abs() < 1e-12);
+
+    // Fouten: ontbrekende file, ongeldig getal, geen data
+    let missing = InstrumentHistory::from_csv(FutureInstrument::Mes, &dir.join("nope.csv"), CsvHeader::default());
+    assert!(matches!(missing, Err(HistoryLoadError::Io(_))));
+
+    let bad = dir.join("bad.csv");
+    fs::write(&bad, "date,open,high,low,close,volume\n2024-01-01,1,2,0.5,abc,10\n").unwrap();
+    match InstrumentHistory::from_csv(FutureInstrument::Mes, &bad, CsvHeader::default()) {
+        Err(HistoryLoadError::Parse { line, column, .. }) => {
+            assert_eq!(line, 2);
+            assert_eq!(column, "close");
+        }
+        other => panic!("expected parse error, got {:?}", other),
+    }
+
+    let empty = dir.join("empty.csv");
+    fs::write(&empty, "date,open,high,low,close,volume\n").unwrap();
+    assert!(matches!(
+        InstrumentHistory::from_csv(FutureInstrument::Mes, &empty, CsvHeader::default()),
+        Err(HistoryLoadError::InsufficientData { rows: 0 })
+    ));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn instrument_history_validate_reports_each_bad_bar() {
+    use engine::strategies::macro_futures_sleeve::{
+        CsvHeader, HistoryLoadError, HistoryValidationError, InstrumentHistory,
+    };
+
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, fixed_as_of());
+    assert_eq!(hist.validate(), Ok(()));
+
+    // Eén bar met high onder de close
+    let bad_ts = hist.bars[50].ts;
+    hist.bars[50].high = hist.bars[50].close - 1.0;
+    let errors = hist.validate().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0], HistoryValidationError::InvalidOHLC { ts, .. } if ts == bad_ts));
+
+    // Meerdere fouten worden allemaal gerapporteerd
+    hist.bars[60].ts = hist.bars[59].ts;
+    hist.bars[70].ts = hist.bars[68].ts - Duration::days(1);
+    hist.bars[80].close = 0.0;
+    hist.bars[80].low = 0.0;
+    hist.bars[80].open = 0.0;
+    let errors = hist.validate().unwrap_err();
+    assert_eq!(errors.len(), 4);
+    assert!(errors.iter().any(|e| matches!(e, HistoryValidationError::DuplicateTimestamp { .. })));
+    assert!(errors.iter().any(|e| matches!(e, HistoryValidationError::MonotoneViolation { .. })));
+    assert!(errors.iter().any(|e| matches!(e, HistoryValidationError::NegativePrice { .. })));
+
+    // from_csv valideert vóór het teruggeven
+    let mut path = env::temp_dir();
+    path.push(format!(
+        "macro_futures_history_validate_{}.csv",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    fs::write(&path, "date,open,high,low,close,volume\n2024-01-01,10,11,9,10,5\n2024-01-01,10,11,9,10,5\n").unwrap();
+    let loaded = InstrumentHistory::from_csv(FutureInstrument::Mes, &path, CsvHeader::default());
+    let _ = fs::remove_file(&path);
+    match loaded {
+        Err(HistoryLoadError::Invalid(errors)) => assert_eq!(errors.len(), 1),
+        other => panic!("expected validation error, got {:?}", other),
+    }
+}
+
+#[test]
+fn instrument_history_extend_and_trim() {
+    use engine::strategies::macro_futures_sleeve::HistoryValidationError;
+
+    let mut hist = make_history_for_test(FutureInstrument::Mes, 100.0, fixed_as_of());
+    let last = *hist.bars.last().unwrap();
+
+    // Volgende dag: wordt toegevoegd
+    let next = DailyFeatureBar { ts: last.ts + Duration::days(1), ..last };
+    hist.extend(&[next]).unwrap();
+    assert_eq!(hist.bars.len(), 131);
+
+    // Bar vóór de laatste: fout, niets toegevoegd
+    let stale = DailyFeatureBar { ts: last.ts, ..last };
+    let err = hist.extend(&[stale]).unwrap_err();
+    assert!(matches!(err, HistoryValidationError::MonotoneViolation { .. }));
+    assert_eq!(hist.bars.len(), 131);
+
+    // Ongeldige bar in een batch: hele batch geweigerd
+    let later = DailyFeatureBar { ts: next.ts + Duration::days(1), ..last };
+    let broken = DailyFeatureBar { ts: next.ts + Duration::days(2), close: -1.0, low: -1.0, ..last };
+    assert!(hist.extend(&[later, broken]).is_err());
+    assert_eq!(hist.bars.len(), 131);
+
+    hist.trim_to_last_n(50);
+    assert_eq!(hist.bars.len(), 50);
+    assert_eq!(hist.bars.last().unwrap().ts, next.ts);
+
+    // Al korter dan n: ongewijzigd
+    hist.trim_to_last_n(100);
+    assert_eq!(hist.bars.len(), 50);
+}
+
+#[test]
+fn futures_sleeve_context_validate_flags_negative_eur_per_usd() {
+    let mut ctx = make_minimal_ctx();
+    assert_eq!(ctx.validate(), Ok(()));
+
+    ctx.eur_per_usd = -0.5;
+    let errors = ctx.validate().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("eur_per_usd"));
+
+    // Alle fouten tegelijk
+    ctx.macro_scalars.usd_scalar = f64::NAN;
+    ctx.risk_envelope.max_position_size_usd = -1.0;
+    assert_eq!(ctx.validate().unwrap_err().len(), 3);
+
+    // Open positie zonder history is geen fout, wel een waarschuwing
+    let mut ctx = make_minimal_ctx();
+    assert!(ctx.warnings().is_empty());
+    ctx.histories.clear();
+    ctx.current_positions.insert(FutureInstrument::Mes, 2);
+    ctx.current_positions.insert(FutureInstrument::Mnq, 0);
+    assert_eq!(ctx.validate(), Ok(()));
+    let warnings = ctx.warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("Mes"), "{:?}", warnings);
+}
+
+#[test]
+fn context_builder_with_methods_require_risk_envelope() {
+    let as_of = fixed_as_of();
+    let ms = MacroScalars {
+        as_of,
+        risk_on_scalar: 1.0,
+        usd_scalar: 1.0,
+        vix_term_slope: 0.0,
+    };
+
+    let err = FuturesSleeveContext::builder()
+        .with_as_of(as_of)
+        .with_macro_scalars(ms)
+        .with_history(FutureInstrument::Mes, make_history_for_test(FutureInstrument::Mes, 100.0, as_of))
+        .build()
+        .unwrap_err();
+    assert_eq!(err, BuildError::MissingField("risk_envelope"));
+    assert!(err.to_string().contains("risk_envelope"));
+
+    let ctx = FuturesSleeveContext::builder()
+        .with_macro_scalars(ms)
+        .with_risk_envelope(base_risk_envelope())
+        .with_current_position(FutureInstrument::Mes, 1)
+        .build()
+        .unwrap();
+    assert_eq!(ctx.as_of, as_of);
+    assert_eq!(ctx.eur_per_usd, 1.0);
+    assert_eq!(ctx.engine_health, EngineHealth::Healthy);
+    assert_eq!(ctx.current_positions.get(&FutureInstrument::Mes), Some(&1));
+
+    // build() draait ook FuturesSleeveContext::validate
+    let invalid = FuturesSleeveContext::builder()
+        .with_macro_scalars(MacroScalars { risk_on_scalar: 0.0, ..ms })
+        .with_risk_envelope(base_risk_envelope())
+        .with_eur_per_usd(0.92)
+        .with_engine_health(EngineHealth::Degraded)
+        .build();
+    assert!(matches!(invalid, Err(BuildError::Invalid(ref e)) if e.len() == 1));
+}
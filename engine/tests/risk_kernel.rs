@@ -0,0 +1,680 @@
+// tests/risk_kernel.rs
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use engine::risk::{
+    derive_portfolio_halt_state,
+    derive_portfolio_risk_state,
+    evaluate_new_position_risk,
+    GlobalRiskKernel,
+    GlobalRiskKernelConfig,
+    HaltState,
+    KernelSnapshot,
+    MarginState,
+    PortfolioRiskConfig,
+    PortfolioRiskState,
+    PortfolioState,
+    RiskDecisionReason,
+    SleeveId,
+    SleeveRiskConfig,
+    SleeveRiskEnvelope,
+    SleeveState,
+    VolatilityRegime,
+};
+
+// 2024-01-02 10:00:00 UTC
+const DAY1_10H: i64 = 1_704_189_600;
+
+fn kernel_config_10k() -> GlobalRiskKernelConfig {
+    GlobalRiskKernelConfig {
+        portfolio: PortfolioRiskConfig {
+            initial_equity_usd: 10_000.0,
+            halt_dd_frac: -0.10,
+            kill_dd_frac: -0.20,
+            max_leverage: 1.5,
+            rebalance_drift_frac: 0.15,
+            max_global_positions: 10,
+            max_daily_loss_usd: 500.0,
+            recovery_buffer_frac: 0.02,
+        },
+        sleeves: vec![
+            SleeveRiskConfig {
+                sleeve_id: SleeveId::MicroFuturesMacroTrend,
+                capital_alloc_usd: 5_000.0,
+                max_single_pos_risk_frac: 0.03,
+                halt_dd_frac: -0.15,
+                soft_halt_dd_frac: -0.15,
+                kill_dd_frac: -0.25,
+                max_concurrent_positions: 4,
+                max_position_size_usd_absolute: None,
+            },
+            SleeveRiskConfig {
+                sleeve_id: SleeveId::EquityLongShort,
+                capital_alloc_usd: 5_000.0,
+                max_single_pos_risk_frac: 0.01,
+                halt_dd_frac: -0.12,
+                soft_halt_dd_frac: -0.12,
+                kill_dd_frac: -0.18,
+                max_concurrent_positions: 6,
+                max_position_size_usd_absolute: None,
+            },
+        ],
+        use_internal_vol_estimate: false,
+        vol_window: 20,
+    }
+}
+
+fn portfolio_with_equity(equity: f64) -> PortfolioState {
+    PortfolioState {
+        cash_usd: equity,
+        open_pnl_usd: 0.0,
+        accrued_interest_usd: 0.0,
+        peak_equity_usd: 10_000.0,
+        total_notional_exposure: 0.0,
+        current_leverage: 0.0,
+    }
+}
+
+fn sleeve_states() -> Vec<SleeveState> {
+    vec![
+        SleeveState {
+            sleeve_id: SleeveId::MicroFuturesMacroTrend,
+            equity_usd: 5_000.0,
+            realized_pnl_usd: 0.0,
+            unrealized_pnl_usd: 0.0,
+            peak_equity_usd: 5_000.0,
+            open_positions: 0,
+        },
+        SleeveState {
+            sleeve_id: SleeveId::EquityLongShort,
+            equity_usd: 5_000.0,
+            realized_pnl_usd: 0.0,
+            unrealized_pnl_usd: 0.0,
+            peak_equity_usd: 5_000.0,
+            open_positions: 0,
+        },
+    ]
+}
+
+fn neutral_margin(equity: f64) -> MarginState {
+    MarginState {
+        internal_margin_req_usd: 0.0,
+        broker_margin_req_usd: 0.0,
+        equity_usd: equity,
+    }
+}
+
+fn normal_vol() -> VolatilityRegime {
+    VolatilityRegime {
+        rv10_annualized: 12.0,
+        vix_level: 18.0,
+        vix_term_slope: 0.3,
+        regime_scalar: 1.0,
+    }
+}
+
+#[test]
+fn daily_loss_breaker_kills_all_envelopes_intraday() {
+    let mut kernel = GlobalRiskKernel::new(kernel_config_10k());
+    let mut sleeves = sleeve_states();
+
+    // Start van de dag: 10k equity → alles normaal
+    let start = portfolio_with_equity(10_000.0);
+    let envs = kernel.evaluate(DAY1_10H, &start, &mut sleeves, &neutral_margin(10_000.0), &normal_vol());
+    assert!(envs.iter().all(|e| e.portfolio_halt == HaltState::None));
+
+    // Zelfde dag: -600 USD. DD = -6% (boven halt), maar daily loss > 500 → Kill
+    let later = portfolio_with_equity(9_400.0);
+    let envs = kernel.evaluate(
+        DAY1_10H + 3_600,
+        &later,
+        &mut sleeves,
+        &neutral_margin(9_400.0),
+        &normal_vol(),
+    );
+
+    assert_eq!(envs.len(), 2);
+    for env in &envs {
+        assert_eq!(
+            env.portfolio_halt,
+            HaltState::Kill,
+            "expected Kill for {:?} after intraday loss above limit",
+            env.sleeve_id
+        );
+        assert_eq!(env.max_position_size_usd, 0.0);
+    }
+}
+
+#[test]
+fn daily_loss_breaker_resets_on_new_day() {
+    let mut kernel = GlobalRiskKernel::new(kernel_config_10k());
+    let mut sleeves = sleeve_states();
+
+    let start = portfolio_with_equity(10_000.0);
+    kernel.evaluate(DAY1_10H, &start, &mut sleeves, &neutral_margin(10_000.0), &normal_vol());
+
+    // Volgende dag start op 9_400 → nieuwe referentie, geen daily-loss Kill
+    let next_day = portfolio_with_equity(9_400.0);
+    let envs = kernel.evaluate(
+        DAY1_10H + 86_400,
+        &next_day,
+        &mut sleeves,
+        &neutral_margin(9_400.0),
+        &normal_vol(),
+    );
+
+    assert!(envs.iter().all(|e| e.portfolio_halt == HaltState::None));
+    assert_eq!(kernel.today_start_equity, 9_400.0);
+}
+
+#[test]
+fn margin_utilization_pct_is_broker_margin_over_equity() {
+    let margin = MarginState {
+        broker_margin_req_usd: 5_000.0,
+        equity_usd: 10_000.0,
+        ..neutral_margin(10_000.0)
+    };
+
+    assert_eq!(margin.margin_utilization_pct(), 50.0);
+    assert!(margin.is_margin_constrained(50.0));
+    assert!(!margin.is_margin_constrained(60.0));
+
+    // Boven equity → geclampt op 100, lege equity → volledig benut
+    let over = MarginState { broker_margin_req_usd: 15_000.0, ..margin };
+    assert_eq!(over.margin_utilization_pct(), 100.0);
+    assert_eq!(neutral_margin(0.0).margin_utilization_pct(), 100.0);
+}
+
+#[test]
+fn evaluate_shared_from_two_threads_returns_envelopes() {
+    let shared = Arc::new(Mutex::new(portfolio_with_equity(10_000.0)));
+
+    let handles: Vec<_> = (0..2)
+        .map(|i| {
+            let portfolio = Arc::clone(&shared);
+            thread::spawn(move || {
+                let mut kernel = GlobalRiskKernel::new(kernel_config_10k());
+                let mut sleeves = sleeve_states();
+                let mut last = Vec::new();
+                for tick in 0..50 {
+                    // Andere sleeve-thread muteert ondertussen de gedeelde state
+                    portfolio.lock().unwrap().open_pnl_usd = (i * 10 + tick % 3) as f64;
+                    last = kernel.evaluate_shared(
+                        DAY1_10H + tick,
+                        Arc::clone(&portfolio),
+                        &mut sleeves,
+                        &neutral_margin(10_000.0),
+                        &normal_vol(),
+                    );
+                }
+                last
+            })
+        })
+        .collect();
+
+    for h in handles {
+        let envs = h.join().expect("thread must not panic or deadlock");
+        assert_eq!(envs.len(), 2);
+        for env in &envs {
+            assert_eq!(env.portfolio_halt, HaltState::None);
+            assert!(env.max_position_size_usd > 0.0);
+        }
+    }
+}
+
+#[test]
+fn portfolio_var_grows_with_vix_and_confidence() {
+    let kernel = GlobalRiskKernel::new(kernel_config_10k());
+    let sleeves = sleeve_states();
+
+    let calm = normal_vol();
+    let stressed = VolatilityRegime { vix_level: 40.0, ..normal_vol() };
+
+    let var_calm_95 = kernel.evaluate_portfolio_var(&sleeves, &calm, 0.95);
+    let var_stress_95 = kernel.evaluate_portfolio_var(&sleeves, &stressed, 0.95);
+    let var_calm_99 = kernel.evaluate_portfolio_var(&sleeves, &calm, 0.99);
+
+    assert!(var_calm_95 > 0.0);
+    assert!(var_stress_95 > var_calm_95);
+    assert!(var_calm_99 > var_calm_95);
+
+    // 10k equity, VIX 18 → dagvol ≈ 1.134%, z(95%) ≈ 1.645 → ≈ 186.5 USD
+    let summary = kernel.portfolio_risk_summary(&sleeves, &calm, 0.95);
+    assert_eq!(summary.total_sleeve_equity_usd, 10_000.0);
+    assert!((summary.portfolio_var_usd - 186.5).abs() < 0.5, "got {}", summary.portfolio_var_usd);
+    assert!((var_calm_99 / var_calm_95 - 2.326 / 1.645).abs() < 1e-3);
+}
+
+#[test]
+fn absolute_position_cap_overrides_fraction_based_size() {
+    let sleeves_cfg = |abs_cap: Option<f64>| {
+        let mut cfg = kernel_config_10k();
+        // Micro futures: 5_000 * 0.03 = 150 USD frac-based
+        cfg.sleeves[0].max_position_size_usd_absolute = abs_cap;
+        cfg
+    };
+
+    let run = |abs_cap: Option<f64>| {
+        let mut kernel = GlobalRiskKernel::new(sleeves_cfg(abs_cap));
+        let mut sleeves = sleeve_states();
+        kernel.evaluate(
+            DAY1_10H,
+            &portfolio_with_equity(10_000.0),
+            &mut sleeves,
+            &neutral_margin(10_000.0),
+            &normal_vol(),
+        )[0]
+            .max_position_size_usd
+    };
+
+    let uncapped = run(None);
+    assert!(uncapped > 100.0, "frac-based size = {}", uncapped);
+
+    // Cap onder de frac-based size → cap wint
+    assert_eq!(run(Some(100.0)), 100.0);
+    // Cap boven de frac-based size → geen effect
+    assert_eq!(run(Some(10_000.0)), uncapped);
+}
+
+#[test]
+fn derive_portfolio_halt_state_uses_dd_thresholds() {
+    let pcfg = PortfolioRiskConfig {
+        halt_dd_frac: -0.10,
+        kill_dd_frac: -0.20,
+        ..kernel_config_10k().portfolio
+    };
+
+    assert_eq!(derive_portfolio_halt_state(-0.15, &pcfg), HaltState::Halt);
+    assert_eq!(derive_portfolio_risk_state(-0.15, &pcfg), PortfolioRiskState::Caution);
+
+    assert_eq!(derive_portfolio_halt_state(-0.05, &pcfg), HaltState::None);
+    assert_eq!(derive_portfolio_halt_state(-0.20, &pcfg), HaltState::Kill);
+    assert_eq!(derive_portfolio_risk_state(-0.25, &pcfg), PortfolioRiskState::Stress);
+}
+
+#[test]
+fn stress_test_25pct_loss_kills_all_envelopes() {
+    let mut cfg = kernel_config_10k();
+    cfg.portfolio.kill_dd_frac = -0.20;
+    let kernel = GlobalRiskKernel::new(cfg);
+    let sleeves = sleeve_states();
+
+    let envs = kernel.stress_test_portfolio(&sleeves, &normal_vol(), 25.0);
+
+    assert_eq!(envs.len(), 2);
+    for env in &envs {
+        assert_eq!(env.portfolio_halt, HaltState::Kill, "{:?}", env.sleeve_id);
+        assert_eq!(env.max_position_size_usd, 0.0);
+    }
+
+    // Mild scenario → geen halt; kernel-state is niet aangeraakt
+    let mild = kernel.stress_test_portfolio(&sleeves, &normal_vol(), 2.0);
+    assert!(mild.iter().all(|e| e.portfolio_halt == HaltState::None));
+    assert_eq!(kernel.internal_portfolio_peak_equity, 10_000.0);
+}
+
+#[test]
+fn evaluate_incremental_matches_full_evaluate_entry() {
+    let mut sleeves = sleeve_states();
+    sleeves[0].open_positions = 2;
+    sleeves[0].equity_usd = 4_800.0;
+    sleeves[1].open_positions = 3;
+
+    let portfolio = portfolio_with_equity(9_800.0);
+    let margin = MarginState {
+        broker_margin_req_usd: 2_000.0,
+        ..neutral_margin(9_800.0)
+    };
+
+    let mut full_kernel = GlobalRiskKernel::new(kernel_config_10k());
+    let mut full_sleeves = sleeves.clone();
+    let full = full_kernel.evaluate(DAY1_10H, &portfolio, &mut full_sleeves, &margin, &normal_vol());
+
+    // Eerst een volledige tick zodat de kernel de open posities van alle sleeves kent
+    let mut inc_kernel = GlobalRiskKernel::new(kernel_config_10k());
+    inc_kernel.evaluate(DAY1_10H, &portfolio, &mut sleeves.clone(), &margin, &normal_vol());
+
+    for (i, expected) in full.iter().enumerate() {
+        let mut sleeve = sleeves[i];
+        let env = inc_kernel.evaluate_incremental(DAY1_10H, &portfolio, &mut sleeve, &margin, &normal_vol());
+        assert_eq!(env, *expected, "{:?}", expected.sleeve_id);
+        assert_eq!(sleeve.peak_equity_usd, full_sleeves[i].peak_equity_usd);
+    }
+}
+
+#[test]
+fn internal_vol_estimate_overrides_stale_external_rv() {
+    let cfg = GlobalRiskKernelConfig {
+        use_internal_vol_estimate: true,
+        vol_window: 20,
+        ..kernel_config_10k()
+    };
+    let mut kernel = GlobalRiskKernel::new(cfg);
+
+    // Extern aangeleverde (stale) RV in het stress-regime
+    let stale_vol = VolatilityRegime { rv10_annualized: 40.0, ..normal_vol() };
+    let portfolio = portfolio_with_equity(10_000.0);
+    let margin = neutral_margin(10_000.0);
+
+    // Window nog niet vol → externe RV wordt gebruikt
+    for i in 0..19 {
+        kernel.push_daily_return(DAY1_10H + i * 86_400, if i % 2 == 0 { 0.005 } else { -0.005 });
+    }
+    assert_eq!(kernel.internal_rv_annualized(), None);
+    let envs = kernel.evaluate(DAY1_10H, &portfolio, &mut sleeve_states(), &margin, &stale_vol);
+    assert!((envs[0].volatility_regime_scalar - 0.55).abs() < 1e-12);
+
+    // 20 returns van ±0.5% → stdev ≈ 0.513% → ≈ 8.1% annualized
+    kernel.push_daily_return(DAY1_10H + 19 * 86_400, -0.005);
+    let rv = kernel.internal_rv_annualized().expect("window full");
+    let expected = 0.005 * (20.0_f64 / 19.0).sqrt() * 252.0_f64.sqrt() * 100.0;
+    assert!((rv - expected).abs() < 1e-9, "rv = {}", rv);
+
+    // Interne schatting overschrijft de stale RV → normaal regime
+    let envs = kernel.evaluate(DAY1_10H, &portfolio, &mut sleeve_states(), &margin, &stale_vol);
+    assert!((envs[0].volatility_regime_scalar - 1.0).abs() < 1e-12);
+
+    // Window blijft op vol_window entries
+    kernel.push_daily_return(DAY1_10H + 20 * 86_400, 0.005);
+    assert!(kernel.internal_rv_annualized().is_some());
+
+    // Zonder de flag blijft de externe RV leidend
+    let mut plain = GlobalRiskKernel::new(kernel_config_10k());
+    for i in 0..20 {
+        plain.push_daily_return(DAY1_10H + i * 86_400, 0.0);
+    }
+    let envs = plain.evaluate(DAY1_10H, &portfolio, &mut sleeve_states(), &margin, &stale_vol);
+    assert!((envs[0].volatility_regime_scalar - 0.55).abs() < 1e-12);
+}
+
+#[test]
+fn portfolio_var_uses_notional_and_last_evaluated_rv() {
+    let mut kernel = GlobalRiskKernel::new(kernel_config_10k());
+    let portfolio = PortfolioState {
+        total_notional_exposure: 10_000.0,
+        ..portfolio_with_equity(10_000.0)
+    };
+    let sleeves = sleeve_states();
+
+    // Nog geen evaluate → geen vol-schatting
+    assert_eq!(kernel.estimate_portfolio_var_usd(&portfolio, &sleeves, 0.99), 0.0);
+
+    let vol = VolatilityRegime { rv10_annualized: 15.0, ..normal_vol() };
+    kernel.evaluate(DAY1_10H, &portfolio, &mut sleeve_states(), &neutral_margin(10_000.0), &vol);
+
+    // 10k * 0.15 / √252 * 2.326 ≈ 220
+    let var_99 = kernel.estimate_portfolio_var_usd(&portfolio, &sleeves, 0.99);
+    assert!((var_99 - 219.8).abs() < 0.5, "VaR99 = {}", var_99);
+
+    let var_95 = kernel.estimate_portfolio_var_usd(&portfolio, &sleeves, 0.95);
+    assert!(var_95 < var_99);
+
+    let flat = PortfolioState { total_notional_exposure: 0.0, ..portfolio };
+    assert_eq!(kernel.estimate_portfolio_var_usd(&flat, &sleeves, 0.99), 0.0);
+}
+
+#[test]
+fn sleeve_drawdown_between_soft_halt_and_halt_gives_soft_halt() {
+    let mut cfg = kernel_config_10k();
+    cfg.sleeves[0].soft_halt_dd_frac = -0.05;
+    let mut kernel = GlobalRiskKernel::new(cfg);
+
+    let mut sleeves = sleeve_states();
+    // -8%: onder soft-halt (-5%), boven halt (-15%)
+    sleeves[0].equity_usd = 4_600.0;
+
+    let envs = kernel.evaluate(
+        DAY1_10H,
+        &portfolio_with_equity(9_600.0),
+        &mut sleeves,
+        &neutral_margin(9_600.0),
+        &normal_vol(),
+    );
+    assert_eq!(envs[0].sleeve_halt, HaltState::SoftHalt);
+    assert!(envs[0].max_position_size_usd > 0.0);
+    // Andere sleeve heeft geen soft-halt zone (= halt_dd_frac)
+    assert_eq!(envs[1].sleeve_halt, HaltState::None);
+
+    // Dieper dan halt_dd_frac → gewone Halt
+    sleeves[0].equity_usd = 4_200.0;
+    let envs = kernel.evaluate(
+        DAY1_10H,
+        &portfolio_with_equity(9_200.0),
+        &mut sleeves,
+        &neutral_margin(9_200.0),
+        &normal_vol(),
+    );
+    assert_eq!(envs[0].sleeve_halt, HaltState::Halt);
+}
+
+#[test]
+fn recovery_after_portfolio_halt_before_back_to_normal() {
+    let mut kernel = GlobalRiskKernel::new(kernel_config_10k());
+    let mut sleeves = sleeve_states();
+
+    let normal = kernel.evaluate(
+        DAY1_10H,
+        &portfolio_with_equity(10_000.0),
+        &mut sleeves,
+        &neutral_margin(10_000.0),
+        &normal_vol(),
+    );
+
+    // -11% (dieper dan halt -10%), op een nieuwe dag zodat de daily-loss breaker niet meespeelt
+    let day2 = DAY1_10H + 86_400;
+    let envs = kernel.evaluate(
+        day2,
+        &portfolio_with_equity(8_900.0),
+        &mut sleeves,
+        &neutral_margin(8_900.0),
+        &normal_vol(),
+    );
+    assert_eq!(envs[0].portfolio_halt, HaltState::Halt);
+
+    // -9%: boven halt, maar niet boven halt + 2% buffer → Recovery, niet Normal
+    let envs = kernel.evaluate(
+        day2 + 3_600,
+        &portfolio_with_equity(9_100.0),
+        &mut sleeves,
+        &neutral_margin(9_100.0),
+        &normal_vol(),
+    );
+    assert_eq!(envs[0].portfolio_halt, HaltState::None);
+    assert_eq!(envs[0].portfolio_risk_state, PortfolioRiskState::Recovery);
+    assert!(envs[0].max_position_size_usd < normal[0].max_position_size_usd);
+    assert_eq!(envs[0].max_concurrent_positions, 2);
+    assert_eq!(envs[1].max_concurrent_positions, 3);
+
+    // -7%: voorbij de buffer → weer Normal met volle concurrency
+    let envs = kernel.evaluate(
+        day2 + 7_200,
+        &portfolio_with_equity(9_300.0),
+        &mut sleeves,
+        &neutral_margin(9_300.0),
+        &normal_vol(),
+    );
+    assert_ne!(envs[0].portfolio_risk_state, PortfolioRiskState::Recovery);
+    assert_eq!(envs[0].max_concurrent_positions, 4);
+}
+
+#[test]
+fn evaluate_single_sleeve_matches_evaluate_with_one_configured_sleeve() {
+    let mut cfg = kernel_config_10k();
+    cfg.sleeves.truncate(1);
+
+    let mut sleeve = sleeve_states()[0];
+    sleeve.open_positions = 2;
+    sleeve.equity_usd = 4_800.0;
+
+    let portfolio = portfolio_with_equity(9_800.0);
+    let margin = MarginState {
+        broker_margin_req_usd: 2_000.0,
+        ..neutral_margin(9_800.0)
+    };
+
+    let mut full_kernel = GlobalRiskKernel::new(cfg.clone());
+    let mut full_sleeves = vec![sleeve];
+    let full = full_kernel.evaluate(DAY1_10H, &portfolio, &mut full_sleeves, &margin, &normal_vol());
+
+    let mut single_kernel = GlobalRiskKernel::new(cfg);
+    let env = single_kernel.evaluate_single_sleeve(DAY1_10H, &portfolio, &mut sleeve, &margin, &normal_vol());
+    assert_eq!(env, full[0]);
+    assert_eq!(sleeve.peak_equity_usd, full_sleeves[0].peak_equity_usd);
+
+    // Met twee sleeves rekent de single-variant de andere sleeve vol (6 slots) → nooit ruimer
+    let mut two = GlobalRiskKernel::new(kernel_config_10k());
+    let mut sleeve = sleeve_states()[0];
+    let env = two.evaluate_single_sleeve(DAY1_10H, &portfolio, &mut sleeve, &margin, &normal_vol());
+    let full = GlobalRiskKernel::new(kernel_config_10k()).evaluate(
+        DAY1_10H,
+        &portfolio,
+        &mut sleeve_states(),
+        &margin,
+        &normal_vol(),
+    );
+    assert!(env.max_concurrent_positions <= full[0].max_concurrent_positions);
+}
+
+#[test]
+fn restored_kernel_keeps_drawdown_tracking_after_restart() {
+    let mut kernel = GlobalRiskKernel::new(kernel_config_10k());
+    let mut sleeves = sleeve_states();
+
+    // Nieuwe HWM boven initial equity: portfolio 12k, microfutures-sleeve 6k
+    sleeves[0].equity_usd = 6_000.0;
+    kernel.evaluate(
+        DAY1_10H,
+        &portfolio_with_equity(12_000.0),
+        &mut sleeves,
+        &neutral_margin(12_000.0),
+        &normal_vol(),
+    );
+
+    // Volgende dag -12.5% portfolio, -16.7% sleeve → beide Halt
+    let day2 = DAY1_10H + 86_400;
+    sleeves[0].equity_usd = 5_000.0;
+    let envs = kernel.evaluate(
+        day2,
+        &portfolio_with_equity(10_500.0),
+        &mut sleeves,
+        &neutral_margin(10_500.0),
+        &normal_vol(),
+    );
+    assert_eq!(envs[0].portfolio_halt, HaltState::Halt);
+    assert_eq!(envs[0].sleeve_halt, HaltState::Halt);
+
+    let json = serde_json::to_string(&kernel.snapshot()).unwrap();
+    let snapshot: KernelSnapshot = serde_json::from_str(&json).unwrap();
+
+    // Na een crash: sleeve-HWM's zijn gereset naar huidige equity
+    let mut restarted_sleeves = sleeve_states();
+    restarted_sleeves[0].equity_usd = 5_000.0;
+    restarted_sleeves[0].peak_equity_usd = 5_000.0;
+
+    let mut restored = GlobalRiskKernel::restore(kernel_config_10k(), snapshot.clone());
+    let envs = restored.evaluate(
+        day2 + 3_600,
+        &portfolio_with_equity(10_500.0),
+        &mut restarted_sleeves,
+        &neutral_margin(10_500.0),
+        &normal_vol(),
+    );
+    assert_eq!(envs[0].portfolio_halt, HaltState::Halt);
+    assert_eq!(envs[0].sleeve_halt, HaltState::Halt);
+    assert_eq!(restarted_sleeves[0].peak_equity_usd, 6_000.0);
+
+    // Zonder restore zou de kernel de DD kwijt zijn
+    let mut fresh_sleeves = sleeve_states();
+    let envs = GlobalRiskKernel::new(kernel_config_10k()).evaluate(
+        day2 + 3_600,
+        &portfolio_with_equity(10_500.0),
+        &mut fresh_sleeves,
+        &neutral_margin(10_500.0),
+        &normal_vol(),
+    );
+    assert_eq!(envs[0].portfolio_halt, HaltState::None);
+
+    // Vol-regime van vóór de crash: VaR direct na de restore gelijk aan het origineel
+    let mut after_restart = GlobalRiskKernel::restore(kernel_config_10k(), snapshot);
+    let exposed = PortfolioState {
+        total_notional_exposure: 20_000.0,
+        ..portfolio_with_equity(10_500.0)
+    };
+    let var_before = kernel.estimate_portfolio_var_usd(&exposed, &[], 0.95);
+    assert!(var_before > 0.0);
+    assert_eq!(after_restart.estimate_portfolio_var_usd(&exposed, &[], 0.95), var_before);
+
+    // Halt-fase overleeft de restart: herstel tot -9% (boven halt, onder halt + buffer) → Recovery
+    let mut recovering_sleeves = sleeve_states();
+    let envs = after_restart.evaluate(
+        day2 + 7_200,
+        &portfolio_with_equity(10_900.0),
+        &mut recovering_sleeves,
+        &neutral_margin(10_900.0),
+        &normal_vol(),
+    );
+    assert_eq!(envs[0].portfolio_halt, HaltState::None);
+    assert_eq!(envs[0].portfolio_risk_state, PortfolioRiskState::Recovery);
+}
+
+#[test]
+fn envelope_is_tradeable_reports_each_blocking_condition() {
+    let mut kernel = GlobalRiskKernel::new(kernel_config_10k());
+    let mut sleeves = sleeve_states();
+    let envs = kernel.evaluate(
+        DAY1_10H,
+        &portfolio_with_equity(10_000.0),
+        &mut sleeves,
+        &neutral_margin(10_000.0),
+        &normal_vol(),
+    );
+    let ok = envs[0];
+    assert!(ok.is_tradeable());
+    assert_eq!(ok.reason_not_tradeable(), None);
+
+    let cases = [
+        (
+            SleeveRiskEnvelope { portfolio_halt: HaltState::Halt, ..ok },
+            RiskDecisionReason::PortfolioHalt,
+        ),
+        (
+            SleeveRiskEnvelope { sleeve_halt: HaltState::Kill, ..ok },
+            RiskDecisionReason::SleeveHalt,
+        ),
+        (
+            SleeveRiskEnvelope { max_position_size_usd: 0.0, ..ok },
+            RiskDecisionReason::PositionSizeZero,
+        ),
+        (
+            SleeveRiskEnvelope { max_concurrent_positions: 0, ..ok },
+            RiskDecisionReason::ConcurrencyLimit,
+        ),
+    ];
+    for (env, reason) in cases {
+        assert!(!env.is_tradeable(), "{reason:?}");
+        assert_eq!(env.reason_not_tradeable(), Some(reason));
+        assert_eq!(evaluate_new_position_risk(&sleeves[0], &env).reason, reason);
+    }
+
+    // Halts gaan voor limieten
+    let both = SleeveRiskEnvelope {
+        sleeve_halt: HaltState::Halt,
+        max_position_size_usd: 0.0,
+        ..ok
+    };
+    assert_eq!(both.reason_not_tradeable(), Some(RiskDecisionReason::SleeveHalt));
+
+    // SoftHalt laat de envelope tradeable (alleen afbouw), maar blokkeert nieuwe posities
+    let soft_sleeve = SleeveRiskEnvelope { sleeve_halt: HaltState::SoftHalt, ..ok };
+    assert!(soft_sleeve.is_tradeable());
+    let decision = evaluate_new_position_risk(&sleeves[0], &soft_sleeve);
+    assert!(!decision.allow_new_position);
+    assert_eq!(decision.reason, RiskDecisionReason::SleeveHalt);
+
+    let soft_portfolio = SleeveRiskEnvelope { portfolio_halt: HaltState::SoftHalt, ..ok };
+    let decision = evaluate_new_position_risk(&sleeves[0], &soft_portfolio);
+    assert!(!decision.allow_new_position);
+    assert_eq!(decision.max_new_positions, 0);
+    assert_eq!(decision.reason, RiskDecisionReason::PortfolioHalt);
+}
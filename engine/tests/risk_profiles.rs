@@ -222,3 +222,21 @@ fn aggressive_kernel_25k_initial_state_matches_profile() {
     );
 }
 
+
+#[test]
+fn profiles_ship_with_soft_halt_disabled() {
+    // soft_halt_dd_frac == halt_dd_frac → SoftHalt wordt nooit bereikt (opt-in per deployment)
+    let configs = [
+        default_global_risk_kernel_config_usd_10k(),
+        aggressive_25k_global_risk_kernel_config(),
+    ];
+    for cfg in &configs {
+        for s in &cfg.sleeves {
+            assert_eq!(
+                s.soft_halt_dd_frac, s.halt_dd_frac,
+                "Sleeve {:?}: soft-halt should default to off",
+                s.sleeve_id
+            );
+        }
+    }
+}